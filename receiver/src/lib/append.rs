@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::{read_dir, rename, write, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::status::{eprint_status, Health};
+
+/// Extension of the file naming the output file being written (`--append`)
+pub const LATEST_EXTENSION: &str = "latest";
+
+/// Minutes since its newest file was written within which a session is continued by default
+pub const DEFAULT_APPEND_MAX_AGE_MINUTES: u64 = 60;
+
+/// Parquet files written with the prefix in a directory, newest first
+///
+/// Matches `<prefix>_<time>*.parquet` as named by the writers, ordered by
+/// modification time (then name), so files renamed after their first record
+/// are ordered by when they were written.
+pub fn session_files(output_dir: &Path, prefix: &str) -> Result<Vec<PathBuf>> {
+    let head = format!("{}_", prefix);
+    let mut files = Vec::new();
+    for entry in read_dir(output_dir)
+        .with_context(|| format!("Failed to read directory {}", output_dir.display()))?
+    {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let matches = name
+            .strip_prefix(&head)
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            && name.ends_with(".parquet");
+        if matches && entry.file_type()?.is_file() {
+            files.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().rev().map(|(_, path)| path).collect())
+}
+
+/// Run ID of the most recent session written with the prefix, to continue it
+///
+/// Only files modified within `max_age` count, so a restart continues the
+/// session that was just interrupted rather than one finished long ago. A
+/// file left without its footer by a crash cannot be read; it is skipped
+/// with a warning and the run ID taken from the newest readable file.
+///
+/// # Returns
+/// The run ID and the file it was read from, or None if no recent file has one
+pub fn resume_run_id(
+    output_dir: &Path,
+    prefix: &str,
+    max_age: Duration,
+) -> Result<Option<(String, PathBuf)>> {
+    for path in session_files(output_dir, prefix)? {
+        let age = path.metadata()?.modified()?.elapsed().unwrap_or_default();
+        if age > max_age {
+            // The rest are older still
+            break;
+        }
+        match file_run_id(&path) {
+            Ok(Some(run_id)) => return Ok(Some((run_id, path))),
+            Ok(None) => {}
+            Err(e) => {
+                let message = format!("Skipping {}: {}", path.display(), e);
+                eprint_status(Health::Degraded, &message);
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Run ID stored in a Parquet file's metadata
+fn file_run_id(path: &Path) -> Result<Option<String>> {
+    let file = File::open(path)?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+    Ok(builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kv| kv.iter().find(|kv| kv.key == "run_id"))
+        .and_then(|kv| kv.value.clone()))
+}
+
+/// Path of the file naming the output file being written
+pub fn latest_pointer_path(output_dir: &Path, prefix: &str) -> PathBuf {
+    output_dir.join(format!("{}.{}", prefix, LATEST_EXTENSION))
+}
+
+/// Points `<prefix>.latest` at an output file
+///
+/// The pointer holds the file name on a single line and is replaced in one
+/// step, so readers always find a complete name.
+pub fn write_latest_pointer(output_dir: &Path, prefix: &str, file: &Path) -> Result<()> {
+    let pointer = latest_pointer_path(output_dir, prefix);
+    let name = file.file_name().unwrap_or(file.as_os_str());
+    let temp = pointer.with_extension("latest.tmp");
+    write(&temp, format!("{}\n", name.to_string_lossy()))
+        .with_context(|| format!("Failed to write {}", temp.display()))?;
+    rename(&temp, &pointer).with_context(|| format!("Failed to replace {}", pointer.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionType, DataSink, ParquetWriter, SensorData, WriterOptions};
+    use tempfile::tempdir;
+
+    fn record(timestamp: u32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp: 1_735_732_800_000 + timestamp as i64,
+            ..Default::default()
+        }
+    }
+
+    fn write_session(dir: &str, run_id: &str) -> String {
+        let options = WriterOptions {
+            run_id: Some(run_id.to_string()),
+            session_column: true,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir, "log", CompressionType::None, 10, options)
+                .unwrap();
+        writer.add_data(record(0)).unwrap();
+        let path = DataSink::output_path(&writer).unwrap().to_string();
+        writer.close().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_resume_skips_truncated_file() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dir_path = dir.to_str().unwrap();
+        let hour = Duration::from_secs(3_600);
+        assert!(resume_run_id(dir, "log", hour).unwrap().is_none());
+
+        let first = write_session(dir_path, "first-run");
+        let second = write_session(dir_path, "second-run");
+        // Distinct modification times, whatever the filesystem's resolution
+        let now = std::time::SystemTime::now();
+        for (path, age) in [(&first, 20), (&second, 10)] {
+            let file = File::options().write(true).open(path).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+        // Other prefixes and the pointer itself are not session files
+        std::fs::write(dir.join("log_extra.parquet"), b"").unwrap();
+        write_latest_pointer(dir, "log", Path::new(&second)).unwrap();
+
+        let files = session_files(dir, "log").unwrap();
+        assert_eq!(files, vec![PathBuf::from(&second), PathBuf::from(&first)]);
+        let (run_id, path) = resume_run_id(dir, "log", hour).unwrap().unwrap();
+        assert_eq!(
+            (run_id.as_str(), path),
+            ("second-run", PathBuf::from(&second))
+        );
+
+        // A crash leaves the newest file without a footer
+        let bytes = std::fs::read(&second).unwrap();
+        std::fs::write(&second, &bytes[..bytes.len() / 2]).unwrap();
+        let (run_id, _) = resume_run_id(dir, "log", hour).unwrap().unwrap();
+        assert_eq!(run_id, "first-run");
+
+        // A session last written two hours ago is not continued
+        let earlier = std::time::SystemTime::now() - 2 * hour;
+        for path in [&first, &second] {
+            let file = File::options().write(true).open(path).unwrap();
+            file.set_modified(earlier).unwrap();
+        }
+        assert!(resume_run_id(dir, "log", hour).unwrap().is_none());
+        assert!(resume_run_id(dir, "log", 3 * hour).unwrap().is_some());
+
+        let pointer = std::fs::read_to_string(latest_pointer_path(dir, "log")).unwrap();
+        let name = Path::new(&second).file_name().unwrap().to_str().unwrap();
+        assert_eq!(pointer, format!("{}\n", name));
+    }
+}
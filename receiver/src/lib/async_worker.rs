@@ -1,32 +1,191 @@
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
+use serialport::SerialPort;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::thread;
-use std::time::Duration as StdDuration;
+use std::time::{Duration as StdDuration, Instant};
 
-use super::serial::{open_serial_port, parse_sensor_data, read_serial_data};
-use super::ParquetWriter;
-use super::SensorData;
+use super::append::write_latest_pointer;
+use super::burst::BurstDetector;
+use super::calibration::Calibration;
+use super::clock_check::ClockCheck;
+use super::control::PauseControl;
+use super::csv_writer::CsvWriter;
+use super::decimate::Decimator;
+use super::error::ReceiverError;
+use super::filter::Filter;
+use super::layout::LineLayout;
+use super::line_status::{LineStatus, LineStatusLog};
+use super::monotonic::{CounterWidth, MonotonicCheck, MonotonicPolicy, TickExtender};
+use super::nonfinite::NonFiniteHandler;
+use super::on_change::ChangeFilter;
+use super::parse_pool::{LineParser, ParsePool};
+use super::preview::{PreviewSeries, DEFAULT_PREVIEW_SIZE};
+use super::range_check::{RangeCheck, RangePolicy};
+use super::rate_detector::{RateChangeDetector, RateObservation};
+use super::raw_capture::RawCapture;
+use super::rotation_hook::RotationHook;
+use super::serial::{
+    is_fatal_port_error, monotonic_timestamp, open_serial_port, read_chunk, split_lines,
+    unpack_timestamp_status, AutoDetectRead, AutoDetectState, BannerWait, BinaryFrameBuffer,
+    ByteOrderCheck, SerialFraming, TimestampSynthesizer, DEFAULT_READ_BUFFER_SIZE,
+};
+use super::sink::DataSink;
+use super::stats::{CaptureSummary, GapTracker, InFlightGauge, RateWindow, ReadCounters};
+use super::status::{eprint_status, print_status, Health};
+use super::timestamp::{DeviceClock, ReadInterpolator};
+use super::watchdog::Watchdog;
+use super::{DataFormat, SensorData};
+
+/// Condition that requested a new output file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RotationTrigger {
+    /// The `split_minutes` interval elapsed
+    Time,
+    /// A sustained sample-rate change was confirmed
+    RateChange,
+    /// The current file reached `max_file_bytes`
+    Size,
+}
+
+impl RotationTrigger {
+    fn description(&self) -> &'static str {
+        match self {
+            RotationTrigger::Time => "time interval",
+            RotationTrigger::RateChange => "sample rate change",
+            RotationTrigger::Size => "file size",
+        }
+    }
+}
+
+/// Batches that may be dropped before the writer thread gives up
+pub const DEFAULT_MAX_DROPPED_BATCHES: usize = 10;
+
+/// Longest wait between attempts to reopen a lost serial port
+pub const DEFAULT_RECONNECT_MAX_DELAY: StdDuration = StdDuration::from_secs(30);
+
+// Wait before the first attempt to reopen a lost port; doubles per failure
+const RECONNECT_INITIAL_DELAY: StdDuration = StdDuration::from_millis(250);
+
+/// Wait before the given (zero-based) attempt to reopen a lost port
+fn reconnect_delay(attempt: u32, max_delay: StdDuration) -> StdDuration {
+    RECONNECT_INITIAL_DELAY
+        .saturating_mul(1 << attempt.min(16))
+        .min(max_delay)
+}
+
+/// Sleeps for `duration` unless `running` is cleared first
+///
+/// # Returns
+/// Whether the process is still running
+fn sleep_while_running(duration: StdDuration, running: &AtomicBool) -> bool {
+    let deadline = Instant::now() + duration;
+    while running.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        thread::sleep(remaining.min(StdDuration::from_millis(50)));
+    }
+    false
+}
+
+/// Source of the current time for rotation decisions
+pub type Clock = Box<dyn Fn() -> DateTime<Utc> + Send>;
+
+/// Source of the free space, in bytes, on the file system holding a directory
+pub type SpaceQuery = Box<dyn Fn(&Path) -> std::io::Result<u64> + Send>;
+
+/// Start of the rotation interval containing `now`
+///
+/// Intervals are counted from the Unix epoch, so an interval that divides a
+/// day (e.g. 15 or 60 minutes) ends on a whole quarter-hour or hour.
+fn interval_start(now: DateTime<Utc>, split_minutes: u32) -> DateTime<Utc> {
+    let interval_ms = split_minutes as i64 * 60_000;
+    let now_ms = now.timestamp_millis();
+    DateTime::from_timestamp_millis(now_ms - now_ms.rem_euclid(interval_ms)).unwrap_or(now)
+}
+
+// Points `<prefix>.latest` at an output file, reporting a failure
+fn point_latest_at(output_dir: &str, prefix: &str, path: &str) {
+    if let Err(e) = write_latest_pointer(Path::new(output_dir), prefix, Path::new(path)) {
+        eprint_status(Health::Degraded, &format!("{:#}", e));
+    }
+}
 
 /// Worker for handling file writing in a separate thread
 ///
 /// This struct is responsible for writing sensor data to Parquet files,
 /// handling file rotation, and managing the background file writing operations.
 pub struct FileWriterWorker {
-    writer: ParquetWriter,
+    writer: Box<dyn DataSink>,
     split_minutes: u32,
+    // Size at which the current file is rotated (0 = no size limit)
+    max_file_bytes: u64,
     last_rotation: DateTime<Utc>,
+    // Rotate on wall-clock multiples of split_minutes instead of relative to start
+    align_rotation: bool,
+    clock: Clock,
     output_dir: String,
     prefix: String,
+    pause: PauseControl,
+    rate_detector: Option<RateChangeDetector>,
+    counter_hz: Option<f64>,
+    // Sample interval last detected, stored in every file closed from then on
+    sample_interval: Option<f64>,
+    // Records held back while a sample-rate change is being confirmed
+    pending: Vec<SensorData>,
+    calibration: Option<Calibration>,
+    nonfinite: Option<NonFiniteHandler>,
+    on_change: Option<ChangeFilter>,
+    filter: Option<Filter>,
+    decimator: Option<Decimator>,
+    burst: Option<BurstDetector>,
+    // Deletes idle files or runs the post-rotate command as files close
+    rotation_hook: Option<RotationHook>,
+    summary: CaptureSummary,
+    in_flight: Option<InFlightGauge>,
+    // Interval of the stats readout, the shortest one requested
+    stats_interval: Option<StdDuration>,
+    // Start of the current throughput period and records received since
+    stats_period: (Instant, u64),
+    // Rolling record rate, shown in the stats readout
+    rate: Option<RateWindow>,
+    // Largest gap between received batches, shown in the stats readout
+    gaps: Option<GapTracker>,
+    csv: Option<CsvWriter>,
+    // Records written to the current output file since the last rotation
+    records_in_file: usize,
+    flush_interval: Option<StdDuration>,
+    last_flush: Instant,
+    // Records taken from the channel per wakeup
+    coalesce: usize,
+    // Batches dropped because they could not be built, and the tolerated number
+    dropped_batches: usize,
+    max_dropped_batches: usize,
+    monotonic: Option<MonotonicCheck>,
+    clock_check: Option<ClockCheck>,
+    preview: Option<(PreviewSeries, PathBuf)>,
+    line_status: Option<LineStatusLog>,
+    // Free space required before rotating into a new file
+    min_free_bytes: Option<u64>,
+    space_query: SpaceQuery,
+    // Set once a rotation found too little free space; writing then stops
+    out_of_space: bool,
+    // Keep `<prefix>.latest` naming the file being written
+    latest_pointer: bool,
+    // Extends the counter of records the reader has not stamped
+    ticks: TickExtender,
 }
 
 impl FileWriterWorker {
     /// Creates a new file writer worker
     ///
     /// # Arguments
-    /// * `writer` - The configured Parquet writer
+    /// * `writer` - The configured output sink
     /// * `split_minutes` - Interval in minutes for file rotation (0 = no splitting)
     /// * `output_dir` - Directory to store Parquet files
     /// * `prefix` - Filename prefix for Parquet files
@@ -34,7 +193,7 @@ impl FileWriterWorker {
     /// # Returns
     /// A new FileWriterWorker instance
     pub fn new(
-        writer: ParquetWriter,
+        writer: Box<dyn DataSink>,
         split_minutes: u32,
         output_dir: String,
         prefix: String,
@@ -42,10 +201,625 @@ impl FileWriterWorker {
         FileWriterWorker {
             writer,
             split_minutes,
+            max_file_bytes: 0,
             last_rotation: Utc::now(),
+            align_rotation: false,
+            clock: Box::new(Utc::now),
             output_dir,
             prefix,
+            pause: PauseControl::default(),
+            rate_detector: None,
+            counter_hz: None,
+            sample_interval: None,
+            pending: Vec::new(),
+            calibration: None,
+            nonfinite: None,
+            on_change: None,
+            filter: None,
+            decimator: None,
+            burst: None,
+            rotation_hook: None,
+            summary: CaptureSummary::new(),
+            in_flight: None,
+            gaps: None,
+            stats_interval: None,
+            stats_period: (Instant::now(), 0),
+            rate: None,
+            csv: None,
+            records_in_file: 0,
+            flush_interval: None,
+            last_flush: Instant::now(),
+            coalesce: 1,
+            dropped_batches: 0,
+            max_dropped_batches: DEFAULT_MAX_DROPPED_BATCHES,
+            monotonic: None,
+            clock_check: None,
+            preview: None,
+            line_status: None,
+            min_free_bytes: None,
+            space_query: Box::new(|path| fs2::available_space(path)),
+            out_of_space: false,
+            latest_pointer: false,
+            ticks: TickExtender::new(),
+        }
+    }
+
+    /// Sets how many failed record batches are dropped before the writer stops
+    ///
+    /// A batch that cannot be built is logged and discarded so the capture
+    /// continues; once more than `max` batches were lost, the error ends the
+    /// writer thread instead.
+    pub fn with_max_dropped_batches(mut self, max: usize) -> Self {
+        self.max_dropped_batches = max;
+        self
+    }
+
+    /// Requires sensor timestamps to be strictly increasing
+    ///
+    /// Every received record is checked, before filtering. Depending on the
+    /// policy a violation stops the writer with an error or is counted and
+    /// reported at shutdown.
+    pub fn with_monotonic_check(mut self, policy: MonotonicPolicy) -> Self {
+        self.monotonic = Some(MonotonicCheck::new(policy));
+        self
+    }
+
+    /// Checks each record's sensor counter against the host clock
+    ///
+    /// Records failing the check are flagged or dropped per the check's
+    /// policy and counted at shutdown. See [`ClockCheck`].
+    pub fn with_clock_check(mut self, check: ClockCheck) -> Self {
+        self.clock_check = Some(check);
+        self
+    }
+
+    /// Plots the accel magnitude of the capture to a PNG file at shutdown
+    ///
+    /// Records are downsampled into a bounded series while capturing, so
+    /// long captures do not grow memory. See [`PreviewSeries`].
+    pub fn with_preview_png(mut self, path: PathBuf) -> Self {
+        self.preview = Some((PreviewSeries::default(), path));
+        self
+    }
+
+    /// Stores the serial line status samples in each file's metadata
+    ///
+    /// All samples recorded so far are written under the `line_status` key
+    /// when a file is rotated or closed. See [`LineStatusLog`].
+    pub fn with_line_status_log(mut self, log: LineStatusLog) -> Self {
+        self.line_status = Some(log);
+        self
+    }
+
+    /// Adds the line status samples to the current file's metadata
+    fn record_line_status(&mut self) {
+        let Some(log) = &self.line_status else {
+            return;
+        };
+        let json = log.is_empty().and_then(|empty| {
+            if empty {
+                Ok(None)
+            } else {
+                log.to_json().map(Some)
+            }
+        });
+        match json {
+            Ok(Some(json)) => self.writer.append_metadata("line_status", json),
+            Ok(None) => {}
+            Err(e) => eprint_status(
+                Health::Degraded,
+                &format!("Line status not recorded: {}", e),
+            ),
+        }
+    }
+
+    /// Aligns time-based rotation to wall-clock boundaries
+    ///
+    /// The first file ends at the next multiple of `split_minutes` (counted
+    /// from midnight UTC for intervals that divide a day) instead of one full
+    /// interval after startup, and every later file stays on that grid.
+    pub fn with_aligned_rotation(mut self) -> Self {
+        self.align_rotation = true;
+        self.last_rotation = self.rotation_start();
+        self
+    }
+
+    /// Stops writing instead of rotating when the output directory's file
+    /// system has fewer than `bytes` bytes free
+    ///
+    /// The current file is closed normally and the running flag is cleared,
+    /// so the capture ends cleanly before the disk fills up.
+    pub fn with_min_free_bytes(mut self, bytes: u64) -> Self {
+        self.min_free_bytes = Some(bytes);
+        self
+    }
+
+    /// Replaces the free-space query used by [`with_min_free_bytes`](Self::with_min_free_bytes)
+    pub fn with_space_query(mut self, query: SpaceQuery) -> Self {
+        self.space_query = query;
+        self
+    }
+
+    /// Rotates the file once it has grown to `bytes` bytes (0 = no size limit)
+    ///
+    /// Works alongside time-based rotation; whichever limit is reached first
+    /// starts the new file. The size is checked between batches of records,
+    /// so files end slightly above the limit.
+    pub fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    /// Replaces the clock used for rotation decisions
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self.last_rotation = self.rotation_start();
+        self
+    }
+
+    /// Start time of a file opened now, for measuring the rotation interval
+    fn rotation_start(&self) -> DateTime<Utc> {
+        let now = (self.clock)();
+        if self.align_rotation && self.split_minutes > 0 {
+            interval_start(now, self.split_minutes)
+        } else {
+            now
+        }
+    }
+
+    /// Sets the pause control used to hold back writing
+    ///
+    /// Records received while paused are discarded.
+    pub fn with_pause_control(mut self, pause: PauseControl) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Sets the device calibration applied to every record before writing
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Substitutes or drops NaN and infinite float values per field
+    ///
+    /// Applied to calibrated values, before the filter. See [`NonFiniteHandler`].
+    pub fn with_nonfinite_handler(mut self, handler: NonFiniteHandler) -> Self {
+        self.nonfinite = Some(handler);
+        self
+    }
+
+    /// Sets a predicate that records must satisfy to be written
+    ///
+    /// The filter sees calibrated values; records that do not match are dropped.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Only writes records in which an axis changed noticeably since the last one written
+    ///
+    /// Applied after the filter, so records it drops never count as kept.
+    /// See [`ChangeFilter`].
+    pub fn with_change_filter(mut self, on_change: ChangeFilter) -> Self {
+        self.on_change = Some(on_change);
+        self
+    }
+
+    /// Writes only the records kept by a decimator
+    ///
+    /// Decimation applies after filtering and rate-change detection, right
+    /// before records reach the output.
+    pub fn with_decimator(mut self, decimator: Decimator) -> Self {
+        self.decimator = Some(decimator);
+        self
+    }
+
+    /// Tags records around acceleration spikes with a burst ID
+    ///
+    /// Detection sees calibrated, filtered records; the records before a
+    /// spike are held back until it is clear whether they lead into one.
+    pub fn with_burst_detector(mut self, detector: BurstDetector) -> Self {
+        self.burst = Some(detector);
+        self
+    }
+
+    /// Releases each record from a gauge once it has been processed
+    ///
+    /// The gauge's count is shown as the backlog in the periodic readout.
+    /// See [`InFlightGauge`].
+    pub fn with_in_flight_gauge(mut self, gauge: InFlightGauge) -> Self {
+        self.in_flight = Some(gauge);
+        self
+    }
+
+    /// Prints a throughput readout once per interval
+    ///
+    /// The readout gives the record rate since the last readout or file
+    /// rotation, whichever was later, the channel backlog and the output
+    /// file. The backlog is only known with an in-flight gauge (see
+    /// [`with_in_flight_gauge`](Self::with_in_flight_gauge)). A zero interval
+    /// does not enable the readout.
+    pub fn with_stats_interval(mut self, interval: StdDuration) -> Self {
+        self.report_every(interval);
+        self
+    }
+
+    /// Adds the record rate smoothed over a rolling window to the stats readout
+    ///
+    /// The readout also gives the average rate since the first record, and is
+    /// printed at least once per window. See [`RateWindow`].
+    pub fn with_rate_window(mut self, window: StdDuration) -> Self {
+        self.rate = Some(RateWindow::new(window));
+        self.report_every(window);
+        self
+    }
+
+    /// Adds the largest gap between received batches to the stats readout
+    ///
+    /// The readout is printed at least once per interval; the largest gap of
+    /// the whole capture is printed at shutdown. See [`GapTracker`].
+    pub fn with_gap_report(mut self, interval: StdDuration) -> Self {
+        self.gaps = Some(GapTracker::new());
+        self.report_every(interval);
+        self
+    }
+
+    /// Shortens the stats readout interval to `interval`, ignoring zero
+    fn report_every(&mut self, interval: StdDuration) {
+        if !interval.is_zero() {
+            self.stats_interval = Some(self.stats_interval.map_or(interval, |i| i.min(interval)));
+        }
+    }
+
+    /// Deletes idle files or runs a command on each file once it is closed
+    ///
+    /// The hook sees every record written, and is called at each rotation
+    /// and at shutdown with the closed file and its CSV companion.
+    pub fn with_rotation_hook(mut self, hook: RotationHook) -> Self {
+        self.rotation_hook = Some(hook);
+        self
+    }
+
+    /// Sets the width of the sensor counter (32 bits by default)
+    ///
+    /// The checks work on the counter extended across rollovers. Records the
+    /// reader already stamped keep its count; others are extended here.
+    pub fn with_counter_width(mut self, width: CounterWidth) -> Self {
+        self.ticks = TickExtender::new().with_width(width);
+        self
+    }
+
+    /// Keeps a `<prefix>.latest` file in the output directory naming the file
+    /// being written, updated at every rotation
+    ///
+    /// At shutdown it is pointed at the last file's final name, which differs
+    /// from the one it was written under with [`FileNaming::FirstRecord`](crate::FileNaming::FirstRecord).
+    pub fn with_latest_pointer(mut self) -> Self {
+        self.latest_pointer = true;
+        self.update_latest_pointer();
+        self
+    }
+
+    // Points `<prefix>.latest` at the current output file
+    fn update_latest_pointer(&self) {
+        if let Some(path) = self.writer.output_path().filter(|_| self.latest_pointer) {
+            point_latest_at(&self.output_dir, &self.prefix, path);
+        }
+    }
+
+    /// Also writes every record to a CSV file, rotated together with the Parquet file
+    pub fn with_csv_writer(mut self, csv: CsvWriter) -> Self {
+        self.csv = Some(csv);
+        self
+    }
+
+    /// Writes buffered records at least this often, even if the buffer is not full
+    pub fn with_flush_interval(mut self, interval: StdDuration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+
+    /// Sets how many queued records are processed per wakeup of the writer thread
+    ///
+    /// Rotation and flush deadlines are checked between groups, so larger
+    /// values reduce per-record overhead at the cost of later checks.
+    pub fn with_coalescing(mut self, records: usize) -> Self {
+        self.coalesce = records.max(1);
+        self
+    }
+
+    /// Enables file rotation when the sensor sample rate changes
+    ///
+    /// A sustained change of the inter-sample interval starts a new file at the
+    /// first record sampled at the new rate. The detected interval (and rate,
+    /// if `counter_hz` is known) is stored in the metadata of each file
+    /// closed once it is known, including files started by other rotations.
+    ///
+    /// # Arguments
+    /// * `counter_hz` - Sensor counter rate used to report the rate in Hz
+    pub fn with_rate_change_rotation(mut self, counter_hz: Option<f64>) -> Self {
+        self.rate_detector = Some(RateChangeDetector::new());
+        self.counter_hz = counter_hz;
+        self
+    }
+
+    /// Checks, calibrates and filters a record and passes it on to be written
+    fn write_record(&mut self, mut data: SensorData) -> Result<()> {
+        if data.extended_timestamp.is_none() {
+            data = self.ticks.stamp(data);
+        }
+        let counter = data.extended_timestamp.unwrap_or_default();
+        if let Some(check) = self.monotonic.as_mut() {
+            check.observe(counter)?;
+        }
+        if let Some(check) = self.clock_check.as_mut() {
+            if !check.observe(&mut data) {
+                return Ok(());
+            }
+        }
+        if let Some(calibration) = &self.calibration {
+            calibration.apply(&mut data);
+        }
+        if let Some(handler) = self.nonfinite.as_mut() {
+            match handler.apply(data) {
+                Some(handled) => data = handled,
+                None => return Ok(()),
+            }
         }
+        if let Some(filter) = &self.filter {
+            if !filter.matches(&data) {
+                return Ok(());
+            }
+        }
+        if let Some(on_change) = self.on_change.as_mut() {
+            if !on_change.keep(&data) {
+                return Ok(());
+            }
+        }
+        match self.burst.as_mut() {
+            Some(detector) => {
+                for data in detector.push(data) {
+                    self.route_record(data)?;
+                }
+                Ok(())
+            }
+            None => self.route_record(data),
+        }
+    }
+
+    /// Routes a checked record through sample-rate change detection to the writer
+    fn route_record(&mut self, data: SensorData) -> Result<()> {
+        self.summary.update(&data);
+        if let Some((series, _)) = self.preview.as_mut() {
+            series.push(&data);
+        }
+
+        let Some(detector) = self.rate_detector.as_mut() else {
+            return self.emit(data);
+        };
+
+        match detector.observe(data.extended_timestamp.unwrap_or_default()) {
+            RateObservation::Steady => self.emit(data),
+            RateObservation::Established(interval) => {
+                self.sample_interval = Some(interval);
+                self.emit(data)
+            }
+            RateObservation::Candidate => {
+                self.pending.push(data);
+                Ok(())
+            }
+            RateObservation::Reverted => {
+                self.pending.push(data);
+                self.drain_pending()
+            }
+            RateObservation::Changed(interval) => {
+                // The file closed here keeps the previous rate
+                self.evaluate_rotation(Some(RotationTrigger::RateChange))?;
+                self.sample_interval = Some(interval);
+                self.pending.push(data);
+                self.drain_pending()
+            }
+        }
+    }
+
+    /// Writes records held back during rate-change confirmation
+    fn drain_pending(&mut self) -> Result<()> {
+        for data in std::mem::take(&mut self.pending) {
+            self.emit(data)?;
+        }
+        Ok(())
+    }
+
+    /// Hands a record to the output writers
+    fn emit(&mut self, data: SensorData) -> Result<()> {
+        if let Some(decimator) = self.decimator.as_mut() {
+            if !decimator.keep(&data) {
+                return Ok(());
+            }
+        }
+        if let Some(hook) = self.rotation_hook.as_mut() {
+            hook.observe(&data);
+        }
+        if let Some(csv) = self.csv.as_mut() {
+            csv.add_data(&data)?;
+        }
+        self.records_in_file += 1;
+        let result = self.writer.add_data(data);
+        self.recover(result)
+    }
+
+    /// Drops a batch the writer failed to build, up to the configured limit
+    ///
+    /// The sink discards such a batch itself and reports it as a
+    /// [`ReceiverError::ParquetError`]; other errors are passed on unchanged.
+    fn recover<T: Default>(&mut self, result: Result<T>) -> Result<T> {
+        let e = match result {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if !matches!(e.downcast_ref(), Some(ReceiverError::ParquetError(_)))
+            || self.dropped_batches >= self.max_dropped_batches
+        {
+            return Err(e);
+        }
+        self.dropped_batches += 1;
+        let message = format!(
+            "Error writing batch ({} of at most {} dropped): {:#}",
+            self.dropped_batches, self.max_dropped_batches, e
+        );
+        eprint_status(Health::Failing, &message);
+        Ok(T::default())
+    }
+
+    /// Decides whether to rotate and starts new output files if so
+    ///
+    /// All rotation triggers go through this single decision so that at most
+    /// one rotation happens per evaluation. A file that has not received any
+    /// records yet is never rotated away; a due time interval restarts instead.
+    ///
+    /// # Arguments
+    /// * `event` - Trigger raised by the record just processed, if any
+    fn evaluate_rotation(&mut self, event: Option<RotationTrigger>) -> Result<()> {
+        let Some(trigger) = event
+            .or_else(|| self.should_rotate_file().then_some(RotationTrigger::Time))
+            .or_else(|| self.file_size_reached().then_some(RotationTrigger::Size))
+        else {
+            return Ok(());
+        };
+
+        if self.records_in_file == 0 {
+            self.last_rotation = self.rotation_start();
+            return Ok(());
+        }
+        if self.out_of_space || !self.has_free_space() {
+            self.out_of_space = true;
+            return Ok(());
+        }
+
+        println!("Rotating file based on {}", trigger.description());
+        self.record_line_status();
+        self.record_sample_rate();
+        let result = self.writer.rotate_file(&self.output_dir, &self.prefix);
+        let mut closed = self.recover(result)?;
+        if let Some(csv) = self.csv.as_mut() {
+            closed.push(csv.rotate_file(&self.output_dir, &self.prefix)?);
+        }
+        if let Some(hook) = self.rotation_hook.as_mut() {
+            hook.file_closed(&closed);
+        }
+        self.update_latest_pointer();
+        self.last_rotation = self.rotation_start();
+        self.records_in_file = 0;
+        // The next readout covers the new file only
+        self.stats_period = (Instant::now(), 0);
+        Ok(())
+    }
+
+    /// Checks that the output directory has the minimum free space for a new file
+    fn has_free_space(&self) -> bool {
+        let Some(min_free_bytes) = self.min_free_bytes else {
+            return true;
+        };
+        match (self.space_query)(Path::new(&self.output_dir)) {
+            Ok(free) if free < min_free_bytes => {
+                let message = format!(
+                    "Only {} bytes free in {} (minimum {}), stopping capture",
+                    free, self.output_dir, min_free_bytes
+                );
+                eprint_status(Health::Failing, &message);
+                false
+            }
+            Ok(_) => true,
+            Err(e) => {
+                // Keep capturing; a failed query says nothing about the disk filling up
+                let message = format!("Could not query free space in {}: {}", self.output_dir, e);
+                eprint_status(Health::Degraded, &message);
+                true
+            }
+        }
+    }
+
+    /// Stores the detected sample interval in the current file's metadata
+    fn record_sample_rate(&mut self) {
+        let Some(interval) = self.sample_interval else {
+            return;
+        };
+        self.writer
+            .append_metadata("sample_interval_counts", format!("{}", interval));
+        if let Some(counter_hz) = self.counter_hz {
+            if interval > 0.0 {
+                self.writer
+                    .append_metadata("sample_rate_hz", format!("{}", counter_hz / interval));
+            }
+        }
+    }
+
+    /// Flushes the writer if the flush interval has elapsed
+    fn flush_if_due(&mut self) -> Result<()> {
+        let Some(interval) = self.flush_interval else {
+            return Ok(());
+        };
+        if self.last_flush.elapsed() >= interval {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Throughput readout, if the stats interval has elapsed at `now`
+    ///
+    /// Starts a new period when a readout is returned.
+    fn stats_report(&mut self, now: Instant) -> Option<String> {
+        let interval = self.stats_interval?;
+        let (start, records) = self.stats_period;
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < interval {
+            return None;
+        }
+        self.stats_period = (now, 0);
+
+        let mut message = format!(
+            "Throughput: {:.1} records/s",
+            records as f64 / elapsed.as_secs_f64()
+        );
+        if let Some(rate) = &self.rate {
+            message.push_str(&format!(
+                ", {:.1} records/s over the last {} s, {:.1} records/s overall",
+                rate.windowed_rate(now),
+                rate.window().as_secs_f64(),
+                rate.cumulative_rate(now)
+            ));
+        }
+        if let Some(gaps) = &mut self.gaps {
+            let gap_ms = gaps.take_period_max().as_secs_f64() * 1e3;
+            message.push_str(&format!(", largest gap {:.1} ms", gap_ms));
+        }
+        if let Some(gauge) = &self.in_flight {
+            message.push_str(&format!(", backlog {} records", gauge.current()));
+        }
+        if let Some(path) = self.writer.output_path() {
+            message.push_str(&format!(", writing {}", path));
+        }
+        Some(message)
+    }
+
+    /// Handles one record taken from the channel
+    fn receive(&mut self, data: SensorData) -> Result<()> {
+        self.stats_period.1 += 1;
+        if let Some(rate) = &mut self.rate {
+            rate.record(Instant::now());
+        }
+        let result = if self.pause.poll() {
+            // Discard data until writing is resumed
+            Ok(())
+        } else {
+            self.write_record(data)
+        };
+        if let Some(gauge) = &self.in_flight {
+            gauge.leave();
+        }
+        result
     }
 
     /// Check if it's time to rotate the file based on split_minutes
@@ -54,15 +828,22 @@ impl FileWriterWorker {
             return false; // Never rotate if split_minutes is 0
         }
 
-        let now = Utc::now();
+        let now = (self.clock)();
         let rotation_interval = Duration::minutes(self.split_minutes as i64);
         now - self.last_rotation >= rotation_interval
     }
 
+    /// Check if the current file has reached max_file_bytes
+    fn file_size_reached(&self) -> bool {
+        self.max_file_bytes > 0 && self.writer.bytes_written() >= self.max_file_bytes
+    }
+
     /// Process incoming sensor data and write it to a Parquet file
     ///
-    /// Runs in a loop until signaled to stop. Handles file rotation based on time
-    /// intervals and writes incoming data to Parquet files.
+    /// Writes incoming data to Parquet files until the producer disconnects,
+    /// rotating files as due. The stop signal ends the producer, whose last
+    /// records are still written. If the disk runs out of space, the writer
+    /// stops the producer itself and discards whatever it still sends.
     ///
     /// # Arguments
     /// * `rx` - Receiver channel for incoming sensor data
@@ -77,36 +858,157 @@ impl FileWriterWorker {
     ) -> Result<()> {
         println!("File writer thread started");
 
-        // Process incoming data until the running flag is set to false
-        while running.load(Ordering::SeqCst) {
+        // Close the files even if writing stopped with an error, keeping what
+        // was written so far. The files are only closed once the producer has
+        // dropped its sender, so records it sends while shutting down, such
+        // as the final batch, are always written.
+        let result = self.receive_loop(&rx, &running);
+        if result.is_ok() {
+            self.discard_remaining(&rx);
+        }
+        let finished = self.finish();
+        result.and(finished)
+    }
+
+    /// Writes incoming records until the producer disconnects
+    ///
+    /// The running flag does not end the loop: the producer stops on it and
+    /// drops its sender once its last records are sent. The writer only
+    /// clears the flag itself, to stop the producer when it runs out of space.
+    fn receive_loop(&mut self, rx: &Receiver<SensorData>, running: &AtomicBool) -> Result<()> {
+        loop {
             // Check if we need to rotate the file based on time
-            if self.should_rotate_file() {
-                println!("Rotating file based on time interval");
-                self.writer.rotate_file(&self.output_dir, &self.prefix)?;
-                self.last_rotation = Utc::now();
+            self.evaluate_rotation(None)?;
+            if self.out_of_space {
+                // Stop the reader too; the current file is closed by finish()
+                running.store(false, Ordering::SeqCst);
+                break;
+            }
+            self.flush_if_due()?;
+            if let Some(message) = self.stats_report(Instant::now()) {
+                print_status(Health::Healthy, &message);
             }
 
             // Try to receive data with a timeout
             match rx.recv_timeout(StdDuration::from_millis(100)) {
                 Ok(data) => {
-                    // Add the data to the writer
-                    self.writer.add_data(data)?;
+                    if let Some(gaps) = &mut self.gaps {
+                        gaps.observe(Instant::now());
+                    }
+                    // Add the data, and whatever else is already queued, to the writer
+                    self.receive(data)?;
+                    for data in rx.try_iter().take(self.coalesce - 1) {
+                        self.receive(data)?;
+                    }
                 }
                 Err(RecvTimeoutError::Timeout) => {
-                    // No data received within timeout, check if we should continue
+                    // No data received within timeout, run the periodic checks
                     continue;
                 }
                 Err(RecvTimeoutError::Disconnected) => {
-                    // Sender has been dropped, exit the loop
-                    println!("Data producer disconnected, stopping file writer");
+                    // Sender has been dropped, nothing more will arrive
+                    println!("Data producer finished, stopping file writer");
                     break;
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Discards the records still sent after running out of space, until the producer disconnects
+    ///
+    /// The producer may still be releasing held-back records. There is no
+    /// room to write them, so they are counted instead; waiting for the
+    /// disconnect keeps a producer blocked on a full channel from hanging.
+    fn discard_remaining(&self, rx: &Receiver<SensorData>) {
+        let discarded = rx.iter().count();
+        if discarded > 0 {
+            let message = format!("Records discarded for lack of space: {}", discarded);
+            eprint_status(Health::Failing, &message);
+        }
+    }
 
+    /// Flushes held-back records, closes the writers and prints the summary
+    fn finish(mut self) -> Result<()> {
         // Ensure all data is flushed before exiting
-        println!("Closing Parquet writer in file writer thread");
-        self.writer.close()?;
+        if let Some(detector) = self.burst.as_mut() {
+            let bursts = detector.bursts();
+            for data in detector.finish() {
+                self.route_record(data)?;
+            }
+            println!("Bursts detected: {}", bursts);
+        }
+        self.drain_pending()?;
+        println!("Closing output writer in file writer thread");
+        self.record_line_status();
+        self.record_sample_rate();
+        let run_id = self.writer.run_id().map(str::to_string);
+        let mut closed = self.writer.close()?;
+        if let Some(csv) = self.csv.take() {
+            closed.push(csv.close()?);
+        }
+        let mut preview = None;
+        if let Some((series, path)) = self.preview.take() {
+            // The capture itself is complete; a missing preview only warrants a warning
+            match series.write_png(&path, DEFAULT_PREVIEW_SIZE) {
+                Ok(()) => {
+                    println!("Wrote preview {}", path.display());
+                    preview = Some(path.to_string_lossy().into_owned());
+                }
+                Err(e) => eprint_status(Health::Degraded, &format!("Preview not written: {:#}", e)),
+            }
+        }
+        if let Some(mut hook) = self.rotation_hook.take() {
+            hook.file_closed(&closed);
+            // The preview covers the whole capture and is mirrored alongside
+            hook.mirror_files(preview.as_slice());
+            hook.finish();
+        }
+        // The last file may have been renamed on close; an idle one is gone
+        if let Some(path) = closed
+            .first()
+            .filter(|path| self.latest_pointer && Path::new(path).exists())
+        {
+            point_latest_at(&self.output_dir, &self.prefix, path);
+        }
+        if let Some(run_id) = run_id {
+            println!("Run ID: {}", run_id);
+        }
+        print_status(Health::Healthy, &self.summary.report());
+        if self.dropped_batches > 0 {
+            let message = format!("Dropped batches: {}", self.dropped_batches);
+            print_status(Health::Degraded, &message);
+        }
+        if let Some(gaps) = &self.gaps {
+            println!(
+                "Largest gap between batches: {:.1} ms",
+                gaps.max_gap().as_secs_f64() * 1e3
+            );
+        }
+        if let Some(check) = self.monotonic.as_ref().filter(|c| c.violations() > 0) {
+            let message = format!("Non-monotonic timestamps: {}", check.violations());
+            print_status(Health::Degraded, &message);
+        }
+        if let Some(check) = self.clock_check.as_ref().filter(|c| c.anomalies() > 0) {
+            let message = format!("Records with disagreeing clocks: {}", check.anomalies());
+            print_status(Health::Degraded, &message);
+        }
+        if let Some(handler) = self
+            .nonfinite
+            .as_ref()
+            .filter(|h| h.replaced() + h.dropped() > 0)
+        {
+            let message = format!(
+                "Non-finite values replaced: {}, records dropped: {}",
+                handler.replaced(),
+                handler.dropped()
+            );
+            print_status(Health::Degraded, &message);
+        }
+        if let Some(on_change) = &self.on_change {
+            let message = format!("Records unchanged and not written: {}", on_change.skipped());
+            print_status(Health::Healthy, &message);
+        }
         println!("File writer thread shutting down");
         Ok(())
     }
@@ -120,6 +1022,38 @@ impl FileWriterWorker {
 pub struct SerialReaderWorker {
     port_name: String,
     baud_rate: u32,
+    framing: SerialFraming,
+    watchdog: Option<Watchdog>,
+    byte_order: ByteOrderCheck,
+    // Counter source for firmware that sends no timestamp field
+    synthesizer: Option<TimestampSynthesizer>,
+    ticks: TickExtender,
+    // Host timestamp reconstruction replacing the parse time, if any
+    interpolator: Option<ReadInterpolator>,
+    device_clock: Option<DeviceClock>,
+    raw_capture: Option<RawCapture>,
+    packed_timestamp: bool,
+    layout: Option<LineLayout>,
+    banner: Option<BannerWait>,
+    data_format: DataFormat,
+    lenient_nulls: bool,
+    // Partial binary frames carried over between reads
+    frames: BinaryFrameBuffer,
+    // Bytes requested from the port per read
+    read_buf: Vec<u8>,
+    // Format detection of `DataFormat::Auto`, restarted for every connection
+    auto_detect: AutoDetectState,
+    detected_format: Arc<OnceLock<DataFormat>>,
+    reconnect_max_delay: StdDuration,
+    // Pause after a read that produced no records
+    idle_sleep: Option<StdDuration>,
+    line_status: Option<LineStatusLog>,
+    // Records failing it are dropped as parse errors
+    range_check: Option<RangeCheck>,
+    counters: ReadCounters,
+    parse_threads: usize,
+    // Started by the read loop when more than one parse thread is configured
+    parse_pool: Option<ParsePool>,
 }
 
 impl SerialReaderWorker {
@@ -135,56 +1069,469 @@ impl SerialReaderWorker {
         SerialReaderWorker {
             port_name,
             baud_rate,
+            framing: SerialFraming::default(),
+            watchdog: None,
+            byte_order: ByteOrderCheck::new(),
+            synthesizer: None,
+            ticks: TickExtender::new(),
+            interpolator: None,
+            device_clock: None,
+            raw_capture: None,
+            packed_timestamp: false,
+            layout: None,
+            banner: None,
+            data_format: DataFormat::Text,
+            lenient_nulls: false,
+            frames: BinaryFrameBuffer::new(),
+            read_buf: vec![0; DEFAULT_READ_BUFFER_SIZE],
+            auto_detect: AutoDetectState::new(),
+            detected_format: Arc::new(OnceLock::new()),
+            reconnect_max_delay: DEFAULT_RECONNECT_MAX_DELAY,
+            idle_sleep: None,
+            line_status: None,
+            range_check: None,
+            counters: ReadCounters::new(),
+            parse_threads: 1,
+            parse_pool: None,
         }
     }
 
-    /// Read data from the serial port and send it to the writer thread
-    pub fn read_serial_loop<F>(self, running: Arc<AtomicBool>, mut data_callback: F) -> Result<()>
-    where
-        F: FnMut(SensorData) -> Result<()>,
-    {
-        println!("Serial reader thread started");
+    /// Sets the data bits, parity and stop bits of the port (8N1 by default)
+    pub fn with_framing(mut self, framing: SerialFraming) -> Self {
+        self.framing = framing;
+        self
+    }
 
-        // Open the serial port
-        let mut port = open_serial_port(&self.port_name, self.baud_rate)?;
-        let mut consecutive_errors = 0;
+    /// Sets the number of bytes requested from the port per read
+    ///
+    /// See [`DEFAULT_READ_BUFFER_SIZE`] for the latency trade-off.
+    pub fn with_read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buf = vec![0; size.max(1)];
+        self
+    }
 
-        while running.load(Ordering::SeqCst) {
-            // Try to read lines from the serial port
-            match read_serial_data(&mut port) {
-                Ok(lines) => {
-                    // Reset error counter on successful read
-                    consecutive_errors = 0;
+    /// Caps the wait between attempts to reopen the port after it was lost
+    pub fn with_reconnect_max_delay(mut self, max_delay: StdDuration) -> Self {
+        self.reconnect_max_delay = max_delay;
+        self
+    }
 
-                    // Process all received lines
-                    for line in lines {
-                        if line.trim().is_empty() {
-                            // Skip empty lines
-                            continue;
-                        }
+    /// Sleeps after each read that produced no records
+    ///
+    /// At low sample rates most reads time out empty and the loop would
+    /// otherwise return to the port at once; the pause lowers CPU use at
+    /// the cost of up to `duration` extra latency. Reads that produced
+    /// records never sleep, so high-rate throughput is unaffected.
+    pub fn with_idle_sleep(mut self, duration: StdDuration) -> Self {
+        self.idle_sleep = Some(duration).filter(|d| !d.is_zero());
+        self
+    }
 
-                        // Parse the line into sensor data
-                        match parse_sensor_data(&line) {
-                            Ok(data) => {
-                                // Send the data to the writer thread
-                                if let Err(e) = data_callback(data) {
-                                    eprintln!("Error sending data to writer: {}", e);
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Error parsing sensor data: {}", e);
-                                // Continue reading even if there's a parse error
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    // Log the error but continue trying to read
-                    consecutive_errors += 1;
+    /// Pause before the next read, given the records the last read produced
+    fn idle_pause(&self, records: usize) -> Option<StdDuration> {
+        self.idle_sleep.filter(|_| records == 0)
+    }
+
+    /// Samples the port's modem control lines on open and every reconnect
+    pub fn with_line_status_log(mut self, log: LineStatusLog) -> Self {
+        self.line_status = Some(log);
+        self
+    }
+
+    /// Drops decoded records with implausible values as parse errors
+    ///
+    /// Only a check with [`RangePolicy::Reject`] has an effect here; tagged
+    /// records are marked by the writer instead.
+    pub fn with_range_check(mut self, check: RangeCheck) -> Self {
+        self.range_check = Some(check).filter(|check| check.policy() == RangePolicy::Reject);
+        self
+    }
+
+    /// Parses text lines on a pool of `threads` worker threads
+    ///
+    /// Splitting the stream into lines stays on the reader thread; see
+    /// [`ParsePool`] for how the original order is restored. One thread
+    /// (the default) parses inline without a pool.
+    pub fn with_parse_threads(mut self, threads: usize) -> Self {
+        self.parse_threads = threads.max(1);
+        self
+    }
+
+    /// Parser for text lines matching the configured layout
+    fn line_parser(&self) -> LineParser {
+        if let Some(layout) = &self.layout {
+            LineParser::Layout(layout.clone())
+        } else if self.synthesizer.is_some() {
+            LineParser::Untimed
+        } else if self.lenient_nulls {
+            LineParser::Lenient
+        } else {
+            LineParser::Strict
+        }
+    }
+
+    /// Records the line status of a freshly opened port, if enabled
+    fn sample_line_status(&self, event: &str, port: &mut Box<dyn SerialPort>) {
+        if let Some(log) = &self.line_status {
+            if let Err(e) = log.record(event, LineStatus::read(port.as_mut())) {
+                eprint_status(
+                    Health::Degraded,
+                    &format!("Line status not recorded: {}", e),
+                );
+            }
+        }
+    }
+
+    /// Counters of bytes read, records decoded and parse errors
+    ///
+    /// The returned handle shares the worker's counters, so it keeps
+    /// updating while the read loop runs and holds the totals afterwards.
+    pub fn read_counters(&self) -> ReadCounters {
+        self.counters.clone()
+    }
+
+    /// Sets a watchdog notified whenever a record is delivered
+    pub fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Reads lines without a timestamp field and numbers records in arrival order
+    ///
+    /// Lines carry six or seven hex fields (see [`super::serial::parse_untimed_sensor_data`]);
+    /// the synthesized counter advances by one per record.
+    pub fn with_synthesized_timestamps(mut self) -> Self {
+        self.synthesizer = Some(TimestampSynthesizer::new());
+        self
+    }
+
+    /// Spreads the host timestamps of each read's records over the time since the previous read
+    ///
+    /// See [`ReadInterpolator`].
+    pub fn with_interpolated_timestamps(mut self) -> Self {
+        self.interpolator = Some(ReadInterpolator::new());
+        self
+    }
+
+    /// Derives host timestamps from the sensor counter running at `counter_hz`
+    ///
+    /// See [`DeviceClock`].
+    pub fn with_device_timestamps(mut self, counter_hz: f64) -> Self {
+        self.device_clock = Some(DeviceClock::new(counter_hz));
+        self
+    }
+
+    /// Logs every byte read from the serial port to a raw capture file
+    pub fn with_raw_capture(mut self, capture: RawCapture) -> Self {
+        self.raw_capture = Some(capture);
+        self
+    }
+
+    /// Parses lines with a custom field order and encoding instead of the default layout
+    pub fn with_layout(mut self, layout: LineLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Sets the encoding of the serial stream (text lines by default)
+    pub fn with_data_format(mut self, format: DataFormat) -> Self {
+        self.data_format = format;
+        self
+    }
+
+    /// Format chosen by [`DataFormat::Auto`] detection, set once decided
+    ///
+    /// The handle stays valid after the worker has moved to its thread.
+    pub fn detected_format(&self) -> Arc<OnceLock<DataFormat>> {
+        self.detected_format.clone()
+    }
+
+    /// Keeps records whose individual float fields fail to decode
+    ///
+    /// See [`super::serial::parse_sensor_data_lenient`]; applies to the default line layout.
+    pub fn with_lenient_nulls(mut self) -> Self {
+        self.lenient_nulls = true;
+        self
+    }
+
+    /// Ignores everything received before a line containing `banner`
+    pub fn with_banner_wait(mut self, banner: &str) -> Self {
+        self.banner = Some(BannerWait::new(banner));
+        self
+    }
+
+    /// Splits the first field into a 24-bit timestamp and an 8-bit status byte
+    ///
+    /// See [`unpack_timestamp_status`].
+    pub fn with_packed_timestamp(mut self) -> Self {
+        self.packed_timestamp = true;
+        self.ticks = TickExtender::new().with_width(CounterWidth::Bits24);
+        self
+    }
+
+    /// Stamps a record with a synthesized counter value, or unpacks its
+    /// timestamp field, if enabled, extends the counter across rollovers,
+    /// then derives its host timestamps from the counter in device timestamp
+    /// mode
+    fn stamp(&mut self, data: SensorData) -> SensorData {
+        let data = if self.packed_timestamp {
+            unpack_timestamp_status(data)
+        } else {
+            match &mut self.synthesizer {
+                Some(synthesizer) => synthesizer.assign(data),
+                None => data,
+            }
+        };
+        let data = self.ticks.stamp(data);
+        match &mut self.device_clock {
+            Some(clock) => clock.map(data),
+            None => data,
+        }
+    }
+
+    /// Parses received lines, reporting and skipping those that fail to parse
+    fn parse_lines(&mut self, lines: Vec<String>) -> Vec<SensorData> {
+        let banner = &mut self.banner;
+        let lines: Vec<String> = lines
+            .into_iter()
+            // Skip empty lines
+            .filter(|line| !line.trim().is_empty())
+            // Drop lines received while still waiting for the boot banner
+            .filter(|line| banner.as_mut().is_none_or(|gate| gate.admit(line)))
+            .collect();
+
+        // Parse the lines into sensor data
+        let parsed = match &mut self.parse_pool {
+            Some(pool) => {
+                pool.submit(lines);
+                pool.ready()
+            }
+            None => {
+                let parser = self.line_parser();
+                lines.iter().map(|line| parser.parse(line)).collect()
+            }
+        };
+        self.keep_parsed(parsed)
+    }
+
+    /// Unwraps parse results, reporting and skipping the failures
+    fn keep_parsed(&mut self, parsed: Vec<Result<SensorData>>) -> Vec<SensorData> {
+        let mut records = Vec::with_capacity(parsed.len());
+        for parsed in parsed {
+            match parsed {
+                Ok(data) => records.push(data),
+                Err(e) => {
+                    self.counters.add_parse_errors(1);
+                    eprint_status(
+                        Health::Failing,
+                        &format!("Error parsing sensor data: {}", e),
+                    );
+                    // Continue reading even if there's a parse error
+                }
+            }
+        }
+        records
+    }
+
+    /// Sends a parsed record on to the writer thread
+    fn deliver<F>(&mut self, data: SensorData, data_callback: &mut F)
+    where
+        F: FnMut(SensorData) -> Result<()>,
+    {
+        // Startup byte-order detection may hold records back
+        for data in self.byte_order.process(data) {
+            let data = self.stamp(data);
+            // Send the data to the writer thread
+            if let Err(e) = data_callback(data) {
+                eprint_status(
+                    Health::Failing,
+                    &format!("Error sending data to writer: {}", e),
+                );
+            } else {
+                self.notify_alive();
+            }
+        }
+    }
+
+    /// Reports liveness after a record was delivered
+    fn notify_alive(&mut self) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.alive();
+        }
+    }
+
+    /// Reads one chunk from the port and decodes the records it completes
+    ///
+    /// Bytes read, decoded records and undecodable lines or frames are
+    /// added to the reader's counters.
+    fn read_records(&mut self, port: &mut Box<dyn SerialPort>) -> Result<Vec<SensorData>> {
+        let n = read_chunk(port, &mut self.read_buf, self.raw_capture.as_mut())?;
+        self.counters.add_bytes(n);
+        let records = match self.data_format {
+            DataFormat::Text if n == 0 => Vec::new(),
+            DataFormat::Text => {
+                let lines = split_lines(&self.read_buf[..n]);
+                self.parse_lines(lines)
+            }
+            DataFormat::Binary => {
+                let corrupt = self.frames.corrupt_frames();
+                let records = self.frames.push(&self.read_buf[..n]);
+                self.counters
+                    .add_parse_errors(self.frames.corrupt_frames() - corrupt);
+                records
+            }
+            DataFormat::Auto => self.decode_auto_detect(n),
+        };
+        let records = self.reject_out_of_range(records);
+        self.counters.add_records(records.len());
+        Ok(records)
+    }
+
+    /// Waits for the lines still being parsed by the pool, if any
+    fn drain_parse_pool(&mut self) -> Vec<SensorData> {
+        let Some(pool) = self.parse_pool.take() else {
+            return Vec::new();
+        };
+        let records = self.keep_parsed(pool.finish());
+        let records = self.reject_out_of_range(records);
+        self.counters.add_records(records.len());
+        records
+    }
+
+    /// Drops records failing the range check, counting them as parse errors
+    fn reject_out_of_range(&mut self, mut records: Vec<SensorData>) -> Vec<SensorData> {
+        let Some(check) = self.range_check else {
+            return records;
+        };
+        let decoded = records.len();
+        records.retain(|data| check.is_valid(data));
+        let rejected = decoded - records.len();
+        if rejected > 0 {
+            self.counters.add_parse_errors(rejected);
+            let warning = format!("Warning: dropped {} out-of-range records", rejected);
+            eprint_status(Health::Degraded, &warning);
+        }
+        records
+    }
+
+    /// Decodes a chunk with format detection, reporting the format once it is decided
+    fn decode_auto_detect(&mut self, n: usize) -> Vec<SensorData> {
+        let undecided = self.auto_detect.detected().is_none();
+        let corrupt = self.auto_detect.corrupt_frames();
+        let read = self.auto_detect.push(&self.read_buf[..n]);
+        self.counters
+            .add_parse_errors(self.auto_detect.corrupt_frames() - corrupt);
+        if let Some(format) = self.auto_detect.detected().filter(|_| undecided) {
+            let _ = self.detected_format.set(format);
+            let message = format!("Detected {} input", format);
+            print_status(Health::Healthy, &message);
+        }
+        match read {
+            AutoDetectRead::Lines(lines) => self.parse_lines(lines),
+            AutoDetectRead::Records(records) => records,
+        }
+    }
+
+    /// Reopens the serial port after it was lost, with exponential backoff
+    ///
+    /// # Returns
+    /// The reopened port, or None if `running` was cleared while waiting
+    fn reconnect(&self, running: &AtomicBool) -> Option<Box<dyn SerialPort>> {
+        let mut attempt = 0;
+        loop {
+            let delay = reconnect_delay(attempt, self.reconnect_max_delay);
+            let message = format!(
+                "Reconnecting to {} in {} ms (attempt {})",
+                self.port_name,
+                delay.as_millis(),
+                attempt + 1
+            );
+            eprint_status(Health::Degraded, &message);
+            if !sleep_while_running(delay, running) {
+                return None;
+            }
+
+            match open_serial_port(&self.port_name, self.baud_rate, self.framing) {
+                Ok(port) => {
+                    let message = format!("Reconnected to {}", self.port_name);
+                    print_status(Health::Healthy, &message);
+                    return Some(port);
+                }
+                Err(e) => {
+                    let message = format!("Reconnect failed: {:#}", e);
+                    eprint_status(Health::Failing, &message);
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Read data from the serial port and send it to the writer thread
+    pub fn read_serial_loop<F>(
+        mut self,
+        running: Arc<AtomicBool>,
+        mut data_callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(SensorData) -> Result<()>,
+    {
+        println!("Serial reader thread started");
+
+        // Open the serial port
+        let mut port = open_serial_port(&self.port_name, self.baud_rate, self.framing)?;
+        self.sample_line_status("open", &mut port);
+        if self.parse_threads > 1 {
+            self.parse_pool = Some(ParsePool::new(self.parse_threads, self.line_parser()));
+        }
+        let mut consecutive_errors = 0;
+
+        while running.load(Ordering::SeqCst) {
+            // Try to read records from the serial port
+            match self.read_records(&mut port) {
+                Ok(mut records) => {
+                    // Reset error counter on successful read
+                    consecutive_errors = 0;
+
+                    if let Some(interpolator) = &mut self.interpolator {
+                        interpolator.spread(&mut records);
+                    }
+
+                    let pause = self.idle_pause(records.len());
+                    for data in records {
+                        self.deliver(data, &mut data_callback);
+                    }
+                    if let Some(pause) = pause {
+                        thread::sleep(pause);
+                    }
+                }
+                Err(e) if is_fatal_port_error(&e) => {
+                    // The handle is dead (e.g. the adapter was unplugged); reopen the port
+                    let message = format!("Lost serial port {}: {}", self.port_name, e);
+                    eprint_status(Health::Failing, &message);
+                    drop(port);
+                    match self.reconnect(&running) {
+                        Some(reopened) => {
+                            port = reopened;
+                            self.sample_line_status("reconnect", &mut port);
+                            self.auto_detect = AutoDetectState::new();
+                            if let Some(interpolator) = &mut self.interpolator {
+                                interpolator.reset();
+                            }
+                        }
+                        None => break,
+                    }
+                    consecutive_errors = 0;
+                }
+                Err(e) => {
+                    // Log the error but continue trying to read
+                    consecutive_errors += 1;
 
                     // Only log errors occasionally to prevent flooding the console
                     if consecutive_errors <= 3 || consecutive_errors % 100 == 0 {
-                        eprintln!("Error reading from serial port: {}", e);
+                        eprint_status(
+                            Health::Failing,
+                            &format!("Error reading from serial port: {}", e),
+                        );
                     }
 
                     // Back off with increasing sleep time on consecutive errors
@@ -195,12 +1542,40 @@ impl SerialReaderWorker {
             }
         }
 
+        for data in self.drain_parse_pool() {
+            self.deliver(data, &mut data_callback);
+        }
+
+        // Release records still held by the byte-order check
+        for data in self.byte_order.finish() {
+            let data = self.stamp(data);
+            if let Err(e) = data_callback(data) {
+                eprint_status(
+                    Health::Failing,
+                    &format!("Error sending data to writer: {}", e),
+                );
+            }
+        }
+
+        if let Some(capture) = self.raw_capture.take() {
+            capture.finish()?;
+        }
+
+        let health = match self.counters.parse_errors() {
+            0 => Health::Healthy,
+            _ => Health::Degraded,
+        };
+        print_status(health, &self.counters.report());
         println!("Serial reader thread shutting down");
         Ok(())
     }
 
     /// Simulate serial data for testing
-    pub fn simulate_data_loop<F>(self, running: Arc<AtomicBool>, mut data_callback: F) -> Result<()>
+    pub fn simulate_data_loop<F>(
+        mut self,
+        running: Arc<AtomicBool>,
+        mut data_callback: F,
+    ) -> Result<()>
     where
         F: FnMut(SensorData) -> Result<()>,
     {
@@ -222,11 +1597,19 @@ impl SerialReaderWorker {
                 ay: 1.1 * i as f32,
                 az: 1.2 * i as f32,
                 system_timestamp: Utc::now().timestamp_millis(),
+                monotonic_timestamp: monotonic_timestamp(),
+                extended_timestamp: Some(i as i64),
+                ..Default::default()
             };
 
             // Send the data to the writer thread
             if let Err(e) = data_callback(data) {
-                eprintln!("Error sending data to writer: {}", e);
+                eprint_status(
+                    Health::Failing,
+                    &format!("Error sending data to writer: {}", e),
+                );
+            } else {
+                self.notify_alive();
             }
 
             // Increment counter and wait
@@ -250,11 +1633,57 @@ impl SerialReaderWorker {
 mod tests {
     use super::*;
     use crate::CompressionType;
+    use crate::FileNaming;
     use crate::ParquetWriter;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use std::sync::mpsc;
     use std::thread;
     use tempfile::tempdir;
 
+    fn test_record(i: u32) -> SensorData {
+        SensorData {
+            timestamp: i,
+            temp: 25.0 + (i as f32 * 0.1),
+            gx: 0.1 * i as f32,
+            gy: 0.2 * i as f32,
+            gz: 0.3 * i as f32,
+            ax: 1.0 * i as f32,
+            ay: 1.1 * i as f32,
+            az: 1.2 * i as f32,
+            system_timestamp: Utc::now().timestamp_millis(),
+            monotonic_timestamp: monotonic_timestamp(),
+            ..Default::default()
+        }
+    }
+
+    // Number of rows in each Parquet file in a directory, in file name order
+    fn parquet_rows_per_file(dir_path: &str) -> Vec<usize> {
+        let mut files: Vec<_> = std::fs::read_dir(dir_path)
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "parquet"))
+            .collect();
+        files.sort();
+        files
+            .iter()
+            .map(|path| {
+                let file = std::fs::File::open(path).unwrap();
+                ParquetRecordBatchReaderBuilder::try_new(file)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                    .map(|batch| batch.unwrap().num_rows())
+                    .sum::<usize>()
+            })
+            .collect()
+    }
+
+    // Total number of rows across all Parquet files in a directory
+    fn count_parquet_rows(dir_path: &str) -> usize {
+        parquet_rows_per_file(dir_path).iter().sum()
+    }
+
     #[test]
     fn test_file_writer_worker() {
         // Create a temporary directory for the test
@@ -279,7 +1708,7 @@ mod tests {
 
         // Create and start FileWriterWorker in a separate thread
         let worker = FileWriterWorker::new(
-            writer,
+            Box::new(writer),
             0, // No file splitting
             dir_path.clone(),
             "test_log".to_string(),
@@ -301,6 +1730,8 @@ mod tests {
                 ay: 1.1 * i as f32,
                 az: 1.2 * i as f32,
                 system_timestamp: Utc::now().timestamp_millis(),
+                monotonic_timestamp: monotonic_timestamp(),
+                ..Default::default()
             };
             tx.send(data).unwrap();
         }
@@ -357,7 +1788,7 @@ mod tests {
 
         // Create and start FileWriterWorker in a separate thread
         let writer_worker = FileWriterWorker::new(
-            writer,
+            Box::new(writer),
             0, // No file splitting
             dir_path.clone(),
             "test_integrated".to_string(),
@@ -405,4 +1836,872 @@ mod tests {
 
         assert!(!parquet_files.is_empty(), "No Parquet files were created");
     }
+
+    #[test]
+    fn test_start_paused_until_resume_trigger() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let trigger = temp_dir.path().join("resume");
+
+        let (tx, rx) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = running.clone();
+
+        let writer = ParquetWriter::new(&dir_path, "paused", CompressionType::Snappy, 10).unwrap();
+        let worker =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "paused".to_string())
+                .with_pause_control(PauseControl::new(true, Some(trigger.clone())));
+
+        let writer_handle = thread::spawn(move || {
+            worker.process_data_loop(rx, running_clone).unwrap();
+        });
+
+        // Records sent before the trigger must be discarded
+        for i in 0..5 {
+            tx.send(test_record(i)).unwrap();
+        }
+        thread::sleep(StdDuration::from_millis(300));
+
+        // Fire the resume trigger; everything after it must be written
+        std::fs::write(&trigger, b"").unwrap();
+        for i in 5..8 {
+            tx.send(test_record(i)).unwrap();
+        }
+        thread::sleep(StdDuration::from_millis(300));
+
+        running.store(false, Ordering::SeqCst);
+        drop(tx);
+        writer_handle.join().unwrap();
+
+        assert_eq!(count_parquet_rows(&dir_path), 3);
+    }
+
+    #[test]
+    fn test_rotation_on_sample_rate_change() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let (tx, rx) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = running.clone();
+
+        let writer = ParquetWriter::new(&dir_path, "rate", CompressionType::Snappy, 50).unwrap();
+        let worker =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "rate".to_string())
+                .with_rate_change_rotation(Some(1000.0));
+
+        let writer_handle = thread::spawn(move || {
+            worker.process_data_loop(rx, running_clone).unwrap();
+        });
+
+        // 100 Hz for 200 samples, then 1 kHz for 200 samples
+        let timestamps = (0..200).map(|i| i * 10).chain((1..=200).map(|i| 1990 + i));
+        for ts in timestamps {
+            tx.send(test_record(ts)).unwrap();
+        }
+
+        drop(tx);
+        writer_handle.join().unwrap();
+        running.store(false, Ordering::SeqCst);
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir_path)
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2, "Expected one rotation at the rate change");
+
+        let mut rates = Vec::new();
+        let mut rows = Vec::new();
+        for path in &files {
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+                    .unwrap();
+            let rate = builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|kv| kv.iter().find(|kv| kv.key == "sample_rate_hz").cloned())
+                .and_then(|kv| kv.value);
+            rates.push(rate);
+            rows.push(
+                builder
+                    .build()
+                    .unwrap()
+                    .map(|batch| batch.unwrap().num_rows())
+                    .sum::<usize>(),
+            );
+        }
+
+        assert_eq!(rows, vec![200, 200]);
+        assert_eq!(
+            rates,
+            vec![Some("100".to_string()), Some("1000".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_sample_rate_kept_across_time_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "steady", CompressionType::None, 50).unwrap();
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "steady".to_string())
+                .with_rate_change_rotation(Some(1000.0));
+
+        // 100 Hz throughout, split in two files by time
+        for i in 0..200 {
+            worker.write_record(test_record(i * 10)).unwrap();
+        }
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+        for i in 200..300 {
+            worker.write_record(test_record(i * 10)).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 2);
+        for path in &files {
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(path).unwrap())
+                    .unwrap();
+            let rates: Vec<_> = builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .into_iter()
+                .flatten()
+                .filter(|kv| kv.key == "sample_rate_hz")
+                .map(|kv| kv.value.clone())
+                .collect();
+            assert_eq!(rates, vec![Some("100".to_string())], "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_coinciding_rotation_triggers_leave_no_empty_file() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let writer = ParquetWriter::new(&dir_path, "both", CompressionType::Snappy, 50).unwrap();
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "both".to_string())
+                .with_rate_change_rotation(None);
+
+        for i in 0..200 {
+            worker.write_record(test_record(i * 10)).unwrap();
+        }
+
+        // The time interval elapses right as the rate changes; checking it
+        // again on the fresh, empty file must not rotate a second time
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+        for i in 1..=200 {
+            worker.write_record(test_record(1990 + i)).unwrap();
+        }
+        worker.finish().unwrap();
+
+        assert_eq!(parquet_rows_per_file(&dir_path), vec![200, 200]);
+    }
+
+    #[test]
+    fn test_aligned_rotation_waits_for_boundary() {
+        use std::sync::Mutex;
+
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let at = |h, m, s| {
+            DateTime::parse_from_rfc3339(&format!("2025-01-01T{:02}:{:02}:{:02}Z", h, m, s))
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let now = Arc::new(Mutex::new(at(12, 34, 56)));
+        let clock_now = now.clone();
+
+        let writer = ParquetWriter::new(&dir_path, "aligned", CompressionType::None, 10).unwrap();
+        let mut worker = FileWriterWorker::new(
+            Box::new(writer),
+            15,
+            dir_path.clone(),
+            "aligned".to_string(),
+        )
+        .with_clock(Box::new(move || *clock_now.lock().unwrap()))
+        .with_aligned_rotation();
+        worker.write_record(test_record(0)).unwrap();
+
+        // The first file ends at the next quarter hour, not 15 minutes after start
+        *now.lock().unwrap() = at(12, 44, 59);
+        assert!(!worker.should_rotate_file());
+        *now.lock().unwrap() = at(12, 45, 0);
+        assert!(worker.should_rotate_file());
+
+        // Later files stay on the grid even if the rotation runs late
+        *now.lock().unwrap() = at(12, 45, 3);
+        worker.evaluate_rotation(None).unwrap();
+        assert_eq!(worker.last_rotation, at(12, 45, 0));
+        *now.lock().unwrap() = at(12, 59, 59);
+        assert!(!worker.should_rotate_file());
+        *now.lock().unwrap() = at(13, 0, 0);
+        assert!(worker.should_rotate_file());
+    }
+
+    #[test]
+    fn test_records_sent_after_stop_reach_writer() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "final", CompressionType::None, 100).unwrap();
+        let worker =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "final".to_string());
+
+        let (tx, rx) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_writer = running.clone();
+        let handle = thread::spawn(move || worker.process_data_loop(rx, running_writer));
+
+        // A tiny capture whose only batch is released by the reader after the
+        // stop signal, once the writer has already left its receive loop
+        running.store(false, Ordering::SeqCst);
+        thread::sleep(StdDuration::from_millis(250));
+        for i in 0..3 {
+            tx.send(test_record(i)).unwrap();
+        }
+        drop(tx);
+        handle.join().unwrap().unwrap();
+
+        assert_eq!(count_parquet_rows(&dir_path), 3);
+    }
+
+    #[test]
+    fn test_rotation_by_file_size() {
+        let write = |max_file_bytes| {
+            let temp_dir = tempdir().unwrap();
+            let dir_path = temp_dir.path().to_str().unwrap().to_string();
+            let writer = ParquetWriter::new(&dir_path, "size", CompressionType::None, 10).unwrap();
+            let mut worker =
+                FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "size".to_string())
+                    .with_max_file_bytes(max_file_bytes);
+            for i in 0..500 {
+                worker.write_record(test_record(i)).unwrap();
+                worker.evaluate_rotation(None).unwrap();
+            }
+            worker.finish().unwrap();
+            parquet_rows_per_file(&dir_path)
+        };
+
+        let rows = write(4_000);
+        assert!(rows.len() > 1, "expected several files, got {:?}", rows);
+        assert_eq!(rows.iter().sum::<usize>(), 500);
+
+        // No limit leaves a single file, exactly as without the option
+        assert_eq!(write(0), vec![500]);
+    }
+
+    #[test]
+    fn test_delay_between_batches_is_max_gap() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "gap", CompressionType::None, 10).unwrap();
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "gap".to_string())
+                .with_gap_report(StdDuration::from_secs(60));
+
+        // Rendezvous channel: each send returns once the worker has taken the record
+        let (tx, rx) = mpsc::sync_channel(0);
+        let sender = thread::spawn(move || {
+            tx.send(test_record(0)).unwrap();
+            thread::sleep(StdDuration::from_millis(20));
+            tx.send(test_record(1)).unwrap();
+            thread::sleep(StdDuration::from_millis(300));
+            tx.send(test_record(2)).unwrap();
+        });
+        worker.receive_loop(&rx, &AtomicBool::new(true)).unwrap();
+        sender.join().unwrap();
+
+        let max_gap = worker.gaps.as_ref().unwrap().max_gap();
+        assert!(
+            max_gap >= StdDuration::from_millis(250),
+            "max gap {:?}",
+            max_gap
+        );
+        assert!(
+            max_gap < StdDuration::from_millis(1_000),
+            "max gap {:?}",
+            max_gap
+        );
+        worker.finish().unwrap();
+    }
+
+    #[test]
+    fn test_reconnect_delay_doubles_up_to_cap() {
+        let max = StdDuration::from_secs(5);
+        let delays: Vec<u128> = (0..7)
+            .map(|attempt| reconnect_delay(attempt, max).as_millis())
+            .collect();
+        assert_eq!(delays, vec![250, 500, 1_000, 2_000, 4_000, 5_000, 5_000]);
+        assert_eq!(reconnect_delay(u32::MAX, max), max);
+
+        let running = AtomicBool::new(false);
+        let start = Instant::now();
+        assert!(!sleep_while_running(StdDuration::from_secs(10), &running));
+        assert!(start.elapsed() < StdDuration::from_secs(1));
+    }
+
+    #[test]
+    fn test_low_disk_space_stops_capture_at_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "space", CompressionType::None, 10).unwrap();
+        let expected_dir = dir_path.clone();
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "space".to_string())
+                .with_min_free_bytes(1_000_000)
+                .with_space_query(Box::new(move |path| {
+                    assert_eq!(path, Path::new(&expected_dir));
+                    Ok(4_096)
+                }));
+        for i in 0..5 {
+            worker.write_record(test_record(i)).unwrap();
+        }
+
+        // The rotation is due, but there is no room for another file
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        let running = AtomicBool::new(true);
+        let (tx, rx) = mpsc::channel();
+        worker.receive_loop(&rx, &running).unwrap();
+        assert!(!running.load(Ordering::SeqCst));
+
+        // Records the reader still sends are not written to the full disk
+        for i in 5..8 {
+            tx.send(test_record(i)).unwrap();
+        }
+        drop(tx);
+        worker.discard_remaining(&rx);
+        worker.finish().unwrap();
+
+        assert_eq!(parquet_rows_per_file(&dir_path), vec![5]);
+    }
+
+    /// Parquet writer whose first `failures` records are reported as dropped batches
+    struct FlakySink {
+        inner: ParquetWriter,
+        failures: usize,
+    }
+
+    impl DataSink for FlakySink {
+        fn add_data(&mut self, data: SensorData) -> Result<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(
+                    ReceiverError::ParquetError("Failed to create record batch".into()).into(),
+                );
+            }
+            self.inner.add_data(data)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.inner.flush()
+        }
+
+        fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+            self.inner.rotate_file(output_dir, prefix)
+        }
+
+        fn close(self: Box<Self>) -> Result<Vec<String>> {
+            self.inner.close()
+        }
+    }
+
+    #[test]
+    fn test_worker_drops_malformed_batches() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let inner = ParquetWriter::new(&dir_path, "drop", CompressionType::None, 5).unwrap();
+        let sink = FlakySink { inner, failures: 1 };
+        let mut worker =
+            FileWriterWorker::new(Box::new(sink), 0, dir_path.clone(), "drop".to_string())
+                .with_max_dropped_batches(1);
+
+        // The worker keeps writing after the failed batch
+        for i in 0..10 {
+            worker.write_record(test_record(i)).unwrap();
+        }
+        assert_eq!(worker.dropped_batches, 1);
+        worker.finish().unwrap();
+        assert_eq!(count_parquet_rows(&dir_path), 9);
+    }
+
+    #[test]
+    fn test_worker_stops_after_dropped_batch_limit() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let inner = ParquetWriter::new(&dir_path, "limit", CompressionType::None, 1).unwrap();
+        let sink = FlakySink {
+            inner,
+            failures: usize::MAX,
+        };
+        let mut worker =
+            FileWriterWorker::new(Box::new(sink), 0, dir_path.clone(), "limit".to_string())
+                .with_max_dropped_batches(2);
+
+        assert!(worker.write_record(test_record(0)).is_ok());
+        assert!(worker.write_record(test_record(1)).is_ok());
+        let err = worker.write_record(test_record(2)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ReceiverError::ParquetError(_))
+        ));
+    }
+
+    /// Sink taking a while for every record
+    struct SlowSink;
+
+    impl DataSink for SlowSink {
+        fn add_data(&mut self, _data: SensorData) -> Result<()> {
+            thread::sleep(StdDuration::from_micros(500));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn rotate_file(&mut self, _output_dir: &str, _prefix: &str) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn close(self: Box<Self>) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Sink recording the calls the worker makes, without writing any file
+    #[derive(Clone, Default)]
+    struct RecordingSink {
+        calls: Arc<std::sync::Mutex<Vec<String>>>,
+        bytes: u64,
+    }
+
+    impl RecordingSink {
+        fn log(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl DataSink for RecordingSink {
+        fn add_data(&mut self, data: SensorData) -> Result<()> {
+            self.bytes += 100;
+            self.log(format!("add {}", data.timestamp));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.log("flush".to_string());
+            Ok(())
+        }
+
+        fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+            self.bytes = 0;
+            self.log(format!("rotate {}/{}", output_dir, prefix));
+            Ok(Vec::new())
+        }
+
+        fn close(self: Box<Self>) -> Result<Vec<String>> {
+            self.log("close".to_string());
+            Ok(Vec::new())
+        }
+
+        fn bytes_written(&self) -> u64 {
+            self.bytes
+        }
+    }
+
+    #[test]
+    fn test_worker_drives_mock_sink() {
+        let sink = RecordingSink::default();
+        let calls = sink.calls.clone();
+        let mut worker =
+            FileWriterWorker::new(Box::new(sink), 0, "out".to_string(), "mock".to_string())
+                .with_max_file_bytes(300);
+        for i in 0..5 {
+            worker.write_record(test_record(i)).unwrap();
+            worker.evaluate_rotation(None).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let calls = calls.lock().unwrap().clone();
+        let added: Vec<&String> = calls.iter().filter(|c| c.starts_with("add")).collect();
+        assert_eq!(added.len(), 5);
+        assert_eq!(added[0], "add 0");
+        // A new file after every third record, in the configured place
+        let rotations: Vec<usize> = calls
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.starts_with("rotate"))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(rotations.len(), 1, "{:?}", calls);
+        assert_eq!(calls[rotations[0]], "rotate out/mock");
+        assert_eq!(calls[rotations[0] - 1], "add 2");
+        assert_eq!(calls.last().unwrap(), "close");
+    }
+
+    #[test]
+    fn test_on_change_stores_only_changed_records() {
+        let sink = RecordingSink::default();
+        let calls = sink.calls.clone();
+        let mut worker = FileWriterWorker::new(Box::new(sink), 0, String::new(), String::new())
+            .with_change_filter(ChangeFilter::new(0.1));
+        for i in 0..20 {
+            let mut data = test_record(i);
+            // Flat for ten records, then a step on every fifth
+            data.gx = 0.0;
+            data.gy = 0.0;
+            data.gz = 0.0;
+            data.ax = 0.0;
+            data.ay = 0.0;
+            data.az = if i < 10 {
+                1.0
+            } else {
+                1.0 + 0.5 * (i / 5) as f32
+            };
+            worker.write_record(data).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let stored: Vec<String> = calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| c.starts_with("add"))
+            .cloned()
+            .collect();
+        assert_eq!(stored, vec!["add 0", "add 10", "add 15"]);
+    }
+
+    #[test]
+    fn test_peak_in_flight_bounded_by_queue() {
+        const QUEUE_CAPACITY: usize = 8;
+
+        let gauge = InFlightGauge::new();
+        let worker = FileWriterWorker::new(Box::new(SlowSink), 0, String::new(), String::new())
+            .with_in_flight_gauge(gauge.clone());
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let producer_gauge = gauge.clone();
+        let producer = thread::spawn(move || {
+            for i in 0..200 {
+                producer_gauge.enter();
+                tx.send(test_record(i)).unwrap();
+            }
+        });
+        worker
+            .process_data_loop(rx, Arc::new(AtomicBool::new(true)))
+            .unwrap();
+        producer.join().unwrap();
+
+        // The queue is full most of the time; beyond it, one record can wait
+        // in a blocked send and one be in the writer
+        assert!(gauge.peak() >= QUEUE_CAPACITY, "peak {}", gauge.peak());
+        assert!(gauge.peak() <= QUEUE_CAPACITY + 2, "peak {}", gauge.peak());
+        assert_eq!(gauge.current(), 0);
+    }
+
+    #[test]
+    fn test_monotonic_check_aborts_writer() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        let writer = ParquetWriter::new(&dir_path, "mono", CompressionType::None, 10).unwrap();
+        let worker =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "mono".to_string())
+                .with_monotonic_check(MonotonicPolicy::Abort);
+
+        let (tx, rx) = mpsc::channel();
+        for i in [1, 2, 3, 2, 4] {
+            tx.send(test_record(i)).unwrap();
+        }
+        drop(tx);
+
+        let err = worker
+            .process_data_loop(rx, Arc::new(AtomicBool::new(true)))
+            .unwrap_err();
+        assert!(err.to_string().contains("2 followed 3"), "{}", err);
+        assert_eq!(count_parquet_rows(&dir_path), 3);
+    }
+
+    #[test]
+    fn test_24_bit_rollover_passes_checks() {
+        let sink = RecordingSink::default();
+        let calls = sink.calls.clone();
+        let mut worker = FileWriterWorker::new(Box::new(sink), 0, String::new(), String::new())
+            .with_counter_width(CounterWidth::Bits24)
+            .with_monotonic_check(MonotonicPolicy::Abort)
+            .with_rate_change_rotation(Some(1000.0));
+        // A packed 24-bit counter stepping by 10 and wrapping halfway
+        for i in 0..200u32 {
+            let data = test_record((0xFF_F9C0 + i * 10) & 0xFF_FFFF);
+            worker.write_record(data).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.iter().filter(|c| c.starts_with("add")).count(), 200);
+        assert!(
+            !calls.iter().any(|c| c.starts_with("rotate")),
+            "{:?}",
+            calls
+        );
+    }
+
+    #[test]
+    fn test_watchdog_notified_during_simulation() {
+        use crate::watchdog::{NotifyEvent, Watchdog};
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let watchdog = Watchdog::new(
+            Some(StdDuration::ZERO),
+            Box::new(move |event| sink.lock().unwrap().push(event)),
+        );
+
+        let running = Arc::new(AtomicBool::new(true));
+        let reader =
+            SerialReaderWorker::new("test_port".to_string(), 115200).with_watchdog(watchdog);
+        reader.simulate_data_loop(running, |_| Ok(())).unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.first(), Some(&NotifyEvent::Ready));
+        let pings = events
+            .iter()
+            .filter(|e| **e == NotifyEvent::Watchdog)
+            .count();
+        assert_eq!(pings, 20, "Expected one ping per simulated record");
+    }
+
+    #[test]
+    fn test_idle_sleep_only_after_empty_reads() {
+        let reader = SerialReaderWorker::new("test_port".to_string(), 115200);
+        assert_eq!(reader.idle_pause(0), None);
+
+        let reader = reader.with_idle_sleep(StdDuration::from_millis(5));
+        assert_eq!(reader.idle_pause(0), Some(StdDuration::from_millis(5)));
+        assert_eq!(reader.idle_pause(1), None);
+        assert_eq!(reader.idle_pause(40), None);
+
+        let reader = reader.with_idle_sleep(StdDuration::ZERO);
+        assert_eq!(reader.idle_pause(0), None);
+    }
+
+    #[test]
+    fn test_read_counters_count_parse_errors() {
+        use crate::serial::tests::MockSerialPort;
+
+        let mut reader = SerialReaderWorker::new("test_port".to_string(), 115200);
+        let counters = reader.read_counters();
+        let input = "00000123,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n\
+                     00000124,41200000,garbage\n\
+                     \n\
+                     00000125,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n";
+        let mut port: Box<dyn SerialPort> = Box::new(MockSerialPort::new(input.as_bytes()));
+        let records = reader.read_records(&mut port).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(reader.read_records(&mut port).unwrap().is_empty());
+
+        assert_eq!(counters.bytes_read(), input.len() as u64);
+        assert_eq!(counters.records(), 2);
+        assert_eq!(counters.parse_errors(), 1);
+        assert!((counters.loss_ratio() - 1.0 / 3.0).abs() < 1e-9);
+        assert!(counters.report().contains("parsed 2, 1 parse errors"));
+    }
+
+    #[test]
+    fn test_stats_readout_interval_and_rotation_reset() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let new_worker = || {
+            let writer = ParquetWriter::new(&dir_path, "stats", CompressionType::None, 10).unwrap();
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "stats".to_string())
+        };
+
+        // A zero interval never reports
+        let mut worker = new_worker().with_stats_interval(StdDuration::ZERO);
+        worker.receive(test_record(0)).unwrap();
+        assert_eq!(
+            worker.stats_report(Instant::now() + StdDuration::from_secs(60)),
+            None
+        );
+
+        let mut worker = new_worker()
+            .with_in_flight_gauge(InFlightGauge::new())
+            .with_stats_interval(StdDuration::from_secs(1))
+            .with_rate_window(StdDuration::from_secs(5))
+            .with_gap_report(StdDuration::from_secs(10));
+        assert_eq!(worker.stats_interval, Some(StdDuration::from_secs(1)));
+        let start = worker.stats_period.0;
+        for i in 0..4 {
+            worker.receive(test_record(i)).unwrap();
+        }
+        assert_eq!(
+            worker.stats_report(start + StdDuration::from_millis(500)),
+            None
+        );
+        let report = worker
+            .stats_report(start + StdDuration::from_secs(2))
+            .unwrap();
+        assert!(
+            report.starts_with(
+                "Throughput: 2.0 records/s, 2.0 records/s over the last 5 s, \
+                 2.0 records/s overall, largest gap 0.0 ms, backlog 0 records, writing "
+            ),
+            "{}",
+            report
+        );
+        assert!(report.ends_with(".parquet"), "{}", report);
+        assert_eq!(worker.stats_period.1, 0);
+
+        // Rotation starts a new period for the next readout
+        worker.receive(test_record(4)).unwrap();
+        worker
+            .evaluate_rotation(Some(RotationTrigger::Time))
+            .unwrap();
+        assert_eq!(worker.stats_period.1, 0);
+        worker.finish().unwrap();
+    }
+
+    #[test]
+    fn test_idle_files_deleted_by_motion_filter() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+
+        // Files are renamed on close and get a sidecar, which goes with them
+        let writer = ParquetWriter::new(&dir_path, "motion", CompressionType::None, 50)
+            .unwrap()
+            .with_file_naming(FileNaming::FirstRecord)
+            .with_stats_sidecar();
+        let hook = RotationHook::new().with_motion_filter(Filter::parse("az > 2.0").unwrap());
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "motion".to_string())
+                .with_rotation_hook(hook);
+        let record = |i: u32, az: f32| SensorData {
+            az,
+            ..test_record(i)
+        };
+
+        // First file: at rest
+        for i in 0..100 {
+            worker.write_record(record(i, 1.0)).unwrap();
+        }
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+
+        // Second file: one record of motion
+        for i in 100..250 {
+            let az = if i == 180 { 3.0 } else { 1.0 };
+            worker.write_record(record(i, az)).unwrap();
+        }
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+
+        // Third file: at rest again, closed at shutdown
+        for i in 250..300 {
+            worker.write_record(record(i, 1.0)).unwrap();
+        }
+        worker.finish().unwrap();
+
+        assert_eq!(parquet_rows_per_file(&dir_path), vec![150]);
+        let names: Vec<String> = std::fs::read_dir(&dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names.len(), 2, "{:?}", names);
+        assert!(names.iter().any(|name| name.ends_with(".stats.json")));
+    }
+
+    #[test]
+    fn test_latest_pointer_follows_rename_on_close() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dir_path = dir.to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "latest", CompressionType::None, 50)
+            .unwrap()
+            .with_file_naming(FileNaming::FirstRecord);
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "latest".to_string())
+                .with_latest_pointer();
+        for i in 0..10 {
+            worker.write_record(test_record(i)).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let pointer = std::fs::read_to_string(crate::latest_pointer_path(dir, "latest")).unwrap();
+        let files = crate::session_files(dir, "latest").unwrap();
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_string_lossy();
+        assert_eq!(pointer, format!("{}\n", name));
+    }
+
+    #[test]
+    fn test_mirror_dir_receives_every_file() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let mirror_dir = tempdir().unwrap();
+        // The mirror drive is not plugged in at first
+        let mirror_path = mirror_dir.path().join("usb");
+        std::fs::write(&mirror_path, b"").unwrap();
+
+        let writer = ParquetWriter::new(&dir_path, "mirror", CompressionType::None, 50)
+            .unwrap()
+            .with_stats_sidecar();
+        let hook = RotationHook::new().with_mirror_dir(mirror_path.clone());
+        let preview = temp_dir.path().join("preview.png");
+        let mut worker =
+            FileWriterWorker::new(Box::new(writer), 1, dir_path.clone(), "mirror".to_string())
+                .with_rotation_hook(hook)
+                .with_preview_png(preview);
+
+        for i in 0..100 {
+            worker.write_record(test_record(i)).unwrap();
+        }
+        worker.last_rotation = Utc::now() - Duration::minutes(2);
+        worker.evaluate_rotation(None).unwrap();
+        // The first copy has failed; it is retried with the next file
+        worker.rotation_hook.as_ref().unwrap().wait_for_mirror();
+        assert!(mirror_path.is_file());
+        std::fs::remove_file(&mirror_path).unwrap();
+        for i in 100..250 {
+            worker.write_record(test_record(i)).unwrap();
+        }
+        worker.finish().unwrap();
+
+        let files = |dir: &Path| {
+            let mut files: Vec<(std::ffi::OsString, Vec<u8>)> = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .map(|path| {
+                    (
+                        path.file_name().unwrap().into(),
+                        std::fs::read(&path).unwrap(),
+                    )
+                })
+                .collect();
+            files.sort();
+            files
+        };
+        let primary = files(Path::new(&dir_path));
+        // Two files, each with its stats sidecar, and the preview
+        assert_eq!(primary.len(), 5);
+        assert_eq!(files(&mirror_path), primary);
+    }
 }
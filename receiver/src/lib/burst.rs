@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use super::types::SensorData;
+
+/// Records kept on either side of a burst unless configured otherwise
+pub const DEFAULT_BURST_WINDOW: usize = 50;
+
+/// Detector of sudden acceleration spikes, such as impacts
+///
+/// A record whose acceleration magnitude exceeds the threshold triggers a
+/// burst. The burst covers the `window` records before the first
+/// triggering record and the `window` records after the last one, so
+/// triggers closer together than that merge into a single burst. Every
+/// record of a burst is tagged with the burst's ID; IDs count up from 1.
+///
+/// Tagging the records before a trigger requires holding back the last
+/// `window` records, which are released by [`finish`](Self::finish).
+#[derive(Debug)]
+pub struct BurstDetector {
+    threshold_g: f32,
+    window: usize,
+    // Records not yet released, oldest first
+    held: VecDeque<SensorData>,
+    // ID of the burst in progress and the records it still covers
+    active: Option<(u32, usize)>,
+    next_id: u32,
+}
+
+impl BurstDetector {
+    /// Creates a detector
+    ///
+    /// # Arguments
+    /// * `threshold_g` - Acceleration magnitude, in g, above which a record triggers
+    /// * `window` - Records tagged before the first and after the last trigger
+    pub fn new(threshold_g: f32, window: usize) -> Self {
+        BurstDetector {
+            threshold_g,
+            window,
+            held: VecDeque::with_capacity(window + 1),
+            active: None,
+            next_id: 1,
+        }
+    }
+
+    /// Number of bursts detected so far
+    pub fn bursts(&self) -> u32 {
+        self.next_id - 1
+    }
+
+    /// Takes a record and returns those that are no longer held back
+    pub fn push(&mut self, mut data: SensorData) -> Vec<SensorData> {
+        let magnitude = (data.ax * data.ax + data.ay * data.ay + data.az * data.az).sqrt();
+        if magnitude > self.threshold_g {
+            let id = match self.active {
+                Some((id, _)) => id,
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    // The lead-in may overlap the tail of the previous burst
+                    for held in self.held.iter_mut().filter(|held| held.burst_id.is_none()) {
+                        held.burst_id = Some(id);
+                    }
+                    id
+                }
+            };
+            data.burst_id = Some(id);
+            self.active = Some((id, self.window));
+        } else if let Some((id, remaining)) = self.active {
+            if remaining > 0 {
+                data.burst_id = Some(id);
+            }
+            self.active = (remaining > 1).then_some((id, remaining - 1));
+        }
+
+        self.held.push_back(data);
+        let release = self.held.len().saturating_sub(self.window);
+        self.held.drain(..release).collect()
+    }
+
+    /// Releases every held-back record
+    pub fn finish(&mut self) -> Vec<SensorData> {
+        self.active = None;
+        self.held.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u32, az: f32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_spike_tags_surrounding_records() {
+        let mut detector = BurstDetector::new(4.0, 3);
+        let mut out = Vec::new();
+        for i in 0..40 {
+            // Two spikes close together, then one on its own
+            let az = if [10, 12, 30].contains(&i) { 8.0 } else { 1.0 };
+            out.extend(detector.push(record(i, az)));
+        }
+        out.extend(detector.finish());
+
+        assert_eq!(out.len(), 40);
+        let ids: Vec<(u32, Option<u32>)> = out
+            .iter()
+            .map(|data| (data.timestamp, data.burst_id))
+            .collect();
+        for (timestamp, id) in ids {
+            let expected = match timestamp {
+                7..=15 => Some(1),
+                27..=33 => Some(2),
+                _ => None,
+            };
+            assert_eq!(id, expected, "record {}", timestamp);
+        }
+        assert_eq!(detector.bursts(), 2);
+    }
+}
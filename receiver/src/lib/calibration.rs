@@ -0,0 +1,344 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::error::ReceiverError;
+use super::types::SensorData;
+
+/// Bias and 3x3 sensitivity correction for one three-axis sensor
+///
+/// The corrected vector is `matrix * (raw - bias)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisCalibration {
+    /// Per-axis offset subtracted from the raw reading
+    pub bias: [f32; 3],
+    /// Row-major sensitivity/misalignment matrix
+    pub matrix: [[f32; 3]; 3],
+}
+
+impl AxisCalibration {
+    /// Calibration that leaves readings unchanged
+    pub const IDENTITY: AxisCalibration = AxisCalibration {
+        bias: [0.0; 3],
+        matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+
+    /// Applies the correction to a raw (x, y, z) reading
+    pub fn apply(&self, raw: [f32; 3]) -> [f32; 3] {
+        let v = [
+            raw[0] - self.bias[0],
+            raw[1] - self.bias[1],
+            raw[2] - self.bias[2],
+        ];
+        let m = &self.matrix;
+        [
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+        ]
+    }
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Device calibration for the gyroscope and accelerometer
+///
+/// Calibration files are plain text with one entry per line. Blank lines and
+/// lines starting with `#` are ignored; values may be separated by whitespace
+/// or commas. Entries that are absent keep their identity defaults.
+///
+/// ```text
+/// # CXD5602PWBIMU calibration
+/// gyro_bias    0.0012 -0.0008 0.0003
+/// gyro_matrix  1.002 0.001 0.000  -0.001 0.998 0.002  0.000 0.000 1.001
+/// accel_bias   0.015 -0.021 0.034
+/// accel_matrix 0.997 0 0  0 1.003 0  0 0 0.999
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Calibration {
+    pub gyro: AxisCalibration,
+    pub accel: AxisCalibration,
+}
+
+impl Calibration {
+    /// Loads a calibration file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the calibration file
+    ///
+    /// # Returns
+    /// The parsed calibration, or an error describing the offending line
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read calibration file {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Invalid calibration file {}", path.display()))
+    }
+
+    /// Parses calibration file contents
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut calibration = Calibration::default();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut tokens = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty());
+            let key = tokens.next().unwrap_or_default();
+            let values = tokens
+                .map(|t| t.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| {
+                    ReceiverError::ConfigError(format!("line {}: invalid number: {}", index + 1, e))
+                })?;
+
+            let (axis, is_matrix) = match key {
+                "gyro_bias" => (&mut calibration.gyro, false),
+                "gyro_matrix" => (&mut calibration.gyro, true),
+                "accel_bias" => (&mut calibration.accel, false),
+                "accel_matrix" => (&mut calibration.accel, true),
+                _ => {
+                    return Err(ReceiverError::ConfigError(format!(
+                        "line {}: unknown calibration entry '{}'",
+                        index + 1,
+                        key
+                    ))
+                    .into())
+                }
+            };
+
+            let expected = if is_matrix { 9 } else { 3 };
+            if values.len() != expected {
+                return Err(ReceiverError::ConfigError(format!(
+                    "line {}: {} expects {} values, got {}",
+                    index + 1,
+                    key,
+                    expected,
+                    values.len()
+                ))
+                .into());
+            }
+
+            if is_matrix {
+                for (row, chunk) in values.chunks(3).enumerate() {
+                    axis.matrix[row].copy_from_slice(chunk);
+                }
+            } else {
+                axis.bias.copy_from_slice(&values);
+            }
+        }
+
+        Ok(calibration)
+    }
+
+    /// Applies the calibration to the gyro and accel fields of a record
+    pub fn apply(&self, data: &mut SensorData) {
+        [data.gx, data.gy, data.gz] = self.gyro.apply([data.gx, data.gy, data.gz]);
+        [data.ax, data.ay, data.az] = self.accel.apply([data.ax, data.ay, data.az]);
+    }
+
+    /// Formats the calibration in the file format read by [`parse`](Self::parse)
+    pub fn to_file_contents(&self) -> String {
+        let row = |values: &[f32]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let matrix = |axis: &AxisCalibration| {
+            axis.matrix
+                .iter()
+                .map(|r| row(r))
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+        format!(
+            "# CXD5602PWBIMU calibration\n\
+             gyro_bias    {}\n\
+             gyro_matrix  {}\n\
+             accel_bias   {}\n\
+             accel_matrix {}\n",
+            row(&self.gyro.bias),
+            matrix(&self.gyro),
+            row(&self.accel.bias),
+            matrix(&self.accel)
+        )
+    }
+
+    /// Writes the calibration to a file readable by [`from_file`](Self::from_file)
+    pub fn write_file(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_file_contents())
+            .with_context(|| format!("Failed to write calibration file {}", path.display()))
+    }
+}
+
+/// Estimates gyro and accel biases from records taken at rest
+///
+/// A resting gyroscope reads its bias, so the gyro bias is the mean of each
+/// axis. The accelerometer reads gravity plus its offset; gravity is taken
+/// to lie along the axis with the largest mean reading, at 1 g in that
+/// axis's direction, and the accel bias is the mean minus that vector. The
+/// sensor therefore has to rest with one axis roughly vertical. Records
+/// with a field that failed to decode are skipped; the matrices stay at
+/// identity.
+#[derive(Debug, Clone, Default)]
+pub struct CalibrationEstimator {
+    // Sums of gx, gy, gz, ax, ay, az
+    sums: [f64; 6],
+    samples: u64,
+}
+
+impl CalibrationEstimator {
+    /// Creates an estimator without samples
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record taken at rest
+    pub fn add(&mut self, data: &SensorData) {
+        let values = [data.gx, data.gy, data.gz, data.ax, data.ay, data.az];
+        if (1..7).any(|index| data.is_null(index)) || values.iter().any(|v| !v.is_finite()) {
+            return;
+        }
+        for (sum, value) in self.sums.iter_mut().zip(values) {
+            *sum += value as f64;
+        }
+        self.samples += 1;
+    }
+
+    /// Number of records used so far
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Estimated calibration, or None without any usable record
+    pub fn estimate(&self) -> Option<Calibration> {
+        if self.samples == 0 {
+            return None;
+        }
+        let mean = self.sums.map(|sum| (sum / self.samples as f64) as f32);
+        let gyro_bias = [mean[0], mean[1], mean[2]];
+        let mut accel_bias = [mean[3], mean[4], mean[5]];
+        let vertical = (0..3)
+            .max_by(|&a, &b| accel_bias[a].abs().total_cmp(&accel_bias[b].abs()))
+            .unwrap_or(2);
+        accel_bias[vertical] -= accel_bias[vertical].signum();
+
+        Some(Calibration {
+            gyro: AxisCalibration {
+                bias: gyro_bias,
+                ..AxisCalibration::IDENTITY
+            },
+            accel: AxisCalibration {
+                bias: accel_bias,
+                ..AxisCalibration::IDENTITY
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SensorData {
+        SensorData {
+            timestamp: 1,
+            temp: 25.0,
+            gx: 0.1,
+            gy: -0.2,
+            gz: 0.3,
+            ax: 1.0,
+            ay: 2.0,
+            az: 3.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identity_is_noop() {
+        let calibration = Calibration::parse(
+            "# identity\n\
+             gyro_bias 0 0 0\n\
+             gyro_matrix 1 0 0 0 1 0 0 0 1\n\
+             accel_matrix 1,0,0, 0,1,0, 0,0,1\n",
+        )
+        .unwrap();
+        assert_eq!(calibration, Calibration::default());
+
+        let mut data = sample();
+        calibration.apply(&mut data);
+        assert_eq!(
+            [data.gx, data.gy, data.gz, data.ax, data.ay, data.az],
+            [0.1, -0.2, 0.3, 1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_known_matrix() {
+        let calibration = Calibration::parse(
+            "accel_bias 1 1 1\n\
+             accel_matrix 2 0 0  0 0 1  0 -1 0\n",
+        )
+        .unwrap();
+
+        let mut data = sample();
+        calibration.apply(&mut data);
+        // (raw - bias) = (0, 1, 2); rows: 2*0, 2, -1
+        assert_eq!([data.ax, data.ay, data.az], [0.0, 2.0, -1.0]);
+        // Gyro keeps its identity defaults
+        assert_eq!([data.gx, data.gy, data.gz], [0.1, -0.2, 0.3]);
+    }
+
+    #[test]
+    fn test_rejects_malformed_entries() {
+        assert!(Calibration::parse("gyro_bias 1 2\n").is_err());
+        assert!(Calibration::parse("mag_bias 1 2 3\n").is_err());
+        assert!(Calibration::parse("accel_bias 1 x 3\n").is_err());
+    }
+
+    #[test]
+    fn test_estimated_biases_match_injected_offsets() {
+        let gyro_bias = [0.012, -0.008, 0.003];
+        let accel_bias = [0.015, -0.021, 0.034];
+        let mut estimator = CalibrationEstimator::new();
+        // Resting upside down, with a deterministic pseudo-random noise
+        let mut seed = 12345u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((seed >> 16) as f32 / 65536.0 - 0.5) * 0.01
+        };
+        for _ in 0..2_000 {
+            estimator.add(&SensorData {
+                gx: gyro_bias[0] + noise(),
+                gy: gyro_bias[1] + noise(),
+                gz: gyro_bias[2] + noise(),
+                ax: accel_bias[0] + noise(),
+                ay: accel_bias[1] + noise(),
+                az: -1.0 + accel_bias[2] + noise(),
+                ..sample()
+            });
+        }
+        assert_eq!(estimator.samples(), 2_000);
+
+        let calibration = estimator.estimate().unwrap();
+        for axis in 0..3 {
+            assert!((calibration.gyro.bias[axis] - gyro_bias[axis]).abs() < 1e-3);
+            assert!((calibration.accel.bias[axis] - accel_bias[axis]).abs() < 1e-3);
+        }
+        assert_eq!(calibration.accel.matrix, AxisCalibration::IDENTITY.matrix);
+
+        // The written file reads back as the same calibration
+        let parsed = Calibration::parse(&calibration.to_file_contents()).unwrap();
+        assert_eq!(parsed, calibration);
+        assert!(CalibrationEstimator::new().estimate().is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use super::types::SensorData;
+
+/// What to do with a record whose sensor and host clocks disagree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClockSkewPolicy {
+    /// Keep the record and count it
+    #[default]
+    Flag,
+    /// Discard the record and count it
+    Drop,
+}
+
+impl std::str::FromStr for ClockSkewPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flag" => Ok(ClockSkewPolicy::Flag),
+            "drop" => Ok(ClockSkewPolicy::Drop),
+            _ => Err(format!("Unknown clock skew policy: {}", s)),
+        }
+    }
+}
+
+/// Time constant of the rolling clock offset, in seconds of sensor time
+pub const CLOCK_FIT_WINDOW_S: f64 = 10.0;
+
+/// Checks that the sensor counter and the host clock agree
+///
+/// The counter is mapped to wall time from the elapsed counts since the
+/// first record at `counter_hz`, plus an offset to the host clock. It uses
+/// the count extended across rollovers
+/// ([`SensorData::extended_timestamp`]), falling back to the raw counter for
+/// records nobody stamped. The offset starts at the first record's and then
+/// follows a rolling average over the accepted records, with a time constant
+/// of [`CLOCK_FIT_WINDOW_S`], so a slow drift between the two clocks is
+/// tracked instead of adding up to an anomaly. A record whose mapped time is
+/// more than the tolerance away from its `system_timestamp` is an anomaly,
+/// which usually means a counter jump, a device reset or a host clock step;
+/// anomalies do not move the offset.
+#[derive(Debug)]
+pub struct ClockCheck {
+    counter_hz: f64,
+    tolerance_ms: i64,
+    policy: ClockSkewPolicy,
+    // Extended counter of the first record
+    first_counter: Option<i64>,
+    // Rolling host offset in ms and the elapsed ms at which it was last updated
+    offset: Option<(f64, f64)>,
+    anomalies: usize,
+}
+
+impl ClockCheck {
+    /// Creates a check
+    ///
+    /// # Arguments
+    /// * `counter_hz` - Sensor counter rate in Hz
+    /// * `tolerance_ms` - Largest accepted difference between the clocks
+    /// * `policy` - Whether anomalous records are flagged or dropped
+    pub fn new(counter_hz: f64, tolerance_ms: i64, policy: ClockSkewPolicy) -> Self {
+        ClockCheck {
+            counter_hz,
+            tolerance_ms,
+            policy,
+            first_counter: None,
+            offset: None,
+            anomalies: 0,
+        }
+    }
+
+    /// Number of anomalous records seen so far
+    pub fn anomalies(&self) -> usize {
+        self.anomalies
+    }
+
+    // Sensor time since the first record in milliseconds
+    fn elapsed_ms(&mut self, data: &SensorData) -> f64 {
+        let counter = data.extended_timestamp.unwrap_or(data.timestamp as i64);
+        let first = *self.first_counter.get_or_insert(counter);
+        (counter - first) as f64 * 1_000.0 / self.counter_hz
+    }
+
+    /// Difference between the mapped sensor time and the host time in milliseconds
+    ///
+    /// The first record establishes the mapping and has no skew.
+    pub fn skew_ms(&mut self, data: &SensorData) -> i64 {
+        let elapsed_ms = self.elapsed_ms(data);
+        let system_ms = data.system_timestamp as f64;
+        let (offset_ms, _) = *self
+            .offset
+            .get_or_insert((system_ms - elapsed_ms, elapsed_ms));
+        (elapsed_ms + offset_ms - system_ms).round() as i64
+    }
+
+    /// Feeds the next record into the check
+    ///
+    /// With [`ClockSkewPolicy::Flag`] an anomalous record gets
+    /// [`SensorData::clock_skew`] set.
+    ///
+    /// # Returns
+    /// Whether the record should be kept
+    pub fn observe(&mut self, data: &mut SensorData) -> bool {
+        if self.skew_ms(data).abs() > self.tolerance_ms {
+            self.anomalies += 1;
+            data.clock_skew = true;
+            return self.policy == ClockSkewPolicy::Flag;
+        }
+        // Move the offset towards this record's by the share of the window
+        // that passed since the last accepted record
+        let elapsed_ms = self.elapsed_ms(data);
+        if let Some((offset_ms, updated_ms)) = self.offset.as_mut() {
+            let weight =
+                ((elapsed_ms - *updated_ms) / (CLOCK_FIT_WINDOW_S * 1_000.0)).clamp(0.0, 1.0);
+            *offset_ms += weight * (data.system_timestamp as f64 - elapsed_ms - *offset_ms);
+            *updated_ms = updated_ms.max(elapsed_ms);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u32, system_timestamp: i64) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_jumped_record_is_flagged() {
+        // 1 kHz counter: one count per millisecond
+        let stream = [
+            record(1_000, 50_000),
+            record(1_100, 50_102),
+            record(1_200, 50_199),
+            record(91_300, 50_301),
+            record(1_400, 50_398),
+        ];
+
+        let mut flag = ClockCheck::new(1_000.0, 20, ClockSkewPolicy::Flag);
+        let mut flagged = stream.clone();
+        let kept: Vec<bool> = flagged.iter_mut().map(|data| flag.observe(data)).collect();
+        assert_eq!(kept, vec![true; 5]);
+        assert_eq!(flag.anomalies(), 1);
+        let marks: Vec<bool> = flagged.iter().map(|data| data.clock_skew).collect();
+        assert_eq!(marks, vec![false, false, false, true, false]);
+
+        let mut drop = ClockCheck::new(1_000.0, 20, ClockSkewPolicy::Drop);
+        let kept: Vec<bool> = stream
+            .clone()
+            .iter_mut()
+            .map(|data| drop.observe(data))
+            .collect();
+        assert_eq!(kept, vec![true, true, true, false, true]);
+        assert_eq!(drop.anomalies(), 1);
+        assert_eq!(drop.skew_ms(&stream[3]), 90_000 - 1);
+    }
+
+    #[test]
+    fn test_slow_drift_is_tracked() {
+        // 100 Hz records of a 1 kHz counter whose clock runs 200 ppm slow
+        // against the host for 100 s, with up to 2 ms of jitter
+        let mut check = ClockCheck::new(1_000.0, 5, ClockSkewPolicy::Drop);
+        for i in 0..10_000u32 {
+            let mut data = record(i * 10, 50_000 + (i as f64 * 10.002) as i64 + (i % 3) as i64);
+            assert!(check.observe(&mut data), "record {} dropped", i);
+        }
+        assert_eq!(check.anomalies(), 0);
+
+        // A device reset is still caught
+        let mut reset = record(0, 50_000 + 100_025);
+        assert!(!check.observe(&mut reset));
+        assert_eq!(check.anomalies(), 1);
+    }
+}
@@ -0,0 +1,387 @@
+use anyhow::{Context, Result};
+use std::fs::{create_dir_all, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use super::error::ReceiverError;
+use super::parquet_writer::new_output_path;
+use super::sink::DataSink;
+use super::types::SensorData;
+
+/// Magic bytes starting every compact binary file
+pub const COMPACT_MAGIC: [u8; 4] = *b"IMUC";
+
+/// Version of the compact record layout written by this logger
+pub const COMPACT_VERSION: u8 = 1;
+
+/// Extension of compact binary files
+pub const COMPACT_EXTENSION: &str = "imuc";
+
+// Zigzag-maps a signed value so small magnitudes encode to few varint bytes
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+// LEB128: seven bits per byte, least significant group first
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Per-file state of the delta encoding
+///
+/// Deltas of the first record of a file are taken from zero.
+#[derive(Debug, Default, Clone, Copy)]
+struct DeltaState {
+    timestamp: u32,
+    system_timestamp: i64,
+    monotonic_timestamp: i64,
+}
+
+/// Appends the encoding of one record and advances the delta state
+///
+/// Layout: zigzag varint deltas of `timestamp` (wrapping, so a counter
+/// rollover stays small), `system_timestamp` and `monotonic_timestamp`; a
+/// varint status (0 = none, otherwise status + 1); the `null_fields` byte;
+/// then the seven floats as raw little-endian f32.
+fn encode_record(out: &mut Vec<u8>, state: &mut DeltaState, data: &SensorData) {
+    let timestamp_delta = data.timestamp.wrapping_sub(state.timestamp) as i32;
+    write_varint(out, zigzag(timestamp_delta as i64));
+    write_varint(
+        out,
+        zigzag(data.system_timestamp.wrapping_sub(state.system_timestamp)),
+    );
+    write_varint(
+        out,
+        zigzag(
+            data.monotonic_timestamp
+                .wrapping_sub(state.monotonic_timestamp),
+        ),
+    );
+    write_varint(out, data.status.map_or(0, |status| status as u64 + 1));
+    out.push(data.null_fields);
+    for value in [
+        data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    state.timestamp = data.timestamp;
+    state.system_timestamp = data.system_timestamp;
+    state.monotonic_timestamp = data.monotonic_timestamp;
+}
+
+/// Writer for the compact binary format (`--format compact`)
+///
+/// For the smallest files when Parquet is not needed: a five-byte header
+/// (`IMUC` and a version byte) followed by delta-varint encoded records, see
+/// [`CompactBinaryReader`] for reading them back. Files are named and rotated
+/// like Parquet files, with the `.imuc` extension.
+pub struct CompactBinaryWriter {
+    output_path: String,
+    writer: BufWriter<File>,
+    state: DeltaState,
+    // Encoding buffer reused between records
+    scratch: Vec<u8>,
+}
+
+impl CompactBinaryWriter {
+    /// Creates a new compact writer and writes the file header
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where files will be saved
+    /// * `prefix` - Filename prefix for the files
+    pub fn new(output_dir: &str, prefix: &str) -> Result<Self> {
+        create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {}", output_dir))?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix)?;
+        println!("Created new compact file: {}", output_path);
+        Ok(CompactBinaryWriter {
+            output_path,
+            writer,
+            state: DeltaState::default(),
+            scratch: Vec::with_capacity(64),
+        })
+    }
+
+    /// Path of the file currently being written
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
+    /// Writes a record to the current file
+    pub fn add_data(&mut self, data: &SensorData) -> Result<()> {
+        self.scratch.clear();
+        encode_record(&mut self.scratch, &mut self.state, data);
+        self.writer
+            .write_all(&self.scratch)
+            .with_context(|| format!("Failed to write data to {}", self.output_path))
+    }
+
+    /// Flushes buffered records to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to flush {}", self.output_path))
+    }
+
+    /// Closes the current file and starts a new one, returning the closed file's path
+    pub fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<String> {
+        self.flush()?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix)?;
+        let closed = std::mem::replace(&mut self.output_path, output_path);
+        self.writer = writer;
+        self.state = DeltaState::default();
+        println!("Rotated to new compact file: {}", self.output_path);
+        Ok(closed)
+    }
+
+    /// Flushes and closes the current file, returning its path
+    pub fn close(mut self) -> Result<String> {
+        self.flush()?;
+        println!("Closed compact file: {}", self.output_path);
+        Ok(self.output_path)
+    }
+
+    fn create_file(output_dir: &str, prefix: &str) -> Result<(String, BufWriter<File>)> {
+        let output_path = new_output_path(output_dir, prefix, COMPACT_EXTENSION)
+            .to_string_lossy()
+            .to_string();
+        let file = File::create(&output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&COMPACT_MAGIC)
+            .and_then(|_| writer.write_all(&[COMPACT_VERSION]))
+            .with_context(|| format!("Failed to write header to {}", output_path))?;
+        Ok((output_path, writer))
+    }
+}
+
+impl DataSink for CompactBinaryWriter {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        CompactBinaryWriter::add_data(self, &data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        CompactBinaryWriter::flush(self)
+    }
+
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+        Ok(vec![CompactBinaryWriter::rotate_file(
+            self, output_dir, prefix,
+        )?])
+    }
+
+    fn close(self: Box<Self>) -> Result<Vec<String>> {
+        Ok(vec![CompactBinaryWriter::close(*self)?])
+    }
+
+    fn output_path(&self) -> Option<&str> {
+        Some(CompactBinaryWriter::output_path(self))
+    }
+}
+
+/// Reader of files written by [`CompactBinaryWriter`]
+///
+/// Iterates over the records of a file; a file cut off in the middle of a
+/// record (e.g. by a power loss) ends with a parse error for that record.
+pub struct CompactBinaryReader<R: Read> {
+    input: R,
+    state: DeltaState,
+    done: bool,
+}
+
+impl CompactBinaryReader<BufReader<File>> {
+    /// Opens a compact file and checks its header
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+        Self::new(BufReader::new(file))
+            .with_context(|| format!("Failed to read {}", path.display()))
+    }
+}
+
+impl<R: Read> CompactBinaryReader<R> {
+    /// Reads and checks the header of a compact stream
+    pub fn new(mut input: R) -> Result<Self> {
+        let mut header = [0u8; 5];
+        input.read_exact(&mut header).map_err(|_| {
+            ReceiverError::ParseError("Compact file is shorter than its header".to_string())
+        })?;
+        if header[..4] != COMPACT_MAGIC {
+            return Err(ReceiverError::ParseError(
+                "Not a compact binary file (bad magic)".to_string(),
+            )
+            .into());
+        }
+        if header[4] != COMPACT_VERSION {
+            return Err(ReceiverError::ParseError(format!(
+                "Unsupported compact format version {}",
+                header[4]
+            ))
+            .into());
+        }
+        Ok(CompactBinaryReader {
+            input,
+            state: DeltaState::default(),
+            done: false,
+        })
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0u8; 1];
+        match self.input.read_exact(&mut byte) {
+            Ok(()) => Ok(Some(byte[0])),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Reads a varint; None at a clean end of input before its first byte
+    fn read_varint(&mut self) -> Result<Option<u64>> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let Some(byte) = self.read_byte()? else {
+                return if shift == 0 {
+                    Ok(None)
+                } else {
+                    Err(truncated())
+                };
+            };
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+        }
+        Err(ReceiverError::ParseError("Compact varint too long".to_string()).into())
+    }
+
+    fn read_record(&mut self) -> Result<Option<SensorData>> {
+        let Some(timestamp_delta) = self.read_varint()? else {
+            return Ok(None);
+        };
+        let mut field = || -> Result<u64> { self.read_varint()?.ok_or_else(truncated) };
+        let system_delta = unzigzag(field()?);
+        let monotonic_delta = unzigzag(field()?);
+        let status = field()?;
+        let null_fields = self.read_byte()?.ok_or_else(truncated)?;
+        let mut floats = [0u8; 28];
+        self.input
+            .read_exact(&mut floats)
+            .map_err(|_| truncated())?;
+        let float = |i: usize| f32::from_le_bytes(floats[i * 4..i * 4 + 4].try_into().unwrap());
+
+        let state = &mut self.state;
+        state.timestamp = state
+            .timestamp
+            .wrapping_add(unzigzag(timestamp_delta) as i32 as u32);
+        state.system_timestamp = state.system_timestamp.wrapping_add(system_delta);
+        state.monotonic_timestamp = state.monotonic_timestamp.wrapping_add(monotonic_delta);
+        Ok(Some(SensorData {
+            timestamp: state.timestamp,
+            temp: float(0),
+            gx: float(1),
+            gy: float(2),
+            gz: float(3),
+            ax: float(4),
+            ay: float(5),
+            az: float(6),
+            system_timestamp: state.system_timestamp,
+            monotonic_timestamp: state.monotonic_timestamp,
+            status: status.checked_sub(1).map(|status| status as u8),
+            null_fields,
+            ..Default::default()
+        }))
+    }
+}
+
+fn truncated() -> anyhow::Error {
+    ReceiverError::ParseError("Compact file ends in the middle of a record".to_string()).into()
+}
+
+impl<R: Read> Iterator for CompactBinaryReader<R> {
+    type Item = Result<SensorData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compact_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+
+        // Includes a counter rollover, a status byte and a null field
+        let records: Vec<SensorData> = (0..100u32)
+            .map(|i| SensorData {
+                timestamp: (u32::MAX - 50).wrapping_add(i),
+                temp: 25.0 + i as f32 * 0.01,
+                gx: -0.5 * i as f32,
+                gy: 0.25,
+                gz: f32::MIN_POSITIVE,
+                ax: if i == 7 { f32::NAN } else { i as f32 },
+                ay: -1.0,
+                az: 1.0,
+                system_timestamp: 1_700_000_000_000 + i as i64,
+                monotonic_timestamp: 5_000_000 + i as i64 * 1_953_125,
+                status: (i % 3 == 0).then_some(i as u8),
+                null_fields: if i == 7 { 1 << 4 } else { 0 },
+                ..Default::default()
+            })
+            .collect();
+
+        let mut writer = CompactBinaryWriter::new(dir_path, "compact").unwrap();
+        for data in &records {
+            writer.add_data(data).unwrap();
+        }
+        let path = writer.output_path().to_string();
+        writer.close().unwrap();
+        assert!(path.ends_with(".imuc"));
+        // Under 40 bytes per record
+        assert!(std::fs::metadata(&path).unwrap().len() < 100 * 40);
+
+        let read: Vec<SensorData> = CompactBinaryReader::open(Path::new(&path))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(read.len(), records.len());
+        for (read, written) in read.iter().zip(&records) {
+            assert_eq!(read.timestamp, written.timestamp);
+            assert_eq!(read.system_timestamp, written.system_timestamp);
+            assert_eq!(read.monotonic_timestamp, written.monotonic_timestamp);
+            assert_eq!(read.status, written.status);
+            assert_eq!(read.null_fields, written.null_fields);
+            let floats =
+                |d: &SensorData| [d.temp, d.gx, d.gy, d.gz, d.ax, d.ay, d.az].map(f32::to_bits);
+            assert_eq!(floats(read), floats(written));
+        }
+
+        // A record cut short is reported, not silently dropped
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 3);
+        let results: Vec<_> = CompactBinaryReader::new(&bytes[..]).unwrap().collect();
+        assert_eq!(results.len(), records.len());
+        assert!(results.last().unwrap().is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, Command};
+use std::ffi::OsString;
+use std::path::Path;
+
+use super::error::ReceiverError;
+
+/// Id of the option naming the config file
+pub const CONFIG_ARG: &str = "config";
+
+/// Merges the values of a TOML config file into command-line arguments
+///
+/// Keys are the option names of the command with underscores or dashes
+/// (`baud_rate = 921600`, `output-dir = "./data"`). A key only takes effect
+/// if the option was given neither on the command line nor through its
+/// environment variable, so the order of precedence is: flag, environment,
+/// config file, built-in default. A key is also skipped if its option
+/// conflicts with one given on the command line or in the environment. Boolean flags are switched on with
+/// `true`; arrays are joined into a comma-separated list.
+///
+/// # Arguments
+/// * `command` - Command definition, which must have a [`CONFIG_ARG`] option
+/// * `args` - Arguments as received, starting with the program name
+///
+/// # Returns
+/// The arguments with an explicit option added for every config value in
+/// effect, unchanged if no config file was given or the arguments do not
+/// parse (so that the real parse reports the problem)
+pub fn args_with_config(command: &Command, args: Vec<OsString>) -> Result<Vec<OsString>> {
+    // Options the config file is meant to supply may still be missing here
+    let Ok(matches) = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&args)
+    else {
+        return Ok(args);
+    };
+    if matches.subcommand().is_some() {
+        return Ok(args);
+    }
+    let Some(path) = matches.get_one::<std::path::PathBuf>(CONFIG_ARG) else {
+        return Ok(args);
+    };
+    let table = load_table(path)?;
+
+    let explicit: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| {
+            matches!(
+                matches.value_source(arg.get_id().as_str()),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        })
+        .collect();
+
+    let mut merged = args;
+    let program = merged.remove(0);
+    let mut from_config = Vec::new();
+    for (key, value) in &table {
+        let id = key.replace('-', "_");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id.as_str() && id != CONFIG_ARG)
+            .filter(|arg| arg.get_long().is_some())
+            .ok_or_else(|| {
+                ReceiverError::ConfigError(format!(
+                    "Unknown option '{}' in {}",
+                    key,
+                    path.display()
+                ))
+            })?;
+        let overridden = explicit
+            .iter()
+            .any(|given| given.get_id() == arg.get_id() || conflicts(command, given, arg));
+        if !overridden {
+            from_config.extend(config_arg(arg, value, path)?);
+        }
+    }
+
+    Ok(std::iter::once(program)
+        .chain(from_config)
+        .chain(merged)
+        .collect())
+}
+
+/// Returns whether either option declares a conflict with the other
+fn conflicts(command: &Command, a: &Arg, b: &Arg) -> bool {
+    let declares = |from: &Arg, to: &Arg| {
+        command
+            .get_arg_conflicts_with(from)
+            .iter()
+            .any(|arg| arg.get_id() == to.get_id())
+    };
+    declares(a, b) || declares(b, a)
+}
+
+/// Reads a config file into a TOML table
+fn load_table(path: &Path) -> Result<toml::Table> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    text.parse::<toml::Table>().map_err(|e| {
+        ReceiverError::ConfigError(format!("Invalid config file {}: {}", path.display(), e)).into()
+    })
+}
+
+/// Command-line form of one config value
+fn config_arg(arg: &Arg, value: &toml::Value, path: &Path) -> Result<Option<OsString>> {
+    let long = arg.get_long().unwrap_or_default();
+    if !arg.get_action().takes_values() {
+        return match value {
+            toml::Value::Boolean(true) => Ok(Some(format!("--{}", long).into())),
+            toml::Value::Boolean(false) => Ok(None),
+            _ => Err(ReceiverError::ConfigError(format!(
+                "Option '{}' in {} must be true or false",
+                long,
+                path.display()
+            ))
+            .into()),
+        };
+    }
+    let text = match value {
+        toml::Value::Array(items) => items
+            .iter()
+            .map(scalar_text)
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(",")),
+        value => scalar_text(value),
+    };
+    let Some(text) = text else {
+        return Err(ReceiverError::ConfigError(format!(
+            "Option '{}' in {} must be a string, number, boolean or array of them",
+            long,
+            path.display()
+        ))
+        .into());
+    };
+    Ok(Some(format!("--{}={}", long, text).into()))
+}
+
+fn scalar_text(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(text) => Some(text.clone()),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        toml::Value::Boolean(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgAction;
+    use tempfile::tempdir;
+
+    fn command() -> Command {
+        Command::new("receiver")
+            .arg(
+                Arg::new(CONFIG_ARG)
+                    .long("config")
+                    .value_parser(clap::value_parser!(std::path::PathBuf)),
+            )
+            .arg(Arg::new("port").long("port").required(true))
+            .arg(
+                Arg::new("baud_rate")
+                    .long("baud-rate")
+                    .default_value("115200")
+                    .env("TEST_CONFIG_FILE_BAUD_RATE"),
+            )
+            .arg(Arg::new("prefix").long("prefix").default_value("sensor"))
+            .arg(
+                Arg::new("simulation")
+                    .long("simulation")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("input")
+                    .long("input")
+                    .conflicts_with_all(["port", "simulation"]),
+            )
+    }
+
+    fn parse(args: &[&str]) -> clap::ArgMatches {
+        let args = args.iter().map(OsString::from).collect();
+        let merged = args_with_config(&command(), args).unwrap();
+        command().try_get_matches_from(merged).unwrap()
+    }
+
+    #[test]
+    fn test_flag_beats_config_beats_default() {
+        let dir = tempdir().unwrap();
+        let config = dir.path().join("receiver.toml");
+        std::fs::write(
+            &config,
+            "port = \"/dev/ttyUSB1\"\nbaud_rate = 921600\nsimulation = true\n",
+        )
+        .unwrap();
+        let config = config.to_str().unwrap();
+
+        // The config fills in what was not given, including a required option
+        let matches = parse(&["receiver", "--config", config]);
+        assert_eq!(matches.get_one::<String>("port").unwrap(), "/dev/ttyUSB1");
+        assert_eq!(matches.get_one::<String>("baud_rate").unwrap(), "921600");
+        assert!(matches.get_flag("simulation"));
+        // Keys missing from the file keep their default
+        assert_eq!(matches.get_one::<String>("prefix").unwrap(), "sensor");
+
+        let matches = parse(&["receiver", "--config", config, "--baud-rate", "9600"]);
+        assert_eq!(matches.get_one::<String>("baud_rate").unwrap(), "9600");
+        assert_eq!(matches.get_one::<String>("port").unwrap(), "/dev/ttyUSB1");
+
+        // Without a config file the built-in default applies
+        let matches = parse(&["receiver", "--port", "COM3"]);
+        assert_eq!(matches.get_one::<String>("baud_rate").unwrap(), "115200");
+    }
+
+    #[test]
+    fn test_flag_beats_conflicting_config_keys() {
+        let dir = tempdir().unwrap();
+        let config = dir.path().join("receiver.toml");
+        std::fs::write(
+            &config,
+            "port = \"/dev/ttyUSB1\"\nbaud_rate = 921600\nsimulation = true\n",
+        )
+        .unwrap();
+
+        // --input conflicts with port and simulation, so only baud_rate applies
+        let args = [
+            "receiver",
+            "--config",
+            config.to_str().unwrap(),
+            "--input",
+            "capture.parquet",
+        ]
+        .iter()
+        .map(OsString::from)
+        .collect();
+        let merged = args_with_config(&command(), args).unwrap();
+        let matches = command()
+            .mut_arg("port", |arg| arg.required(false))
+            .try_get_matches_from(merged)
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>("input").unwrap(),
+            "capture.parquet"
+        );
+        assert_eq!(matches.get_one::<String>("port"), None);
+        assert!(!matches.get_flag("simulation"));
+        assert_eq!(matches.get_one::<String>("baud_rate").unwrap(), "921600");
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let dir = tempdir().unwrap();
+        let config = dir.path().join("receiver.toml");
+        std::fs::write(&config, "baud = 921600\n").unwrap();
+
+        let args = ["receiver", "--config", config.to_str().unwrap()]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let error = args_with_config(&command(), args).unwrap_err();
+        assert!(error.to_string().contains("Unknown option 'baud'"));
+    }
+}
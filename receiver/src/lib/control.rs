@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::status::{eprint_status, Health};
+
+/// Shared pause state for the writer thread
+///
+/// While paused, incoming records are discarded instead of being written.
+/// The logger can be resumed programmatically or by creating a trigger file,
+/// which lets external tooling start a capture on a synchronized rig without
+/// having to open the port or create the output file at that moment.
+#[derive(Debug, Clone)]
+pub struct PauseControl {
+    paused: Arc<AtomicBool>,
+    resume_file: Option<PathBuf>,
+}
+
+impl PauseControl {
+    /// Creates a new pause control
+    ///
+    /// When starting paused, a trigger file left over from an earlier run is
+    /// removed, so it cannot resume the new one before it is asked to.
+    ///
+    /// # Arguments
+    /// * `start_paused` - Whether to begin in the paused state
+    /// * `resume_file` - Optional path whose appearance resumes writing
+    ///
+    /// # Returns
+    /// A new PauseControl instance
+    pub fn new(start_paused: bool, resume_file: Option<PathBuf>) -> Self {
+        if let Some(path) = resume_file
+            .as_deref()
+            .filter(|path| start_paused && path.exists())
+        {
+            let message = match std::fs::remove_file(path) {
+                Ok(()) => format!("Removed stale resume trigger {}", path.display()),
+                Err(e) => format!(
+                    "Failed to remove stale resume trigger {}: {}",
+                    path.display(),
+                    e
+                ),
+            };
+            eprint_status(Health::Degraded, &message);
+        }
+        PauseControl {
+            paused: Arc::new(AtomicBool::new(start_paused)),
+            resume_file,
+        }
+    }
+
+    /// Returns true if writing is currently paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Resumes writing
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Checks the resume trigger and resumes if it has fired
+    ///
+    /// The trigger file is removed once consumed so it can be reused for the
+    /// next capture.
+    ///
+    /// # Returns
+    /// true if writing is still paused after the check
+    pub fn poll(&self) -> bool {
+        if !self.is_paused() {
+            return false;
+        }
+
+        if let Some(path) = &self.resume_file {
+            if path.exists() {
+                println!("Resume trigger {} detected, resuming", path.display());
+                if let Err(e) = std::fs::remove_file(path) {
+                    eprintln!("Failed to remove resume trigger {}: {}", path.display(), e);
+                }
+                self.resume();
+            }
+        }
+
+        self.is_paused()
+    }
+}
+
+impl Default for PauseControl {
+    fn default() -> Self {
+        PauseControl::new(false, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resume_file_trigger() {
+        let temp_dir = tempdir().unwrap();
+        let trigger = temp_dir.path().join("resume");
+
+        // A trigger left from an earlier run does not resume the new one
+        std::fs::write(&trigger, b"").unwrap();
+        let control = PauseControl::new(true, Some(trigger.clone()));
+        assert!(control.poll(), "Should stay paused without a trigger");
+
+        std::fs::write(&trigger, b"").unwrap();
+        assert!(!control.poll(), "Trigger file should resume writing");
+        assert!(!trigger.exists(), "Trigger file should be consumed");
+    }
+}
@@ -0,0 +1,437 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::Fields;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::csv_writer::CsvProfile;
+use super::error::ReceiverError;
+use super::monotonic::{CounterWidth, TickExtender};
+use super::parquet_writer::WriterOptions;
+use super::replay::ReplayReader;
+use super::sink::{DataSink, SinkConfig, SinkRegistry};
+use super::status::{eprint_status, Health};
+use super::types::{CompressionType, FileNaming};
+
+/// Records per written batch of a converted file
+pub const CONVERT_BUFFER_SIZE: usize = 10_000;
+
+/// Columns derived from capture settings that the files do not store
+const DERIVED_COLUMNS: [&str; 4] = ["timestamp_ns", "reference_time", "valid", "clock_skew"];
+
+/// Expands a path whose file name may contain `*` and `?` wildcards
+///
+/// Only the last component is matched; the directory must be given
+/// literally. A pattern without wildcards is returned as is.
+///
+/// # Returns
+/// The matching files, sorted by name, or an error if none match
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let pattern: Vec<char> = name.chars().collect();
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+    {
+        let entry = entry?;
+        let file_name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if entry.file_type()?.is_file() && wildcard_match(&pattern, &file_name) {
+            paths.push(entry.path());
+        }
+    }
+    if paths.is_empty() {
+        return Err(ReceiverError::ConfigError(format!("No files match {}", name)).into());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+// Whether a name matches a pattern of literal characters, `*` and `?`
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some((c, rest)) => name
+            .split_first()
+            .is_some_and(|(n, name)| (*c == '?' || c == n) && wildcard_match(rest, name)),
+    }
+}
+
+/// Orders captured files by the host time of their first record
+///
+/// Files without any record are left out.
+pub fn order_by_start(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut starts = Vec::with_capacity(paths.len());
+    for path in paths {
+        match first_record_time(&path)? {
+            Some(start) => starts.push((start, path)),
+            None => println!("Skipping {}: no records", path.display()),
+        }
+    }
+    starts.sort();
+    Ok(starts.into_iter().map(|(_, path)| path).collect())
+}
+
+// Host time of the first record of a file, if it has any
+fn first_record_time(path: &Path) -> Result<Option<i64>> {
+    let running = Arc::new(AtomicBool::new(true));
+    let stop = running.clone();
+    let mut first = None;
+    ReplayReader::new(path).replay_loop(running, |data| {
+        first.get_or_insert(data.system_timestamp);
+        stop.store(false, Ordering::SeqCst);
+        Ok(())
+    })?;
+    Ok(first)
+}
+
+/// Column layout and run ID of a captured file
+///
+/// Files other than Parquet have the plain layout and no run ID.
+fn input_layout(path: &Path) -> Result<(Option<Fields>, Option<String>)> {
+    if path.extension().is_none_or(|ext| ext != "parquet") {
+        return Ok((None, None));
+    }
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let run_id = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kv| kv.iter().find(|kv| kv.key == "run_id"))
+        .and_then(|kv| kv.value.clone());
+    Ok((Some(builder.schema().fields().clone()), run_id))
+}
+
+/// Writer options reproducing the optional columns of the files to convert
+///
+/// The layout is taken from the first file: the nullable sensor columns and
+/// the `accel_mag`, `status`, `burst_id` and `session_id` columns are carried
+/// over, and the run ID is kept if all files share it. Files whose columns
+/// differ from the first, and files with columns derived from capture
+/// settings they do not store (`timestamp_ns`, `reference_time`, `valid`,
+/// `clock_skew`), are refused rather than losing columns.
+pub fn writer_options_for(inputs: &[PathBuf]) -> Result<WriterOptions> {
+    let mut options = WriterOptions {
+        extended_timestamp: true,
+        ..Default::default()
+    };
+    let layouts = inputs
+        .iter()
+        .map(|path| input_layout(path))
+        .collect::<Result<Vec<_>>>()?;
+    let Some(((fields, run_id), first)) = layouts.first().zip(inputs.first()) else {
+        return Ok(options);
+    };
+    for ((other, _), path) in layouts.iter().zip(inputs).skip(1) {
+        if other != fields {
+            return Err(ReceiverError::ConfigError(format!(
+                "{} does not have the columns of {}",
+                path.display(),
+                first.display()
+            ))
+            .into());
+        }
+    }
+    if layouts.iter().all(|(_, other)| other == run_id) {
+        options.run_id = run_id.clone();
+    }
+    let Some(fields) = fields else {
+        return Ok(options);
+    };
+
+    let field = |name: &str| fields.iter().find(|field| field.name() == name);
+    if let Some(name) = DERIVED_COLUMNS.iter().find(|name| field(name).is_some()) {
+        return Err(ReceiverError::ConfigError(format!(
+            "{} has a {} column, which cannot be rebuilt from the file",
+            first.display(),
+            name
+        ))
+        .into());
+    }
+    options.nullable_fields = field("temp").is_some_and(|field| field.is_nullable());
+    options.accel_mag = field("accel_mag").is_some();
+    options.status_column = field("status").is_some();
+    options.burst_column = field("burst_id").is_some();
+    options.session_column = field("session_id").is_some();
+    if options.session_column && options.run_id.is_none() {
+        return Err(ReceiverError::ConfigError(
+            "Files of different sessions cannot be joined into one session_id column".to_string(),
+        )
+        .into());
+    }
+    Ok(options)
+}
+
+/// Streams the records of several files, one after the other, into one sink
+///
+/// The files should be in time order, see [`order_by_start`]. The sensor
+/// counter, of the given width, is extended across all of them, so
+/// `extended_timestamp` keeps increasing where the counter wraps within a
+/// file or between two. A
+/// file starting before the previous one ended is reported as overlapping.
+///
+/// # Returns
+/// The number of records written
+pub fn concatenate(
+    paths: &[PathBuf],
+    width: CounterWidth,
+    sink: &mut dyn DataSink,
+) -> Result<usize> {
+    let mut ticks = TickExtender::new().with_width(width);
+    let mut last_time: Option<i64> = None;
+    let mut written = 0;
+    for path in paths {
+        let mut first = true;
+        let running = Arc::new(AtomicBool::new(true));
+        ReplayReader::new(path).replay_loop(running, |data| {
+            if first && last_time.is_some_and(|last| data.system_timestamp < last) {
+                let message = format!("{} overlaps the previous file", path.display());
+                eprint_status(Health::Degraded, &message);
+            }
+            first = false;
+            last_time = Some(data.system_timestamp);
+            sink.add_data(ticks.stamp(data))?;
+            written += 1;
+            Ok(())
+        })?;
+    }
+    Ok(written)
+}
+
+/// Converts the files matching a pattern into one file, in time order
+///
+/// The output format follows the extension of `to`: `parquet`, `csv`,
+/// `jsonl` or `imuc`. Parquet output keeps the optional columns of the
+/// inputs (see [`writer_options_for`]) and carries the `extended_timestamp`
+/// column. An existing `to` is only replaced with `overwrite`.
+///
+/// # Returns
+/// The number of records written
+pub fn convert(
+    from: &str,
+    to: &Path,
+    compression: CompressionType,
+    overwrite: bool,
+) -> Result<usize> {
+    let format = match to.extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => "parquet",
+        Some("csv") => "csv",
+        Some("jsonl") => "jsonl",
+        Some("imuc") => "compact",
+        _ => {
+            return Err(ReceiverError::ConfigError(format!(
+                "Unknown output format of {} (use .parquet, .csv, .jsonl or .imuc)",
+                to.display()
+            ))
+            .into())
+        }
+    };
+    if to.exists() && !overwrite {
+        return Err(ReceiverError::ConfigError(format!(
+            "{} already exists (use --overwrite to replace it)",
+            to.display()
+        ))
+        .into());
+    }
+    let inputs = order_by_start(expand_glob(from)?)?;
+    let options = writer_options_for(&inputs)?;
+
+    let output_dir = match to.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let config = SinkConfig {
+        output_dir: output_dir.to_string_lossy().into_owned(),
+        prefix: to
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        compression,
+        buffer_size: CONVERT_BUFFER_SIZE,
+        options,
+        max_records_per_file: None,
+        sort_on_flush: false,
+        file_per_row_group: false,
+        row_group_alignment: None,
+        file_naming: FileNaming::default(),
+        stats_sidecar: false,
+        csv_profile: CsvProfile::default(),
+    };
+    let mut sink = SinkRegistry::with_builtin_sinks().create(format, &config)?;
+    let written = concatenate(&inputs, CounterWidth::default(), sink.as_mut())?;
+
+    // The writers name their files after the time; move it to the requested name
+    if let Some(output_path) = sink.close()?.first().map(PathBuf::from) {
+        std::fs::rename(&output_path, to).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                output_path.display(),
+                to.display()
+            )
+        })?;
+    }
+    println!(
+        "Converted {} records from {} files into {}",
+        written,
+        inputs.len(),
+        to.display()
+    );
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParquetWriter, SensorData};
+    use tempfile::tempdir;
+
+    fn record(timestamp: u32, system_timestamp: i64) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp,
+            ..Default::default()
+        }
+    }
+
+    // Writes a Parquet file of records with consecutive counters
+    fn write_capture(dir: &str, prefix: &str, first: u32, start_ms: i64, count: u32) {
+        let mut writer = ParquetWriter::new(dir, prefix, CompressionType::None, 16).unwrap();
+        for i in 0..count {
+            let data = record(first.wrapping_add(i), start_ms + i as i64);
+            writer.add_data(data).unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let matches = |pattern: &str, name: &str| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            wildcard_match(&pattern, &name)
+        };
+        assert!(matches("session_*.parquet", "session_20250101.parquet"));
+        assert!(matches("session_*.parquet", "session_.parquet"));
+        assert!(matches("s?ss*", "session"));
+        assert!(!matches("session_*.parquet", "session_1.csv"));
+        assert!(!matches("?", ""));
+    }
+
+    #[test]
+    fn test_concatenated_files_continue_across_rollover() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().join("logs");
+        let dir = dir_path.to_str().unwrap();
+        // The later half sorts first by name; the counter wraps between the files
+        write_capture(dir, "session_a", 0, 2_000_000, 40);
+        write_capture(dir, "session_b", u32::MAX - 59, 1_999_940, 60);
+
+        let to = temp_dir.path().join("session.parquet");
+        let pattern = format!("{}/session_*.parquet", dir);
+        let written = convert(&pattern, &to, CompressionType::None, false).unwrap();
+        assert_eq!(written, 100);
+
+        let mut records = Vec::new();
+        ReplayReader::new(&to)
+            .replay_loop(Arc::new(AtomicBool::new(true)), |data| {
+                records.push(data);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(records.len(), 100);
+        assert_eq!(records[0].timestamp, u32::MAX - 59);
+        assert_eq!(records[99].timestamp, 39);
+        assert_eq!(records[99].extended_timestamp, Some(u32::MAX as i64 + 40));
+        assert!(records.windows(2).all(|pair| pair[1].system_timestamp
+            == pair[0].system_timestamp + 1
+            && pair[1].timestamp == pair[0].timestamp.wrapping_add(1)));
+    }
+
+    #[test]
+    fn test_converted_file_keeps_input_columns() {
+        use arrow::array::{Array, UInt32Array};
+
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().join("logs");
+        let dir = dir_path.to_str().unwrap();
+        let options = WriterOptions {
+            nullable_fields: true,
+            status_column: true,
+            burst_column: true,
+            run_id: Some("run-1".to_string()),
+            session_column: true,
+            ..Default::default()
+        };
+        for (prefix, start_ms) in [("tagged_a", 1_000), ("tagged_b", 2_000)] {
+            let mut writer = ParquetWriter::new_with_options(
+                dir,
+                prefix,
+                CompressionType::None,
+                16,
+                options.clone(),
+            )
+            .unwrap();
+            for i in 0..10 {
+                let data = SensorData {
+                    status: Some(3),
+                    burst_id: (i >= 5).then_some(1),
+                    ..record(i, start_ms + i as i64)
+                };
+                writer.add_data(data).unwrap();
+            }
+            writer.close().unwrap();
+        }
+
+        let to = temp_dir.path().join("tagged.parquet");
+        let pattern = format!("{}/tagged_*.parquet", dir);
+        convert(&pattern, &to, CompressionType::None, false).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&to).unwrap()).unwrap();
+        let schema = builder.schema().clone();
+        for name in ["status", "burst_id", "session_id", "extended_timestamp"] {
+            assert!(schema.field_with_name(name).is_ok(), "missing {}", name);
+        }
+        assert!(schema.field_with_name("temp").unwrap().is_nullable());
+        let batch = builder.build().unwrap().next().unwrap().unwrap();
+        let bursts = batch
+            .column_by_name("burst_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert_eq!(bursts.null_count(), 10);
+
+        // An existing output is only replaced on request
+        assert!(convert(&pattern, &to, CompressionType::None, false).is_err());
+        convert(&pattern, &to, CompressionType::None, true).unwrap();
+
+        // A file with another layout is refused rather than losing columns
+        write_capture(dir, "tagged_c", 0, 3_000, 10);
+        let to = temp_dir.path().join("mixed.parquet");
+        let err = convert(&pattern, &to, CompressionType::None, false).unwrap_err();
+        assert!(
+            err.to_string().contains("does not have the columns"),
+            "{}",
+            err
+        );
+        assert!(!to.exists());
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+
+use super::parquet_writer::new_output_path;
+use super::sink::DataSink;
+use super::types::SensorData;
+
+/// Header written by the legacy profile, as expected by the MATLAB importer
+pub const LEGACY_HEADER: &str =
+    "Counter,SystemTime_ms,Temp_degC,GyroX_rad_s,GyroY_rad_s,GyroZ_rad_s,AccX_g,AccY_g,AccZ_g";
+
+/// Header written by the standard profile, matching the Parquet column names
+pub const STANDARD_HEADER: &str = SensorData::CSV_HEADER;
+
+/// Named CSV formatting profile
+///
+/// A profile fixes the header, column order and float formatting of the
+/// written CSV so that downstream tools can rely on it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvProfile {
+    /// Parquet column names and order, shortest round-trip float formatting
+    #[default]
+    Standard,
+    /// Legacy MATLAB importer layout: counter and host time first, floats with
+    /// six fixed decimals
+    Legacy,
+}
+
+impl CsvProfile {
+    /// Header line for this profile (without line terminator)
+    pub fn header(&self) -> &'static str {
+        match self {
+            CsvProfile::Standard => STANDARD_HEADER,
+            CsvProfile::Legacy => LEGACY_HEADER,
+        }
+    }
+
+    /// Formats a record as a CSV line (without line terminator)
+    pub fn format_record(&self, data: &SensorData) -> String {
+        match self {
+            CsvProfile::Standard => data.to_csv_row(),
+            CsvProfile::Legacy => {
+                let values = [
+                    data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+                ];
+                let floats: Vec<String> = values.iter().map(|v| format!("{:.6}", v)).collect();
+                format!(
+                    "{},{},{}",
+                    data.timestamp,
+                    data.system_timestamp,
+                    floats.join(",")
+                )
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for CsvProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "standard" => Ok(CsvProfile::Standard),
+            "legacy" => Ok(CsvProfile::Legacy),
+            _ => Err(format!("Unknown CSV profile: {}", s)),
+        }
+    }
+}
+
+/// Writer for saving sensor data to CSV files
+///
+/// Mirrors the file naming and rotation of the Parquet writer, formatting
+/// records according to a `CsvProfile`.
+pub struct CsvWriter {
+    profile: CsvProfile,
+    output_path: String,
+    writer: BufWriter<File>,
+}
+
+impl CsvWriter {
+    /// Creates a new CSV writer and writes the profile's header
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where CSV files will be saved
+    /// * `prefix` - Filename prefix for CSV files
+    /// * `profile` - Formatting profile for the header and records
+    ///
+    /// # Returns
+    /// A new CsvWriter writing to a timestamped file
+    pub fn new(output_dir: &str, prefix: &str, profile: CsvProfile) -> Result<Self> {
+        create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {}", output_dir))?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix, profile)?;
+        println!("Created new CSV file: {}", output_path);
+
+        Ok(CsvWriter {
+            profile,
+            output_path,
+            writer,
+        })
+    }
+
+    /// Path of the file currently being written
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
+    /// Writes a record to the current file
+    pub fn add_data(&mut self, data: &SensorData) -> Result<()> {
+        writeln!(self.writer, "{}", self.profile.format_record(data))
+            .with_context(|| format!("Failed to write data to {}", self.output_path))
+    }
+
+    /// Flushes buffered lines to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to flush {}", self.output_path))
+    }
+
+    /// Closes the current file and starts a new one
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where the new CSV file will be saved
+    /// * `prefix` - Filename prefix for the new file
+    ///
+    /// # Returns
+    /// The path of the file closed
+    pub fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<String> {
+        self.flush()?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix, self.profile)?;
+        let closed = std::mem::replace(&mut self.output_path, output_path);
+        self.writer = writer;
+        println!("Rotated to new CSV file: {}", self.output_path);
+        Ok(closed)
+    }
+
+    /// Flushes and closes the current file, returning its path
+    pub fn close(mut self) -> Result<String> {
+        self.flush()?;
+        println!("Closed CSV file: {}", self.output_path);
+        Ok(self.output_path)
+    }
+
+    fn create_file(
+        output_dir: &str,
+        prefix: &str,
+        profile: CsvProfile,
+    ) -> Result<(String, BufWriter<File>)> {
+        let output_path = new_output_path(output_dir, prefix, "csv")
+            .to_string_lossy()
+            .to_string();
+        let file = File::create(&output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", profile.header())
+            .with_context(|| format!("Failed to write header to {}", output_path))?;
+        Ok((output_path, writer))
+    }
+}
+
+impl DataSink for CsvWriter {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        CsvWriter::add_data(self, &data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        CsvWriter::flush(self)
+    }
+
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+        Ok(vec![CsvWriter::rotate_file(self, output_dir, prefix)?])
+    }
+
+    fn close(self: Box<Self>) -> Result<Vec<String>> {
+        Ok(vec![CsvWriter::close(*self)?])
+    }
+
+    fn output_path(&self) -> Option<&str> {
+        Some(CsvWriter::output_path(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn test_record() -> SensorData {
+        SensorData {
+            timestamp: 1234,
+            temp: 25.5,
+            gx: 0.1,
+            gy: -0.2,
+            gz: 0.3,
+            az: 1.0,
+            system_timestamp: 1_700_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_legacy_profile_header() {
+        let dir = tempdir().unwrap();
+        let output_dir = dir.path().to_str().unwrap();
+        let mut writer = CsvWriter::new(output_dir, "legacy", CsvProfile::Legacy).unwrap();
+        writer.add_data(&test_record()).unwrap();
+        let path = writer.output_path().to_string();
+        writer.close().unwrap();
+
+        let content = std::fs::read_to_string(path).unwrap();
+        let mut lines = content.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "Counter,SystemTime_ms,Temp_degC,GyroX_rad_s,GyroY_rad_s,GyroZ_rad_s,AccX_g,AccY_g,AccZ_g"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "1234,1700000000000,25.500000,0.100000,-0.200000,0.300000,0.000000,0.000000,1.000000"
+        );
+    }
+
+    #[test]
+    fn test_standard_profile_record() {
+        let line = CsvProfile::Standard.format_record(&test_record());
+        assert_eq!(line, "1234,25.5,0.1,-0.2,0.3,0,0,1,1700000000000");
+    }
+}
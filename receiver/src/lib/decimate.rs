@@ -0,0 +1,204 @@
+use super::filter::Filter;
+use super::types::SensorData;
+
+/// How a decimator reduces each window of N records to one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimateMode {
+    /// Keep the first record of every window
+    #[default]
+    Every,
+    /// Average the float fields of the window
+    Average,
+}
+
+impl std::str::FromStr for DecimateMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "every" | "keep" => Ok(DecimateMode::Every),
+            "average" | "mean" => Ok(DecimateMode::Average),
+            _ => Err(format!("Unknown decimation mode: {}", s)),
+        }
+    }
+}
+
+/// Reduces the record rate by keeping every Nth record
+///
+/// An optional trigger marks records that must survive decimation, such as
+/// the sample at which motion crossed a threshold. The trigger is evaluated
+/// first and its keep flag overrides the decimation phase; the phase keeps
+/// advancing on every record, so the regular samples stay evenly spaced.
+///
+/// In [`DecimateMode::Average`], [`push`](Self::push) instead turns each
+/// window of N consecutive records into one record carrying the first
+/// record's timestamps and the mean of every float field. Fields that failed
+/// to decode are left out of the mean, and are null only if they failed in
+/// every record of the window. A window still incomplete when the source
+/// ends is discarded.
+#[derive(Debug, Clone)]
+pub struct Decimator {
+    factor: usize,
+    phase: usize,
+    trigger: Option<Filter>,
+    mode: DecimateMode,
+    // First record of the current averaging window, with per-field sums and counts
+    window: Option<(SensorData, [f64; 7], [u32; 7])>,
+}
+
+impl Decimator {
+    /// Creates a decimator keeping one record in `factor` (1 keeps all)
+    pub fn new(factor: usize) -> Self {
+        Decimator {
+            factor: factor.max(1),
+            phase: 0,
+            trigger: None,
+            mode: DecimateMode::Every,
+            window: None,
+        }
+    }
+
+    /// Sets how each window of records is reduced
+    pub fn with_mode(mut self, mode: DecimateMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Always keeps records matching the trigger expression
+    pub fn with_trigger(mut self, trigger: Filter) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    /// Returns whether a record is a trigger event
+    pub fn is_trigger(&self, data: &SensorData) -> bool {
+        self.trigger
+            .as_ref()
+            .is_some_and(|trigger| trigger.matches(data))
+    }
+
+    /// Returns whether the next record is kept
+    pub fn keep(&mut self, data: &SensorData) -> bool {
+        let triggered = self.is_trigger(data);
+        let in_phase = self.phase == 0;
+        self.phase = (self.phase + 1) % self.factor;
+        triggered || in_phase
+    }
+
+    /// Takes the next record and returns the record written in its place, if any
+    pub fn push(&mut self, data: SensorData) -> Option<SensorData> {
+        if self.mode == DecimateMode::Every {
+            return self.keep(&data).then_some(data);
+        }
+
+        let values = float_values(&data);
+        let (_, sums, counts) = self
+            .window
+            .get_or_insert_with(|| (data.clone(), [0.0; 7], [0; 7]));
+        for (index, value) in values.into_iter().enumerate() {
+            if !data.is_null(index) && value.is_finite() {
+                sums[index] += value as f64;
+                counts[index] += 1;
+            }
+        }
+        self.phase += 1;
+        if self.phase < self.factor {
+            return None;
+        }
+
+        self.phase = 0;
+        let (mut first, sums, counts) = self.window.take()?;
+        first.null_fields = 0;
+        let mut means = [f32::NAN; 7];
+        for index in 0..7 {
+            if counts[index] > 0 {
+                means[index] = (sums[index] / counts[index] as f64) as f32;
+            } else {
+                first.null_fields |= 1 << index;
+            }
+        }
+        [
+            first.temp, first.gx, first.gy, first.gz, first.ax, first.ay, first.az,
+        ] = means;
+        Some(first)
+    }
+}
+
+// Float fields in the order of SensorData::FLOAT_FIELDS
+fn float_values(data: &SensorData) -> [f32; 7] {
+    [
+        data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimation_keeps_trigger_events() {
+        let mut decimator = Decimator::new(10).with_trigger(Filter::parse("az > 5").unwrap());
+        // Spikes land between the decimation points
+        let spikes = [3, 17, 18, 55, 99];
+        let kept: Vec<u32> = (0..100)
+            .map(|i| SensorData {
+                timestamp: i,
+                temp: 25.0,
+                az: if spikes.contains(&i) { 9.0 } else { 1.0 },
+                ..Default::default()
+            })
+            .filter(|data| decimator.keep(data))
+            .map(|data| data.timestamp)
+            .collect();
+
+        for spike in spikes {
+            assert!(kept.contains(&spike), "trigger record {} dropped", spike);
+        }
+        let regular: Vec<u32> = kept.into_iter().filter(|i| !spikes.contains(i)).collect();
+        assert_eq!(regular, (0..100).step_by(10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_average_mode_averages_each_window() {
+        let mut decimator = Decimator::new(4).with_mode(DecimateMode::Average);
+        let out: Vec<SensorData> = (0..10)
+            .map(|i| SensorData {
+                timestamp: 100 + i,
+                temp: 25.0,
+                gx: i as f32,
+                gy: -(i as f32) * 2.0,
+                az: 1.0,
+                system_timestamp: 1_000 + i as i64,
+                ..Default::default()
+            })
+            .filter_map(|data| decimator.push(data))
+            .collect();
+
+        // 10 records make two full windows; the last two are discarded
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].timestamp, 100);
+        assert_eq!(out[0].system_timestamp, 1_000);
+        assert_eq!(out[0].gx, 1.5);
+        assert_eq!(out[0].gy, -3.0);
+        assert_eq!(out[0].temp, 25.0);
+        assert_eq!(out[1].timestamp, 104);
+        assert_eq!(out[1].gx, 5.5);
+        assert_eq!(out[1].az, 1.0);
+    }
+
+    #[test]
+    fn test_every_mode_push_keeps_every_nth() {
+        let mut decimator = Decimator::new(3);
+        let kept: Vec<u32> = (0..10)
+            .map(|i| SensorData {
+                timestamp: i,
+                temp: 25.0,
+                az: 1.0,
+                ..Default::default()
+            })
+            .filter_map(|data| decimator.push(data))
+            .map(|data| data.timestamp)
+            .collect();
+        assert_eq!(kept, vec![0, 3, 6, 9]);
+    }
+}
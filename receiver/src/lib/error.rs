@@ -16,4 +16,10 @@ pub enum ReceiverError {
 
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
+
+    #[error("Non-monotonic timestamp: {0}")]
+    MonotonicityError(String),
+
+    #[error("Lock poisoned: {0}")]
+    PoisonError(String),
 }
@@ -0,0 +1,313 @@
+use anyhow::Result;
+
+use super::error::ReceiverError;
+use super::types::SensorData;
+
+/// Record field that a filter expression can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Timestamp,
+    Temp,
+    Gx,
+    Gy,
+    Gz,
+    Ax,
+    Ay,
+    Az,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "timestamp" => Some(Field::Timestamp),
+            "temp" => Some(Field::Temp),
+            "gx" => Some(Field::Gx),
+            "gy" => Some(Field::Gy),
+            "gz" => Some(Field::Gz),
+            "ax" => Some(Field::Ax),
+            "ay" => Some(Field::Ay),
+            "az" => Some(Field::Az),
+            _ => None,
+        }
+    }
+
+    fn value(&self, data: &SensorData) -> f64 {
+        match self {
+            Field::Timestamp => data.timestamp as f64,
+            Field::Temp => data.temp as f64,
+            Field::Gx => data.gx as f64,
+            Field::Gy => data.gy as f64,
+            Field::Gz => data.gz as f64,
+            Field::Ax => data.ax as f64,
+            Field::Ay => data.ay as f64,
+            Field::Az => data.az as f64,
+        }
+    }
+}
+
+/// Comparison operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    fn holds(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+        }
+    }
+}
+
+/// Parsed filter expression tree
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Compare(Field, Comparison, f64),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, data: &SensorData) -> bool {
+        match self {
+            Expr::Compare(field, op, value) => op.holds(field.value(data), *value),
+            Expr::And(lhs, rhs) => lhs.eval(data) && rhs.eval(data),
+            Expr::Or(lhs, rhs) => lhs.eval(data) || rhs.eval(data),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Op(Comparison),
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+/// Record-level predicate such as `az > 2.0 || gx < -1.0`
+///
+/// The grammar is deliberately small: comparisons of a field (`timestamp`,
+/// `temp`, `gx`..`az`) against a number with `<`, `<=`, `>`, `>=`, `==` or
+/// `!=`, combined with `&&` and `||` (`&&` binds tighter) and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses a filter expression
+    ///
+    /// # Arguments
+    /// * `source` - Expression text, e.g. `az > 2.0 && temp < 40`
+    ///
+    /// # Returns
+    /// The parsed filter, or a parse error describing the first problem
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parse_error(format!("unexpected {:?}", token)));
+        }
+        Ok(Filter { expr })
+    }
+
+    /// Returns whether a record satisfies the filter
+    pub fn matches(&self, data: &SensorData) -> bool {
+        self.expr.eval(data)
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Filter::parse(s)
+    }
+}
+
+fn parse_error(message: String) -> anyhow::Error {
+    ReceiverError::ParseError(format!("Invalid filter: {}", message)).into()
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let (token, len) = match (c, next) {
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('<', Some('=')) => (Token::Op(Comparison::Le), 2),
+            ('>', Some('=')) => (Token::Op(Comparison::Ge), 2),
+            ('=', Some('=')) => (Token::Op(Comparison::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Comparison::Ne), 2),
+            ('<', _) => (Token::Op(Comparison::Lt), 1),
+            ('>', _) => (Token::Op(Comparison::Gt), 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                    .count();
+                let name: String = chars[i..i + len].iter().collect();
+                (Token::Ident(name), len)
+            }
+            _ if c.is_ascii_digit() || c == '.' || c == '-' || c == '+' => {
+                let mut len = 1;
+                while let Some(&d) = chars.get(i + len) {
+                    let exponent_sign =
+                        (d == '-' || d == '+') && matches!(chars[i + len - 1], 'e' | 'E');
+                    if d.is_ascii_digit() || d == '.' || d == 'e' || d == 'E' || exponent_sign {
+                        len += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let text: String = chars[i..i + len].iter().collect();
+                let value = text
+                    .parse::<f64>()
+                    .map_err(|_| parse_error(format!("invalid number '{}'", text)))?;
+                (Token::Number(value), len)
+            }
+            _ => return Err(parse_error(format!("unexpected character '{}'", c))),
+        };
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // or := and ("||" and)*
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    // and := term ("&&" term)*
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_term()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_term()?));
+        }
+        Ok(expr)
+    }
+
+    // term := "(" or ")" | field op number
+    fn parse_term(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(parse_error("missing ')'".to_string())),
+                }
+            }
+            Some(Token::Ident(name)) => {
+                let field = Field::from_name(&name)
+                    .ok_or_else(|| parse_error(format!("unknown field '{}'", name)))?;
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    _ => return Err(parse_error(format!("expected comparison after '{}'", name))),
+                };
+                match self.next() {
+                    Some(Token::Number(value)) => Ok(Expr::Compare(field, op, value)),
+                    _ => Err(parse_error(format!("expected number after '{}'", name))),
+                }
+            }
+            Some(token) => Err(parse_error(format!("unexpected {:?}", token))),
+            None => Err(parse_error("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(gx: f32, az: f32) -> SensorData {
+        SensorData {
+            timestamp: 1000,
+            temp: 25.0,
+            gx,
+            az,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_or_of_comparisons() {
+        let filter = Filter::parse("az > 2.0 || gx < -1.0").unwrap();
+        assert!(filter.matches(&record(0.0, 2.5)));
+        assert!(filter.matches(&record(-1.5, 0.0)));
+        assert!(!filter.matches(&record(0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        // && binds tighter than ||
+        let filter = Filter::parse("az>2 || gx<-1 && timestamp>=2000").unwrap();
+        assert!(!filter.matches(&record(-1.5, 0.0)));
+        assert!(filter.matches(&record(-1.5, 3.0)));
+
+        let filter = Filter::parse("(az > 2 || gx < -1) && temp == 25").unwrap();
+        assert!(filter.matches(&record(-1.5, 0.0)));
+        assert!(!filter.matches(&record(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_rejects_malformed_expressions() {
+        assert!(Filter::parse("").is_err());
+        assert!(Filter::parse("speed > 1").is_err());
+        assert!(Filter::parse("az >").is_err());
+        assert!(Filter::parse("az > 1 &&").is_err());
+        assert!(Filter::parse("(az > 1").is_err());
+        assert!(Filter::parse("az > 1 gx < 2").is_err());
+        assert!(Filter::parse("az = 1").is_err());
+    }
+}
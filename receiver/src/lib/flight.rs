@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaResult, Ticket,
+};
+use futures::channel::mpsc::{self, Sender};
+use futures::channel::oneshot;
+use futures::stream::{BoxStream, StreamExt};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+use super::parquet_writer::BatchSink;
+use super::status::{eprint_status, Health};
+
+/// Batches queued for each Flight client before further batches are dropped for it
+pub const FLIGHT_CLIENT_QUEUE: usize = 16;
+
+type Subscribers = Arc<Mutex<Vec<Subscriber>>>;
+
+/// Live stream of one `do_get` client
+struct Subscriber {
+    batches: Sender<RecordBatch>,
+    // Whether batches are currently being dropped for the client
+    lagging: bool,
+}
+
+/// Publishes record batches to every client streaming from the Flight server
+///
+/// Each `do_get` call subscribes to the live stream from that point on.
+/// Every client has a queue of [`FLIGHT_CLIENT_QUEUE`] batches; a client
+/// that does not keep up misses the batches published while its queue is
+/// full, with a warning, so it holds up neither the writer nor the memory.
+/// Dropping the publisher ends all client streams and stops the server.
+pub struct FlightPublisher {
+    subscribers: Subscribers,
+    // Batches dropped for lagging clients
+    dropped: AtomicU64,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl FlightPublisher {
+    /// Sends a batch to all current subscribers, forgetting disconnected ones
+    pub fn publish(&self, batch: RecordBatch) {
+        self.subscribers.lock().unwrap().retain_mut(|subscriber| {
+            match subscriber.batches.try_send(batch.clone()) {
+                Ok(()) => subscriber.lagging = false,
+                Err(e) if e.is_full() => {
+                    self.dropped.fetch_add(1, Ordering::SeqCst);
+                    if !subscriber.lagging {
+                        let message = "A Flight client is falling behind; dropping batches";
+                        eprint_status(Health::Degraded, message);
+                        subscriber.lagging = true;
+                    }
+                }
+                Err(_) => return false,
+            }
+            true
+        });
+    }
+
+    /// Wraps the publisher as the sink of a streaming `ParquetWriter`
+    pub fn into_sink(self) -> BatchSink {
+        Box::new(move |batch| {
+            self.publish(batch);
+            Ok(())
+        })
+    }
+}
+
+impl Drop for FlightPublisher {
+    fn drop(&mut self) {
+        // Ending the client streams lets the server shut down gracefully
+        self.subscribers.lock().unwrap().clear();
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(server) = self.server.take() {
+            let _ = server.join();
+        }
+        let dropped = self.dropped.load(Ordering::SeqCst);
+        if dropped > 0 {
+            let message = format!("Flight batches dropped for lagging clients: {}", dropped);
+            eprint_status(Health::Degraded, &message);
+        }
+    }
+}
+
+/// Starts an Arrow Flight server streaming the logged records
+///
+/// The server runs on its own thread with a dedicated runtime. Clients pull
+/// the stream with `do_get` (any ticket); other Flight calls are unsupported.
+///
+/// # Arguments
+/// * `addr` - Address to listen on
+/// * `schema` - Schema of the published batches
+///
+/// # Returns
+/// The publisher feeding connected clients
+pub fn start_flight_server(addr: SocketAddr, schema: SchemaRef) -> Result<FlightPublisher> {
+    let subscribers: Subscribers = Arc::new(Mutex::new(Vec::new()));
+    let service = RecordStreamService {
+        schema,
+        subscribers: subscribers.clone(),
+    };
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start the Flight runtime")?;
+    let (shutdown, stopped) = oneshot::channel::<()>();
+
+    let server = thread::spawn(move || {
+        runtime.block_on(async move {
+            println!("Arrow Flight server listening on {}", addr);
+            if let Err(e) = Server::builder()
+                .add_service(FlightServiceServer::new(service))
+                .serve_with_shutdown(addr, async {
+                    let _ = stopped.await;
+                })
+                .await
+            {
+                eprintln!("Error in Arrow Flight server: {}", e);
+            }
+        });
+    });
+
+    Ok(FlightPublisher {
+        subscribers,
+        dropped: AtomicU64::new(0),
+        shutdown: Some(shutdown),
+        server: Some(server),
+    })
+}
+
+struct RecordStreamService {
+    schema: SchemaRef,
+    subscribers: Subscribers,
+}
+
+#[tonic::async_trait]
+impl FlightService for RecordStreamService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn do_get(
+        &self,
+        _request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let (batches, rx) = mpsc::channel(FLIGHT_CLIENT_QUEUE);
+        self.subscribers.lock().unwrap().push(Subscriber {
+            batches,
+            lagging: false,
+        });
+
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(self.schema.clone())
+            .build(rx.map(Ok::<RecordBatch, FlightError>))
+            .map(|data| data.map_err(|e| Status::internal(e.to_string())));
+        Ok(Response::new(stream.boxed()))
+    }
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake is not supported"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights is not supported"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("get_flight_info is not supported"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("get_schema is not supported"))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("do_put is not supported"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action is not supported"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions is not supported"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange is not supported"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParquetWriter, SensorData, WriterOptions};
+    use arrow_flight::client::FlightClient;
+    use futures::TryStreamExt;
+    use std::time::Duration;
+    use tonic::transport::Channel;
+
+    #[test]
+    fn test_flight_client_pulls_stream() {
+        // Reserve a free port for the server
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let options = WriterOptions::default();
+        let schema = Arc::new(ParquetWriter::build_schema(&options));
+        let publisher = start_flight_server(addr, schema).unwrap();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let batches: Vec<RecordBatch> = runtime.block_on(async move {
+            let channel = loop {
+                let endpoint = Channel::from_shared(format!("http://{}", addr)).unwrap();
+                match endpoint.connect().await {
+                    Ok(channel) => break channel,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+                }
+            };
+            let mut client = FlightClient::new(channel);
+            let stream = client.do_get(Ticket::new("live")).await.unwrap();
+
+            // The client is subscribed; closing the writer ends the stream
+            let mut writer = ParquetWriter::for_stream(10, options, publisher.into_sink()).unwrap();
+            for i in 0..25 {
+                writer
+                    .add_data(SensorData {
+                        timestamp: i,
+                        temp: 25.0,
+                        az: 1.0,
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            writer.close().unwrap();
+
+            stream.try_collect().await.unwrap()
+        });
+
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(rows, 25);
+    }
+}
@@ -0,0 +1,31 @@
+/// Machine and account that produced a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub username: String,
+}
+
+impl HostInfo {
+    /// Looks up the host name and the name of the user running the receiver
+    ///
+    /// Either value is `unknown` if it cannot be determined.
+    pub fn gather() -> Self {
+        let hostname = gethostname::gethostname().to_string_lossy().into_owned();
+        // USER on Unix, USERNAME on Windows
+        let username = ["USER", "USERNAME", "LOGNAME"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()));
+        HostInfo {
+            hostname: non_empty_or_unknown(hostname),
+            username: non_empty_or_unknown(username.unwrap_or_default()),
+        }
+    }
+}
+
+fn non_empty_or_unknown(value: String) -> String {
+    if value.is_empty() {
+        "unknown".to_string()
+    } else {
+        value
+    }
+}
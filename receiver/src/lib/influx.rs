@@ -0,0 +1,486 @@
+use anyhow::Result;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::error::ReceiverError;
+use super::send_queue::{QueueReceiver, SendQueue};
+use super::sink::DataSink;
+use super::status::{eprint_status, Health};
+use super::types::SensorData;
+
+/// Batches waiting to be sent before new ones are dropped
+pub const DEFAULT_INFLUX_QUEUE: usize = 64;
+
+/// Time allowed for connecting and for each read or write of a request
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default wait before the first retry of a failed request; doubled on every retry
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// InfluxDB server and the bucket records are written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluxTarget {
+    pub host: String,
+    pub port: u16,
+    /// Path prefix of the API, empty for a server at the root
+    pub base_path: String,
+    pub bucket: String,
+    pub org: Option<String>,
+    pub token: Option<String>,
+    /// Wait before the first retry of a failed request
+    pub retry_backoff: Duration,
+}
+
+impl InfluxTarget {
+    /// Creates a target from a server URL of the form `http://host[:port][/path]`
+    pub fn new(url: &str, bucket: &str) -> Result<Self, ReceiverError> {
+        let invalid = || ReceiverError::ConfigError(format!("Invalid InfluxDB URL: {}", url));
+        let rest = match url.split_once("://") {
+            Some(("http", rest)) => rest,
+            Some((scheme, _)) => {
+                return Err(ReceiverError::ConfigError(format!(
+                    "Unsupported InfluxDB scheme: {} (only http is supported)",
+                    scheme
+                )))
+            }
+            None => url,
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        if bucket.is_empty() {
+            return Err(ReceiverError::ConfigError(
+                "An InfluxDB bucket is required".to_string(),
+            ));
+        }
+        Ok(InfluxTarget {
+            host: host.to_string(),
+            port,
+            base_path: path.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            org: None,
+            token: None,
+            retry_backoff: INITIAL_BACKOFF,
+        })
+    }
+
+    /// Sets the organization owning the bucket
+    pub fn with_org(mut self, org: Option<String>) -> Self {
+        self.org = org;
+        self
+    }
+
+    /// Sets the API token sent with every request
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Sets the wait before the first retry of a failed request
+    ///
+    /// The wait doubles on every further retry of the same batch, up to 30 s.
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Path and query of the write endpoint, with millisecond precision
+    pub fn write_path(&self) -> String {
+        let mut path = format!(
+            "{}/api/v2/write?bucket={}&precision=ms",
+            self.base_path,
+            percent_encode(&self.bucket)
+        );
+        if let Some(org) = &self.org {
+            path.push_str(&format!("&org={}", percent_encode(org)));
+        }
+        path
+    }
+}
+
+/// Formats a record as one line of InfluxDB line protocol (without terminator)
+///
+/// The line holds the float fields `temp`, `gx`, `gy`, `gz`, `ax`, `ay` and
+/// `az` and the `system_timestamp` in milliseconds. Fields that failed to
+/// decode are left out; None is returned if no field is left.
+pub fn influx_line(measurement: &str, data: &SensorData) -> Option<String> {
+    let values = [
+        data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+    ];
+    let fields: Vec<String> = SensorData::FLOAT_FIELDS
+        .iter()
+        .zip(values)
+        .enumerate()
+        .filter(|(index, (_, value))| !data.is_null(*index) && value.is_finite())
+        .map(|(_, (name, value))| format!("{}={}", name, value))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let measurement = measurement
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(' ', "\\ ");
+    Some(format!(
+        "{} {} {}",
+        measurement,
+        fields.join(","),
+        data.system_timestamp
+    ))
+}
+
+/// Sink writing records to InfluxDB over its HTTP write API
+///
+/// Records are collected into batches of the writer's buffer size, and every
+/// batch (including the partial one at each flush) is sent as one request by
+/// a background thread. Failed requests are retried with exponential
+/// backoff, so an outage of the server only delays the data; meanwhile new
+/// batches queue up and are dropped with a warning once the queue is full
+/// (see [`SendQueue`]). Requests the server rejects as malformed (4xx other
+/// than 429) are not retried. At shutdown a failing batch gets one more
+/// attempt without waiting out its backoff; if that fails too, it and the
+/// batches still queued are given up and counted.
+pub struct InfluxSink {
+    measurement: String,
+    batch_size: usize,
+    lines: Vec<String>,
+    queue: SendQueue<String>,
+    // Batches the server rejected or that were abandoned at shutdown
+    failed: Arc<AtomicU64>,
+}
+
+impl InfluxSink {
+    /// Starts a sink writing to the given target
+    ///
+    /// # Arguments
+    /// * `target` - Server and bucket
+    /// * `measurement` - Measurement name of every line
+    /// * `batch_size` - Records sent per request
+    /// * `capacity` - Batches queued before new ones are dropped
+    pub fn start(
+        target: InfluxTarget,
+        measurement: &str,
+        batch_size: usize,
+        capacity: usize,
+    ) -> Result<Self> {
+        if measurement.is_empty() {
+            return Err(ReceiverError::ConfigError(
+                "The InfluxDB measurement name must not be empty".to_string(),
+            )
+            .into());
+        }
+        let failed = Arc::new(AtomicU64::new(0));
+        let queue = {
+            let failed = failed.clone();
+            SendQueue::start("InfluxDB", "batches", capacity, move |batches| {
+                send_loop(target, batches, failed)
+            })
+        };
+        Ok(InfluxSink {
+            measurement: measurement.to_string(),
+            batch_size: batch_size.max(1),
+            lines: Vec::new(),
+            queue,
+            failed,
+        })
+    }
+
+    // Hands the collected lines to the sender thread as one batch
+    fn send_batch(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let mut body = self.lines.join("\n");
+        body.push('\n');
+        self.lines.clear();
+        self.queue.push(body);
+    }
+}
+
+impl DataSink for InfluxSink {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        if let Some(line) = influx_line(&self.measurement, &data) {
+            self.lines.push(line);
+        }
+        if self.lines.len() >= self.batch_size {
+            self.send_batch();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.send_batch();
+        Ok(())
+    }
+
+    fn rotate_file(&mut self, _output_dir: &str, _prefix: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn close(mut self: Box<Self>) -> Result<Vec<String>> {
+        self.send_batch();
+        self.queue.close();
+        let (dropped, failed) = (self.queue.dropped(), self.failed.load(Ordering::SeqCst));
+        if dropped > 0 || failed > 0 {
+            let message = format!(
+                "InfluxDB batches not written: {} dropped from a full queue, {} failed",
+                dropped, failed
+            );
+            eprint_status(Health::Degraded, &message);
+        }
+        Ok(Vec::new())
+    }
+}
+
+// Sends every queued batch, retrying failed requests with backoff
+fn send_loop(target: InfluxTarget, batches: QueueReceiver<String>, failed: Arc<AtomicU64>) {
+    while let Some(body) = batches.recv() {
+        let mut backoff = target.retry_backoff;
+        let mut reported = false;
+        let mut final_attempt = false;
+        loop {
+            let error = match post(&target, &body) {
+                Ok(status) if (200..300).contains(&status) => break,
+                Ok(status) if status != 429 && (400..500).contains(&status) => {
+                    let message = format!("InfluxDB rejected a batch (HTTP {})", status);
+                    eprint_status(Health::Failing, &message);
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+                Ok(status) => format!("HTTP {}", status),
+                Err(e) => e.to_string(),
+            };
+            if final_attempt {
+                // Shutting down while the server fails: give up on the rest
+                failed.fetch_add(1 + batches.abandon(), Ordering::SeqCst);
+                return;
+            }
+            if !reported {
+                let message = format!("InfluxDB write failed ({}); retrying", error);
+                eprint_status(Health::Degraded, &message);
+                reported = true;
+            }
+            // Closing ends the wait, for one last attempt
+            batches.sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            final_attempt = batches.is_closing();
+        }
+    }
+}
+
+// Posts one batch of lines, returning the HTTP status code
+fn post(target: &InfluxTarget, body: &str) -> io::Result<u16> {
+    let address = (target.host.as_str(), target.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "server address not found"))?;
+    let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+         Content-Length: {}\r\nConnection: close\r\n",
+        target.write_path(),
+        target.host,
+        target.port,
+        body.len()
+    );
+    if let Some(token) = &target.token {
+        request.push_str(&format!("Authorization: Token {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream.take(1024)).read_line(&mut status_line)?;
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))
+}
+
+// Encodes a query parameter value
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn record(timestamp: u32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.5,
+            gy: -1.25,
+            az: 1.0,
+            system_timestamp: 1_735_732_800_000 + timestamp as i64,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_line_protocol() {
+        let line = influx_line("imu", &record(5)).unwrap();
+        assert_eq!(
+            line,
+            "imu temp=25.5,gx=0,gy=-1.25,gz=0,ax=0,ay=0,az=1 1735732800005"
+        );
+
+        let mut data = record(0);
+        data.null_fields = 0b1;
+        data.temp = f32::NAN;
+        let line = influx_line("lab imu,1", &data).unwrap();
+        assert!(line.starts_with("lab\\ imu\\,1 gx=0,"));
+
+        data.null_fields = 0x7F;
+        assert!(influx_line("imu", &data).is_none());
+    }
+
+    #[test]
+    fn test_target_paths() {
+        let target = InfluxTarget::new("http://localhost:8086/influx/", "lab data")
+            .unwrap()
+            .with_org(Some("my org".to_string()));
+        assert_eq!(target.port, 8086);
+        assert_eq!(
+            target.write_path(),
+            "/influx/api/v2/write?bucket=lab%20data&precision=ms&org=my%20org"
+        );
+        assert!(InfluxTarget::new("https://localhost", "b").is_err());
+        assert!(InfluxTarget::new("http://localhost", "").is_err());
+    }
+
+    // Reads one request, returning its request line and body
+    fn read_request(reader: &mut BufReader<TcpStream>) -> (String, String) {
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header == "\r\n" {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+            if header.starts_with("Authorization") {
+                assert_eq!(header, "Authorization: Token secret\r\n");
+            }
+        }
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).unwrap();
+        (request_line, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_batches_retried_after_server_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Server failing the first request and accepting the rest
+        let server = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for (attempt, stream) in listener.incoming().take(3).enumerate() {
+                let mut reader = BufReader::new(stream.unwrap());
+                let (request_line, body) = read_request(&mut reader);
+                let status = if attempt == 0 {
+                    "503 Unavailable"
+                } else {
+                    "204 No Content"
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                bodies.push((request_line, body));
+            }
+            bodies
+        });
+
+        let target = InfluxTarget::new(&format!("http://127.0.0.1:{}", port), "lab")
+            .unwrap()
+            .with_token(Some("secret".to_string()));
+        let mut sink: Box<dyn DataSink> = Box::new(InfluxSink::start(target, "imu", 2, 8).unwrap());
+        for i in 0..3 {
+            sink.add_data(record(i)).unwrap();
+        }
+        sink.close().unwrap();
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0]
+            .0
+            .starts_with("POST /api/v2/write?bucket=lab&precision=ms HTTP/1.1"));
+        // The failed first batch is sent again, then the partial last one
+        assert_eq!(bodies[0].1, bodies[1].1);
+        assert_eq!(bodies[1].1.lines().count(), 2);
+        assert_eq!(bodies[2].1.lines().count(), 1);
+        assert!(bodies[2].1.ends_with(" 1735732800002\n"));
+    }
+
+    #[test]
+    fn test_close_gives_up_on_failing_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Server failing every request, announcing each one
+        let (requested, requests) = mpsc::channel();
+        let server = thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut reader = BufReader::new(stream.unwrap());
+                read_request(&mut reader);
+                let response = "HTTP/1.1 503 Unavailable\r\nContent-Length: 0\r\n\r\n";
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                requested.send(()).unwrap();
+            }
+        });
+
+        // A backoff far longer than the test may take
+        let backoff = Duration::from_secs(60);
+        let target = InfluxTarget::new(&format!("http://127.0.0.1:{}", port), "lab")
+            .unwrap()
+            .with_retry_backoff(backoff);
+        let mut sink = InfluxSink::start(target, "imu", 1, 8).unwrap();
+        let failed = sink.failed.clone();
+        for i in 0..4 {
+            sink.add_data(record(i)).unwrap();
+        }
+        // The first batch has failed and the sender is backing off
+        requests.recv().unwrap();
+
+        // Closing does not wait out the backoff nor retry each queued batch
+        let start = std::time::Instant::now();
+        Box::new(sink).close().unwrap();
+        assert!(start.elapsed() < backoff / 2);
+        server.join().unwrap();
+        assert_eq!(requests.try_iter().count(), 1);
+        assert_eq!(failed.load(Ordering::SeqCst), 4);
+    }
+}
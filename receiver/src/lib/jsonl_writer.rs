@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+
+use super::parquet_writer::new_output_path;
+use super::sink::DataSink;
+use super::types::SensorData;
+
+/// File extension of JSON Lines output
+pub const JSONL_EXTENSION: &str = "jsonl";
+
+/// Writer for saving sensor data to JSON Lines files
+///
+/// Each record is one line holding the object built by
+/// [`SensorData::to_json`], so fields that failed to decode, and non-finite
+/// values, are `null`. Files are named and rotated like Parquet files.
+pub struct JsonlWriter {
+    output_path: String,
+    writer: BufWriter<File>,
+    // Bytes written to the current file, for size-based rotation
+    bytes_written: u64,
+}
+
+impl JsonlWriter {
+    /// Creates a new JSON Lines writer
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where files will be saved
+    /// * `prefix` - Filename prefix for files
+    ///
+    /// # Returns
+    /// A new JsonlWriter writing to a timestamped file
+    pub fn new(output_dir: &str, prefix: &str) -> Result<Self> {
+        create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {}", output_dir))?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix)?;
+        println!("Created new JSON Lines file: {}", output_path);
+
+        Ok(JsonlWriter {
+            output_path,
+            writer,
+            bytes_written: 0,
+        })
+    }
+
+    /// Path of the file currently being written
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
+    /// Writes a record to the current file
+    pub fn add_data(&mut self, data: &SensorData) -> Result<()> {
+        let line = data.to_json().to_string();
+        writeln!(self.writer, "{}", line)
+            .with_context(|| format!("Failed to write data to {}", self.output_path))?;
+        self.bytes_written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Flushes buffered lines to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to flush {}", self.output_path))
+    }
+
+    /// Closes the current file and starts a new one
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where the new file will be saved
+    /// * `prefix` - Filename prefix for the new file
+    ///
+    /// # Returns
+    /// The path of the file closed
+    pub fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<String> {
+        self.flush()?;
+        let (output_path, writer) = Self::create_file(output_dir, prefix)?;
+        let closed = std::mem::replace(&mut self.output_path, output_path);
+        self.writer = writer;
+        self.bytes_written = 0;
+        println!("Rotated to new JSON Lines file: {}", self.output_path);
+        Ok(closed)
+    }
+
+    /// Flushes and closes the current file, returning its path
+    pub fn close(mut self) -> Result<String> {
+        self.flush()?;
+        println!("Closed JSON Lines file: {}", self.output_path);
+        Ok(self.output_path)
+    }
+
+    fn create_file(output_dir: &str, prefix: &str) -> Result<(String, BufWriter<File>)> {
+        let output_path = new_output_path(output_dir, prefix, JSONL_EXTENSION)
+            .to_string_lossy()
+            .to_string();
+        let file = File::create(&output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path))?;
+        Ok((output_path, BufWriter::new(file)))
+    }
+}
+
+impl DataSink for JsonlWriter {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        JsonlWriter::add_data(self, &data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        JsonlWriter::flush(self)
+    }
+
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+        Ok(vec![JsonlWriter::rotate_file(self, output_dir, prefix)?])
+    }
+
+    fn close(self: Box<Self>) -> Result<Vec<String>> {
+        Ok(vec![JsonlWriter::close(*self)?])
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    fn output_path(&self) -> Option<&str> {
+        Some(JsonlWriter::output_path(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn test_record(i: u32) -> SensorData {
+        SensorData {
+            timestamp: 0xFFFF_FFF0 + i,
+            temp: 25.5 + i as f32 * 0.25,
+            gx: 0.01 * i as f32,
+            gy: -0.02,
+            gz: 0.03,
+            ax: 0.1,
+            ay: -1.0 / 3.0,
+            az: 0.98765,
+            system_timestamp: 1_735_732_800_123 + i as i64,
+            monotonic_timestamp: 42_000_000 * i as i64,
+            status: (i == 1).then_some(7),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lines_parse_back_to_records() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = JsonlWriter::new(dir_path, "jsonl").unwrap();
+        let records: Vec<SensorData> = (0..4).map(test_record).collect();
+        for data in &records {
+            writer.add_data(data).unwrap();
+        }
+        let path = writer.output_path().to_string();
+        assert!(path.ends_with(".jsonl"));
+        writer.close().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), records.len());
+        for (line, data) in lines.iter().zip(&records) {
+            let object: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(object["timestamp"], data.timestamp);
+            assert_eq!(object["system_timestamp"], data.system_timestamp);
+            assert_eq!(object["monotonic_timestamp"], data.monotonic_timestamp);
+            let values = [
+                data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+            ];
+            for (name, value) in SensorData::FLOAT_FIELDS.iter().zip(values) {
+                // Floats survive the round trip exactly
+                assert_eq!(object[*name].as_f64().unwrap() as f32, value, "{}", name);
+            }
+            assert_eq!(object["status"].as_u64(), data.status.map(u64::from));
+        }
+    }
+}
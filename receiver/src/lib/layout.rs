@@ -0,0 +1,280 @@
+use anyhow::Result;
+use chrono::Utc;
+
+use super::error::ReceiverError;
+use super::serial::{monotonic_timestamp, split_fields};
+use super::types::SensorData;
+
+/// Sensor channel carried by a field of a serial line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Timestamp,
+    Temp,
+    Gx,
+    Gy,
+    Gz,
+    Ax,
+    Ay,
+    Az,
+}
+
+impl Channel {
+    const ALL: [Channel; 8] = [
+        Channel::Timestamp,
+        Channel::Temp,
+        Channel::Gx,
+        Channel::Gy,
+        Channel::Gz,
+        Channel::Ax,
+        Channel::Ay,
+        Channel::Az,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Channel::Timestamp => "timestamp",
+            Channel::Temp => "temp",
+            Channel::Gx => "gx",
+            Channel::Gy => "gy",
+            Channel::Gz => "gz",
+            Channel::Ax => "ax",
+            Channel::Ay => "ay",
+            Channel::Az => "az",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Channel> {
+        Channel::ALL
+            .into_iter()
+            .find(|channel| channel.name() == name)
+    }
+
+    fn set(&self, data: &mut SensorData, value: f32) {
+        match self {
+            Channel::Timestamp => data.timestamp = value as u32,
+            Channel::Temp => data.temp = value,
+            Channel::Gx => data.gx = value,
+            Channel::Gy => data.gy = value,
+            Channel::Gz => data.gz = value,
+            Channel::Ax => data.ax = value,
+            Channel::Ay => data.ay = value,
+            Channel::Az => data.az = value,
+        }
+    }
+}
+
+/// Encoding of one comma-separated hex field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldLayout {
+    /// A 32-bit word: the counter for `timestamp`, an f32 bit pattern otherwise
+    Word(Channel),
+    /// Two signed 16-bit integers (high half first), each multiplied by the scale
+    Paired(Channel, Channel),
+}
+
+/// Order and encoding of the fields of a serial line
+///
+/// The layout is written as a comma-separated list of channel names in the
+/// order the firmware sends them, e.g. the default
+/// `timestamp,temp,gx,gy,gz,ax,ay,az`. Two float channels joined with `:`
+/// (`ax:ay`) share one 32-bit field holding two signed 16-bit integers, the
+/// first channel in the high half; they are converted to floats with the
+/// paired scale (physical units per count).
+///
+/// `temp` may be left out for firmware that sends no temperature, such as
+/// the 7-field `timestamp,gx,gy,gz,ax,ay,az` layout (also available as
+/// `no-temp`); its value is then NaN and flagged as null. Every other
+/// channel must appear exactly once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineLayout {
+    fields: Vec<FieldLayout>,
+    paired_scale: f32,
+}
+
+impl Default for LineLayout {
+    fn default() -> Self {
+        LineLayout {
+            fields: Channel::ALL.into_iter().map(FieldLayout::Word).collect(),
+            paired_scale: 1.0,
+        }
+    }
+}
+
+impl LineLayout {
+    /// Parses a layout description
+    ///
+    /// # Arguments
+    /// * `spec` - Channel names in line order, e.g. `timestamp,temp,gx,gy,gz,ax:ay,az`,
+    ///   or `no-temp` for the 7-field layout without temperature
+    /// * `paired_scale` - Value of one count of a paired 16-bit field
+    ///
+    /// # Returns
+    /// The layout, or a configuration error if a channel is unknown, missing
+    /// or repeated, or if `timestamp` is paired
+    pub fn parse(spec: &str, paired_scale: f32) -> Result<LineLayout> {
+        if spec.trim() == "no-temp" {
+            return Self::parse("timestamp,gx,gy,gz,ax,ay,az", paired_scale);
+        }
+        let invalid = |message: String| -> anyhow::Error {
+            ReceiverError::ConfigError(format!("Invalid layout '{}': {}", spec, message)).into()
+        };
+        let channel = |name: &str| {
+            Channel::from_name(name.trim())
+                .ok_or_else(|| invalid(format!("unknown channel '{}'", name.trim())))
+        };
+
+        let mut fields = Vec::new();
+        for field in spec.split(',') {
+            fields.push(match field.split_once(':') {
+                Some((high, low)) => {
+                    let (high, low) = (channel(high)?, channel(low)?);
+                    if high == Channel::Timestamp || low == Channel::Timestamp {
+                        return Err(invalid("timestamp cannot be paired".to_string()));
+                    }
+                    FieldLayout::Paired(high, low)
+                }
+                None => FieldLayout::Word(channel(field)?),
+            });
+        }
+
+        let layout = LineLayout {
+            fields,
+            paired_scale,
+        };
+        for channel in Channel::ALL {
+            match layout.channels().filter(|&c| c == channel).count() {
+                1 => {}
+                0 if channel == Channel::Temp => {}
+                0 => return Err(invalid(format!("missing channel '{}'", channel.name()))),
+                _ => return Err(invalid(format!("repeated channel '{}'", channel.name()))),
+            }
+        }
+        Ok(layout)
+    }
+
+    // Channels in line order
+    fn channels(&self) -> impl Iterator<Item = Channel> + '_ {
+        self.fields.iter().flat_map(|field| match *field {
+            FieldLayout::Word(channel) => vec![channel],
+            FieldLayout::Paired(high, low) => vec![high, low],
+        })
+    }
+
+    /// Number of comma-separated fields in a line
+    pub fn field_count(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns whether lines carry a temperature field
+    pub fn has_temp(&self) -> bool {
+        self.channels().any(|channel| channel == Channel::Temp)
+    }
+
+    /// Parses a line of hex fields laid out as described
+    pub fn parse_line(&self, line: &str) -> Result<SensorData> {
+        let parts = split_fields(line);
+        if parts.len() != self.fields.len() {
+            return Err(ReceiverError::ParseError(format!(
+                "Expected {} parts, got {}: {}",
+                self.fields.len(),
+                parts.len(),
+                line
+            ))
+            .into());
+        }
+
+        let mut data = SensorData {
+            system_timestamp: Utc::now().timestamp_millis(),
+            monotonic_timestamp: monotonic_timestamp(),
+            ..Default::default()
+        };
+        if !self.has_temp() {
+            data.temp = f32::NAN;
+            data.null_fields |= 1;
+        }
+
+        for (part, field) in parts.iter().zip(&self.fields) {
+            let word = |name: &str| {
+                u32::from_str_radix(part, 16).map_err(|e| {
+                    ReceiverError::ParseError(format!("Invalid {}: {}, error: {}", name, part, e))
+                })
+            };
+            match *field {
+                FieldLayout::Word(Channel::Timestamp) => data.timestamp = word("timestamp")?,
+                FieldLayout::Word(channel) => {
+                    channel.set(&mut data, f32::from_bits(word(channel.name())?))
+                }
+                FieldLayout::Paired(high, low) => {
+                    let bits = word(&format!("{}:{}", high.name(), low.name()))?;
+                    let (high_count, low_count) = split_paired(bits);
+                    high.set(&mut data, high_count as f32 * self.paired_scale);
+                    low.set(&mut data, low_count as f32 * self.paired_scale);
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Splits a 32-bit word into its high and low halves as signed 16-bit integers
+pub fn split_paired(bits: u32) -> (i16, i16) {
+    ((bits >> 16) as u16 as i16, bits as u16 as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paired_field_decodes_signed_scaled_values() {
+        assert_eq!(split_paired(0xC000_4000), (-16384, 16384));
+        assert_eq!(split_paired(0x7FFF_8000), (i16::MAX, i16::MIN));
+
+        let layout = LineLayout::parse("timestamp,temp,gx,gy,gz,ax:ay,az", 1.0 / 16384.0).unwrap();
+        assert_eq!(layout.field_count(), 7);
+
+        let data = layout
+            .parse_line("00000123,41C80000,3F800000,40000000,40400000,C0004000,3F800000")
+            .unwrap();
+        assert_eq!(data.timestamp, 0x123);
+        assert_eq!(data.temp, 25.0);
+        assert_eq!((data.gx, data.gy, data.gz), (1.0, 2.0, 3.0));
+        assert_eq!((data.ax, data.ay), (-1.0, 1.0));
+        assert_eq!(data.az, 1.0);
+    }
+
+    #[test]
+    fn test_layout_rejects_incomplete_specs() {
+        assert!(LineLayout::parse("timestamp,temp,gx,gy,gz,ax,ay", 1.0).is_err());
+        assert!(LineLayout::parse("timestamp,temp,gx,gy,gz,ax,ay,az,az", 1.0).is_err());
+        assert!(LineLayout::parse("timestamp:temp,gx,gy,gz,ax,ay,az", 1.0).is_err());
+        assert!(LineLayout::parse("timestamp,temp,gx,gy,gz,ax,ay,bz", 1.0).is_err());
+
+        let default = LineLayout::parse("timestamp,temp,gx,gy,gz,ax,ay,az", 1.0).unwrap();
+        assert_eq!(default, LineLayout::default());
+    }
+
+    #[test]
+    fn test_seven_field_layout_without_temp() {
+        let layout = LineLayout::parse("no-temp", 1.0).unwrap();
+        assert_eq!(layout.field_count(), 7);
+        assert!(!layout.has_temp());
+        assert!(LineLayout::default().has_temp());
+
+        let data = layout
+            .parse_line("00000123,3F800000,40000000,40400000,BF800000,00000000,3F800000")
+            .unwrap();
+        assert_eq!(data.timestamp, 0x123);
+        assert!(data.temp.is_nan());
+        assert!(data.is_null(0));
+        assert_eq!((data.gx, data.gy, data.gz), (1.0, 2.0, 3.0));
+        assert_eq!((data.ax, data.ay, data.az), (-1.0, 0.0, 1.0));
+        assert!((1..7).all(|index| !data.is_null(index)));
+
+        // An 8-field line does not fit the layout
+        assert!(layout
+            .parse_line("00000123,41C80000,3F800000,40000000,40400000,BF800000,00000000,3F800000")
+            .is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::Utc;
+use serialport::SerialPort;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::error::ReceiverError;
+
+/// Modem control lines reported by a serial port
+///
+/// A line whose state the driver could not report (e.g. a USB adapter
+/// without the signal) is `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineStatus {
+    pub cts: Option<bool>,
+    pub dsr: Option<bool>,
+    pub cd: Option<bool>,
+    pub ri: Option<bool>,
+}
+
+impl LineStatus {
+    /// Samples the control lines of an open port
+    pub fn read(port: &mut dyn SerialPort) -> Self {
+        LineStatus {
+            cts: port.read_clear_to_send().ok(),
+            dsr: port.read_data_set_ready().ok(),
+            cd: port.read_carrier_detect().ok(),
+            ri: port.read_ring_indicator().ok(),
+        }
+    }
+}
+
+/// Line status samples taken over a capture, for the file metadata
+///
+/// The reader records a sample whenever it opens or reopens the port; the
+/// writer stores all samples so far under the `line_status` key of each
+/// file. Clones share the same samples.
+#[derive(Debug, Clone, Default)]
+pub struct LineStatusLog {
+    samples: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl LineStatusLog {
+    /// Creates an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sample taken at `event` (e.g. "open", "reconnect")
+    pub fn record(&self, event: &str, status: LineStatus) -> Result<(), ReceiverError> {
+        let sample = serde_json::json!({
+            "event": event,
+            "time": Utc::now().to_rfc3339(),
+            "cts": status.cts,
+            "dsr": status.dsr,
+            "cd": status.cd,
+            "ri": status.ri,
+        });
+        self.samples()?.push(sample);
+        Ok(())
+    }
+
+    /// Number of samples recorded
+    pub fn len(&self) -> Result<usize, ReceiverError> {
+        Ok(self.samples()?.len())
+    }
+
+    /// Returns whether no sample was recorded yet
+    pub fn is_empty(&self) -> Result<bool, ReceiverError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// All samples as a JSON array, oldest first
+    pub fn to_json(&self) -> Result<String, ReceiverError> {
+        Ok(serde_json::Value::Array(self.samples()?.clone()).to_string())
+    }
+
+    /// Locks the samples, failing if a thread panicked while holding them
+    fn samples(&self) -> Result<MutexGuard<'_, Vec<serde_json::Value>>, ReceiverError> {
+        self.samples
+            .lock()
+            .map_err(|_| ReceiverError::PoisonError("line status log".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_status_recorded_in_metadata() {
+        use crate::{parse_sensor_data, CompressionType, FileWriterWorker, ParquetWriter};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use std::sync::atomic::AtomicBool;
+        use std::sync::mpsc;
+        use tempfile::tempdir;
+
+        let status = LineStatus {
+            cts: Some(true),
+            dsr: Some(true),
+            cd: Some(true),
+            ri: Some(false),
+        };
+        let log = LineStatusLog::new();
+        log.record("open", status).unwrap();
+        assert_eq!(log.len().unwrap(), 1);
+
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let writer = ParquetWriter::new(&dir_path, "lines", CompressionType::None, 10).unwrap();
+        let worker = FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "lines".into())
+            .with_line_status_log(log.clone());
+        let (tx, rx) = mpsc::channel();
+        tx.send(parse_sensor_data("00000123,0,0,0,0,0,0,0").unwrap())
+            .unwrap();
+        drop(tx);
+        worker
+            .process_data_loop(rx, Arc::new(AtomicBool::new(true)))
+            .unwrap();
+
+        let path = std::fs::read_dir(&dir_path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let reader = SerializedFileReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        let metadata = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        let value = metadata
+            .iter()
+            .find(|kv| kv.key == "line_status")
+            .and_then(|kv| kv.value.clone())
+            .unwrap();
+        let samples: serde_json::Value = serde_json::from_str(&value).unwrap();
+        assert_eq!(samples[0]["event"], "open");
+        assert_eq!(samples[0]["cts"], true);
+        assert_eq!(samples[0]["dsr"], true);
+        assert_eq!(samples[0]["cd"], true);
+        assert_eq!(samples[0]["ri"], false);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Event label typed during a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    /// Host time the label was entered, in milliseconds since the Unix epoch
+    pub system_timestamp: i64,
+    pub label: String,
+}
+
+/// Companion file of markers, one JSON object per line
+///
+/// Each line holds `system_timestamp` (milliseconds, comparable with the
+/// data's `system_timestamp`), `time` (RFC 3339) and `label`. Lines are
+/// flushed as they are written, so markers survive an abrupt stop.
+pub struct MarkerLog {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl MarkerLog {
+    /// Creates (or truncates) the markers file
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create markers file: {}", path.display()))?;
+        Ok(MarkerLog {
+            path,
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Path of the markers file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stores a label with the given time
+    pub fn record_at(&mut self, label: &str, time: DateTime<Utc>) -> Result<Marker> {
+        let marker = Marker {
+            system_timestamp: time.timestamp_millis(),
+            label: label.to_string(),
+        };
+        let line = serde_json::json!({
+            "system_timestamp": marker.system_timestamp,
+            "time": time.to_rfc3339(),
+            "label": marker.label,
+        });
+        writeln!(self.file, "{}", line)
+            .and_then(|_| self.file.flush())
+            .with_context(|| format!("Failed to write markers file: {}", self.path.display()))?;
+        Ok(marker)
+    }
+
+    /// Stores a label with the current time
+    pub fn record(&mut self, label: &str) -> Result<Marker> {
+        self.record_at(label, Utc::now())
+    }
+}
+
+/// Records every non-empty input line as a marker
+///
+/// Meant to run on its own thread reading stdin; it returns at the end of
+/// the input, or at the next line once the running flag is cleared. Each
+/// recorded marker is passed to `on_marker`, e.g. to confirm it to the user.
+///
+/// # Returns
+/// The number of markers recorded
+pub fn marker_loop<R: BufRead>(
+    input: R,
+    mut log: MarkerLog,
+    running: Arc<AtomicBool>,
+    on_marker: &mut dyn FnMut(&Marker),
+) -> Result<usize> {
+    let mut recorded = 0;
+    for line in input.lines() {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        let line = line.context("Failed to read marker input")?;
+        let label = line.trim();
+        if label.is_empty() {
+            continue;
+        }
+        let marker = log.record(label)?;
+        on_marker(&marker);
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_typed_lines_recorded_with_timestamps() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("markers.jsonl");
+        let log = MarkerLog::create(&path).unwrap();
+
+        let input = Cursor::new("subject started walking\n\n  stairs \"up\"  \n");
+        let before = Utc::now().timestamp_millis();
+        let mut seen = Vec::new();
+        let recorded = marker_loop(input, log, Arc::new(AtomicBool::new(true)), &mut |m| {
+            seen.push(m.label.clone())
+        })
+        .unwrap();
+        let after = Utc::now().timestamp_millis();
+        assert_eq!(recorded, 2);
+        assert_eq!(seen, vec!["subject started walking", "stairs \"up\""]);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let markers: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let labels: Vec<_> = markers
+            .iter()
+            .map(|m| m["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["subject started walking", "stairs \"up\""]);
+        for marker in &markers {
+            let timestamp = marker["system_timestamp"].as_i64().unwrap();
+            assert!((before..=after).contains(&timestamp));
+            let time = DateTime::parse_from_rfc3339(marker["time"].as_str().unwrap()).unwrap();
+            assert_eq!(time.timestamp_millis(), timestamp);
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use anyhow::{Context, Result};
+use arrow::array::{ArrayRef, Float32Array, Int64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use std::collections::VecDeque;
+use std::fs::{create_dir_all, File};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use super::parquet_writer::{
+    build_writer_properties, check_buffer_size, new_output_path, wall_time_array, wall_time_type,
+    WriterOptions,
+};
+use super::types::{CompressionType, SensorData};
+
+/// Column suffixes of the two merged devices
+const DEVICE_SUFFIXES: [&str; 2] = ["a", "b"];
+
+/// Pairs records of two device streams by nearest host timestamp
+///
+/// Records of the primary stream (device A) are matched against the closest
+/// record of the secondary stream (device B) by `system_timestamp`. A primary
+/// record is only matched once a secondary record at or after its time has
+/// arrived (or the secondary stream ended), so the nearest candidate is known.
+/// Primary records with no secondary record within the tolerance are dropped.
+pub struct StreamMerger {
+    tolerance_ms: i64,
+    primary: VecDeque<SensorData>,
+    secondary: VecDeque<SensorData>,
+    unmatched: u64,
+}
+
+impl StreamMerger {
+    /// Creates a merger
+    ///
+    /// # Arguments
+    /// * `tolerance_ms` - Largest host-time difference of a matched pair
+    pub fn new(tolerance_ms: i64) -> Self {
+        StreamMerger {
+            tolerance_ms,
+            primary: VecDeque::new(),
+            secondary: VecDeque::new(),
+            unmatched: 0,
+        }
+    }
+
+    /// Queues a record from device A
+    pub fn push_primary(&mut self, data: SensorData) {
+        self.primary.push_back(data);
+    }
+
+    /// Queues a record from device B
+    pub fn push_secondary(&mut self, data: SensorData) {
+        self.secondary.push_back(data);
+    }
+
+    /// Number of device A records dropped for lack of a close enough partner
+    pub fn unmatched(&self) -> u64 {
+        self.unmatched
+    }
+
+    /// Returns the next aligned pair, if one can be decided yet
+    ///
+    /// # Arguments
+    /// * `secondary_done` - Whether device B will deliver no more records
+    pub fn next_pair(&mut self, secondary_done: bool) -> Option<(SensorData, SensorData)> {
+        loop {
+            let time = self.primary.front()?.system_timestamp;
+
+            // Skip secondary records that a later one is at least as close to
+            while self.secondary.len() >= 2
+                && (self.secondary[1].system_timestamp - time).abs()
+                    <= (self.secondary[0].system_timestamp - time).abs()
+            {
+                self.secondary.pop_front();
+            }
+
+            let candidate = match self.secondary.front() {
+                Some(candidate) => candidate,
+                None if secondary_done => {
+                    self.unmatched += self.primary.len() as u64;
+                    self.primary.clear();
+                    return None;
+                }
+                None => return None,
+            };
+            // A later secondary record might still be closer
+            if self.secondary.len() == 1 && candidate.system_timestamp < time && !secondary_done {
+                return None;
+            }
+
+            let primary = self.primary.pop_front()?;
+            if (candidate.system_timestamp - time).abs() <= self.tolerance_ms {
+                return Some((primary, candidate.clone()));
+            }
+            self.unmatched += 1;
+        }
+    }
+}
+
+/// Writer for merged two-device records
+///
+/// Each row holds the fields of both devices, suffixed `_a` and `_b`.
+pub struct MergedParquetWriter {
+    schema: Arc<Schema>,
+    buffer: Vec<(SensorData, SensorData)>,
+    buffer_size: usize,
+    output_path: String,
+    writer: ArrowWriter<File>,
+}
+
+impl MergedParquetWriter {
+    /// Creates a new merged Parquet writer
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where the Parquet file will be saved
+    /// * `prefix` - Filename prefix for the Parquet file
+    /// * `compression` - Compression type to use
+    /// * `buffer_size` - Number of rows to buffer before writing
+    /// * `max_row_group_size` - Largest number of rows per row group, if limited
+    ///
+    /// # Returns
+    /// A new MergedParquetWriter writing to a timestamped file
+    pub fn new(
+        output_dir: &str,
+        prefix: &str,
+        compression: CompressionType,
+        buffer_size: usize,
+        max_row_group_size: Option<usize>,
+    ) -> Result<Self> {
+        check_buffer_size(buffer_size)?;
+        let schema = Arc::new(Self::build_schema());
+        create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create output directory: {}", output_dir))?;
+
+        let output_path = new_output_path(output_dir, prefix, "parquet")
+            .to_string_lossy()
+            .to_string();
+        let file = File::create(&output_path)
+            .with_context(|| format!("Failed to create file: {}", output_path))?;
+        let options = WriterOptions {
+            max_row_group_size,
+            ..Default::default()
+        };
+        let props = build_writer_properties(&compression, &options);
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))
+            .with_context(|| format!("Failed to create Parquet writer for {}", output_path))?;
+        println!("Created new merged Parquet file: {}", output_path);
+
+        Ok(MergedParquetWriter {
+            schema,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size,
+            output_path,
+            writer,
+        })
+    }
+
+    /// Builds the merged schema: every sensor column once per device
+    pub fn build_schema() -> Schema {
+        let mut fields = Vec::new();
+        for suffix in DEVICE_SUFFIXES {
+            fields.push(Field::new(
+                format!("timestamp_{}", suffix),
+                DataType::Int64,
+                false,
+            ));
+            for name in ["temp", "gx", "gy", "gz", "ax", "ay", "az"] {
+                fields.push(Field::new(
+                    format!("{}_{}", name, suffix),
+                    DataType::Float32,
+                    false,
+                ));
+            }
+            fields.push(Field::new(
+                format!("system_timestamp_{}", suffix),
+                wall_time_type(),
+                false,
+            ));
+        }
+        Schema::new(fields)
+    }
+
+    /// Path of the file being written
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
+    /// Adds an aligned pair, flushing when the buffer is full
+    pub fn add_pair(&mut self, pair: (SensorData, SensorData)) -> Result<()> {
+        self.buffer.push(pair);
+        if self.buffer.len() >= self.buffer_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes buffered rows to the file
+    pub fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut columns: Vec<ArrayRef> = Vec::new();
+        for device in 0..DEVICE_SUFFIXES.len() {
+            let records: Vec<&SensorData> = self
+                .buffer
+                .iter()
+                .map(|(a, b)| if device == 0 { a } else { b })
+                .collect();
+            let floats = |f: fn(&SensorData) -> f32| -> ArrayRef {
+                Arc::new(records.iter().map(|d| f(d)).collect::<Float32Array>())
+            };
+            columns.push(Arc::new(
+                records
+                    .iter()
+                    .map(|d| d.timestamp as i64)
+                    .collect::<Int64Array>(),
+            ));
+            columns.extend([
+                floats(|d| d.temp),
+                floats(|d| d.gx),
+                floats(|d| d.gy),
+                floats(|d| d.gz),
+                floats(|d| d.ax),
+                floats(|d| d.ay),
+                floats(|d| d.az),
+            ]);
+            columns.push(Arc::new(wall_time_array(
+                records.iter().map(|d| d.system_timestamp),
+            )));
+        }
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)
+            .with_context(|| "Failed to create merged record batch")?;
+        self.writer
+            .write(&batch)
+            .with_context(|| format!("Failed to write data to {}", self.output_path))?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Flushes remaining rows and finalizes the file
+    pub fn close(mut self) -> Result<()> {
+        self.flush()?;
+        self.writer
+            .close()
+            .with_context(|| format!("Failed to close Parquet writer for {}", self.output_path))?;
+        println!("Closed merged Parquet file: {}", self.output_path);
+        Ok(())
+    }
+}
+
+/// Record from one of the merged channels, `None` once that channel ends
+enum MergeInput {
+    Primary(Option<SensorData>),
+    Secondary(Option<SensorData>),
+}
+
+// Passes the records of one reader channel on to the merger's channel
+fn forward(
+    rx: Receiver<SensorData>,
+    tx: Sender<MergeInput>,
+    wrap: fn(Option<SensorData>) -> MergeInput,
+) {
+    thread::spawn(move || {
+        for data in rx {
+            if tx.send(wrap(Some(data))).is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(wrap(None));
+    });
+}
+
+/// Merges the records of two reader channels into one Parquet file
+///
+/// Runs until both channels disconnect, i.e. both readers have stopped and
+/// sent their last records, then writes any pairs that can still be decided
+/// and closes the file.
+///
+/// # Arguments
+/// * `rx_a` - Channel of device A (the primary stream, one row per record)
+/// * `rx_b` - Channel of device B (matched to each device A record)
+/// * `merger` - Alignment state and tolerance
+/// * `writer` - Destination of the merged rows
+pub fn merge_loop(
+    rx_a: Receiver<SensorData>,
+    rx_b: Receiver<SensorData>,
+    mut merger: StreamMerger,
+    mut writer: MergedParquetWriter,
+) -> Result<()> {
+    println!("Stream merger thread started");
+    // Both channels feed one, so the merger sleeps until either has a record
+    let (tx, rx) = mpsc::channel();
+    forward(rx_a, tx.clone(), MergeInput::Primary);
+    forward(rx_b, tx, MergeInput::Secondary);
+
+    let mut b_done = false;
+    for input in rx {
+        match input {
+            MergeInput::Primary(Some(data)) => merger.push_primary(data),
+            MergeInput::Secondary(Some(data)) => merger.push_secondary(data),
+            MergeInput::Secondary(None) => b_done = true,
+            MergeInput::Primary(None) => {}
+        }
+        while let Some(pair) = merger.next_pair(b_done) {
+            writer.add_pair(pair)?;
+        }
+    }
+
+    // Both readers have stopped; everything queued so far is final
+    while let Some(pair) = merger.next_pair(true) {
+        writer.add_pair(pair)?;
+    }
+
+    if merger.unmatched() > 0 {
+        println!(
+            "Dropped {} device A records without a device B record within tolerance",
+            merger.unmatched()
+        );
+    }
+    writer.close()?;
+    println!("Stream merger thread shutting down");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerialReaderWorker;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    fn record(timestamp: u32, system_timestamp: i64) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_nearest_alignment() {
+        let mut merger = StreamMerger::new(4);
+        for (i, t) in [100, 110, 120, 200].into_iter().enumerate() {
+            merger.push_primary(record(i as u32, t));
+        }
+        merger.push_secondary(record(0, 98));
+        merger.push_secondary(record(1, 107));
+        // 110 and 120 need a later device B record before they can be decided
+        let (a, b) = merger.next_pair(false).unwrap();
+        assert_eq!((a.timestamp, b.timestamp), (0, 0));
+        assert!(merger.next_pair(false).is_none());
+
+        merger.push_secondary(record(2, 121));
+        let (a, b) = merger.next_pair(false).unwrap();
+        assert_eq!((a.timestamp, b.timestamp), (1, 1));
+        let (a, b) = merger.next_pair(false).unwrap();
+        assert_eq!((a.timestamp, b.timestamp), (2, 2));
+
+        // Nothing within tolerance of 200 once device B ended
+        assert!(merger.next_pair(true).is_none());
+        assert_eq!(merger.unmatched(), 1);
+    }
+
+    #[test]
+    fn test_merge_simulated_streams() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+
+        let running = Arc::new(AtomicBool::new(true));
+        let mut channels = Vec::new();
+        let mut readers = Vec::new();
+        for port in ["sim_a", "sim_b"] {
+            let (tx, rx) = mpsc::channel();
+            let reader = SerialReaderWorker::new(port.to_string(), 115200);
+            let running = running.clone();
+            readers.push(thread::spawn(move || {
+                reader
+                    .simulate_data_loop(running, move |data| {
+                        tx.send(data)
+                            .map_err(|e| anyhow::anyhow!("Channel send error: {}", e))
+                    })
+                    .unwrap();
+            }));
+            channels.push(rx);
+        }
+        let rx_b = channels.pop().unwrap();
+        let rx_a = channels.pop().unwrap();
+
+        let writer =
+            MergedParquetWriter::new(dir_path, "merged", CompressionType::None, 8, Some(4))
+                .unwrap();
+        let path = writer.output_path().to_string();
+        merge_loop(rx_a, rx_b, StreamMerger::new(50), writer).unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        // Each flush of 8 rows is split at the row group size
+        let row_groups = builder.metadata().row_groups();
+        assert!(row_groups.iter().all(|group| group.num_rows() <= 4));
+        let batches: Vec<RecordBatch> = builder
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap())
+            .collect();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert!(
+            rows >= 18,
+            "Expected nearly all 20 records merged, got {}",
+            rows
+        );
+
+        let batch = &batches[0];
+        for name in ["gx_a", "az_a", "gx_b", "az_b"] {
+            assert!(batch.column_by_name(name).is_some(), "missing {}", name);
+        }
+        // Both simulated boards emit the same counter sequence in lockstep
+        let counters = |name| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .values()
+                .to_vec()
+        };
+        assert_eq!(counters("timestamp_a"), counters("timestamp_b"));
+        for name in ["system_timestamp_a", "system_timestamp_b"] {
+            let field = batch.schema().field_with_name(name).unwrap().clone();
+            assert_eq!(field.data_type(), &crate::parquet_writer::wall_time_type());
+        }
+    }
+}
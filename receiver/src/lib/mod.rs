@@ -1,11 +1,118 @@
+pub mod append;
 pub mod async_worker;
+pub mod burst;
+pub mod calibration;
+pub mod clock_check;
+pub mod compact;
+pub mod config_file;
+pub mod control;
+pub mod convert;
+pub mod csv_writer;
+pub mod decimate;
 pub mod error;
+pub mod filter;
+#[cfg(feature = "flight")]
+pub mod flight;
+pub mod host_info;
+pub mod influx;
+pub mod jsonl_writer;
+pub mod layout;
+pub mod line_status;
+pub mod markers;
+pub mod merge;
+pub mod monotonic;
+pub mod mqtt;
+pub mod nonfinite;
+pub mod on_change;
+pub mod output_lock;
 pub mod parquet_writer;
+pub mod parse_pool;
+pub mod preview;
+pub mod range_check;
+pub mod rate_detector;
+pub mod raw_capture;
+pub mod replay;
+pub mod rotation_hook;
+pub mod run_id;
+pub mod schema_check;
+pub mod send_queue;
 pub mod serial;
+pub mod sink;
+pub mod source;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod timestamp;
+pub mod tuning;
 pub mod types;
+pub mod watchdog;
 
-pub use async_worker::{FileWriterWorker, SerialReaderWorker};
+pub use append::{
+    latest_pointer_path, resume_run_id, session_files, write_latest_pointer,
+    DEFAULT_APPEND_MAX_AGE_MINUTES, LATEST_EXTENSION,
+};
+pub use async_worker::{
+    Clock, FileWriterWorker, SerialReaderWorker, SpaceQuery, DEFAULT_MAX_DROPPED_BATCHES,
+    DEFAULT_RECONNECT_MAX_DELAY,
+};
+pub use burst::{BurstDetector, DEFAULT_BURST_WINDOW};
+pub use calibration::{AxisCalibration, Calibration, CalibrationEstimator};
+pub use clock_check::{ClockCheck, ClockSkewPolicy};
+pub use compact::{
+    CompactBinaryReader, CompactBinaryWriter, COMPACT_EXTENSION, COMPACT_MAGIC, COMPACT_VERSION,
+};
+pub use config_file::{args_with_config, CONFIG_ARG};
+pub use control::PauseControl;
+pub use convert::{concatenate, convert, expand_glob, order_by_start, writer_options_for};
+pub use csv_writer::{CsvProfile, CsvWriter};
+pub use decimate::{DecimateMode, Decimator};
 pub use error::ReceiverError;
-pub use parquet_writer::ParquetWriter;
-pub use serial::{open_serial_port, parse_sensor_data, read_serial_data};
-pub use types::{CompressionType, SensorData};
+pub use filter::Filter;
+pub use host_info::HostInfo;
+pub use influx::{influx_line, InfluxSink, InfluxTarget, DEFAULT_INFLUX_QUEUE};
+pub use jsonl_writer::{JsonlWriter, JSONL_EXTENSION};
+pub use layout::{split_paired, LineLayout};
+pub use line_status::{LineStatus, LineStatusLog};
+pub use markers::{marker_loop, Marker, MarkerLog};
+pub use merge::{merge_loop, MergedParquetWriter, StreamMerger};
+pub use monotonic::{CounterWidth, MonotonicCheck, MonotonicPolicy, TickExtender};
+pub use mqtt::{MqttSink, MqttUrl, DEFAULT_MQTT_QUEUE};
+pub use nonfinite::{NonFiniteHandler, NonFinitePolicy};
+pub use on_change::ChangeFilter;
+pub use output_lock::OutputLock;
+pub use parquet_writer::{BatchSink, ParquetWriter, WriterOptions};
+pub use parse_pool::{LineParser, ParsePool};
+pub use preview::{PreviewSeries, DEFAULT_PREVIEW_BUCKETS, DEFAULT_PREVIEW_SIZE};
+pub use range_check::{RangeCheck, RangePolicy};
+pub use rate_detector::{RateChangeDetector, RateObservation};
+pub use raw_capture::RawCapture;
+pub use replay::{records_from_batch, ReplayClock, ReplayLoops, ReplayReader, ReplayWindow};
+pub use rotation_hook::RotationHook;
+pub use run_id::generate_run_id;
+pub use schema_check::{schema_drift, schema_json, validate_file};
+pub use serial::{
+    crc16, detect_data_format, encode_binary_frame, is_fatal_port_error, monotonic_timestamp,
+    open_serial_port, parse_binary_sensor_data, parse_sensor_data, parse_sensor_data_lenient,
+    parse_untimed_sensor_data, read_auto_detect_data, read_binary_sensor_data, read_serial_data,
+    read_serial_data_with_capture, split_fields, swap_byte_order, unpack_timestamp_status,
+    AutoDetectRead, AutoDetectState, BannerWait, BaudRateCheck, BinaryFrameBuffer, ByteOrderCheck,
+    SerialFraming, TimestampSynthesizer, BINARY_FRAME_LEN, BINARY_SYNC,
+};
+pub use sink::{
+    split_format_names, DataSink, FanoutSink, SinkConfig, SinkConstructor, SinkFailurePolicy,
+    SinkRegistry,
+};
+pub use source::{
+    run, run_bounded, BackpressurePolicy, BoundedSender, CalibratingSource, DataSource,
+    DecimatedSource, SimulatedSource, DEFAULT_CHANNEL_CAPACITY,
+};
+pub use state::{SessionState, STATE_MAGIC, STATE_VERSION};
+pub use stats::{
+    CaptureSummary, FileStats, GapTracker, InFlightGauge, P2Quantile, RateWindow, ReadCounters,
+    RunningStats,
+};
+pub use status::{disable_color, Health};
+pub use timestamp::{DeviceClock, ReadInterpolator, ReferenceClock, Resampler, TimestampMode};
+pub use tuning::{TuningParams, TuningPreset};
+pub use types::{CompressionType, DataFormat, FileNaming, PrimaryTime, SensorData};
+pub use watchdog::{NotifyEvent, NotifyHook, Watchdog};
@@ -0,0 +1,263 @@
+use anyhow::Result;
+
+use super::error::ReceiverError;
+use super::types::SensorData;
+
+/// What to do when the sensor timestamp fails to increase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MonotonicPolicy {
+    /// Stop the capture with an error
+    #[default]
+    Abort,
+    /// Keep the record and count the violation
+    Count,
+}
+
+impl std::str::FromStr for MonotonicPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "abort" => Ok(MonotonicPolicy::Abort),
+            "count" => Ok(MonotonicPolicy::Count),
+            _ => Err(format!("Unknown monotonic policy: {}", s)),
+        }
+    }
+}
+
+/// Checks that sensor timestamps are strictly increasing
+///
+/// Works on counts extended across rollovers by a [`TickExtender`], so the
+/// counter wrapping to small values counts as a step forward whatever its
+/// width. A repeated count or a step backwards is a violation.
+#[derive(Debug)]
+pub struct MonotonicCheck {
+    policy: MonotonicPolicy,
+    previous: Option<i64>,
+    violations: usize,
+}
+
+impl MonotonicCheck {
+    /// Creates a check applying the given policy to violations
+    pub fn new(policy: MonotonicPolicy) -> Self {
+        MonotonicCheck {
+            policy,
+            previous: None,
+            violations: 0,
+        }
+    }
+
+    /// Number of violations seen so far
+    pub fn violations(&self) -> usize {
+        self.violations
+    }
+
+    /// Feeds the extended count of the next record into the check
+    ///
+    /// # Returns
+    /// An error on a violation under [`MonotonicPolicy::Abort`]; Ok otherwise
+    pub fn observe(&mut self, timestamp: i64) -> Result<()> {
+        let previous = self.previous.replace(timestamp);
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        if timestamp > previous {
+            return Ok(());
+        }
+
+        self.violations += 1;
+        let message = format!(
+            "Timestamp did not increase: {} followed {}",
+            timestamp, previous
+        );
+        match self.policy {
+            MonotonicPolicy::Abort => Err(ReceiverError::MonotonicityError(message).into()),
+            MonotonicPolicy::Count => Ok(()),
+        }
+    }
+}
+
+/// Number of bits the sensor counter counts in before it rolls over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CounterWidth {
+    /// 24-bit counter, left when the status byte is packed into the top of
+    /// the timestamp field (`--packed-timestamp`)
+    Bits24,
+    /// Full u32 counter
+    #[default]
+    Bits32,
+}
+
+impl CounterWidth {
+    /// Number of distinct counter values (2^24 or 2^32)
+    pub fn modulus(self) -> i64 {
+        match self {
+            CounterWidth::Bits24 => 1 << 24,
+            CounterWidth::Bits32 => 1 << 32,
+        }
+    }
+}
+
+/// Extends the sensor counter to 64 bits across rollovers
+///
+/// This is the one place that does counter wrap arithmetic: readers stamp
+/// every record with the extended count, and the checks and time mappings
+/// downstream work on [`SensorData::extended_timestamp`].
+///
+/// Each value is placed in the rollover period that brings it closest to
+/// the highest count seen so far. A step backwards by more than half the
+/// counter range is thus the counter wrapping to small values, while a
+/// smaller step back, e.g. a repeated or reordered record, stays in its
+/// period, even when it was sampled just before a wrap and arrives after it.
+#[derive(Debug, Clone, Default)]
+pub struct TickExtender {
+    width: CounterWidth,
+    // Highest extended count so far
+    latest: Option<i64>,
+}
+
+impl TickExtender {
+    /// Creates an extender for a u32 counter, starting at the first value it sees
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the width of the counter (32 bits by default)
+    pub fn with_width(mut self, width: CounterWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Width of the counter being extended
+    pub fn width(&self) -> CounterWidth {
+        self.width
+    }
+
+    /// Number of rollovers seen so far
+    pub fn rollovers(&self) -> i64 {
+        self.latest
+            .map_or(0, |latest| latest.div_euclid(self.width.modulus()))
+    }
+
+    /// Extended count of the next counter value
+    ///
+    /// Bits above the counter width are ignored.
+    pub fn extend(&mut self, timestamp: u32) -> i64 {
+        let modulus = self.width.modulus();
+        let value = timestamp as i64 & (modulus - 1);
+        let Some(latest) = self.latest else {
+            self.latest = Some(value);
+            return value;
+        };
+        let mut extended = latest - latest.rem_euclid(modulus) + value;
+        if extended - latest > modulus / 2 {
+            extended -= modulus;
+        } else if latest - extended > modulus / 2 {
+            extended += modulus;
+        }
+        self.latest = Some(latest.max(extended));
+        extended
+    }
+
+    /// Stores the extended count in a record
+    pub fn stamp(&mut self, mut data: SensorData) -> SensorData {
+        data.extended_timestamp = Some(self.extend(data.timestamp));
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backwards_timestamp_per_policy() {
+        let mut abort = MonotonicCheck::new(MonotonicPolicy::Abort);
+        for timestamp in [100, 200, 300] {
+            abort.observe(timestamp).unwrap();
+        }
+        let err = abort.observe(250).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ReceiverError::MonotonicityError(_))
+        ));
+
+        let mut count = MonotonicCheck::new(MonotonicPolicy::Count);
+        for timestamp in [100, 200, 150, 150, 300] {
+            count.observe(timestamp).unwrap();
+        }
+        assert_eq!(count.violations(), 2);
+    }
+
+    #[test]
+    fn test_counter_wrap_is_not_a_violation() {
+        let mut check = MonotonicCheck::new(MonotonicPolicy::Abort);
+        let mut ticks = TickExtender::new();
+        for timestamp in [0xFFFF_FFF0, 0xFFFF_FFFF, 0x0000_0010, 0x0000_0020] {
+            check.observe(ticks.extend(timestamp)).unwrap();
+        }
+
+        assert_eq!(check.violations(), 0);
+
+        // The 24-bit counter left by a packed timestamp field wraps far earlier
+        let mut check = MonotonicCheck::new(MonotonicPolicy::Abort);
+        let mut ticks = TickExtender::new().with_width(CounterWidth::Bits24);
+        for timestamp in [0xFF_FFF0, 0xFF_FFFF, 0x00_0010, 0x00_0020] {
+            check.observe(ticks.extend(timestamp)).unwrap();
+        }
+        assert_eq!(check.violations(), 0);
+    }
+
+    #[test]
+    fn test_extended_count_increases_across_rollover() {
+        let mut extender = TickExtender::new();
+        let counters = [
+            0xFFFF_FFF0,
+            0xFFFF_FFF8,
+            0xFFFF_FFFF,
+            0x0000_0007,
+            0x0000_000F,
+        ];
+        let extended: Vec<i64> = counters.iter().map(|&c| extender.extend(c)).collect();
+        assert_eq!(
+            extended,
+            [
+                0xFFFF_FFF0,
+                0xFFFF_FFF8,
+                0xFFFF_FFFF,
+                0x1_0000_0007,
+                0x1_0000_000F
+            ]
+        );
+        assert!(extended.windows(2).all(|pair| pair[1] > pair[0]));
+
+        // A small step back is not a rollover
+        assert_eq!(extender.extend(0x0000_000A), 0x1_0000_000A);
+        assert_eq!(extender.rollovers(), 1);
+
+        // The next wrap counts again, once the counter went round
+        assert_eq!(extender.extend(0x8000_0000), 0x1_8000_0000);
+        assert_eq!(extender.extend(0xFFFF_FFFE), 0x1_FFFF_FFFE);
+        assert_eq!(extender.extend(0x0000_0002), 0x2_0000_0002);
+        assert_eq!(extender.rollovers(), 2);
+
+        // A record sampled before the wrap but arriving after it stays before it
+        assert_eq!(extender.extend(0xFFFF_FFFF), 0x1_FFFF_FFFF);
+        assert_eq!(extender.extend(0x0000_0003), 0x2_0000_0003);
+    }
+
+    #[test]
+    fn test_extended_count_across_24_bit_rollover() {
+        let mut extender = TickExtender::new().with_width(CounterWidth::Bits24);
+        let counters = [0xFF_FFF0, 0xFF_FFFF, 0x00_0007, 0x00_0010];
+        let extended: Vec<i64> = counters.iter().map(|&c| extender.extend(c)).collect();
+        assert_eq!(extended, [0xFF_FFF0, 0xFF_FFFF, 0x100_0007, 0x100_0010]);
+        assert_eq!(extender.rollovers(), 1);
+
+        // Half the 24-bit range back is still a small step for a u32 counter
+        let mut wide = TickExtender::new();
+        assert_eq!(wide.extend(0xFF_FFFF), 0xFF_FFFF);
+        assert_eq!(wide.extend(0x00_0007), 0x00_0007);
+        assert_eq!(wide.rollovers(), 0);
+    }
+}
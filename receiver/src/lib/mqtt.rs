@@ -0,0 +1,605 @@
+use anyhow::Result;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::error::ReceiverError;
+use super::send_queue::{QueueReceiver, SendQueue};
+use super::sink::DataSink;
+use super::status::{eprint_status, Health};
+use super::types::SensorData;
+
+/// Records waiting for the broker before new ones are dropped
+pub const DEFAULT_MQTT_QUEUE: usize = 10_000;
+
+/// Port used when the broker URL does not name one
+const DEFAULT_MQTT_PORT: u16 = 1883;
+
+/// Time allowed for connecting, sending and waiting for acknowledgements
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause after a failed connection before the next attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Messages sent at QoS 1 or 2 before waiting for their acknowledgements
+const ACK_WINDOW: usize = 32;
+
+/// Keep-alive interval announced to the broker; the publisher pings it after
+/// half of this without sending anything
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+// MQTT 3.1.1 control packet types (upper nibble of the first byte)
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const PUBREC: u8 = 0x50;
+const PUBREL: u8 = 0x62;
+const PUBCOMP: u8 = 0x70;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 0xD0;
+const DISCONNECT: u8 = 0xE0;
+
+/// Address of an MQTT broker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttUrl {
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::str::FromStr for MqttUrl {
+    type Err = String;
+
+    /// Parses `mqtt://host[:port]` (or `tcp://`); the scheme may be left out
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = match s.split_once("://") {
+            Some(("mqtt" | "tcp", rest)) => rest,
+            Some((scheme, _)) => return Err(format!("Unsupported MQTT scheme: {}", scheme)),
+            None => s,
+        };
+        let rest = rest.trim_end_matches('/');
+        let (host, port) = match rest.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("Invalid MQTT port: {}", port))?,
+            ),
+            None => (rest, DEFAULT_MQTT_PORT),
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err(format!("Invalid MQTT URL: {}", s));
+        }
+        Ok(MqttUrl {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+/// Sink publishing every record as a JSON message to an MQTT broker
+///
+/// Records are queued for a background thread that talks to the broker, so
+/// a slow or unreachable broker never holds up the writer. Once the queue is
+/// full, records are dropped with a warning until it drains again (see
+/// [`SendQueue`]). Each
+/// message is the record's [`to_json`](SensorData::to_json) object, sent with
+/// the configured QoS (0 to 2). At QoS 1 and 2 up to 32 messages are sent
+/// ahead of their acknowledgements, so the round trip to the broker does not
+/// limit the rate; records already queued go out in one write. The broker
+/// is pinged when no record was sent for half the keep-alive interval of
+/// 60 s, so both sides notice a dead connection. A lost connection is re-established on the next record
+/// after a short delay; records arriving in between are dropped, and those
+/// still unacknowledged count as failed. At shutdown the queued records are
+/// still published and acknowledged, unless publishing fails, in which case
+/// the rest are given up and counted.
+pub struct MqttSink {
+    queue: SendQueue<SensorData>,
+    // Records the publisher thread failed to deliver
+    failed: Arc<AtomicU64>,
+}
+
+impl MqttSink {
+    /// Starts publishing to a broker
+    ///
+    /// The connection is made by the publisher thread, so an unreachable
+    /// broker is reported as a warning rather than an error.
+    ///
+    /// # Arguments
+    /// * `url` - Broker address
+    /// * `topic` - Topic every record is published to
+    /// * `qos` - Quality of service level (0, 1 or 2)
+    /// * `client_id` - Client identifier announced to the broker
+    /// * `capacity` - Records queued before new ones are dropped
+    pub fn start(
+        url: MqttUrl,
+        topic: &str,
+        qos: u8,
+        client_id: &str,
+        capacity: usize,
+    ) -> Result<Self> {
+        if qos > 2 {
+            return Err(
+                ReceiverError::ConfigError(format!("Invalid MQTT QoS: {} (0-2)", qos)).into(),
+            );
+        }
+        if topic.is_empty() || topic.contains(['+', '#']) {
+            return Err(ReceiverError::ConfigError(format!(
+                "Invalid MQTT topic: '{}' (must be non-empty without wildcards)",
+                topic
+            ))
+            .into());
+        }
+        let failed = Arc::new(AtomicU64::new(0));
+        let mut publisher = Publisher::new(url, topic, qos, client_id, failed.clone());
+        let queue = SendQueue::start("MQTT broker", "records", capacity, move |records| {
+            publisher.run(records)
+        });
+        Ok(MqttSink { queue, failed })
+    }
+
+    /// Records dropped so far because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped()
+    }
+}
+
+impl DataSink for MqttSink {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        self.queue.push(data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn rotate_file(&mut self, _output_dir: &str, _prefix: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn close(mut self: Box<Self>) -> Result<Vec<String>> {
+        // The publisher sends what is queued, then disconnects
+        self.queue.close();
+        let (dropped, failed) = (self.queue.dropped(), self.failed.load(Ordering::SeqCst));
+        if dropped > 0 || failed > 0 {
+            let message = format!(
+                "MQTT records not delivered: {} dropped from a full queue, {} failed",
+                dropped, failed
+            );
+            eprint_status(Health::Degraded, &message);
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// Connection to the broker, owned by the publisher thread
+struct Publisher {
+    url: MqttUrl,
+    topic: String,
+    qos: u8,
+    client_id: String,
+    stream: Option<TcpStream>,
+    keep_alive: Duration,
+    // When the last packet was sent to the broker
+    last_sent: Instant,
+    // No connection attempts before this time, after a failure
+    retry_at: Option<Instant>,
+    next_packet_id: u16,
+    // Packet IDs of the messages sent but not yet fully acknowledged
+    unacked: Vec<u16>,
+    failed: Arc<AtomicU64>,
+}
+
+impl Publisher {
+    fn new(url: MqttUrl, topic: &str, qos: u8, client_id: &str, failed: Arc<AtomicU64>) -> Self {
+        Publisher {
+            url,
+            topic: topic.to_string(),
+            qos,
+            client_id: client_id.to_string(),
+            stream: None,
+            keep_alive: KEEP_ALIVE,
+            last_sent: Instant::now(),
+            retry_at: None,
+            next_packet_id: 1,
+            unacked: Vec::new(),
+            failed,
+        }
+    }
+
+    fn run(&mut self, records: QueueReceiver<SensorData>) {
+        loop {
+            let data = match records.recv_timeout(self.keep_alive / 2) {
+                Ok(data) => data,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.ping_if_idle();
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            // Records already queued go out with it, as far as the window allows
+            let room = ACK_WINDOW.saturating_sub(self.unacked.len()).max(1);
+            let mut batch = vec![data];
+            batch.extend(std::iter::from_fn(|| records.try_recv()).take(room - 1));
+            if let Err(e) = self.publish(&batch) {
+                self.connection_lost(batch.len());
+                if records.is_closing() {
+                    // Shutting down without a broker: give up on the rest
+                    self.failed.fetch_add(records.abandon(), Ordering::SeqCst);
+                    break;
+                }
+                if self.stream.take().is_some() || self.retry_at.is_none() {
+                    let message = format!("MQTT publish to {} failed: {}", self.url.host, e);
+                    eprint_status(Health::Degraded, &message);
+                }
+                self.retry_at = Some(Instant::now() + RECONNECT_DELAY);
+            }
+        }
+        // Wait for the outstanding acknowledgements before disconnecting
+        while !self.unacked.is_empty() && self.stream.is_some() {
+            if let Err(e) = self.read_reply() {
+                let message = format!("MQTT acknowledgements from {} lost: {}", self.url.host, e);
+                eprint_status(Health::Degraded, &message);
+                self.connection_lost(0);
+            }
+        }
+        if let Some(mut stream) = self.stream.take() {
+            let _ = stream.write_all(&[DISCONNECT, 0]);
+        }
+    }
+
+    // Counts the `batch` messages that failed, and any still unacknowledged,
+    // as not delivered
+    fn connection_lost(&mut self, batch: usize) {
+        let lost = self.unacked.len().max(batch) as u64;
+        self.failed.fetch_add(lost, Ordering::SeqCst);
+        self.unacked.clear();
+    }
+
+    // Publishes the records as one message each, in a single write
+    fn publish(&mut self, batch: &[SensorData]) -> io::Result<()> {
+        if self.stream.is_none() {
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                return Err(io::Error::new(io::ErrorKind::NotConnected, "not connected"));
+            }
+            self.stream = Some(self.connect()?);
+            self.retry_at = None;
+        }
+
+        let mut bytes = Vec::new();
+        for data in batch {
+            let packet_id = self.next_packet_id;
+            self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+            let mut body = encode_string(&self.topic);
+            if self.qos > 0 {
+                body.extend(packet_id.to_be_bytes());
+                self.unacked.push(packet_id);
+            }
+            body.extend(data.to_json().to_string().into_bytes());
+            bytes.extend(packet(PUBLISH | (self.qos << 1), &body));
+        }
+        self.send(&bytes)?;
+
+        while self.unacked.len() >= ACK_WINDOW {
+            self.read_reply()?;
+        }
+        Ok(())
+    }
+
+    // Pings the broker if nothing was sent for half the keep-alive interval
+    fn ping_if_idle(&mut self) {
+        if self.stream.is_none() || self.last_sent.elapsed() < self.keep_alive / 2 {
+            return;
+        }
+        if let Err(e) = self.ping() {
+            let message = format!("MQTT connection to {} lost: {}", self.url.host, e);
+            eprint_status(Health::Degraded, &message);
+            self.connection_lost(0);
+            self.stream = None;
+        }
+    }
+
+    // Sends a PINGREQ and waits for the response, handling acknowledgements
+    // that arrive ahead of it
+    fn ping(&mut self) -> io::Result<()> {
+        self.send(&[PINGREQ, 0])?;
+        while self.read_reply()? != PINGRESP {}
+        Ok(())
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "not connected"));
+        };
+        stream.write_all(bytes)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    // Reads one packet from the broker, returning its type; acknowledgements
+    // are matched to their messages and a PUBREC is answered with its PUBREL
+    fn read_reply(&mut self) -> io::Result<u8> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "not connected"));
+        };
+        let (header, body) = read_packet(stream)?;
+        if header == PINGRESP {
+            return Ok(header);
+        }
+        let position = body
+            .get(..2)
+            .map(|id| u16::from_be_bytes([id[0], id[1]]))
+            .and_then(|id| self.unacked.iter().position(|&unacked| unacked == id))
+            .ok_or_else(|| unexpected(header))?;
+        match (self.qos, header & 0xF0) {
+            (1, PUBACK) | (2, PUBCOMP) => {
+                self.unacked.remove(position);
+            }
+            (2, PUBREC) => stream.write_all(&packet(PUBREL, &body[..2]))?,
+            _ => return Err(unexpected(header)),
+        }
+        Ok(header)
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let address = (self.url.host.as_str(), self.url.port);
+        let address = std::net::ToSocketAddrs::to_socket_addrs(&address)?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "broker address not found"))?;
+        let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+
+        // Protocol level 4 (3.1.1), clean session, keep-alive in seconds
+        let mut body = encode_string("MQTT");
+        body.extend([4, 0x02]);
+        body.extend((self.keep_alive.as_secs().max(1) as u16).to_be_bytes());
+        body.extend(encode_string(&self.client_id));
+        stream.write_all(&packet(CONNECT, &body))?;
+
+        let (header, reply) = read_packet(&mut stream)?;
+        match (header, reply.as_slice()) {
+            (CONNACK, [_, 0]) => Ok(stream),
+            (CONNACK, [_, code]) => Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("broker refused the connection (code {})", code),
+            )),
+            _ => Err(unexpected(header)),
+        }
+    }
+}
+
+// Frames a control packet with its remaining length
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        bytes.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+// UTF-8 string prefixed by its two-byte length
+fn encode_string(text: &str) -> Vec<u8> {
+    let mut bytes = (text.len() as u16).to_be_bytes().to_vec();
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+// Reads one control packet, returning its first byte and body
+fn read_packet(stream: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0u8];
+    stream.read_exact(&mut byte)?;
+    let header = byte[0];
+    let mut length = 0usize;
+    for shift in (0..28).step_by(7) {
+        stream.read_exact(&mut byte)?;
+        length |= ((byte[0] & 0x7F) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body)?;
+    Ok((header, body))
+}
+
+fn unexpected(header: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected packet from broker (0x{:02X})", header),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
+
+    fn record(timestamp: u32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            gx: 0.5,
+            az: 1.0,
+            system_timestamp: 1_000 + timestamp as i64,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_url_parsing() {
+        let url = MqttUrl::from_str("mqtt://broker.local:1884").unwrap();
+        assert_eq!(url.host, "broker.local");
+        assert_eq!(url.port, 1884);
+        assert_eq!(MqttUrl::from_str("localhost").unwrap().port, 1883);
+        assert!(MqttUrl::from_str("http://localhost").is_err());
+        assert!(MqttUrl::from_str("mqtt://localhost:port").is_err());
+    }
+
+    #[test]
+    fn test_records_published_with_qos1() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Minimal broker acknowledging every publish
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (header, connect) = read_packet(&mut stream).unwrap();
+            assert_eq!(header, CONNECT);
+            assert_eq!(connect[8..10], KEEP_ALIVE.as_secs().to_be_bytes()[6..]);
+            assert!(connect.ends_with(b"test-client"));
+            stream.write_all(&[CONNACK, 2, 0, 0]).unwrap();
+
+            let mut messages = Vec::new();
+            loop {
+                let (header, body) = read_packet(&mut stream).unwrap();
+                if header == DISCONNECT {
+                    break;
+                }
+                assert_eq!(header, PUBLISH | 0x02);
+                let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+                let packet_id = &body[2 + topic_len..4 + topic_len];
+                stream.write_all(&packet(PUBACK, packet_id)).unwrap();
+                let payload = &body[4 + topic_len..];
+                let json: serde_json::Value = serde_json::from_slice(payload).unwrap();
+                messages.push((topic, json));
+            }
+            messages
+        });
+
+        let url = MqttUrl {
+            host: "127.0.0.1".to_string(),
+            port,
+        };
+        let mut sink: Box<dyn DataSink> =
+            Box::new(MqttSink::start(url, "lab/imu", 1, "test-client", 16).unwrap());
+        for i in 0..3 {
+            sink.add_data(record(i)).unwrap();
+        }
+        sink.close().unwrap();
+
+        let messages = broker.join().unwrap();
+        assert_eq!(messages.len(), 3);
+        for (i, (topic, json)) in messages.iter().enumerate() {
+            assert_eq!(topic, "lab/imu");
+            assert_eq!(json["timestamp"], i as u64);
+            assert_eq!(json["gx"], 0.5);
+            assert_eq!(json["system_timestamp"], 1_000 + i as i64);
+        }
+    }
+
+    #[test]
+    fn test_qos2_messages_sent_ahead_of_acknowledgements() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Broker that only acknowledges once all five messages have arrived
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_packet(&mut stream).unwrap();
+            stream.write_all(&[CONNACK, 2, 0, 0]).unwrap();
+
+            let mut ids = Vec::new();
+            for _ in 0..5 {
+                let (header, body) = read_packet(&mut stream).unwrap();
+                assert_eq!(header, PUBLISH | 0x04);
+                let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                ids.push(body[2 + topic_len..4 + topic_len].to_vec());
+            }
+            for id in &ids {
+                stream.write_all(&packet(PUBREC, id)).unwrap();
+            }
+            for id in &ids {
+                let (header, body) = read_packet(&mut stream).unwrap();
+                assert_eq!((header, &body), (PUBREL, id));
+                stream.write_all(&packet(PUBCOMP, id)).unwrap();
+            }
+            let (header, _) = read_packet(&mut stream).unwrap();
+            assert_eq!(header, DISCONNECT);
+            ids.len()
+        });
+
+        let url = MqttUrl {
+            host: "127.0.0.1".to_string(),
+            port,
+        };
+        let sink = MqttSink::start(url, "lab/imu", 2, "test-client", 16).unwrap();
+        let failed = sink.failed.clone();
+        let mut sink: Box<dyn DataSink> = Box::new(sink);
+        for i in 0..5 {
+            sink.add_data(record(i)).unwrap();
+        }
+        sink.close().unwrap();
+
+        assert_eq!(broker.join().unwrap(), 5);
+        assert_eq!(failed.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_idle_connection_pinged() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Broker answering pings; reports each packet it gets
+        let (received, packets) = std::sync::mpsc::channel();
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_packet(&mut stream).unwrap();
+            stream.write_all(&[CONNACK, 2, 0, 0]).unwrap();
+            loop {
+                let (header, _) = read_packet(&mut stream).unwrap();
+                if header == PINGREQ {
+                    stream.write_all(&[PINGRESP, 0]).unwrap();
+                }
+                received.send(header).unwrap();
+                if header == DISCONNECT {
+                    break;
+                }
+            }
+        });
+
+        let url = MqttUrl {
+            host: "127.0.0.1".to_string(),
+            port,
+        };
+        let failed = Arc::new(AtomicU64::new(0));
+        let mut publisher = Publisher::new(url, "lab/imu", 0, "test-client", failed.clone());
+        publisher.keep_alive = Duration::from_millis(200);
+        let mut queue = SendQueue::start("MQTT broker", "records", 16, move |records| {
+            publisher.run(records)
+        });
+        queue.push(record(0));
+        assert_eq!(packets.recv().unwrap(), PUBLISH);
+        // Nothing else to send: the publisher keeps the connection alive
+        assert_eq!(packets.recv().unwrap(), PINGREQ);
+        queue.close();
+        assert_eq!(packets.recv().unwrap(), DISCONNECT);
+
+        broker.join().unwrap();
+        assert_eq!(failed.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_invalid_settings_rejected() {
+        let url = MqttUrl::from_str("localhost").unwrap();
+        assert!(MqttSink::start(url.clone(), "lab/imu", 3, "id", 1).is_err());
+        assert!(MqttSink::start(url, "lab/#", 0, "id", 1).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use super::types::SensorData;
+
+/// What to do with a float field holding NaN or an infinity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Keep the value as it is
+    #[default]
+    Keep,
+    /// Discard the whole record
+    Drop,
+    /// Replace the value with 0
+    Zero,
+    /// Repeat the field's last finite value
+    Hold,
+    /// Store the field as null (the value stays NaN in memory)
+    Null,
+}
+
+impl std::str::FromStr for NonFinitePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "keep" => Ok(NonFinitePolicy::Keep),
+            "drop" => Ok(NonFinitePolicy::Drop),
+            "zero" => Ok(NonFinitePolicy::Zero),
+            "hold" => Ok(NonFinitePolicy::Hold),
+            "null" => Ok(NonFinitePolicy::Null),
+            _ => Err(format!("Unknown non-finite policy: {}", s)),
+        }
+    }
+}
+
+/// Applies a [`NonFinitePolicy`] to each float field of the records written
+///
+/// Fields that failed to decode are already null and left alone. Under
+/// [`NonFinitePolicy::Hold`] a field without any finite value yet is stored
+/// as null, like [`NonFinitePolicy::Null`]; both need a layout with nullable
+/// sensor columns for the null to reach the file.
+#[derive(Debug, Clone, Default)]
+pub struct NonFiniteHandler {
+    // Per field of SensorData::FLOAT_FIELDS
+    policies: [NonFinitePolicy; 7],
+    last_finite: [Option<f32>; 7],
+    replaced: usize,
+    dropped: usize,
+}
+
+impl NonFiniteHandler {
+    /// Parses a policy for all fields, optionally followed by per-field overrides
+    ///
+    /// e.g. `"hold"` or `"zero,temp=null,az=drop"`; an entry without a field
+    /// name sets the policy of all fields not named.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut default = NonFinitePolicy::Keep;
+        let mut overrides = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((field, policy)) => {
+                    let field = field.trim();
+                    let index = SensorData::FLOAT_FIELDS
+                        .iter()
+                        .position(|name| name.eq_ignore_ascii_case(field))
+                        .ok_or_else(|| format!("Unknown field in non-finite policy: {}", field))?;
+                    overrides.push((index, policy.trim().parse()?));
+                }
+                None => default = entry.parse()?,
+            }
+        }
+        let mut policies = [default; 7];
+        for (index, policy) in overrides {
+            policies[index] = policy;
+        }
+        Ok(NonFiniteHandler {
+            policies,
+            ..Default::default()
+        })
+    }
+
+    /// Whether some field may be stored as null
+    pub fn needs_nullable(&self) -> bool {
+        self.policies
+            .iter()
+            .any(|policy| matches!(policy, NonFinitePolicy::Hold | NonFinitePolicy::Null))
+    }
+
+    /// Number of values replaced or set to null so far
+    pub fn replaced(&self) -> usize {
+        self.replaced
+    }
+
+    /// Number of records dropped so far
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Applies the policies to a record
+    ///
+    /// # Returns
+    /// The record with its non-finite fields handled, or None if it is dropped
+    pub fn apply(&mut self, mut data: SensorData) -> Option<SensorData> {
+        let null_fields = data.null_fields;
+        let mut fields = [
+            &mut data.temp,
+            &mut data.gx,
+            &mut data.gy,
+            &mut data.gz,
+            &mut data.ax,
+            &mut data.ay,
+            &mut data.az,
+        ];
+        let mut nulls = 0;
+        let mut replaced = 0;
+        for (index, value) in fields.iter_mut().enumerate() {
+            if null_fields & (1 << index) != 0 {
+                continue;
+            }
+            if value.is_finite() {
+                self.last_finite[index] = Some(**value);
+                continue;
+            }
+            match (self.policies[index], self.last_finite[index]) {
+                (NonFinitePolicy::Keep, _) => continue,
+                (NonFinitePolicy::Drop, _) => {
+                    self.dropped += 1;
+                    return None;
+                }
+                (NonFinitePolicy::Zero, _) => **value = 0.0,
+                (NonFinitePolicy::Hold, Some(last)) => **value = last,
+                (NonFinitePolicy::Hold, None) | (NonFinitePolicy::Null, _) => {
+                    nulls |= 1 << index;
+                }
+            }
+            replaced += 1;
+        }
+        data.null_fields |= nulls;
+        self.replaced += replaced;
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(gx: f32) -> SensorData {
+        SensorData {
+            temp: 25.0,
+            gx,
+            gy: 0.2,
+            gz: 0.3,
+            az: 1.0,
+            ..Default::default()
+        }
+    }
+
+    // Runs a finite record, then one with gx = NaN, through the policy
+    fn run(spec: &str) -> (Option<SensorData>, NonFiniteHandler) {
+        let mut handler = NonFiniteHandler::parse(spec).unwrap();
+        assert_eq!(handler.apply(record(0.5)).unwrap().gx, 0.5);
+        let result = handler.apply(record(f32::NAN));
+        (result, handler)
+    }
+
+    #[test]
+    fn test_drop_policy_discards_record() {
+        let (result, handler) = run("drop");
+        assert!(result.is_none());
+        assert_eq!(handler.dropped(), 1);
+    }
+
+    #[test]
+    fn test_zero_policy_substitutes_zero() {
+        let (result, handler) = run("zero");
+        assert_eq!(result.unwrap().gx, 0.0);
+        assert_eq!(handler.replaced(), 1);
+    }
+
+    #[test]
+    fn test_hold_policy_repeats_last_finite_value() {
+        let (result, _) = run("hold");
+        assert_eq!(result.unwrap().gx, 0.5);
+
+        // Nothing to hold yet: stored as null
+        let mut handler = NonFiniteHandler::parse("hold").unwrap();
+        let data = handler.apply(record(f32::INFINITY)).unwrap();
+        assert!(data.is_null(1));
+    }
+
+    #[test]
+    fn test_null_policy_marks_field_null() {
+        let (result, handler) = run("null");
+        let data = result.unwrap();
+        assert!(data.is_null(1));
+        assert!(!data.is_null(0));
+        assert_eq!(handler.replaced(), 1);
+        assert!(handler.needs_nullable());
+    }
+
+    #[test]
+    fn test_per_field_overrides() {
+        // gx is held even though the others would drop the record
+        let (result, _) = run("drop,gx=hold");
+        assert_eq!(result.unwrap().gx, 0.5);
+        let (result, _) = run("zero,GY=drop");
+        assert_eq!(result.unwrap().gx, 0.0);
+        // Keep leaves the value untouched
+        let (result, _) = run("keep");
+        assert!(result.unwrap().gx.is_nan());
+
+        assert!(NonFiniteHandler::parse("zero,speed=null").is_err());
+        assert!(NonFiniteHandler::parse("substitute").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use super::types::SensorData;
+
+/// Keeps a record only when an axis moved by more than a threshold (`--on-change`)
+///
+/// Each record is compared with the last one kept, not with its direct
+/// predecessor, so a slow drift is still stored once it adds up to more than
+/// the threshold. The first record is always kept. Only the six gyro and
+/// accel axes are compared; a field that turns null or NaN, or stops being
+/// so, counts as a change.
+#[derive(Debug, Clone)]
+pub struct ChangeFilter {
+    epsilon: f32,
+    // Axes and null mask of the last record kept
+    last_kept: Option<([f32; 6], u8)>,
+    skipped: usize,
+}
+
+impl ChangeFilter {
+    /// Creates a filter keeping records that differ by more than `epsilon` on some axis
+    pub fn new(epsilon: f32) -> Self {
+        ChangeFilter {
+            epsilon: epsilon.abs(),
+            last_kept: None,
+            skipped: 0,
+        }
+    }
+
+    /// Number of records left out so far
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Returns whether a record is kept, remembering it if so
+    pub fn keep(&mut self, data: &SensorData) -> bool {
+        let axes = [data.gx, data.gy, data.gz, data.ax, data.ay, data.az];
+        // Null flags of the axes (bits 1 to 6 of the mask)
+        let nulls = (data.null_fields >> 1) & 0x3F;
+        let changed = match &self.last_kept {
+            None => true,
+            Some((last, last_nulls)) => {
+                nulls != *last_nulls
+                    || axes.iter().zip(last).enumerate().any(|(index, (a, b))| {
+                        nulls & (1 << index) == 0
+                            && (a.is_nan() != b.is_nan() || (a - b).abs() > self.epsilon)
+                    })
+            }
+        };
+        if changed {
+            self.last_kept = Some((axes, nulls));
+        } else {
+            self.skipped += 1;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u32, gx: f32, az: f32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0 + timestamp as f32,
+            gx,
+            az,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_flat_then_changing_stream() {
+        let mut filter = ChangeFilter::new(0.05);
+        // Flat, with noise below the threshold and a temperature change
+        let mut stream: Vec<SensorData> = (0..10)
+            .map(|i| record(i, 0.01 * (i % 2) as f32, 1.0))
+            .collect();
+        // Motion on one axis, then a slow drift on another
+        stream.push(record(10, 0.5, 1.0));
+        stream.push(record(11, 0.52, 1.0));
+        stream.push(record(12, -0.5, 1.0));
+        stream.extend((13..20).map(|i| record(i, -0.5, 1.0 + 0.02 * (i - 12) as f32)));
+
+        let kept: Vec<u32> = stream
+            .iter()
+            .filter(|data| filter.keep(data))
+            .map(|data| data.timestamp)
+            .collect();
+        // The drift passes 0.05 at the third step, and again three steps later
+        assert_eq!(kept, vec![0, 10, 12, 15, 18]);
+        assert_eq!(filter.skipped(), stream.len() - kept.len());
+    }
+
+    #[test]
+    fn test_null_and_nan_transitions_are_changes() {
+        let mut filter = ChangeFilter::new(1.0);
+        assert!(filter.keep(&record(0, 0.0, 1.0)));
+        assert!(filter.keep(&record(1, f32::NAN, 1.0)));
+        assert!(!filter.keep(&record(2, f32::NAN, 1.0)));
+
+        let mut nulled = record(3, f32::NAN, 1.0);
+        nulled.null_fields = 1 << 1;
+        assert!(filter.keep(&nulled));
+        assert!(!filter.keep(&nulled));
+        assert!(filter.keep(&record(4, 0.0, 1.0)));
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::error::ReceiverError;
+
+/// Exclusive claim on an output directory and file prefix
+///
+/// Held by a running receiver so that a second instance writing to the same
+/// directory with the same prefix refuses to start instead of interleaving
+/// files with the first. The lock file `.<prefix>.lock` holds the owner's
+/// process ID. The lock is an OS file lock, so it is released even if the
+/// process dies; a clean shutdown also removes the file.
+#[derive(Debug)]
+pub struct OutputLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl OutputLock {
+    /// Takes the lock for a prefix in an output directory
+    ///
+    /// # Returns
+    /// The held lock, or a configuration error if another instance holds it
+    pub fn acquire(output_dir: &Path, prefix: &str) -> Result<Self> {
+        let path = output_dir.join(format!(".{}.lock", prefix));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))?;
+
+        if file.try_lock_exclusive().is_err() {
+            let owner = std::fs::read_to_string(&path).unwrap_or_default();
+            let owner = match owner.trim() {
+                "" => String::new(),
+                pid => format!(" (process {})", pid),
+            };
+            return Err(ReceiverError::ConfigError(format!(
+                "Another receiver{} is writing files with prefix '{}' to {}; \
+                 choose another --prefix or --output-dir (lock file {})",
+                owner,
+                prefix,
+                output_dir.display(),
+                path.display()
+            ))
+            .into());
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())
+            .with_context(|| format!("Failed to write lock file: {}", path.display()))?;
+        Ok(OutputLock { path, file })
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // Remove the file while still holding the lock, so no other instance
+        // can have locked it in between. If the file at the path no longer
+        // names this process, another instance replaced it and owns it now.
+        let owner = std::fs::read_to_string(&self.path).unwrap_or_default();
+        if owner.trim() == std::process::id().to_string() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_second_instance_cannot_acquire_held_lock() {
+        let temp_dir = tempdir().unwrap();
+        let lock = OutputLock::acquire(temp_dir.path(), "sensor_log").unwrap();
+        assert!(lock.path().exists());
+
+        let err = OutputLock::acquire(temp_dir.path(), "sensor_log").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Another receiver"), "{}", message);
+        assert!(
+            message.contains(&std::process::id().to_string()),
+            "{}",
+            message
+        );
+
+        // Other prefixes in the same directory are independent
+        let other = OutputLock::acquire(temp_dir.path(), "other").unwrap();
+
+        drop(lock);
+        drop(other);
+        assert!(!temp_dir.path().join(".sensor_log.lock").exists());
+        OutputLock::acquire(temp_dir.path(), "sensor_log").unwrap();
+    }
+
+    #[test]
+    fn test_drop_keeps_lock_file_taken_over_by_another_process() {
+        let temp_dir = tempdir().unwrap();
+        let lock = OutputLock::acquire(temp_dir.path(), "sensor_log").unwrap();
+        let path = lock.path().to_path_buf();
+
+        // Another instance replaced the lock file with its own
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "999999999").unwrap();
+
+        drop(lock);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "999999999");
+    }
+}
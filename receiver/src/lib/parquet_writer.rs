@@ -1,16 +1,80 @@
 use anyhow::{Context, Result};
-use arrow::array::{Float32Array, Int64Array};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, Int32Array, Int64Array, StringArray, StructArray,
+    TimestampMillisecondArray, TimestampNanosecondArray, UInt32Array, UInt8Array,
+};
+use arrow::datatypes::{DataType, Field, Fields, Schema, TimeUnit};
 use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, Utc};
 use parquet::arrow::ArrowWriter;
 use parquet::basic::Compression;
+use parquet::file::metadata::KeyValue;
 use parquet::file::properties::WriterProperties;
-use std::fs::{create_dir_all, File};
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, rename, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 
 use super::error::ReceiverError;
-use super::types::{CompressionType, SensorData};
+use super::host_info::HostInfo;
+use super::monotonic::{CounterWidth, TickExtender};
+use super::range_check::{RangeCheck, RangePolicy};
+use super::sink::DataSink;
+use super::stats::FileStats;
+use super::status::{print_status, Health};
+use super::timestamp::ReferenceClock;
+use super::types::{CompressionType, FileNaming, PrimaryTime, SensorData};
+
+/// Optional features affecting the layout of written files
+#[derive(Debug, Clone, Default)]
+pub struct WriterOptions {
+    /// Sensor counter rate in Hz; when set, a wall-aligned `timestamp_ns`
+    /// column is derived from the counter
+    pub counter_hz: Option<f64>,
+    /// Store gyro and accel as `{x, y, z}` struct columns instead of six flat columns
+    pub nested_schema: bool,
+    /// Clock stored in the primary `timestamp` column
+    pub primary_time: PrimaryTime,
+    /// Add an `accel_mag` column with the acceleration magnitude
+    pub accel_mag: bool,
+    /// Capture session identifier stored as `run_id` in every file's metadata
+    pub run_id: Option<String>,
+    /// Host and user stored as `hostname` and `username` in every file's metadata
+    pub host: Option<HostInfo>,
+    /// Add a nullable `status` column with the device status byte
+    pub status_column: bool,
+    /// Largest number of rows per row group (parquet default when unset)
+    pub max_row_group_size: Option<usize>,
+    /// Prepended to every top-level column name (e.g. `imu_` gives `imu_ax`)
+    pub column_prefix: Option<String>,
+    /// Make the float sensor columns nullable, storing fields that failed to
+    /// decode (see [`SensorData::null_fields`]) as null
+    pub nullable_fields: bool,
+    /// External timebase; when set, a `reference_time` column maps the
+    /// counter onto it
+    pub reference_clock: Option<ReferenceClock>,
+    /// Sensor range check; with [`RangePolicy::Tag`] a boolean `valid`
+    /// column records whether each record passed it
+    pub range_check: Option<RangeCheck>,
+    /// Add a nullable `burst_id` column with the burst each record belongs to
+    /// (see [`BurstDetector`](super::burst::BurstDetector))
+    pub burst_column: bool,
+    /// Add a `clock_skew` column marking records flagged by the clock check
+    /// (see [`ClockCheck`](super::clock_check::ClockCheck))
+    pub clock_skew_column: bool,
+    /// Add a nullable `extended_timestamp` column with the counter extended
+    /// across rollovers (see [`SensorData::extended_timestamp`])
+    pub extended_timestamp: bool,
+    /// Width of the sensor counter, for extending it across rollovers in
+    /// records the reader has not stamped
+    pub counter_width: CounterWidth,
+    /// Add a `session_id` column repeating the run ID, tying together the
+    /// files of a session continued after restarts (`--append`)
+    pub session_column: bool,
+}
+
+/// Receives the record batches of a writer that streams instead of writing files
+pub type BatchSink = Box<dyn FnMut(RecordBatch) -> Result<()> + Send>;
 
 /// Writer for saving sensor data to Parquet files
 ///
@@ -24,6 +88,29 @@ pub struct ParquetWriter {
     buffer_size: usize,
     output_path: String,
     writer: Option<ArrowWriter<File>>,
+    options: WriterOptions,
+    // First (extended counter, system_timestamp) pair, anchoring counter-derived times
+    time_anchor: Option<(i64, i64)>,
+    // Extends the counter of records the reader has not stamped
+    ticks: TickExtender,
+    output_dir: String,
+    prefix: String,
+    max_records_per_file: Option<usize>,
+    sort_on_flush: bool,
+    file_per_row_group: bool,
+    file_naming: FileNaming,
+    // Host time (ms) of the earliest record written to the current file
+    first_record_ms: Option<i64>,
+    // Records written to the current file
+    records_in_file: usize,
+    // Summary of the current file, when a stats sidecar is written
+    file_stats: Option<FileStats>,
+    // Final paths of the files closed since the last rotation, with their sidecars
+    closed: Vec<String>,
+    // Record count every row group but a file's last is cut to
+    row_group_alignment: Option<usize>,
+    // Destination of the batches when streaming instead of writing files
+    sink: Option<BatchSink>,
 }
 
 impl ParquetWriter {
@@ -43,66 +130,289 @@ impl ParquetWriter {
         compression: CompressionType,
         buffer_size: usize,
     ) -> Result<Self> {
+        Self::new_with_options(
+            output_dir,
+            prefix,
+            compression,
+            buffer_size,
+            WriterOptions::default(),
+        )
+    }
+
+    /// Creates a new Parquet writer with optional features enabled
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory where Parquet files will be saved
+    /// * `prefix` - Filename prefix for Parquet files
+    /// * `compression` - Compression type to use
+    /// * `buffer_size` - Number of records to buffer before writing
+    /// * `options` - Optional features affecting the file layout
+    ///
+    /// # Returns
+    /// A new ParquetWriter configured with the specified parameters
+    pub fn new_with_options(
+        output_dir: &str,
+        prefix: &str,
+        compression: CompressionType,
+        buffer_size: usize,
+        options: WriterOptions,
+    ) -> Result<Self> {
+        check_buffer_size(buffer_size)?;
+        ensure_codec_available(parquet_compression(&compression))?;
+
         // Create schema
-        let schema = Arc::new(Schema::new(vec![
-            Field::new("timestamp", DataType::Int64, false),
-            Field::new("temp", DataType::Float32, false),
-            Field::new("gx", DataType::Float32, false),
-            Field::new("gy", DataType::Float32, false),
-            Field::new("gz", DataType::Float32, false),
-            Field::new("ax", DataType::Float32, false),
-            Field::new("ay", DataType::Float32, false),
-            Field::new("az", DataType::Float32, false),
-            Field::new("system_timestamp", DataType::Int64, false),
-        ]));
+        let schema = Arc::new(Self::build_schema(&options));
 
         // Ensure output directory exists
         create_dir_all(output_dir)
             .with_context(|| format!("Failed to create output directory: {}", output_dir))?;
 
         // Generate output file path
-        let now = chrono::Utc::now();
-        let filename = format!("{}_{}.parquet", prefix, now.format("%Y%m%d_%H%M%S"));
-        let output_path = Path::new(output_dir).join(filename);
+        let output_path = new_output_path(output_dir, prefix, "parquet");
         let output_path_str = output_path.to_string_lossy().to_string();
 
         // Create a new Parquet writer
         let file = File::create(&output_path)
             .with_context(|| format!("Failed to create file: {}", output_path_str))?;
 
-        // Convert compression type to Parquet compression
-        let props = match compression {
-            CompressionType::None => WriterProperties::builder()
-                .set_compression(Compression::UNCOMPRESSED)
-                .build(),
-            CompressionType::Snappy => WriterProperties::builder()
-                .set_compression(Compression::SNAPPY)
-                .build(),
-            CompressionType::Gzip => WriterProperties::builder()
-                .set_compression(Compression::GZIP(Default::default()))
-                .build(),
-            CompressionType::Lz4 => WriterProperties::builder()
-                .set_compression(Compression::LZ4)
-                .build(),
-            CompressionType::Zstd => WriterProperties::builder()
-                .set_compression(Compression::ZSTD(Default::default()))
-                .build(),
-        };
+        let props = build_writer_properties(&compression, &options);
 
         // Initialize the ArrowWriter
         let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))
             .with_context(|| format!("Failed to create Parquet writer for {}", output_path_str))?;
 
-        Ok(ParquetWriter {
+        let mut writer = ParquetWriter {
             schema,
             compression,
             buffer: Vec::with_capacity(buffer_size),
             buffer_size,
             output_path: output_path_str,
             writer: Some(writer),
+            ticks: TickExtender::new().with_width(options.counter_width),
+            options,
+            time_anchor: None,
+            output_dir: output_dir.to_string(),
+            prefix: prefix.to_string(),
+            max_records_per_file: None,
+            sort_on_flush: false,
+            file_per_row_group: false,
+            file_naming: FileNaming::Creation,
+            first_record_ms: None,
+            records_in_file: 0,
+            file_stats: None,
+            closed: Vec::new(),
+            row_group_alignment: None,
+            sink: None,
+        };
+        writer.write_file_metadata();
+        Ok(writer)
+    }
+
+    /// Creates a writer that hands record batches to a sink instead of a file
+    ///
+    /// Batches use the same schema and construction as written files; rotation
+    /// only flushes the buffer.
+    ///
+    /// # Arguments
+    /// * `buffer_size` - Number of records per batch
+    /// * `options` - Optional features affecting the batch layout
+    /// * `sink` - Receives every batch
+    ///
+    /// # Returns
+    /// A new ParquetWriter that does not create any files
+    pub fn for_stream(
+        buffer_size: usize,
+        options: WriterOptions,
+        sink: BatchSink,
+    ) -> Result<Self, ReceiverError> {
+        check_buffer_size(buffer_size)?;
+        Ok(ParquetWriter {
+            schema: Arc::new(Self::build_schema(&options)),
+            compression: CompressionType::None,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size,
+            output_path: "record batch stream".to_string(),
+            writer: None,
+            ticks: TickExtender::new().with_width(options.counter_width),
+            options,
+            time_anchor: None,
+            output_dir: String::new(),
+            prefix: String::new(),
+            max_records_per_file: None,
+            sort_on_flush: false,
+            file_per_row_group: false,
+            file_naming: FileNaming::Creation,
+            first_record_ms: None,
+            records_in_file: 0,
+            file_stats: None,
+            closed: Vec::new(),
+            row_group_alignment: None,
+            sink: Some(sink),
         })
     }
 
+    /// Limits every file to exactly `max_records` records
+    ///
+    /// A buffered batch that straddles the limit is split: the records that
+    /// fit complete the current file and the remainder starts the next one, in
+    /// the directory and with the prefix of the latest file.
+    pub fn with_max_records_per_file(mut self, max_records: usize) -> Self {
+        self.max_records_per_file = Some(max_records.max(1));
+        self
+    }
+
+    /// Sorts each buffered batch by sensor `timestamp` before it is written
+    ///
+    /// Guarantees monotonic timestamps within every batch (and so within its
+    /// row group pages) even if records arrive out of order, at the cost of a
+    /// sort per flush. Records with equal timestamps keep their arrival order.
+    pub fn with_sort_on_flush(mut self) -> Self {
+        self.sort_on_flush = true;
+        self
+    }
+
+    /// Writes a `<name>.stats.json` summary next to every closed file
+    ///
+    /// The sidecar holds the run ID, the record count, the host time span,
+    /// the first and last sensor counter and the min, max and mean of each
+    /// sensor channel, collected while writing (see [`FileStats`]), so a
+    /// sidecar can be matched to its capture without opening the file.
+    pub fn with_stats_sidecar(mut self) -> Self {
+        self.file_stats = Some(FileStats::new());
+        self
+    }
+
+    /// Writes every flushed batch to a file of its own
+    ///
+    /// Each flush creates a new file, writes the batch as its only row group
+    /// (unless the batch exceeds the maximum row group size) and closes the
+    /// file, so downstream ingest only ever sees complete files. Rotation
+    /// just switches the directory and prefix used for the next file.
+    pub fn with_file_per_row_group(mut self) -> Self {
+        self.file_per_row_group = true;
+        self
+    }
+
+    /// Cuts row groups at exact multiples of `records` (e.g. 4096)
+    ///
+    /// Flushes hand only whole groups to the file, each ending its row group,
+    /// and keep the records short of the next boundary buffered; the buffer
+    /// therefore grows to at least `records` whatever the buffer size. When
+    /// the file is rotated or closed, the remaining records form its last,
+    /// shorter row group. Does not apply to per-row-group files or streams.
+    pub fn with_row_group_alignment(mut self, records: usize) -> Self {
+        self.row_group_alignment = Some(records.max(1));
+        self
+    }
+
+    /// Selects the time each file is named after
+    ///
+    /// With [`FileNaming::FirstRecord`], a file is renamed when it is closed to
+    /// carry the host time (`system_timestamp`) of its earliest record. The
+    /// rename replaces the name in one step, so readers never see a partial
+    /// file under either name; a file without records keeps its name.
+    pub fn with_file_naming(mut self, naming: FileNaming) -> Self {
+        self.file_naming = naming;
+        self
+    }
+
+    /// Builds the Arrow schema written for the given options
+    ///
+    /// # Arguments
+    /// * `options` - Optional features affecting the file layout
+    ///
+    /// # Returns
+    /// The schema of every record batch written by this writer
+    pub fn build_schema(options: &WriterOptions) -> Schema {
+        let primary = match options.primary_time {
+            PrimaryTime::Counter | PrimaryTime::Monotonic => DataType::Int64,
+            PrimaryTime::Wall => wall_time_type(),
+        };
+        let nullable = options.nullable_fields;
+        let mut fields = vec![
+            Field::new("timestamp", primary, false),
+            Field::new("temp", DataType::Float32, nullable),
+        ];
+
+        if options.nested_schema {
+            let axes = DataType::Struct(axis_fields(nullable));
+            fields.push(Field::new("gyro", axes.clone(), false));
+            fields.push(Field::new("accel", axes, false));
+        } else {
+            fields.extend([
+                Field::new("gx", DataType::Float32, nullable),
+                Field::new("gy", DataType::Float32, nullable),
+                Field::new("gz", DataType::Float32, nullable),
+                Field::new("ax", DataType::Float32, nullable),
+                Field::new("ay", DataType::Float32, nullable),
+                Field::new("az", DataType::Float32, nullable),
+            ]);
+        }
+
+        if options.accel_mag {
+            fields.push(Field::new("accel_mag", DataType::Float32, false));
+        }
+
+        for (name, _) in secondary_time_columns(options.primary_time) {
+            fields.push(Field::new(*name, time_column_type(name), false));
+        }
+
+        if options.counter_hz.is_some() {
+            fields.push(Field::new(
+                "timestamp_ns",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+                false,
+            ));
+        }
+
+        if options.extended_timestamp {
+            fields.push(Field::new("extended_timestamp", DataType::Int64, true));
+        }
+
+        if options.reference_clock.is_some() {
+            fields.push(Field::new(
+                "reference_time",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ));
+        }
+
+        if options.status_column {
+            fields.push(Field::new("status", DataType::UInt8, true));
+        }
+
+        if options
+            .range_check
+            .is_some_and(|check| check.policy() == RangePolicy::Tag)
+        {
+            fields.push(Field::new("valid", DataType::Boolean, false));
+        }
+
+        if options.clock_skew_column {
+            fields.push(Field::new("clock_skew", DataType::Boolean, false));
+        }
+
+        if options.burst_column {
+            fields.push(Field::new("burst_id", DataType::UInt32, true));
+        }
+
+        if options.session_column {
+            fields.push(Field::new("session_id", DataType::Utf8, false));
+        }
+
+        if let Some(prefix) = &options.column_prefix {
+            fields = fields
+                .into_iter()
+                .map(|field| {
+                    let name = format!("{}{}", prefix, field.name());
+                    field.with_name(name)
+                })
+                .collect();
+        }
+
+        Schema::new(fields)
+    }
+
     /// Adds a single sensor data record to the buffer
     ///
     /// Automatically flushes the buffer to disk when it reaches the configured buffer size
@@ -113,6 +423,14 @@ impl ParquetWriter {
     /// # Returns
     /// Result indicating success or error
     pub fn add_data(&mut self, data: SensorData) -> Result<()> {
+        let data = match data.extended_timestamp {
+            Some(_) => data,
+            None => self.ticks.stamp(data),
+        };
+        let counter = data.extended_timestamp.unwrap_or_default();
+        self.time_anchor
+            .get_or_insert((counter, data.system_timestamp));
+
         self.buffer.push(data);
 
         if self.buffer.len() >= self.buffer_size {
@@ -130,24 +448,90 @@ impl ParquetWriter {
     /// # Returns
     /// Result indicating success or error
     pub fn flush(&mut self) -> Result<()> {
+        self.flush_buffer(false)
+    }
+
+    // Flushes the buffer; `file_end` writes even a partial aligned row group
+    fn flush_buffer(&mut self, file_end: bool) -> Result<()> {
+        if self.sort_on_flush {
+            self.buffer.sort_by_key(|data| data.timestamp);
+        }
+
+        if self.file_per_row_group && self.sink.is_none() {
+            return self.write_row_group_file();
+        }
+
+        if let Some(alignment) = self.row_group_alignment.filter(|_| self.sink.is_none()) {
+            return self.write_aligned(alignment, file_end);
+        }
+
+        let Some(limit) = self.max_records_per_file else {
+            return self.write_buffer();
+        };
+
+        while !self.buffer.is_empty() {
+            if self.records_in_file >= limit {
+                let (output_dir, prefix) = (self.output_dir.clone(), self.prefix.clone());
+                self.start_new_file(&output_dir, &prefix)?;
+            }
+
+            // Carry records beyond the limit over into the next file
+            let room = limit - self.records_in_file;
+            let carryover = if self.buffer.len() > room {
+                self.buffer.split_off(room)
+            } else {
+                Vec::new()
+            };
+            self.write_buffer()?;
+            self.buffer = carryover;
+        }
+
+        Ok(())
+    }
+
+    // Writes the whole buffer to the current file
+    fn write_buffer(&mut self) -> Result<()> {
         if self.buffer.is_empty() {
             return Ok(());
         }
 
-        // Create the RecordBatch from buffered data
-        let batch = self._create_record_batch()?;
+        // Create the RecordBatch from buffered data; a batch that cannot be
+        // built is dropped so that later records can still be written
+        let batch = match self._create_record_batch() {
+            Ok(batch) => batch,
+            Err(e) => {
+                let dropped = self.buffer.len();
+                self.buffer.clear();
+                return Err(ReceiverError::ParquetError(format!(
+                    "{}; dropped {} buffered records",
+                    e, dropped
+                ))
+                .into());
+            }
+        };
 
         // Write the batch to the Parquet file
-        if let Some(writer) = &mut self.writer {
+        if let Some(sink) = &mut self.sink {
+            sink(batch)?;
+        } else if let Some(writer) = &mut self.writer {
             writer
                 .write(&batch)
                 .with_context(|| format!("Failed to write data to {}", self.output_path))?;
 
-            println!(
+            let message = format!(
                 "Wrote {} records to {}",
                 self.buffer.len(),
                 self.output_path
             );
+            print_status(Health::Healthy, &message);
+
+            if let Some(stats) = &mut self.file_stats {
+                self.buffer.iter().for_each(|data| stats.update(data));
+            }
+            if let Some(earliest) = self.buffer.iter().map(|data| data.system_timestamp).min() {
+                self.first_record_ms =
+                    Some(self.first_record_ms.map_or(earliest, |ms| ms.min(earliest)));
+            }
         } else {
             return Err(
                 ReceiverError::ParquetError("Writer is not initialized".to_string()).into(),
@@ -155,11 +539,42 @@ impl ParquetWriter {
         }
 
         // Clear the buffer
+        self.records_in_file += self.buffer.len();
         self.buffer.clear();
 
         Ok(())
     }
 
+    // Writes the buffer as row groups of exactly `alignment` records, holding
+    // back the remainder unless the file ends here
+    fn write_aligned(&mut self, alignment: usize, file_end: bool) -> Result<()> {
+        while self.buffer.len() >= alignment || (file_end && !self.buffer.is_empty()) {
+            let rest = self.buffer.split_off(alignment.min(self.buffer.len()));
+            let result = self.write_buffer();
+            self.buffer = rest;
+            result?;
+            if let Some(writer) = &mut self.writer {
+                writer
+                    .flush()
+                    .with_context(|| format!("Failed to end row group in {}", self.output_path))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Writes the whole buffer to a new file and closes it
+    fn write_row_group_file(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        if self.writer.is_none() {
+            let (output_dir, prefix) = (self.output_dir.clone(), self.prefix.clone());
+            self.open_file(&output_dir, &prefix)?;
+        }
+        self.write_buffer()?;
+        self.close_file()
+    }
+
     /// Creates a new file (for file splitting)
     ///
     /// Closes the current file after flushing any remaining data,
@@ -170,107 +585,333 @@ impl ParquetWriter {
     /// * `prefix` - Filename prefix for the new file
     ///
     /// # Returns
-    /// Result indicating success or error
-    pub fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<()> {
+    /// The final paths of the files closed since the last rotation, each
+    /// followed by its stats sidecar, if written
+    pub fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
         // Flush any remaining data
-        self.flush()?;
+        self.flush_buffer(true)?;
+        self.start_new_file(output_dir, prefix)?;
+        Ok(std::mem::take(&mut self.closed))
+    }
+
+    // Closes the current file and opens a new one without flushing the buffer
+    fn start_new_file(&mut self, output_dir: &str, prefix: &str) -> Result<()> {
+        if self.sink.is_some() {
+            self.records_in_file = 0;
+            return Ok(());
+        }
+
+        self.close_file()?;
+        if self.file_per_row_group {
+            // The next flush opens the file
+            self.output_dir = output_dir.to_string();
+            self.prefix = prefix.to_string();
+            self.records_in_file = 0;
+            return Ok(());
+        }
+
+        self.open_file(output_dir, prefix)?;
+        println!("Rotated to new file: {}", self.output_path);
+
+        Ok(())
+    }
 
-        // Close the current writer by taking it and dropping it
+    // Closes the current file, if one is open
+    fn close_file(&mut self) -> Result<()> {
         if let Some(writer) = self.writer.take() {
             writer.close().with_context(|| {
                 format!("Failed to close Parquet writer for {}", self.output_path)
             })?;
+            if self.file_naming == FileNaming::FirstRecord {
+                self.rename_to_first_record()?;
+            }
+            self.closed.push(self.output_path.clone());
+            self.write_stats_sidecar()?;
         }
+        self.first_record_ms = None;
+        Ok(())
+    }
 
+    // Writes the summary of the file just closed and starts a new one
+    fn write_stats_sidecar(&mut self) -> Result<()> {
+        let Some(stats) = self.file_stats.as_mut().map(std::mem::take) else {
+            return Ok(());
+        };
+        let path = Path::new(&self.output_path).with_extension("stats.json");
+        let file_name = Path::new(&self.output_path)
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        let json = stats.to_json(&file_name, self.options.run_id.as_deref());
+        std::fs::write(&path, json.to_string())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.closed.push(path.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    // Renames the closed file after the host time of its earliest record
+    fn rename_to_first_record(&mut self) -> Result<()> {
+        let Some(time) = self
+            .first_record_ms
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+        else {
+            return Ok(());
+        };
+
+        // Already named after that second (possibly with a suffix)
+        let current = PathBuf::from(&self.output_path);
+        let stem = format!("{}_{}", self.prefix, time.format("%Y%m%d_%H%M%S"));
+        if current
+            .file_stem()
+            .is_some_and(|name| name.to_string_lossy().starts_with(&stem))
+        {
+            return Ok(());
+        }
+
+        let candidates = output_path_candidates(&self.output_dir, &self.prefix, "parquet", time);
+        let target = rename_no_clobber(&current, candidates).with_context(|| {
+            format!(
+                "Failed to rename {} after its first record",
+                self.output_path
+            )
+        })?;
+        println!("Renamed {} to {}", self.output_path, target.display());
+        self.output_path = target.to_string_lossy().to_string();
+        Ok(())
+    }
+
+    // Opens a new file in the given directory
+    fn open_file(&mut self, output_dir: &str, prefix: &str) -> Result<()> {
         // Ensure output directory exists
         create_dir_all(output_dir)
             .with_context(|| format!("Failed to create output directory: {}", output_dir))?;
 
         // Generate new output file path
-        let now = chrono::Utc::now();
-        let filename = format!("{}_{}.parquet", prefix, now.format("%Y%m%d_%H%M%S"));
-        let output_path = Path::new(output_dir).join(filename);
+        let output_path = new_output_path(output_dir, prefix, "parquet");
         self.output_path = output_path.to_string_lossy().to_string();
 
         // Create a new Parquet writer
         let file = File::create(&output_path)
             .with_context(|| format!("Failed to create file: {}", self.output_path))?;
 
-        // Convert compression type to Parquet compression and build properties
-        let props = match self.compression {
-            CompressionType::None => WriterProperties::builder()
-                .set_compression(Compression::UNCOMPRESSED)
-                .build(),
-            CompressionType::Snappy => WriterProperties::builder()
-                .set_compression(Compression::SNAPPY)
-                .build(),
-            CompressionType::Gzip => WriterProperties::builder()
-                .set_compression(Compression::GZIP(Default::default()))
-                .build(),
-            CompressionType::Lz4 => WriterProperties::builder()
-                .set_compression(Compression::LZ4)
-                .build(),
-            CompressionType::Zstd => WriterProperties::builder()
-                .set_compression(Compression::ZSTD(Default::default()))
-                .build(),
-        };
+        let props = build_writer_properties(&self.compression, &self.options);
 
         // Initialize the ArrowWriter
         let writer = ArrowWriter::try_new(file, self.schema.clone(), Some(props))
             .with_context(|| format!("Failed to create Parquet writer for {}", self.output_path))?;
 
         self.writer = Some(writer);
-
-        println!("Rotated to new file: {}", self.output_path);
+        self.output_dir = output_dir.to_string();
+        self.prefix = prefix.to_string();
+        self.records_in_file = 0;
+        self.write_file_metadata();
 
         Ok(())
     }
 
+    /// Adds a key-value entry to the footer metadata of the current file
+    ///
+    /// # Arguments
+    /// * `key` - Metadata key
+    /// * `value` - Metadata value
+    pub fn append_metadata(&mut self, key: &str, value: String) {
+        if let Some(writer) = &mut self.writer {
+            writer.append_key_value_metadata(KeyValue::new(key.to_string(), value));
+        }
+    }
+
+    /// Bytes written to the current file so far
+    ///
+    /// Counts the compressed size of completed row groups plus the
+    /// estimated encoded size of the row group in progress; records still in
+    /// the buffer are not included.
+    pub fn bytes_written(&self) -> u64 {
+        self.writer.as_ref().map_or(0, |writer| {
+            let flushed: i64 = writer
+                .flushed_row_groups()
+                .iter()
+                .map(|row_group| row_group.compressed_size())
+                .sum();
+            flushed as u64 + writer.in_progress_size() as u64
+        })
+    }
+
+    /// Capture session identifier embedded in the written files, if any
+    pub fn run_id(&self) -> Option<&str> {
+        self.options.run_id.as_deref()
+    }
+
+    // Stores the run ID and host in the metadata of a newly opened file
+    fn write_file_metadata(&mut self) {
+        if let Some(run_id) = self.options.run_id.clone() {
+            self.append_metadata("run_id", run_id);
+        }
+        if let Some(host) = self.options.host.clone() {
+            self.append_metadata("hostname", host.hostname);
+            self.append_metadata("username", host.username);
+        }
+    }
+
     // Convert buffer data to Arrow RecordBatch (for actual file writing)
-    fn _create_record_batch(&self) -> Result<RecordBatch> {
+    fn _create_record_batch(&self) -> Result<RecordBatch, ReceiverError> {
         // Extract data into columns
-        let timestamps: Int64Array = self
-            .buffer
-            .iter()
-            .map(|data| data.timestamp as i64)
-            .collect();
+        let timestamps: ArrayRef = match self.options.primary_time {
+            PrimaryTime::Counter => Arc::new(self.time_column(SENSOR_TIME.1)),
+            PrimaryTime::Monotonic => Arc::new(self.time_column(MONOTONIC_TIME.1)),
+            PrimaryTime::Wall => Arc::new(wall_time_array(self.buffer.iter().map(SYSTEM_TIME.1))),
+        };
 
-        let temps: Float32Array = self.buffer.iter().map(|data| data.temp).collect();
+        let temps = self.float_column(0, |data| data.temp);
 
-        let gxs: Float32Array = self.buffer.iter().map(|data| data.gx).collect();
+        let gxs = self.float_column(1, |data| data.gx);
 
-        let gys: Float32Array = self.buffer.iter().map(|data| data.gy).collect();
+        let gys = self.float_column(2, |data| data.gy);
 
-        let gzs: Float32Array = self.buffer.iter().map(|data| data.gz).collect();
+        let gzs = self.float_column(3, |data| data.gz);
 
-        let axs: Float32Array = self.buffer.iter().map(|data| data.ax).collect();
+        let axs = self.float_column(4, |data| data.ax);
 
-        let ays: Float32Array = self.buffer.iter().map(|data| data.ay).collect();
+        let ays = self.float_column(5, |data| data.ay);
 
-        let azs: Float32Array = self.buffer.iter().map(|data| data.az).collect();
+        let azs = self.float_column(6, |data| data.az);
 
-        let system_timestamps: Int64Array = self
-            .buffer
-            .iter()
-            .map(|data| data.system_timestamp)
-            .collect();
+        let mut columns: Vec<ArrayRef> = vec![timestamps, Arc::new(temps)];
 
-        // Create record batch
-        RecordBatch::try_new(
-            self.schema.clone(),
-            vec![
-                Arc::new(timestamps),
-                Arc::new(temps),
-                Arc::new(gxs),
+        if self.options.nested_schema {
+            let gyro = StructArray::new(
+                axis_fields(self.options.nullable_fields),
+                vec![Arc::new(gxs), Arc::new(gys), Arc::new(gzs)],
+                None,
+            );
+            let accel = StructArray::new(
+                axis_fields(self.options.nullable_fields),
+                vec![Arc::new(axs), Arc::new(ays), Arc::new(azs)],
+                None,
+            );
+            columns.push(Arc::new(gyro));
+            columns.push(Arc::new(accel));
+        } else {
+            columns.extend([
+                Arc::new(gxs) as ArrayRef,
                 Arc::new(gys),
                 Arc::new(gzs),
                 Arc::new(axs),
                 Arc::new(ays),
                 Arc::new(azs),
-                Arc::new(system_timestamps),
-            ],
-        )
-        .with_context(|| "Failed to create record batch")
+            ]);
+        }
+
+        if self.options.accel_mag {
+            let magnitudes: Float32Array = self
+                .buffer
+                .iter()
+                .map(SensorData::accel_magnitude)
+                .collect();
+            columns.push(Arc::new(magnitudes));
+        }
+
+        for (name, clock) in secondary_time_columns(self.options.primary_time) {
+            if *name == SYSTEM_TIME.0 {
+                columns.push(Arc::new(wall_time_array(self.buffer.iter().map(clock))));
+            } else {
+                columns.push(Arc::new(self.time_column(*clock)));
+            }
+        }
+
+        if let Some(counter_hz) = self.options.counter_hz {
+            let (anchor_counter, anchor_ms) = self.time_anchor.unwrap_or_default();
+            let timestamps_ns: Vec<i64> = self
+                .buffer
+                .iter()
+                .map(|data| {
+                    let counter = data.extended_timestamp.unwrap_or_default();
+                    counter_to_nanos(counter, anchor_counter, anchor_ms, counter_hz)
+                })
+                .collect();
+            let timestamps_ns = TimestampNanosecondArray::from(timestamps_ns).with_timezone("UTC");
+            columns.push(Arc::new(timestamps_ns));
+        }
+
+        if self.options.extended_timestamp {
+            let extended: Int64Array = self
+                .buffer
+                .iter()
+                .map(|data| data.extended_timestamp)
+                .collect();
+            columns.push(Arc::new(extended));
+        }
+
+        if let Some(clock) = &self.options.reference_clock {
+            let reference: Vec<i64> = self
+                .buffer
+                .iter()
+                .map(|data| clock.map(data.timestamp))
+                .collect();
+            columns.push(Arc::new(TimestampNanosecondArray::from(reference)));
+        }
+
+        if self.options.status_column {
+            let status: UInt8Array = self.buffer.iter().map(|data| data.status).collect();
+            columns.push(Arc::new(status));
+        }
+
+        if let Some(check) = self
+            .options
+            .range_check
+            .filter(|check| check.policy() == RangePolicy::Tag)
+        {
+            let valid: BooleanArray = self
+                .buffer
+                .iter()
+                .map(|data| Some(check.is_valid(data)))
+                .collect();
+            columns.push(Arc::new(valid));
+        }
+
+        if self.options.clock_skew_column {
+            let skew: BooleanArray = self
+                .buffer
+                .iter()
+                .map(|data| Some(data.clock_skew))
+                .collect();
+            columns.push(Arc::new(skew));
+        }
+
+        if self.options.burst_column {
+            let burst: UInt32Array = self.buffer.iter().map(|data| data.burst_id).collect();
+            columns.push(Arc::new(burst));
+        }
+
+        if self.options.session_column {
+            let run_id = self.options.run_id.as_deref().unwrap_or_default();
+            let session: StringArray = self.buffer.iter().map(|_| Some(run_id)).collect();
+            columns.push(Arc::new(session));
+        }
+
+        // Create record batch
+        RecordBatch::try_new(self.schema.clone(), columns).map_err(|e| {
+            ReceiverError::ParquetError(format!("Failed to create record batch: {}", e))
+        })
+    }
+
+    // Swaps the schema batches are built against, to provoke batch failures
+    #[cfg(test)]
+    pub(crate) fn replace_schema(&mut self, schema: Arc<Schema>) -> Arc<Schema> {
+        std::mem::replace(&mut self.schema, schema)
+    }
+
+    // Extract a float field as a column, null where it failed to decode if
+    // the layout allows nulls
+    fn float_column(&self, index: usize, value: fn(&SensorData) -> f32) -> Float32Array {
+        let nullable = self.options.nullable_fields;
+        self.buffer
+            .iter()
+            .map(|data| (!(nullable && data.is_null(index))).then(|| value(data)))
+            .collect()
+    }
+
+    // Extract one of the per-record clocks as an Int64 column
+    fn time_column(&self, clock: fn(&SensorData) -> i64) -> Int64Array {
+        self.buffer.iter().map(clock).collect()
     }
 
     /// Close the writer and finalize the file
@@ -279,19 +920,1141 @@ impl ParquetWriter {
     /// This should be called when finished with the writer to ensure all data is saved.
     ///
     /// # Returns
-    /// Result indicating success or error
-    pub fn close(mut self) -> Result<()> {
+    /// The final paths of the files closed since the last rotation, as
+    /// returned by [`rotate_file`](Self::rotate_file)
+    pub fn close(mut self) -> Result<Vec<String>> {
         // Flush any remaining data
-        self.flush()?;
+        self.flush_buffer(true)?;
 
         // Close the writer
-        if let Some(writer) = self.writer.take() {
-            writer.close().with_context(|| {
-                format!("Failed to close Parquet writer for {}", self.output_path)
-            })?;
+        if self.writer.is_some() {
+            self.close_file()?;
             println!("Closed Parquet file: {}", self.output_path);
         }
 
+        Ok(self.closed)
+    }
+}
+
+impl DataSink for ParquetWriter {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        ParquetWriter::add_data(self, data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        ParquetWriter::flush(self)
+    }
+
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+        ParquetWriter::rotate_file(self, output_dir, prefix)
+    }
+
+    fn close(self: Box<Self>) -> Result<Vec<String>> {
+        ParquetWriter::close(*self)
+    }
+
+    fn append_metadata(&mut self, key: &str, value: String) {
+        ParquetWriter::append_metadata(self, key, value)
+    }
+
+    fn bytes_written(&self) -> u64 {
+        ParquetWriter::bytes_written(self)
+    }
+
+    fn output_path(&self) -> Option<&str> {
+        Some(&self.output_path)
+    }
+
+    fn run_id(&self) -> Option<&str> {
+        ParquetWriter::run_id(self)
+    }
+}
+
+/// Rejects a buffer size of 0
+///
+/// A zero-record buffer would reach its size before holding anything, so
+/// every flush check would fire; at least one record has to be buffered.
+pub(crate) fn check_buffer_size(buffer_size: usize) -> Result<(), ReceiverError> {
+    if buffer_size == 0 {
+        return Err(ReceiverError::ConfigError(
+            "Invalid buffer size: 0 (must be at least 1)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Properties every file of a writer is created with
+///
+/// The single place that turns the compression option and the writer
+/// options into Parquet settings, shared by the first file and every file
+/// after a rotation.
+pub(crate) fn build_writer_properties(
+    compression: &CompressionType,
+    options: &WriterOptions,
+) -> WriterProperties {
+    let mut builder = WriterProperties::builder().set_compression(parquet_compression(compression));
+    if let Some(size) = options.max_row_group_size {
+        builder = builder.set_max_row_group_size(size);
+    }
+    builder.build()
+}
+
+/// Parquet compression codec for a compression option
+pub(crate) fn parquet_compression(compression: &CompressionType) -> Compression {
+    match compression {
+        CompressionType::None => Compression::UNCOMPRESSED,
+        CompressionType::Snappy => Compression::SNAPPY,
+        CompressionType::Gzip(level) => Compression::GZIP(*level),
+        CompressionType::Lz4 => Compression::LZ4,
+        CompressionType::Zstd(level) => Compression::ZSTD(*level),
+    }
+}
+
+/// Checks that the parquet crate can write with a codec
+///
+/// Codecs compiled out of the parquet crate make its column writers panic on
+/// first use. A one-row file is written to memory up front instead, and a
+/// failure is reported as a configuration error naming the missing feature.
+/// Each codec is probed once per process; later writers reuse the outcome.
+pub(crate) fn ensure_codec_available(codec: Compression) -> Result<()> {
+    // Codecs probed so far, by name, and whether they work
+    static PROBED: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+
+    let name = format!("{:?}", codec);
+    let name = name.split('(').next().unwrap_or_default();
+    // Held while probing, so that only one thread swaps the panic hook
+    let mut probed = PROBED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if *probed
+        .entry(name.to_string())
+        .or_insert_with(|| codec_works(codec))
+    {
+        return Ok(());
+    }
+
+    let feature = match codec {
+        Compression::LZ4 | Compression::LZ4_RAW => Some("lz4"),
+        Compression::ZSTD(_) => Some("zstd"),
+        Compression::GZIP(_) => Some("flate2"),
+        Compression::SNAPPY => Some("snap"),
+        Compression::BROTLI(_) => Some("brotli"),
+        _ => None,
+    };
+    let message = match feature {
+        Some(feature) => format!(
+            "{} compression is not available: the parquet crate was built without its `{}` \
+             feature; enable it or choose another --compression",
+            name, feature
+        ),
+        None => format!(
+            "{} compression is not supported by the parquet crate; choose another --compression",
+            name
+        ),
+    };
+    Err(ReceiverError::ConfigError(message).into())
+}
+
+// Writes a one-row file with the codec, returning whether that worked
+fn codec_works(codec: Compression) -> bool {
+    let probe = || -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "probe",
+            DataType::Int32,
+            false,
+        )]));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(vec![0]))])?;
+        let props = WriterProperties::builder().set_compression(codec).build();
+        let mut writer = ArrowWriter::try_new(Vec::new(), schema, Some(props))?;
+        writer.write(&batch)?;
+        writer.close()?;
         Ok(())
+    };
+
+    // Keep the expected panic from being printed while probing
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(probe);
+    std::panic::set_hook(hook);
+    matches!(result, Ok(Ok(())))
+}
+
+/// A named per-record clock stored as a 64-bit column
+type TimeColumn = (&'static str, fn(&SensorData) -> i64);
+
+const SENSOR_TIME: TimeColumn = ("sensor_timestamp", |data| data.timestamp as i64);
+const SYSTEM_TIME: TimeColumn = ("system_timestamp", |data| data.system_timestamp);
+const MONOTONIC_TIME: TimeColumn = ("monotonic_timestamp", |data| data.monotonic_timestamp);
+
+/// Time columns stored after the sensor values for the given primary clock
+///
+/// The default counter layout predates clock selection and only carries the
+/// wall clock; the other layouts keep both clocks not used as primary.
+fn secondary_time_columns(primary: PrimaryTime) -> &'static [TimeColumn] {
+    match primary {
+        PrimaryTime::Counter => &[SYSTEM_TIME],
+        PrimaryTime::Wall => &[SENSOR_TIME, MONOTONIC_TIME],
+        PrimaryTime::Monotonic => &[SENSOR_TIME, SYSTEM_TIME],
+    }
+}
+
+/// Arrow type of the host wall clock in every file written
+///
+/// A UTC timestamp, so that readers such as DuckDB and pandas pick it up as
+/// a time axis in absolute time, whichever column it is stored in.
+pub(crate) fn wall_time_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+/// Column of host wall clock values in milliseconds, typed as [`wall_time_type`]
+pub(crate) fn wall_time_array(millis: impl IntoIterator<Item = i64>) -> TimestampMillisecondArray {
+    TimestampMillisecondArray::from_iter_values(millis).with_timezone("UTC")
+}
+
+/// Arrow type of a secondary time column
+///
+/// The host wall clock is a timestamp (see [`wall_time_type`]); the counter
+/// and monotonic clocks are plain tick counts.
+fn time_column_type(name: &str) -> DataType {
+    if name == SYSTEM_TIME.0 {
+        wall_time_type()
+    } else {
+        DataType::Int64
+    }
+}
+
+/// Child fields of the nested gyro/accel struct columns
+fn axis_fields(nullable: bool) -> Fields {
+    Fields::from(vec![
+        Field::new("x", DataType::Float32, nullable),
+        Field::new("y", DataType::Float32, nullable),
+        Field::new("z", DataType::Float32, nullable),
+    ])
+}
+
+/// Generates a timestamped file path that does not clobber an existing file
+///
+/// File names have one-second resolution, so rotations within the same second
+/// get a numeric suffix instead of truncating the previous file.
+pub(crate) fn new_output_path(output_dir: &str, prefix: &str, extension: &str) -> PathBuf {
+    output_path_at(output_dir, prefix, extension, Utc::now())
+}
+
+/// Generates a file path named after `time` that does not clobber an existing file
+pub(crate) fn output_path_at(
+    output_dir: &str,
+    prefix: &str,
+    extension: &str,
+    time: DateTime<Utc>,
+) -> PathBuf {
+    output_path_candidates(output_dir, prefix, extension, time)
+        .find(|path| !path.exists())
+        .unwrap_or_default()
+}
+
+// Paths named after `time`, plain first and then with increasing suffixes
+fn output_path_candidates(
+    output_dir: &str,
+    prefix: &str,
+    extension: &str,
+    time: DateTime<Utc>,
+) -> impl Iterator<Item = PathBuf> {
+    let dir = PathBuf::from(output_dir);
+    let stem = format!("{}_{}", prefix, time.format("%Y%m%d_%H%M%S"));
+    let extension = extension.to_string();
+    (0..).map(move |suffix| match suffix {
+        0 => dir.join(format!("{}.{}", stem, extension)),
+        _ => dir.join(format!("{}_{}.{}", stem, suffix, extension)),
+    })
+}
+
+/// Renames a file to the first candidate path not taken, without ever replacing a file
+///
+/// The file is hard-linked under the new name, which fails instead of
+/// replacing a file that appeared since, and the old name is then removed.
+/// File systems without hard links, such as FAT on SD cards, fall back to
+/// renaming to the first candidate that does not exist yet.
+fn rename_no_clobber(
+    from: &Path,
+    candidates: impl Iterator<Item = PathBuf>,
+) -> std::io::Result<PathBuf> {
+    for target in candidates {
+        match std::fs::hard_link(from, &target) {
+            Ok(()) => {
+                std::fs::remove_file(from)?;
+                return Ok(target);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(_) if !target.exists() => {
+                rename(from, &target)?;
+                return Ok(target);
+            }
+            Err(_) => continue,
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        "no free file name",
+    ))
+}
+
+/// Converts a sensor counter value into wall-aligned nanoseconds since the epoch
+///
+/// The counter is anchored to the host time at which the first record was
+/// received; later values advance by `(counter - anchor_counter) / counter_hz`.
+/// Both are counts extended across rollovers by a
+/// [`TickExtender`](super::monotonic::TickExtender), so the result stays
+/// continuous across any number of rollovers and for reordered records.
+pub(crate) fn counter_to_nanos(
+    counter: i64,
+    anchor_counter: i64,
+    anchor_ms: i64,
+    counter_hz: f64,
+) -> i64 {
+    let elapsed_counts = (counter - anchor_counter) as f64;
+    let elapsed_ns = (elapsed_counts * 1e9 / counter_hz).round() as i64;
+    anchor_ms * 1_000_000 + elapsed_ns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Array;
+    use arrow::record_batch::RecordBatchReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use tempfile::tempdir;
+
+    fn test_record(timestamp: u32, system_timestamp: i64) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timestamp_ns_from_counter() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            counter_hz: Some(1920.0),
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "ns", CompressionType::None, 100, options)
+                .unwrap();
+
+        let start_ms = 1_700_000_000_000;
+        for counter in [1000, 1001, 1000 + 1920] {
+            writer.add_data(test_record(counter, start_ms + 5)).unwrap();
+        }
+
+        let batch = writer._create_record_batch().unwrap();
+        let column = batch
+            .column_by_name("timestamp_ns")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+
+        let start_ns = start_ms * 1_000_000 + 5_000_000;
+        assert_eq!(column.value(0), start_ns);
+        assert_eq!(column.value(1), start_ns + 520_833); // 1 / 1920 Hz
+        assert_eq!(column.value(2), start_ns + 1_000_000_000);
+    }
+
+    #[test]
+    fn test_timestamp_ns_across_24_bit_rollover() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            counter_hz: Some(1000.0),
+            counter_width: CounterWidth::Bits24,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "ns24", CompressionType::None, 100, options)
+                .unwrap();
+        for counter in [0xFF_FFFE, 0xFF_FFFF, 0x00_0000, 0x00_0001] {
+            writer.add_data(test_record(counter, 1_000)).unwrap();
+        }
+
+        let batch = writer._create_record_batch().unwrap();
+        let column = batch
+            .column_by_name("timestamp_ns")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        // One millisecond per count, straight through the rollover
+        let offsets: Vec<i64> = column
+            .values()
+            .iter()
+            .map(|ns| ns - column.value(0))
+            .collect();
+        assert_eq!(offsets, vec![0, 1_000_000, 2_000_000, 3_000_000]);
+    }
+
+    #[test]
+    fn test_timestamp_ns_with_reordered_records_and_two_rollovers() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            counter_hz: Some(1000.0),
+            counter_width: CounterWidth::Bits24,
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new_with_options(
+            dir_path,
+            "reorder",
+            CompressionType::None,
+            100,
+            options,
+        )
+        .unwrap();
+        // Round the counter in quarter laps, wrapping twice; the record
+        // sampled just before the second wrap arrives after it
+        let counters = [
+            0xFF_FFF0, 0x40_0000, 0x80_0000, 0xC0_0000, 0x00_0004, 0xFF_FFFC,
+        ];
+        for counter in counters {
+            writer.add_data(test_record(counter, 1_000)).unwrap();
+        }
+
+        let batch = writer._create_record_batch().unwrap();
+        let column = batch
+            .column_by_name("timestamp_ns")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        let counts: Vec<i64> = column
+            .values()
+            .iter()
+            .map(|ns| (ns - column.value(0)) / 1_000_000)
+            .collect();
+        let lap = 1 << 24;
+        assert_eq!(
+            counts,
+            vec![0, 0x40_0010, 0x80_0010, 0xC0_0010, lap + 0x14, lap + 0xC]
+        );
+    }
+
+    #[test]
+    fn test_counter_to_nanos_wraps() {
+        let mut ticks = TickExtender::new();
+        let anchor = ticks.extend(u32::MAX - 4);
+        let ns = counter_to_nanos(ticks.extend(5), anchor, 0, 1000.0);
+        assert_eq!(ns, 10_000_000);
+    }
+
+    #[test]
+    fn test_column_prefix_applies_to_rotated_files() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            accel_mag: true,
+            column_prefix: Some("imu_".to_string()),
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new_with_options(
+            dir_path,
+            "prefixed",
+            CompressionType::None,
+            10,
+            options,
+        )
+        .unwrap();
+        let mut paths = Vec::new();
+        for i in 0..6 {
+            writer.add_data(test_record(i, 0)).unwrap();
+            if i == 2 {
+                paths.push(writer.output_path.clone());
+                writer.rotate_file(dir_path, "prefixed").unwrap();
+            }
+        }
+        paths.push(writer.output_path.clone());
+        writer.close().unwrap();
+
+        for path in paths {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap();
+            let schema = reader.schema();
+            let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+            assert_eq!(
+                names,
+                vec![
+                    "imu_timestamp",
+                    "imu_temp",
+                    "imu_gx",
+                    "imu_gy",
+                    "imu_gz",
+                    "imu_ax",
+                    "imu_ay",
+                    "imu_az",
+                    "imu_accel_mag",
+                    "imu_system_timestamp",
+                ]
+            );
+            let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+            assert_eq!(rows, 3);
+        }
+    }
+
+    #[test]
+    fn test_system_timestamp_is_timestamp_column() {
+        use parquet::basic::{LogicalType, TimeUnit as ParquetTimeUnit};
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::format::MilliSeconds;
+
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "wall", CompressionType::None, 4).unwrap();
+        let output_path = writer.output_path.clone();
+        writer.add_data(test_record(7, 1_700_000_000_123)).unwrap();
+        writer.close().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&output_path).unwrap()).unwrap();
+        let schema = reader.metadata().file_metadata().schema_descr_ptr();
+        let column = |name: &str| {
+            (0..schema.num_columns())
+                .map(|i| schema.column(i))
+                .find(|column| column.name() == name)
+                .unwrap()
+        };
+        assert_eq!(
+            column("system_timestamp").logical_type(),
+            Some(LogicalType::Timestamp {
+                is_adjusted_to_u_t_c: true,
+                unit: ParquetTimeUnit::MILLIS(MilliSeconds {}),
+            })
+        );
+        assert_eq!(column("timestamp").logical_type(), None);
+
+        let batch = ParquetRecordBatchReaderBuilder::try_new(File::open(output_path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let system = batch
+            .column_by_name("system_timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(system.value(0), 1_700_000_000_123);
+    }
+
+    #[test]
+    fn test_nested_schema_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            nested_schema: true,
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new_with_options(
+            dir_path,
+            "nested",
+            CompressionType::Snappy,
+            10,
+            options,
+        )
+        .unwrap();
+        for i in 0..5 {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        let path = writer.output_path.clone();
+        writer.close().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let schema = reader.schema();
+        assert!(schema.field_with_name("gx").is_err());
+
+        for name in ["gyro", "accel"] {
+            let DataType::Struct(children) = schema.field_with_name(name).unwrap().data_type()
+            else {
+                panic!("{} should be a struct column", name);
+            };
+            let names: Vec<_> = children.iter().map(|f| f.name().as_str()).collect();
+            assert_eq!(names, vec!["x", "y", "z"]);
+        }
+
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 5);
+    }
+
+    #[test]
+    fn test_accel_mag_column() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            accel_mag: true,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "mag", CompressionType::None, 10, options)
+                .unwrap();
+        for (ax, ay, az) in [(3.0, 4.0, 0.0), (1.0, -2.0, 2.0), (0.0, 0.0, -9.5)] {
+            let mut record = test_record(0, 0);
+            (record.ax, record.ay, record.az) = (ax, ay, az);
+            writer.add_data(record).unwrap();
+        }
+
+        let batch = writer._create_record_batch().unwrap();
+        let magnitudes = batch
+            .column_by_name("accel_mag")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float32Array>()
+            .unwrap();
+        assert_eq!(magnitudes.values(), &[5.0, 3.0, 9.5]);
+    }
+
+    #[test]
+    fn test_valid_column_tags_out_of_range_records() {
+        use arrow::array::BooleanArray;
+
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            range_check: Some(RangeCheck::new(16.0, 2000.0, RangePolicy::Tag)),
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "valid", CompressionType::None, 10, options)
+                .unwrap();
+        for az in [1.0, f32::NAN, f32::INFINITY, 250.0, -3.0] {
+            let mut record = test_record(0, 0);
+            record.az = az;
+            writer.add_data(record).unwrap();
+        }
+
+        let batch = writer._create_record_batch().unwrap();
+        let valid = batch
+            .column_by_name("valid")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap();
+        let valid: Vec<bool> = valid.iter().map(Option::unwrap).collect();
+        assert_eq!(valid, vec![true, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_compression_level_changes_file_size() {
+        let size_with = |compression: &str| {
+            let temp_dir = tempdir().unwrap();
+            let dir_path = temp_dir.path().to_str().unwrap();
+            let compression = compression.parse().unwrap();
+            let mut writer = ParquetWriter::new(dir_path, "level", compression, 1000).unwrap();
+            for i in 0..1000 {
+                let mut record = test_record(i, i as i64);
+                record.gx = (i % 37) as f32 * 0.25;
+                record.ax = (i % 11) as f32;
+                writer.add_data(record).unwrap();
+            }
+            let path = writer.output_path.clone();
+            writer.close().unwrap();
+            std::fs::metadata(path).unwrap().len()
+        };
+        assert!(size_with("gzip:0") > size_with("gzip:9"));
+    }
+
+    #[test]
+    fn test_max_records_per_file_splits_batches() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        // A buffer size that does not divide the limit makes batches straddle it
+        let mut writer = ParquetWriter::new(dir_path, "count", CompressionType::None, 30)
+            .unwrap()
+            .with_max_records_per_file(100);
+        for i in 0..250 {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        let rows: Vec<usize> = files
+            .iter()
+            .map(|path| {
+                ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                    .map(|batch| batch.unwrap().num_rows())
+                    .sum()
+            })
+            .collect();
+        assert_eq!(rows, vec![100, 100, 50]);
+    }
+
+    #[test]
+    fn test_stats_sidecar_written_on_rotation_and_close() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            run_id: Some("sidecar-run".to_string()),
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::new_with_options(
+            dir_path,
+            "sidecar",
+            CompressionType::None,
+            10,
+            options,
+        )
+        .unwrap()
+        .with_stats_sidecar();
+        let mut paths = Vec::new();
+        for i in 0..25 {
+            let mut record = test_record(i, 1_000 + i as i64 * 10);
+            record.ax = i as f32;
+            writer.add_data(record).unwrap();
+            if i == 14 {
+                paths.push(writer.output_path.clone());
+                writer.rotate_file(dir_path, "sidecar").unwrap();
+            }
+        }
+        paths.push(writer.output_path.clone());
+        writer.close().unwrap();
+
+        let sidecar = |path: &str| -> serde_json::Value {
+            let path = Path::new(path).with_extension("stats.json");
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+        };
+        let first = sidecar(&paths[0]);
+        assert_eq!(first["run_id"], "sidecar-run");
+        assert_eq!(first["count"], 15);
+        assert_eq!(first["start_ms"], 1_000);
+        assert_eq!(first["duration_ms"], 140);
+        assert_eq!(first["channels"]["ax"]["min"], 0.0);
+        assert_eq!(first["channels"]["ax"]["max"], 14.0);
+        assert_eq!(first["channels"]["ax"]["mean"], 7.0);
+        assert_eq!(first["channels"]["temp"]["mean"], 25.0);
+
+        let second = sidecar(&paths[1]);
+        assert_eq!(second["count"], 10);
+        assert_eq!(second["first_timestamp"], 15);
+        assert_eq!(second["last_timestamp"], 24);
+        assert_eq!(
+            second["file"],
+            Path::new(&paths[1]).file_name().unwrap().to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_max_row_group_size_applies_after_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            max_row_group_size: Some(100),
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "groups", CompressionType::None, 50, options)
+                .unwrap();
+        let mut paths = Vec::new();
+        for i in 0..600 {
+            writer.add_data(test_record(i, 0)).unwrap();
+            if i == 349 {
+                paths.push(writer.output_path.clone());
+                writer.rotate_file(dir_path, "groups").unwrap();
+            }
+        }
+        paths.push(writer.output_path.clone());
+        writer.close().unwrap();
+
+        let row_groups: Vec<Vec<i64>> = paths
+            .iter()
+            .map(|path| {
+                let builder =
+                    ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+                builder
+                    .metadata()
+                    .row_groups()
+                    .iter()
+                    .map(|group| group.num_rows())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            row_groups,
+            vec![vec![100, 100, 100, 50], vec![100, 100, 50]]
+        );
+    }
+
+    #[test]
+    fn test_row_groups_aligned_to_record_multiples() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "aligned", CompressionType::None, 100)
+            .unwrap()
+            .with_row_group_alignment(256);
+        for i in 0..1000 {
+            writer.add_data(test_record(i, 0)).unwrap();
+            // Interval flushes in between do not cut a group short
+            if i % 333 == 0 {
+                writer.flush().unwrap();
+            }
+        }
+        let path = writer.output_path.clone();
+        writer.close().unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        let rows: Vec<i64> = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|group| group.num_rows())
+            .collect();
+        assert_eq!(rows, vec![256, 256, 256, 232]);
+    }
+
+    #[test]
+    fn test_file_per_row_group_writes_one_file_per_flush() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "groups", CompressionType::None, 10)
+            .unwrap()
+            .with_file_per_row_group();
+        for i in 0..40 {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        // A rotation between flushes does not leave an empty file behind
+        writer.rotate_file(dir_path, "groups").unwrap();
+        for i in 40..45 {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 5);
+        let row_groups: Vec<(usize, i64)> = files
+            .iter()
+            .map(|path| {
+                let builder =
+                    ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+                let metadata = builder.metadata();
+                (
+                    metadata.num_row_groups(),
+                    metadata.file_metadata().num_rows(),
+                )
+            })
+            .collect();
+        assert_eq!(row_groups, vec![(1, 10), (1, 10), (1, 10), (1, 10), (1, 5)]);
+    }
+
+    #[test]
+    fn test_first_record_naming_renames_on_close() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "named", CompressionType::None, 4)
+            .unwrap()
+            .with_file_naming(FileNaming::FirstRecord);
+        let created = writer.output_path.clone();
+
+        // 2021-01-01 00:00:05 UTC, with the earliest record arriving second
+        let base_ms = 1_609_459_205_000;
+        for (i, offset) in [(0, 2_000), (1, 0), (2, 1_000), (3, 3_000), (4, 4_000)] {
+            writer.add_data(test_record(i, base_ms + offset)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, vec!["named_20210101_000005.parquet".to_string()]);
+        assert!(!Path::new(&created).exists());
+
+        let path = temp_dir.path().join(&files[0]);
+        let rows: usize = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(rows, 5);
+    }
+
+    #[test]
+    fn test_first_record_rename_never_replaces_a_file() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "named", CompressionType::None, 4)
+            .unwrap()
+            .with_file_naming(FileNaming::FirstRecord);
+        writer.add_data(test_record(0, 1_609_459_205_000)).unwrap();
+
+        // Another file took the name in the meantime
+        let taken = temp_dir.path().join("named_20210101_000005.parquet");
+        std::fs::write(&taken, b"taken").unwrap();
+        let closed = writer.close().unwrap();
+
+        let renamed = temp_dir.path().join("named_20210101_000005_1.parquet");
+        assert_eq!(closed, vec![renamed.to_string_lossy().to_string()]);
+        assert_eq!(std::fs::read(&taken).unwrap(), b"taken");
+        assert_eq!(std::fs::read_dir(dir_path).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_sort_on_flush_orders_each_batch() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "sorted", CompressionType::None, 8)
+            .unwrap()
+            .with_sort_on_flush();
+        let output_path = writer.output_path.clone();
+        let shuffled = [5, 3, 7, 1, 0, 6, 2, 4, 13, 9, 15, 8, 12, 11, 14, 10];
+        for i in shuffled {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(output_path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let timestamps: Vec<i64> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("timestamp").unwrap();
+                let column = column.as_any().downcast_ref::<Int64Array>().unwrap();
+                column.values().to_vec()
+            })
+            .collect();
+        assert_eq!(timestamps, (0..16).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn test_batch_failure_is_typed_and_drops_buffer() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "typed", CompressionType::None, 3).unwrap();
+        let original = writer.replace_schema(Arc::new(Schema::empty()));
+
+        writer.add_data(test_record(0, 0)).unwrap();
+        writer.add_data(test_record(1, 0)).unwrap();
+        let err = writer.add_data(test_record(2, 0)).unwrap_err();
+        match err.downcast_ref::<ReceiverError>() {
+            Some(ReceiverError::ParquetError(message)) => {
+                assert!(
+                    message.contains("dropped 3 buffered records"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("Expected a Parquet error, got {:?}", other),
+        }
+        assert!(writer.buffer.is_empty());
+
+        writer.replace_schema(original);
+        writer.add_data(test_record(3, 0)).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_stream_writer_hands_batches_to_sink() {
+        let rows = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = rows.clone();
+        let mut writer = ParquetWriter::for_stream(
+            10,
+            WriterOptions::default(),
+            Box::new(move |batch| {
+                seen.lock().unwrap().push(batch.num_rows());
+                Ok(())
+            }),
+        )
+        .unwrap();
+        for i in 0..25 {
+            writer.add_data(test_record(i, 0)).unwrap();
+        }
+        writer.rotate_file("unused", "unused").unwrap();
+        writer.close().unwrap();
+
+        assert_eq!(*rows.lock().unwrap(), vec![10, 10, 5]);
+    }
+
+    #[test]
+    fn test_run_id_shared_across_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            run_id: Some(crate::generate_run_id()),
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "run", CompressionType::None, 10, options)
+                .unwrap();
+        writer.add_data(test_record(0, 0)).unwrap();
+        writer.rotate_file(dir_path, "run").unwrap();
+        writer.add_data(test_record(1, 0)).unwrap();
+        let expected = writer.run_id().unwrap().to_string();
+        writer.close().unwrap();
+
+        let mut run_ids = Vec::new();
+        for entry in std::fs::read_dir(dir_path).unwrap() {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(
+                File::open(entry.unwrap().path()).unwrap(),
+            )
+            .unwrap();
+            let run_id = builder
+                .metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|kv| kv.iter().find(|kv| kv.key == "run_id").cloned())
+                .and_then(|kv| kv.value);
+            run_ids.push(run_id);
+        }
+        assert_eq!(run_ids, vec![Some(expected.clone()), Some(expected)]);
+    }
+
+    #[test]
+    fn test_host_metadata_written_when_enabled() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            host: Some(HostInfo::gather()),
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "host", CompressionType::None, 10, options)
+                .unwrap();
+        writer.add_data(test_record(0, 0)).unwrap();
+        writer.close().unwrap();
+
+        let path = std::fs::read_dir(dir_path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        let metadata = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        let value = |key: &str| {
+            metadata
+                .iter()
+                .find(|kv| kv.key == key)
+                .and_then(|kv| kv.value.clone())
+        };
+        assert!(value("hostname").is_some_and(|hostname| !hostname.is_empty()));
+        assert!(value("username").is_some());
+    }
+
+    #[test]
+    fn test_zero_buffer_size_is_config_error() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let err = ParquetWriter::new(dir_path, "empty", CompressionType::None, 0)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(ReceiverError::ConfigError(message)) if message.contains("buffer size")
+        ));
+        assert_eq!(std::fs::read_dir(dir_path).unwrap().count(), 0);
+        let sink: BatchSink = Box::new(|_| Ok(()));
+        assert!(matches!(
+            ParquetWriter::for_stream(0, WriterOptions::default(), sink),
+            Err(ReceiverError::ConfigError(_))
+        ));
+
+        // The smallest valid buffer writes every record as it arrives
+        let mut writer = ParquetWriter::new(dir_path, "single", CompressionType::None, 1).unwrap();
+        writer.add_data(test_record(0, 0)).unwrap();
+        assert!(writer.buffer.is_empty());
+        assert_eq!(writer.records_in_file, 1);
+    }
+
+    #[test]
+    fn test_unsupported_codec_is_config_error() {
+        assert!(ensure_codec_available(Compression::SNAPPY).is_ok());
+
+        let err = ensure_codec_available(Compression::LZO).unwrap_err();
+        match err.downcast_ref::<ReceiverError>() {
+            Some(ReceiverError::ConfigError(message)) => {
+                assert!(
+                    message.contains("LZO compression is not supported"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("Expected a configuration error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_primary_time_wall() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let options = WriterOptions {
+            primary_time: PrimaryTime::Wall,
+            ..Default::default()
+        };
+        let mut writer =
+            ParquetWriter::new_with_options(dir_path, "wall", CompressionType::None, 10, options)
+                .unwrap();
+        let mut record = test_record(42, 1_700_000_000_123);
+        record.monotonic_timestamp = 7;
+        writer.add_data(record).unwrap();
+
+        let batch = writer._create_record_batch().unwrap();
+        let schema = batch.schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "timestamp",
+                "temp",
+                "gx",
+                "gy",
+                "gz",
+                "ax",
+                "ay",
+                "az",
+                "sensor_timestamp",
+                "monotonic_timestamp"
+            ]
+        );
+
+        let primary = batch
+            .column_by_name("timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(primary.value(0), 1_700_000_000_123);
+
+        let counter = batch
+            .column_by_name("sensor_timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(counter.value(0), 42);
     }
 }
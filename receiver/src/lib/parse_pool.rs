@@ -0,0 +1,196 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::layout::LineLayout;
+use super::serial::{parse_sensor_data, parse_sensor_data_lenient, parse_untimed_sensor_data};
+use super::types::SensorData;
+
+/// Batches queued per worker before `submit` blocks
+const QUEUED_BATCHES_PER_THREAD: usize = 4;
+
+/// How a text line is turned into a record
+#[derive(Debug, Clone)]
+pub enum LineParser {
+    /// Default layout, see [`parse_sensor_data`]
+    Strict,
+    /// Default layout keeping undecodable fields, see [`parse_sensor_data_lenient`]
+    Lenient,
+    /// Lines without a timestamp field, see [`parse_untimed_sensor_data`]
+    Untimed,
+    /// Custom field order and encoding
+    Layout(LineLayout),
+}
+
+impl LineParser {
+    /// Parses one line
+    pub fn parse(&self, line: &str) -> Result<SensorData> {
+        match self {
+            LineParser::Strict => parse_sensor_data(line),
+            LineParser::Lenient => parse_sensor_data_lenient(line),
+            LineParser::Untimed => parse_untimed_sensor_data(line),
+            LineParser::Layout(layout) => layout.parse_line(line),
+        }
+    }
+}
+
+type Batch = (u64, Vec<String>);
+type Parsed = (u64, Vec<Result<SensorData>>);
+
+/// Pool of threads parsing batches of text lines
+///
+/// The reader keeps cutting the stream into lines on its own thread and
+/// submits each read's lines as one batch; the workers parse batches in
+/// parallel and may finish them in any order. Results are held back until
+/// every earlier batch is done, so they come out in the order the lines
+/// were received, one result per line, parse errors included.
+pub struct ParsePool {
+    jobs: Option<SyncSender<Batch>>,
+    results: Receiver<Parsed>,
+    workers: Vec<JoinHandle<()>>,
+    // Sequence number of the next submitted batch
+    submitted: u64,
+    // Sequence number of the next batch to hand out
+    released: u64,
+    // Finished batches waiting for an earlier one
+    pending: BTreeMap<u64, Vec<Result<SensorData>>>,
+}
+
+impl ParsePool {
+    /// Starts `threads` parse workers (at least one)
+    pub fn new(threads: usize, parser: LineParser) -> Self {
+        let threads = threads.max(1);
+        let (jobs, job_rx) = mpsc::sync_channel::<Batch>(threads * QUEUED_BATCHES_PER_THREAD);
+        let (result_tx, results) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let parser = Arc::new(parser);
+        let workers = (0..threads)
+            .map(|_| {
+                let job_rx = job_rx.clone();
+                let result_tx = result_tx.clone();
+                let parser = parser.clone();
+                thread::spawn(move || loop {
+                    // Holding the lock only while waiting lets the others parse
+                    let job = job_rx.lock().map(|rx| rx.recv());
+                    let Ok(Ok((seq, lines))) = job else {
+                        break;
+                    };
+                    let parsed = lines.iter().map(|line| parser.parse(line)).collect();
+                    if result_tx.send((seq, parsed)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        ParsePool {
+            jobs: Some(jobs),
+            results,
+            workers,
+            submitted: 0,
+            released: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Queues a batch of lines, waiting if the workers are far behind
+    pub fn submit(&mut self, lines: Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+        if let Some(jobs) = &self.jobs {
+            if jobs.send((self.submitted, lines)).is_ok() {
+                self.submitted += 1;
+            }
+        }
+    }
+
+    /// Batches submitted but not yet handed out
+    pub fn in_flight(&self) -> u64 {
+        self.submitted - self.released
+    }
+
+    /// Results of the batches finished so far, in submission order
+    ///
+    /// Does not wait; a finished batch is held back while an earlier one
+    /// is still being parsed.
+    pub fn ready(&mut self) -> Vec<Result<SensorData>> {
+        while let Ok((seq, parsed)) = self.results.try_recv() {
+            self.pending.insert(seq, parsed);
+        }
+        self.release()
+    }
+
+    /// Waits for every submitted batch and stops the workers
+    pub fn finish(mut self) -> Vec<Result<SensorData>> {
+        self.jobs = None;
+        while self.pending.len() as u64 != self.in_flight() {
+            match self.results.recv() {
+                Ok((seq, parsed)) => {
+                    self.pending.insert(seq, parsed);
+                }
+                Err(_) => break,
+            }
+        }
+        let parsed = self.release();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        parsed
+    }
+
+    // Hands out the finished batches that directly follow the last one released
+    fn release(&mut self) -> Vec<Result<SensorData>> {
+        let mut parsed = Vec::new();
+        while let Some(batch) = self.pending.remove(&self.released) {
+            parsed.extend(batch);
+            self.released += 1;
+        }
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(counter: u32) -> String {
+        format!(
+            "{:08X},41C80000,00000000,00000000,00000000,00000000,00000000,3F800000",
+            counter
+        )
+    }
+
+    #[test]
+    fn test_parallel_parse_is_complete_and_ordered() {
+        let mut pool = ParsePool::new(4, LineParser::Strict);
+        let mut results = Vec::new();
+        let mut counter = 0;
+        for batch in 0..200 {
+            // Batches of varying size so workers finish out of order
+            let mut lines: Vec<String> = (0..1 + batch % 7)
+                .map(|_| {
+                    counter += 1;
+                    line(counter)
+                })
+                .collect();
+            if batch % 50 == 0 {
+                lines.push("garbage".to_string());
+            }
+            pool.submit(lines);
+            results.extend(pool.ready());
+        }
+        results.extend(pool.finish());
+
+        let errors = results.iter().filter(|r| r.is_err()).count();
+        assert_eq!(errors, 4);
+        let counters: Vec<u32> = results
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .map(|data| data.timestamp)
+            .collect();
+        assert_eq!(counters, (1..=counter).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use plotters::prelude::*;
+use std::path::Path;
+
+use super::types::SensorData;
+
+/// Default number of buckets kept by a preview series
+pub const DEFAULT_PREVIEW_BUCKETS: usize = 2048;
+
+/// Default size of the preview image in pixels
+pub const DEFAULT_PREVIEW_SIZE: (u32, u32) = (1200, 400);
+
+// Range of accel magnitude over a run of consecutive records
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bucket {
+    start_ms: i64,
+    end_ms: i64,
+    min: f32,
+    max: f32,
+}
+
+impl Bucket {
+    fn merge(self, other: Bucket) -> Bucket {
+        Bucket {
+            start_ms: self.start_ms,
+            end_ms: other.end_ms,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+/// Downsampled accel magnitude of a capture, for a preview image
+///
+/// Each bucket holds the minimum and maximum magnitude of a run of records,
+/// so short spikes stay visible however long the capture is. Once all
+/// buckets are used, neighbouring buckets are merged in pairs and each new
+/// bucket covers twice as many records; memory stays bounded by the bucket
+/// count.
+#[derive(Debug, Clone)]
+pub struct PreviewSeries {
+    capacity: usize,
+    per_bucket: usize,
+    buckets: Vec<Bucket>,
+    // Records in the last bucket
+    filled: usize,
+}
+
+impl PreviewSeries {
+    /// Creates a series keeping at most `capacity` buckets (at least 2)
+    pub fn new(capacity: usize) -> Self {
+        PreviewSeries {
+            capacity: capacity.max(2),
+            per_bucket: 1,
+            buckets: Vec::new(),
+            filled: 0,
+        }
+    }
+
+    /// Number of buckets currently held
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Returns whether no record was added
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Adds a record
+    pub fn push(&mut self, data: &SensorData) {
+        let magnitude = (data.ax * data.ax + data.ay * data.ay + data.az * data.az).sqrt();
+        if !magnitude.is_finite() {
+            return;
+        }
+        let sample = Bucket {
+            start_ms: data.system_timestamp,
+            end_ms: data.system_timestamp,
+            min: magnitude,
+            max: magnitude,
+        };
+        match self.buckets.last_mut() {
+            Some(last) if self.filled < self.per_bucket => {
+                *last = last.merge(sample);
+                self.filled += 1;
+            }
+            _ => {
+                if self.buckets.len() == self.capacity {
+                    self.halve();
+                }
+                self.buckets.push(sample);
+                self.filled = 1;
+            }
+        }
+    }
+
+    // Merges neighbouring buckets, doubling the records per bucket
+    fn halve(&mut self) {
+        self.buckets = self
+            .buckets
+            .chunks(2)
+            .map(|pair| pair.iter().copied().reduce(Bucket::merge).unwrap())
+            .collect();
+        self.per_bucket *= 2;
+    }
+
+    /// Plots the series as a PNG image
+    ///
+    /// The x axis is seconds since the first record, the y axis the accel
+    /// magnitude; each bucket is drawn as a vertical bar from its minimum to
+    /// its maximum.
+    pub fn write_png(&self, path: &Path, (width, height): (u32, u32)) -> Result<()> {
+        let failed = |e: &dyn std::fmt::Display| {
+            anyhow::anyhow!("Failed to draw preview {}: {}", path.display(), e)
+        };
+        let origin = self.buckets.first().map_or(0, |bucket| bucket.start_ms);
+        let seconds = |ms: i64| (ms - origin) as f64 / 1e3;
+        let x_end = self
+            .buckets
+            .last()
+            .map_or(1.0, |bucket| seconds(bucket.end_ms).max(1e-3));
+        let (y_min, y_max) = self
+            .buckets
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), bucket| {
+                (lo.min(bucket.min), hi.max(bucket.max))
+            });
+        let (y_min, y_max) = if y_min <= y_max {
+            let margin = ((y_max - y_min) * 0.05).max(1e-3);
+            ((y_min - margin) as f64, (y_max + margin) as f64)
+        } else {
+            (0.0, 1.0)
+        };
+
+        let root = BitMapBackend::new(path, (width, height)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| failed(&e))?;
+        let mut chart = ChartBuilder::on(&root)
+            .margin(10)
+            .build_cartesian_2d(0.0..x_end, y_min..y_max)
+            .map_err(|e| failed(&e))?;
+        chart
+            .draw_series(self.buckets.iter().map(|bucket| {
+                let x = (seconds(bucket.start_ms) + seconds(bucket.end_ms)) / 2.0;
+                PathElement::new(
+                    vec![(x, bucket.min as f64), (x, bucket.max as f64)],
+                    BLUE.stroke_width(1),
+                )
+            }))
+            .map_err(|e| failed(&e))?;
+        chart
+            .draw_series(LineSeries::new(
+                self.buckets.iter().map(|bucket| {
+                    let x = (seconds(bucket.start_ms) + seconds(bucket.end_ms)) / 2.0;
+                    (x, ((bucket.min + bucket.max) / 2.0) as f64)
+                }),
+                &BLUE,
+            ))
+            .map_err(|e| failed(&e))?;
+        root.present()
+            .with_context(|| format!("Failed to write preview {}", path.display()))?;
+        Ok(())
+    }
+}
+
+impl Default for PreviewSeries {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREVIEW_BUCKETS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(system_timestamp: i64, az: f32) -> SensorData {
+        SensorData {
+            timestamp: system_timestamp as u32,
+            temp: 25.0,
+            az,
+            system_timestamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_long_capture_stays_bounded_and_keeps_spikes() {
+        let mut series = PreviewSeries::new(16);
+        for i in 0..10_000 {
+            let az = if i == 7_777 { 9.0 } else { 1.0 };
+            series.push(&record(i, az));
+        }
+        assert!(series.len() <= 16);
+        assert!(series.len() > 8);
+        let peak = series.buckets.iter().map(|b| b.max).fold(0.0, f32::max);
+        assert_eq!(peak, 9.0);
+        assert_eq!(series.buckets[0].start_ms, 0);
+        assert_eq!(series.buckets.last().unwrap().end_ms, 9_999);
+    }
+}
@@ -0,0 +1,129 @@
+use super::error::ReceiverError;
+use super::types::SensorData;
+
+/// What to do with a record outside the configured sensor ranges
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RangePolicy {
+    /// Keep the record and mark it in a boolean `valid` column
+    #[default]
+    Tag,
+    /// Discard the record as a parse error
+    Reject,
+}
+
+impl std::str::FromStr for RangePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tag" => Ok(RangePolicy::Tag),
+            "reject" => Ok(RangePolicy::Reject),
+            _ => Err(format!("Unknown range policy: {}", s)),
+        }
+    }
+}
+
+/// Plausibility check of decoded sensor values
+///
+/// A value that is NaN, infinite or beyond the full-scale range of the IMU
+/// cannot be a real measurement and usually means the line or frame was
+/// misaligned. Accelerations are compared in g and angular rates, which the
+/// firmware sends in rad/s, against a limit given in degrees per second.
+/// Fields that failed to decode (see [`SensorData::null_fields`]) are
+/// already marked and not checked again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeCheck {
+    max_g: f32,
+    max_rad_s: f32,
+    policy: RangePolicy,
+}
+
+impl RangeCheck {
+    /// Creates a check
+    ///
+    /// # Arguments
+    /// * `max_g` - Largest accepted acceleration magnitude per axis, in g
+    /// * `max_dps` - Largest accepted angular rate per axis, in degrees per second
+    /// * `policy` - Whether failing records are tagged or rejected
+    pub fn new(max_g: f32, max_dps: f32, policy: RangePolicy) -> Self {
+        RangeCheck {
+            max_g,
+            max_rad_s: max_dps.to_radians(),
+            policy,
+        }
+    }
+
+    /// Parses `MAX_G,MAX_DPS` (e.g. `16,4000`)
+    pub fn parse(spec: &str, policy: RangePolicy) -> anyhow::Result<Self> {
+        let limits = spec.split_once(',').and_then(|(g, dps)| {
+            let g: f32 = g.trim().parse().ok()?;
+            let dps: f32 = dps.trim().parse().ok()?;
+            (g > 0.0 && dps > 0.0).then_some((g, dps))
+        });
+        let Some((max_g, max_dps)) = limits else {
+            return Err(ReceiverError::ConfigError(format!(
+                "Invalid range '{}': expected MAX_G,MAX_DPS with positive limits",
+                spec
+            ))
+            .into());
+        };
+        Ok(Self::new(max_g, max_dps, policy))
+    }
+
+    /// What happens to records failing the check
+    pub fn policy(&self) -> RangePolicy {
+        self.policy
+    }
+
+    /// Returns whether every decoded value of a record is finite and in range
+    pub fn is_valid(&self, data: &SensorData) -> bool {
+        let values = [
+            (0, data.temp, f32::INFINITY),
+            (1, data.gx, self.max_rad_s),
+            (2, data.gy, self.max_rad_s),
+            (3, data.gz, self.max_rad_s),
+            (4, data.ax, self.max_g),
+            (5, data.ay, self.max_g),
+            (6, data.az, self.max_g),
+        ];
+        values
+            .iter()
+            .filter(|(index, _, _)| !data.is_null(*index))
+            .all(|&(_, value, limit)| value.is_finite() && value.abs() <= limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(gx: f32, az: f32) -> SensorData {
+        SensorData {
+            temp: 25.0,
+            gx,
+            az,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_nan_infinite_and_out_of_range_are_invalid() {
+        let check = RangeCheck::parse("16,2000", RangePolicy::Tag).unwrap();
+        assert!(check.is_valid(&record(0.5, 1.0)));
+        // 2000 dps is about 34.9 rad/s
+        assert!(check.is_valid(&record(34.0, -16.0)));
+
+        assert!(!check.is_valid(&record(f32::NAN, 1.0)));
+        assert!(!check.is_valid(&record(0.0, f32::INFINITY)));
+        assert!(!check.is_valid(&record(0.0, 1.0e6)));
+        assert!(!check.is_valid(&record(36.0, 1.0)));
+
+        // A field that failed to decode is already flagged as null
+        let mut lenient = record(f32::NAN, 1.0);
+        lenient.null_fields = 1 << 1;
+        assert!(check.is_valid(&lenient));
+
+        assert!(RangeCheck::parse("16", RangePolicy::Tag).is_err());
+        assert!(RangeCheck::parse("-1,2000", RangePolicy::Tag).is_err());
+    }
+}
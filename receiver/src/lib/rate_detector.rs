@@ -0,0 +1,202 @@
+/// Relative deviation from the baseline interval treated as a rate change
+const DEFAULT_TOLERANCE: f64 = 0.2;
+
+/// Number of consecutive deviating intervals required to confirm a change
+const DEFAULT_CONFIRM_SAMPLES: usize = 32;
+
+/// Result of feeding one timestamp into the rate-change detector
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateObservation {
+    /// The interval matches the established rate (or no rate is established yet)
+    Steady,
+    /// The baseline rate was established for the first time
+    Established(f64),
+    /// The interval deviates from the baseline; a change may be under way
+    Candidate,
+    /// The deviation persisted long enough; carries the new interval in counts
+    Changed(f64),
+    /// A candidate change fell back to the baseline rate
+    Reverted,
+}
+
+/// Detects sustained changes of the inter-sample interval
+///
+/// The detector works on the sensor counter extended across rollovers, so
+/// a wrap of the counter is an ordinary interval. A change is only confirmed
+/// after a run of consecutive intervals that all deviate from the baseline and
+/// agree with each other, so isolated dropped samples or jitter do not cause
+/// spurious rotations.
+#[derive(Debug)]
+pub struct RateChangeDetector {
+    tolerance: f64,
+    confirm_samples: usize,
+    last_timestamp: Option<i64>,
+    baseline: Option<f64>,
+    // Intervals collected while establishing a baseline or confirming a change
+    window: Vec<f64>,
+}
+
+impl RateChangeDetector {
+    /// Creates a detector with the default tolerance and confirmation length
+    pub fn new() -> Self {
+        Self::with_params(DEFAULT_TOLERANCE, DEFAULT_CONFIRM_SAMPLES)
+    }
+
+    /// Creates a detector with custom hysteresis parameters
+    ///
+    /// # Arguments
+    /// * `tolerance` - Relative interval deviation considered a change (e.g. 0.2 = 20%)
+    /// * `confirm_samples` - Consecutive deviating intervals needed to confirm a change
+    ///
+    /// # Returns
+    /// A new RateChangeDetector instance
+    pub fn with_params(tolerance: f64, confirm_samples: usize) -> Self {
+        RateChangeDetector {
+            tolerance,
+            confirm_samples: confirm_samples.max(1),
+            last_timestamp: None,
+            baseline: None,
+            window: Vec::with_capacity(confirm_samples),
+        }
+    }
+
+    /// Current baseline interval in counter units, if established
+    pub fn interval(&self) -> Option<f64> {
+        self.baseline
+    }
+
+    /// Feeds the next sensor timestamp into the detector
+    ///
+    /// # Arguments
+    /// * `timestamp` - Sensor counter of the record, extended across
+    ///   rollovers (see [`TickExtender`](super::monotonic::TickExtender))
+    ///
+    /// # Returns
+    /// How this record relates to the currently established rate
+    pub fn observe(&mut self, timestamp: i64) -> RateObservation {
+        let Some(last) = self.last_timestamp.replace(timestamp) else {
+            return RateObservation::Steady;
+        };
+        let interval = (timestamp - last) as f64;
+
+        let Some(baseline) = self.baseline else {
+            return self.establish(interval);
+        };
+
+        if self.within(interval, baseline) {
+            if self.window.is_empty() {
+                return RateObservation::Steady;
+            }
+            self.window.clear();
+            return RateObservation::Reverted;
+        }
+
+        // A deviating interval must also agree with the candidate so far
+        if let Some(candidate) = self.window_mean() {
+            if !self.within(interval, candidate) {
+                self.window.clear();
+            }
+        }
+        self.window.push(interval);
+
+        if self.window.len() < self.confirm_samples {
+            return RateObservation::Candidate;
+        }
+
+        let new_interval = self.window_mean().unwrap_or(interval);
+        self.baseline = Some(new_interval);
+        self.window.clear();
+        RateObservation::Changed(new_interval)
+    }
+
+    // Collect the first intervals into a baseline
+    fn establish(&mut self, interval: f64) -> RateObservation {
+        if let Some(candidate) = self.window_mean() {
+            if !self.within(interval, candidate) {
+                self.window.clear();
+            }
+        }
+        self.window.push(interval);
+
+        if self.window.len() < self.confirm_samples {
+            return RateObservation::Steady;
+        }
+
+        let baseline = self.window_mean().unwrap_or(interval);
+        self.baseline = Some(baseline);
+        self.window.clear();
+        RateObservation::Established(baseline)
+    }
+
+    fn window_mean(&self) -> Option<f64> {
+        if self.window.is_empty() {
+            None
+        } else {
+            Some(self.window.iter().sum::<f64>() / self.window.len() as f64)
+        }
+    }
+
+    fn within(&self, interval: f64, reference: f64) -> bool {
+        (interval - reference).abs() <= reference.abs() * self.tolerance
+    }
+}
+
+impl Default for RateChangeDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CounterWidth, TickExtender};
+
+    #[test]
+    fn test_detects_sustained_change() {
+        let mut detector = RateChangeDetector::with_params(0.2, 8);
+        let mut observations = Vec::new();
+
+        // 100 samples every 10 counts, then 100 samples every 1 count
+        let timestamps = (0..100).map(|i| i * 10).chain((1..=100).map(|i| 990 + i));
+        for ts in timestamps {
+            observations.push(detector.observe(ts));
+        }
+
+        assert!(observations.contains(&RateObservation::Established(10.0)));
+        let changes: Vec<_> = observations
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| matches!(o, RateObservation::Changed(_)))
+            .collect();
+        assert_eq!(changes.len(), 1, "Exactly one change should be detected");
+        // The faster rate starts at index 100 and needs 8 intervals to confirm
+        assert_eq!(changes[0].0, 107);
+        assert_eq!(detector.interval(), Some(1.0));
+    }
+
+    #[test]
+    fn test_ignores_isolated_gap() {
+        let mut detector = RateChangeDetector::with_params(0.2, 8);
+        let mut ts = 0i64;
+        for i in 0..100 {
+            // A single dropped sample doubles one interval
+            ts += if i == 50 { 20 } else { 10 };
+            let observation = detector.observe(ts);
+            assert!(!matches!(observation, RateObservation::Changed(_)));
+        }
+        assert_eq!(detector.interval(), Some(10.0));
+    }
+
+    #[test]
+    fn test_24_bit_rollover_is_not_a_rate_change() {
+        let mut detector = RateChangeDetector::with_params(0.2, 8);
+        let mut ticks = TickExtender::new().with_width(CounterWidth::Bits24);
+        for i in 0..100u32 {
+            let counter = (0xFF_FE00 + i * 10) & 0xFF_FFFF;
+            let observation = detector.observe(ticks.extend(counter));
+            assert!(!matches!(observation, RateObservation::Changed(_)));
+        }
+        assert_eq!(detector.interval(), Some(10.0));
+    }
+}
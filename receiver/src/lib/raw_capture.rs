@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Returns whether a path names a zstd-compressed file (`.zst` extension)
+pub fn is_zstd_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst")
+}
+
+enum CaptureSink {
+    Plain(BufWriter<File>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+/// Log of the raw bytes received from the serial port
+///
+/// The bytes are stored exactly as read, so the file can be replayed with
+/// `--input` to reproduce a capture. Paths ending in `.zst` are compressed
+/// with zstd as the bytes are written.
+pub struct RawCapture {
+    path: PathBuf,
+    sink: CaptureSink,
+}
+
+impl RawCapture {
+    /// Creates (or truncates) the capture file
+    ///
+    /// # Arguments
+    /// * `path` - Capture file; a `.zst` extension selects zstd compression
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create raw capture: {}", path.display()))?;
+        let file = BufWriter::new(file);
+        let sink = if is_zstd_path(&path) {
+            CaptureSink::Zstd(zstd::Encoder::new(file, 0)?)
+        } else {
+            CaptureSink::Plain(file)
+        };
+        Ok(RawCapture { path, sink })
+    }
+
+    /// Path of the capture file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends received bytes to the capture
+    pub fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        match &mut self.sink {
+            CaptureSink::Plain(file) => file.write_all(bytes),
+            CaptureSink::Zstd(encoder) => encoder.write_all(bytes),
+        }
+        .with_context(|| format!("Failed to write raw capture: {}", self.path.display()))
+    }
+
+    /// Flushes the capture and completes the zstd frame, if compressing
+    pub fn finish(self) -> Result<()> {
+        let mut file = match self.sink {
+            CaptureSink::Plain(file) => file,
+            CaptureSink::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()
+            .with_context(|| format!("Failed to write raw capture: {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_sensor_data, ReplayReader};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_zstd_capture_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("capture.zst");
+        let lines: Vec<String> = (0..200)
+            .map(|i| format!("{:08X},41C80000,3F800000,{:08X},0,0,0,3F800000", i, i * 3))
+            .collect();
+        let bytes = lines.join("\n").into_bytes();
+
+        // Write in uneven chunks, as serial reads split lines arbitrarily
+        let mut capture = RawCapture::create(&path).unwrap();
+        for chunk in bytes.chunks(37) {
+            capture.write(chunk).unwrap();
+        }
+        capture.finish().unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < bytes.len() as u64);
+
+        let mut replayed = Vec::new();
+        ReplayReader::new(&path)
+            .replay_loop(Arc::new(AtomicBool::new(true)), |data| {
+                replayed.push(data);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(replayed.len(), lines.len());
+        for (data, line) in replayed.iter().zip(&lines) {
+            let expected = parse_sensor_data(line).unwrap();
+            assert_eq!(data.timestamp, expected.timestamp);
+            assert_eq!(
+                (data.temp, data.gx, data.gy, data.az),
+                (expected.temp, expected.gx, expected.gy, expected.az)
+            );
+        }
+    }
+}
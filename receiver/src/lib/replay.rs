@@ -0,0 +1,553 @@
+use anyhow::{Context, Result};
+use arrow::array::{
+    Array, Float32Array, Int64Array, TimestampMillisecondArray, UInt32Array, UInt8Array,
+};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::compact::{CompactBinaryReader, COMPACT_EXTENSION};
+use super::error::ReceiverError;
+use super::monotonic::{CounterWidth, TickExtender};
+use super::raw_capture::is_zstd_path;
+use super::serial::{detect_data_format, parse_sensor_data, BinaryFrameBuffer};
+use super::types::{DataFormat, SensorData};
+
+/// Clock that a replay window is expressed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplayClock {
+    /// Sensor counter (`timestamp`)
+    #[default]
+    Counter,
+    /// Host wall clock in milliseconds (`system_timestamp`)
+    System,
+}
+
+impl std::str::FromStr for ReplayClock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "timestamp" | "counter" => Ok(ReplayClock::Counter),
+            "system_timestamp" | "system" => Ok(ReplayClock::System),
+            _ => Err(format!("Unknown replay clock: {}", s)),
+        }
+    }
+}
+
+/// How many times a replay runs through its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayLoops {
+    /// Fixed number of passes
+    Times(u64),
+    /// Repeats until stopped
+    Infinite,
+}
+
+impl Default for ReplayLoops {
+    fn default() -> Self {
+        ReplayLoops::Times(1)
+    }
+}
+
+impl ReplayLoops {
+    // Whether the pass with this zero-based index runs
+    fn includes(self, pass: u64) -> bool {
+        match self {
+            ReplayLoops::Times(times) => pass < times,
+            ReplayLoops::Infinite => true,
+        }
+    }
+}
+
+impl std::str::FromStr for ReplayLoops {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "infinite" | "inf" | "forever" => Ok(ReplayLoops::Infinite),
+            n => match n.parse::<u64>() {
+                Ok(times) if times > 0 => Ok(ReplayLoops::Times(times)),
+                _ => Err(format!(
+                    "Invalid replay loop count: {} (expected a positive number or 'infinite')",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Inclusive time range of records to replay
+#[derive(Debug, Clone, Default)]
+pub struct ReplayWindow {
+    /// Clock the bounds refer to
+    pub clock: ReplayClock,
+    /// First time to replay (unbounded if None)
+    pub start: Option<i64>,
+    /// Last time to replay (unbounded if None)
+    pub end: Option<i64>,
+}
+
+impl ReplayWindow {
+    /// Returns whether a record falls inside the window
+    pub fn contains(&self, data: &SensorData) -> bool {
+        let time = match self.clock {
+            ReplayClock::Counter => data.timestamp as i64,
+            ReplayClock::System => data.system_timestamp,
+        };
+        self.start.is_none_or(|start| time >= start) && self.end.is_none_or(|end| time <= end)
+    }
+}
+
+/// Reader replaying previously captured data instead of a live serial port
+///
+/// Parquet files written by this logger (flat layout) are read back with their
+/// original host timestamps. Any other file is treated as a capture of the
+/// serial stream (text lines, or binary frames if so configured), parsed
+/// exactly like live input; captures ending in `.zst` are decompressed on the
+/// fly.
+pub struct ReplayReader {
+    path: PathBuf,
+    window: ReplayWindow,
+    data_format: DataFormat,
+    loops: ReplayLoops,
+    offset_loops: bool,
+    counter_width: CounterWidth,
+}
+
+impl ReplayReader {
+    /// Creates a reader for a captured file
+    ///
+    /// # Arguments
+    /// * `path` - Parquet file or text capture to replay
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ReplayReader {
+            path: path.into(),
+            window: ReplayWindow::default(),
+            data_format: DataFormat::Text,
+            loops: ReplayLoops::default(),
+            offset_loops: false,
+            counter_width: CounterWidth::default(),
+        }
+    }
+
+    /// Sets the encoding of a serial capture (text lines by default)
+    pub fn with_data_format(mut self, format: DataFormat) -> Self {
+        self.data_format = format;
+        self
+    }
+
+    // Format of a text or binary capture, sniffing its head under `Auto`
+    fn data_format(&self) -> DataFormat {
+        if self.data_format != DataFormat::Auto {
+            return self.data_format;
+        }
+        let mut head = Vec::new();
+        if let Ok(capture) = open_capture(&self.path) {
+            let _ = capture.take(64 * 1024).read_to_end(&mut head);
+        }
+        detect_data_format(&head).unwrap_or(DataFormat::Text)
+    }
+
+    /// Sets the width of the sensor counter, for extending it across rollovers
+    pub fn with_counter_width(mut self, width: CounterWidth) -> Self {
+        self.counter_width = width;
+        self
+    }
+
+    /// Only replays records inside the given time window
+    pub fn with_window(mut self, window: ReplayWindow) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Runs through the file repeatedly (once by default)
+    ///
+    /// # Arguments
+    /// * `loops` - Number of passes
+    /// * `offset` - Shift the timestamps of every pass past those of the
+    ///   previous one, so that they keep increasing across passes
+    pub fn with_loops(mut self, loops: ReplayLoops, offset: bool) -> Self {
+        self.loops = loops;
+        self.offset_loops = offset;
+        self
+    }
+
+    /// Replays the file, handing each record in the window to the callback
+    ///
+    /// Records of later passes are shifted by the time spanned by the
+    /// records of the earlier ones, plus one sample interval, if offsetting
+    /// is enabled. A pass without any record in the window ends the replay.
+    ///
+    /// # Arguments
+    /// * `running` - Atomic flag; replay stops early when it is cleared
+    /// * `data_callback` - Receives every replayed record
+    ///
+    /// # Returns
+    /// The number of records handed to the callback
+    pub fn replay_loop<F>(self, running: Arc<AtomicBool>, mut data_callback: F) -> Result<usize>
+    where
+        F: FnMut(SensorData) -> Result<()>,
+    {
+        println!("Replaying {}", self.path.display());
+        let mut emitted = 0;
+        // Shift applied to the counter, wall clock and monotonic clock
+        let mut shift = [0i64; 3];
+        let mut pass = 0;
+        let mut ticks = TickExtender::new().with_width(self.counter_width);
+        while self.loops.includes(pass) && running.load(Ordering::SeqCst) {
+            let mut spans = [ClockSpan::default(); 3];
+            let mut emit = |mut data: SensorData| -> Result<bool> {
+                if !running.load(Ordering::SeqCst) {
+                    return Ok(false);
+                }
+                if self.window.contains(&data) {
+                    let clocks = [
+                        data.timestamp as i64,
+                        data.system_timestamp,
+                        data.monotonic_timestamp,
+                    ];
+                    for (span, time) in spans.iter_mut().zip(clocks) {
+                        span.observe(time);
+                    }
+                    if self.offset_loops {
+                        let counter = data.timestamp as i64 + shift[0];
+                        data.timestamp = counter.rem_euclid(self.counter_width.modulus()) as u32;
+                        data.system_timestamp += shift[1];
+                        data.monotonic_timestamp += shift[2];
+                    }
+                    data_callback(ticks.stamp(data))?;
+                    emitted += 1;
+                }
+                Ok(true)
+            };
+            let completed = self.replay_pass(&mut emit)?;
+            if !completed || spans[0].is_empty() {
+                break;
+            }
+            for (shift, span) in shift.iter_mut().zip(spans) {
+                *shift += span.length();
+            }
+            pass += 1;
+        }
+
+        if pass > 1 {
+            println!("Replay finished: {} records in {} passes", emitted, pass);
+        } else {
+            println!("Replay finished: {} records", emitted);
+        }
+        Ok(emitted)
+    }
+
+    // Runs once through the file, returning false if `emit` asked to stop
+    fn replay_pass(&self, emit: &mut dyn FnMut(SensorData) -> Result<bool>) -> Result<bool> {
+        if self.path.extension().is_some_and(|ext| ext == "parquet") {
+            let file = File::open(&self.path)
+                .with_context(|| format!("Failed to open file: {}", self.path.display()))?;
+            let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .with_context(|| format!("Failed to read {}", self.path.display()))?
+                .build()?;
+            for batch in reader {
+                for data in records_from_batch(&batch?)? {
+                    if !emit(data)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        } else if self
+            .path
+            .extension()
+            .is_some_and(|ext| ext == COMPACT_EXTENSION)
+        {
+            for data in CompactBinaryReader::open(&self.path)? {
+                if !emit(data?)? {
+                    return Ok(false);
+                }
+            }
+        } else if self.data_format() == DataFormat::Binary {
+            // Frames are cut from chunks of the size read from a live port
+            let mut capture = open_capture(&self.path)?;
+            let mut frames = BinaryFrameBuffer::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = capture
+                    .read(&mut buf)
+                    .with_context(|| format!("Failed to read {}", self.path.display()))?;
+                if n == 0 {
+                    break;
+                }
+                for data in frames.push(&buf[..n]) {
+                    if !emit(data)? {
+                        return Ok(false);
+                    }
+                }
+            }
+        } else {
+            for line in open_capture(&self.path)?.lines() {
+                let line =
+                    line.with_context(|| format!("Failed to read {}", self.path.display()))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_sensor_data(&line) {
+                    Ok(data) => {
+                        if !emit(data)? {
+                            return Ok(false);
+                        }
+                    }
+                    Err(e) => eprintln!("Error parsing sensor data: {}", e),
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Range of one clock over a replay pass
+#[derive(Debug, Clone, Copy, Default)]
+struct ClockSpan {
+    // First, second to last and last time seen
+    times: Option<(i64, i64, i64)>,
+}
+
+impl ClockSpan {
+    fn observe(&mut self, time: i64) {
+        self.times = Some(match self.times {
+            Some((first, _, last)) => (first, last, time),
+            None => (time, time, time),
+        });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.times.is_none()
+    }
+
+    // Time from the first record to one interval past the last
+    fn length(&self) -> i64 {
+        match self.times {
+            Some((first, previous, last)) => last - first + (last - previous),
+            None => 0,
+        }
+    }
+}
+
+// Opens a serial capture for reading, decompressing `.zst` files
+fn open_capture(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    if is_zstd_path(path) {
+        let decoder = zstd::Decoder::new(file)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(Box::new(BufReader::new(decoder)));
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Converts a record batch of the flat file layout back into sensor records
+///
+/// # Arguments
+/// * `batch` - Batch with Int64 `timestamp`, Timestamp or Int64 `system_timestamp`
+///   and Float32 sensor columns
+///
+/// # Returns
+/// One record per row; a missing `monotonic_timestamp` column reads as zero
+/// and missing `status` and `burst_id` columns as no status and no burst
+pub fn records_from_batch(batch: &RecordBatch) -> Result<Vec<SensorData>> {
+    let ints = |name: &str| -> Result<&Int64Array> {
+        batch
+            .column_by_name(name)
+            .and_then(|c| c.as_any().downcast_ref::<Int64Array>())
+            .ok_or_else(|| {
+                ReceiverError::ParseError(format!("missing Int64 column '{}'", name)).into()
+            })
+    };
+    let floats = |name: &str| -> Result<&Float32Array> {
+        batch
+            .column_by_name(name)
+            .and_then(|c| c.as_any().downcast_ref::<Float32Array>())
+            .ok_or_else(|| {
+                ReceiverError::ParseError(format!("missing Float32 column '{}'", name)).into()
+            })
+    };
+
+    // Timestamp-typed since the wall clock became a timestamp column; older
+    // files store it as Int64
+    let system_column = batch.column_by_name("system_timestamp");
+    let system_millis = system_column
+        .and_then(|c| c.as_any().downcast_ref::<TimestampMillisecondArray>())
+        .map(|c| c.values().clone());
+    let system_timestamp = match system_millis {
+        Some(values) => values,
+        None => ints("system_timestamp")?.values().clone(),
+    };
+
+    let timestamp = ints("timestamp")?;
+    let monotonic_timestamp = ints("monotonic_timestamp").ok();
+    let status = batch
+        .column_by_name("status")
+        .and_then(|c| c.as_any().downcast_ref::<UInt8Array>());
+    let burst_id = batch
+        .column_by_name("burst_id")
+        .and_then(|c| c.as_any().downcast_ref::<UInt32Array>());
+    let [temp, gx, gy, gz, ax, ay, az] = ["temp", "gx", "gy", "gz", "ax", "ay", "az"].map(floats);
+    let (temp, gx, gy, gz, ax, ay, az) = (temp?, gx?, gy?, gz?, ax?, ay?, az?);
+
+    Ok((0..batch.num_rows())
+        .map(|i| SensorData {
+            timestamp: timestamp.value(i) as u32,
+            temp: temp.value(i),
+            gx: gx.value(i),
+            gy: gy.value(i),
+            gz: gz.value(i),
+            ax: ax.value(i),
+            ay: ay.value(i),
+            az: az.value(i),
+            system_timestamp: system_timestamp[i],
+            monotonic_timestamp: monotonic_timestamp.map_or(0, |m| m.value(i)),
+            status: status.and_then(|s| s.is_valid(i).then(|| s.value(i))),
+            burst_id: burst_id.and_then(|b| b.is_valid(i).then(|| b.value(i))),
+            null_fields: [temp, gx, gy, gz, ax, ay, az]
+                .iter()
+                .enumerate()
+                .filter(|(_, column)| column.is_null(i))
+                .fold(0, |mask, (index, _)| mask | 1 << index),
+            ..Default::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionType, ParquetWriter};
+    use tempfile::tempdir;
+
+    fn replay(reader: ReplayReader) -> Vec<SensorData> {
+        let mut records = Vec::new();
+        reader
+            .replay_loop(Arc::new(AtomicBool::new(true)), |data| {
+                records.push(data);
+                Ok(())
+            })
+            .unwrap();
+        records
+    }
+
+    #[test]
+    fn test_replay_text_window() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("capture.txt");
+        let lines: Vec<String> = (0..10)
+            .map(|i| format!("{:08X},41C80000,0,0,0,0,0,3F800000", i * 100))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let window = ReplayWindow {
+            clock: ReplayClock::Counter,
+            start: Some(300),
+            end: Some(600),
+        };
+        let records = replay(ReplayReader::new(&path).with_window(window));
+        let timestamps: Vec<u32> = records.iter().map(|d| d.timestamp).collect();
+        assert_eq!(timestamps, vec![300, 400, 500, 600]);
+    }
+
+    #[test]
+    fn test_replay_compressed_binary_frames() {
+        use crate::encode_binary_frame;
+        use std::io::Write;
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("frames.bin.zst");
+        let records: Vec<SensorData> = (0..300)
+            .map(|i| SensorData {
+                timestamp: i * 10,
+                temp: 25.0,
+                gx: i as f32,
+                az: 1.0,
+                ..Default::default()
+            })
+            .collect();
+
+        // Leading noise and one corrupt frame must not disturb the others;
+        // 300 frames span several 4 KiB reads, so frames straddle chunks
+        let mut bytes = vec![0x00, 0xAA, 0x13];
+        for (i, data) in records.iter().enumerate() {
+            let mut frame = encode_binary_frame(data);
+            if i == 100 {
+                frame[10] ^= 0xFF;
+            }
+            bytes.extend(frame);
+        }
+        let mut encoder = zstd::Encoder::new(File::create(&path).unwrap(), 0).unwrap();
+        encoder.write_all(&bytes).unwrap();
+        encoder.finish().unwrap();
+
+        let replayed = replay(ReplayReader::new(&path).with_data_format(DataFormat::Binary));
+        let expected: Vec<u32> = (0..300).filter(|&i| i != 100).map(|i| i * 10).collect();
+        let timestamps: Vec<u32> = replayed.iter().map(|d| d.timestamp).collect();
+        assert_eq!(timestamps, expected);
+        assert!(replayed.iter().all(|d| d.gx == (d.timestamp / 10) as f32));
+    }
+
+    #[test]
+    fn test_replay_parquet_system_window() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer = ParquetWriter::new(dir_path, "replay", CompressionType::None, 4).unwrap();
+        for i in 0..10 {
+            writer
+                .add_data(SensorData {
+                    timestamp: i,
+                    temp: 25.0,
+                    az: 1.0,
+                    system_timestamp: 1_000 + i as i64 * 10,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        writer.close().unwrap();
+        let path = std::fs::read_dir(dir_path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+
+        let window = ReplayWindow {
+            clock: ReplayClock::System,
+            start: Some(1_025),
+            end: None,
+        };
+        let records = replay(ReplayReader::new(path).with_window(window));
+        let timestamps: Vec<u32> = records.iter().map(|d| d.timestamp).collect();
+        assert_eq!(timestamps, vec![3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(records[0].system_timestamp, 1_030);
+    }
+
+    #[test]
+    fn test_replay_loop_repeats_with_offset() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("capture.txt");
+        let lines: Vec<String> = (0..10)
+            .map(|i| format!("{:08X},41C80000,0,0,0,0,0,3F800000", 1_000 + i * 100))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let records = replay(ReplayReader::new(&path).with_loops(ReplayLoops::Times(3), false));
+        assert_eq!(records.len(), 30);
+        assert_eq!(records[10].timestamp, 1_000);
+
+        let records = replay(ReplayReader::new(&path).with_loops(ReplayLoops::Times(3), true));
+        assert_eq!(records.len(), 30);
+        let timestamps: Vec<u32> = records.iter().map(|d| d.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            (0..30).map(|i| 1_000 + i * 100).collect::<Vec<_>>()
+        );
+    }
+}
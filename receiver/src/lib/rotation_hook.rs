@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use super::filter::Filter;
+use super::status::{eprint_status, print_status, Health};
+use super::types::SensorData;
+
+/// Action taken on each output file once it is closed
+///
+/// With a motion filter, a file none of whose records matched the filter
+/// is idle and gets deleted instead of kept. Kept files are copied to the
+/// mirror directory, if any, and handed to the post-rotate command, if any;
+/// both happen in the background so a slow drive or upload does not hold up
+/// writing, and the command only starts once the copy is done. The hook gets
+/// the final paths the writers report on close, so a file renamed after its
+/// first record and its stats sidecar are deleted, copied and passed on
+/// together; the capture preview is mirrored at shutdown. The command is
+/// run by the shell with the file's path as its first argument (`$1`),
+/// followed by its stats sidecar and CSV companion, if written.
+#[derive(Debug, Default)]
+pub struct RotationHook {
+    motion: Option<Filter>,
+    command: Option<String>,
+    mirror: Option<Mirror>,
+    // Whether the current file has received a motion record
+    motion_in_file: bool,
+}
+
+impl RotationHook {
+    /// Creates a hook that keeps every file and runs no command
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deletes files without any record matching the motion filter
+    pub fn with_motion_filter(mut self, motion: Filter) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    /// Runs a shell command for every kept file
+    pub fn with_command(mut self, command: String) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    /// Copies every kept file into a second directory
+    ///
+    /// A copy that fails, e.g. because the mirror drive is unplugged, is
+    /// reported and retried whenever the next file is closed; files that
+    /// still could not be copied are listed at shutdown.
+    pub fn with_mirror_dir(mut self, dir: PathBuf) -> Self {
+        self.mirror = Some(Mirror::start(dir));
+        self
+    }
+
+    /// Notes a record written to the current file
+    pub fn observe(&mut self, data: &SensorData) {
+        if !self.motion_in_file {
+            self.motion_in_file = self
+                .motion
+                .as_ref()
+                .is_some_and(|motion| motion.matches(data));
+        }
+    }
+
+    /// Deletes or hands on the files just closed and starts tracking the next one
+    ///
+    /// # Arguments
+    /// * `paths` - Final paths of the closed output files and their companions
+    ///
+    /// # Returns
+    /// Whether the files were kept
+    pub fn file_closed(&mut self, paths: &[String]) -> bool {
+        let idle = self.motion.is_some() && !self.motion_in_file;
+        self.motion_in_file = false;
+        if paths.is_empty() {
+            return !idle;
+        }
+
+        if idle {
+            for path in paths {
+                match std::fs::remove_file(path) {
+                    Ok(()) => println!("Discarded idle file: {}", path),
+                    Err(e) => {
+                        let message = format!("Could not delete idle file {}: {}", path, e);
+                        eprint_status(Health::Degraded, &message);
+                    }
+                }
+            }
+            return false;
+        }
+        match &self.mirror {
+            // The mirror runs the command once the files are copied
+            Some(mirror) => mirror.send(paths.to_vec(), self.command.clone()),
+            None => {
+                if let Some(command) = &self.command {
+                    run_command(command, paths);
+                }
+            }
+        }
+        true
+    }
+
+    /// Copies files that belong to no single output file, such as the
+    /// capture preview, to the mirror directory, if any
+    pub fn mirror_files(&self, paths: &[String]) {
+        if let Some(mirror) = &self.mirror {
+            mirror.send(paths.to_vec(), None);
+        }
+    }
+
+    /// Waits until the mirror has tried to copy every file handed over so far
+    pub fn wait_for_mirror(&self) {
+        if let Some(mirror) = &self.mirror {
+            mirror.wait();
+        }
+    }
+
+    /// Waits for pending mirror copies
+    pub fn finish(self) {
+        if let Some(mirror) = self.mirror {
+            mirror.finish();
+        }
+    }
+}
+
+/// Request to the mirror thread
+#[derive(Debug)]
+enum MirrorJob {
+    /// Files to copy and the post-rotate command to run afterwards
+    Copy(Vec<String>, Option<String>),
+    /// Signal once every earlier job has been handled
+    Wait(Sender<()>),
+}
+
+/// Thread copying closed files to a mirror directory
+#[derive(Debug)]
+struct Mirror {
+    jobs: Sender<MirrorJob>,
+    worker: JoinHandle<()>,
+}
+
+impl Mirror {
+    fn start(dir: PathBuf) -> Self {
+        let (jobs, rx) = mpsc::channel();
+        let worker = thread::spawn(move || mirror_loop(dir, rx));
+        Mirror { jobs, worker }
+    }
+
+    fn send(&self, paths: Vec<String>, command: Option<String>) {
+        let _ = self.jobs.send(MirrorJob::Copy(paths, command));
+    }
+
+    fn wait(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.jobs.send(MirrorJob::Wait(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    fn finish(self) {
+        drop(self.jobs);
+        let _ = self.worker.join();
+    }
+}
+
+// Copies each job's files, retrying earlier failures first
+fn mirror_loop(dir: PathBuf, jobs: Receiver<MirrorJob>) {
+    let mut pending: Vec<String> = Vec::new();
+    let mut failing = false;
+    for job in jobs {
+        let (paths, command) = match job {
+            MirrorJob::Copy(paths, command) => (paths, command),
+            MirrorJob::Wait(done) => {
+                let _ = done.send(());
+                continue;
+            }
+        };
+        pending.extend(paths.iter().cloned());
+        let mut failed = Vec::new();
+        for path in pending.drain(..) {
+            if let Err(e) = copy_to(&dir, &path) {
+                if !failing {
+                    let message = format!(
+                        "Could not mirror {} to {}, will retry: {}",
+                        path,
+                        dir.display(),
+                        e
+                    );
+                    eprint_status(Health::Degraded, &message);
+                }
+                failed.push(path);
+            }
+        }
+        if failing && failed.is_empty() {
+            let message = format!("Mirror {} available again", dir.display());
+            print_status(Health::Healthy, &message);
+        }
+        failing = !failed.is_empty();
+        pending = failed;
+        if let Some(command) = command {
+            run_command(&command, &paths);
+        }
+    }
+    if !pending.is_empty() {
+        let message = format!(
+            "Files not mirrored to {}: {}",
+            dir.display(),
+            pending.join(", ")
+        );
+        eprint_status(Health::Failing, &message);
+    }
+}
+
+// Copies a file into the directory, via a temporary name so that the
+// mirror never holds a partial file under the final name
+fn copy_to(dir: &Path, path: &str) -> std::io::Result<()> {
+    let name = Path::new(path)
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no file name"))?;
+    std::fs::create_dir_all(dir)?;
+    let target = dir.join(name);
+    let partial = dir.join(format!(".{}.partial", name.to_string_lossy()));
+    std::fs::copy(path, &partial)?;
+    std::fs::rename(&partial, &target)
+}
+
+// Starts the post-rotate command and reports its outcome once it exits
+fn run_command(command: &str, paths: &[String]) {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).arg("receiver-post-rotate");
+        shell
+    };
+    shell.args(paths);
+
+    let file = Path::new(&paths[0])
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match shell.spawn() {
+        Ok(mut child) => {
+            thread::spawn(move || match child.wait() {
+                Ok(exit) if exit.success() => {
+                    let message = format!("Post-rotate command finished for {}", file);
+                    print_status(Health::Healthy, &message);
+                }
+                Ok(exit) => {
+                    let message = format!("Post-rotate command failed for {}: {}", file, exit);
+                    eprint_status(Health::Degraded, &message);
+                }
+                Err(e) => {
+                    let message = format!("Post-rotate command failed for {}: {}", file, e);
+                    eprint_status(Health::Degraded, &message);
+                }
+            });
+        }
+        Err(e) => {
+            let message = format!("Could not run post-rotate command: {}", e);
+            eprint_status(Health::Degraded, &message);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::path::Path;
+
+use super::parquet_writer::{ParquetWriter, WriterOptions};
+
+/// Compares a schema against the expected one and describes every difference
+///
+/// Field order is not significant; names, data types and nullability are.
+///
+/// # Arguments
+/// * `actual` - Schema found in a file
+/// * `expected` - Canonical schema
+///
+/// # Returns
+/// A human-readable description of each difference (empty if they match)
+pub fn schema_drift(actual: &Schema, expected: &Schema) -> Vec<String> {
+    let mut drift = Vec::new();
+
+    for field in expected.fields() {
+        match actual.field_with_name(field.name()) {
+            Err(_) => drift.push(format!("missing column '{}'", field.name())),
+            Ok(found) if found.data_type() != field.data_type() => drift.push(format!(
+                "column '{}' has type {}, expected {}",
+                field.name(),
+                found.data_type(),
+                field.data_type()
+            )),
+            Ok(found) if found.is_nullable() != field.is_nullable() => drift.push(format!(
+                "column '{}' is {}, expected {}",
+                field.name(),
+                nullability(found.is_nullable()),
+                nullability(field.is_nullable())
+            )),
+            Ok(_) => {}
+        }
+    }
+
+    for field in actual.fields() {
+        if expected.field_with_name(field.name()).is_err() {
+            drift.push(format!("unexpected column '{}'", field.name()));
+        }
+    }
+
+    drift
+}
+
+/// Validates the schema of a Parquet file written by this logger
+///
+/// # Arguments
+/// * `path` - Parquet file to check
+/// * `options` - Writer options the file is expected to have been written with
+///
+/// # Returns
+/// The list of schema differences (empty if the file matches)
+pub fn validate_file(path: &Path, options: &WriterOptions) -> Result<Vec<String>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| format!("Failed to read Parquet metadata from {}", path.display()))?;
+
+    let expected = ParquetWriter::build_schema(options);
+    Ok(schema_drift(builder.schema(), &expected))
+}
+
+/// Describes a schema in the JSON format of the Arrow integration tests
+///
+/// Each field becomes an object with its `name`, `nullable` flag, `type`
+/// (e.g. `{"name": "floatingpoint", "precision": "SINGLE"}`) and `children`,
+/// which generators in other languages can map onto their own Arrow types.
+pub fn schema_json(schema: &Schema) -> Value {
+    json!({
+        "fields": schema.fields().iter().map(|field| field_json(field)).collect::<Vec<_>>(),
+    })
+}
+
+fn field_json(field: &Field) -> Value {
+    let children: Vec<Value> = match field.data_type() {
+        DataType::Struct(fields) => fields.iter().map(|child| field_json(child)).collect(),
+        _ => Vec::new(),
+    };
+    json!({
+        "name": field.name(),
+        "nullable": field.is_nullable(),
+        "type": type_json(field.data_type()),
+        "children": children,
+    })
+}
+
+fn type_json(data_type: &DataType) -> Value {
+    let int = |bit_width: u8, signed: bool| {
+        json!({
+            "name": "int",
+            "bitWidth": bit_width,
+            "isSigned": signed,
+        })
+    };
+    match data_type {
+        DataType::Boolean => json!({"name": "bool"}),
+        DataType::Int8 => int(8, true),
+        DataType::Int16 => int(16, true),
+        DataType::Int32 => int(32, true),
+        DataType::Int64 => int(64, true),
+        DataType::UInt8 => int(8, false),
+        DataType::UInt16 => int(16, false),
+        DataType::UInt32 => int(32, false),
+        DataType::UInt64 => int(64, false),
+        DataType::Float32 => json!({"name": "floatingpoint", "precision": "SINGLE"}),
+        DataType::Float64 => json!({"name": "floatingpoint", "precision": "DOUBLE"}),
+        DataType::Utf8 => json!({"name": "utf8"}),
+        DataType::Struct(_) => json!({"name": "struct"}),
+        DataType::Timestamp(unit, timezone) => {
+            let unit = match unit {
+                TimeUnit::Second => "SECOND",
+                TimeUnit::Millisecond => "MILLISECOND",
+                TimeUnit::Microsecond => "MICROSECOND",
+                TimeUnit::Nanosecond => "NANOSECOND",
+            };
+            match timezone {
+                Some(timezone) => {
+                    json!({"name": "timestamp", "unit": unit, "timezone": timezone.as_ref()})
+                }
+                None => json!({"name": "timestamp", "unit": unit}),
+            }
+        }
+        other => json!({"name": other.to_string()}),
+    }
+}
+
+fn nullability(nullable: bool) -> &'static str {
+    if nullable {
+        "nullable"
+    } else {
+        "required"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionType, SensorData};
+    use arrow::array::Int32Array;
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_written_file_passes() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let mut writer =
+            ParquetWriter::new(dir_path, "valid", CompressionType::Snappy, 10).unwrap();
+        writer
+            .add_data(SensorData {
+                timestamp: 1,
+                temp: 25.0,
+                az: 1.0,
+                ..Default::default()
+            })
+            .unwrap();
+        writer.close().unwrap();
+
+        let path = std::fs::read_dir(dir_path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let drift = validate_file(&path, &WriterOptions::default()).unwrap();
+        assert!(drift.is_empty(), "Unexpected drift: {:?}", drift);
+
+        // The same file does not match the nested layout
+        let nested = WriterOptions {
+            nested_schema: true,
+            ..Default::default()
+        };
+        assert!(!validate_file(&path, &nested).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_file_fails() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("mismatch.parquet");
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Int32, false),
+            Field::new("extra", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(Int32Array::from(vec![Some(2)])),
+            ],
+        )
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&path).unwrap(), schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let drift = validate_file(&path, &WriterOptions::default()).unwrap();
+        assert!(drift.contains(&"column 'timestamp' has type Int32, expected Int64".to_string()));
+        assert!(drift.contains(&"missing column 'temp'".to_string()));
+        assert!(drift.contains(&"unexpected column 'extra'".to_string()));
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::status::{eprint_status, Health};
+
+/// Bounded queue feeding the background thread of a network sink
+///
+/// The writer pushes items without ever blocking: once the queue is full,
+/// new items are dropped and counted, with one warning per overflow. Closing
+/// tells the sender thread to stop waiting: its backoff sleeps end at once,
+/// and a sender that fails while closing abandons the items still queued
+/// instead of trying each of them against a server that is down.
+pub struct SendQueue<T> {
+    // What is sent and what is dropped, for the overflow warning
+    name: &'static str,
+    items: &'static str,
+    queue: Option<SyncSender<T>>,
+    sender: Option<JoinHandle<()>>,
+    closing: Arc<Closing>,
+    // Items dropped because the queue was full, and whether that is ongoing
+    dropped: u64,
+    overflowing: bool,
+}
+
+/// Receiving end of a [`SendQueue`], owned by the sender thread
+pub struct QueueReceiver<T> {
+    items: Receiver<T>,
+    closing: Arc<Closing>,
+}
+
+// Closing flag the sender thread can wait on
+#[derive(Default)]
+struct Closing {
+    flag: Mutex<bool>,
+    signal: Condvar,
+}
+
+impl<T: Send + 'static> SendQueue<T> {
+    /// Starts the sender thread
+    ///
+    /// # Arguments
+    /// * `name` - Destination named in warnings (e.g. "InfluxDB")
+    /// * `items` - What is queued, in the plural (e.g. "batches")
+    /// * `capacity` - Items queued before new ones are dropped
+    /// * `sender` - Body of the sender thread
+    pub fn start(
+        name: &'static str,
+        items: &'static str,
+        capacity: usize,
+        sender: impl FnOnce(QueueReceiver<T>) + Send + 'static,
+    ) -> Self {
+        let (queue, rx) = mpsc::sync_channel(capacity.max(1));
+        let closing = Arc::new(Closing::default());
+        let receiver = QueueReceiver {
+            items: rx,
+            closing: closing.clone(),
+        };
+        SendQueue {
+            name,
+            items,
+            queue: Some(queue),
+            sender: Some(thread::spawn(move || sender(receiver))),
+            closing,
+            dropped: 0,
+            overflowing: false,
+        }
+    }
+
+    /// Queues an item, dropping it if the queue is full
+    pub fn push(&mut self, item: T) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        match queue.try_send(item) {
+            Ok(()) => self.overflowing = false,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if !self.overflowing {
+                    let message =
+                        format!("{} is falling behind; dropping {}", self.name, self.items);
+                    eprint_status(Health::Degraded, &message);
+                    self.overflowing = true;
+                }
+            }
+            // The sender only stops once the queue is closed
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// Items dropped so far because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Closes the queue and waits for the sender thread to finish
+    pub fn close(&mut self) {
+        if let Ok(mut flag) = self.closing.flag.lock() {
+            *flag = true;
+        }
+        self.closing.signal.notify_all();
+        self.queue = None;
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+impl<T> QueueReceiver<T> {
+    /// Next queued item, or None once the queue is closed and empty
+    pub fn recv(&self) -> Option<T> {
+        self.items.recv().ok()
+    }
+
+    /// Next queued item, waiting at most `timeout` for one
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.items.recv_timeout(timeout)
+    }
+
+    /// Next queued item, if one is waiting
+    pub fn try_recv(&self) -> Option<T> {
+        self.items.try_recv().ok()
+    }
+
+    /// Whether the sink is being closed
+    pub fn is_closing(&self) -> bool {
+        self.closing.flag.lock().map_or(true, |flag| *flag)
+    }
+
+    /// Waits for the given time, or until the sink is being closed
+    pub fn sleep(&self, duration: Duration) {
+        if let Ok(flag) = self.closing.flag.lock() {
+            let _ = self
+                .closing
+                .signal
+                .wait_timeout_while(flag, duration, |closing| !*closing);
+        }
+    }
+
+    /// Discards the items still queued, returning how many there were
+    pub fn abandon(&self) -> u64 {
+        self.items.try_iter().count() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_close_interrupts_backoff_and_abandons_queue() {
+        let (report, reported) = mpsc::channel();
+        let mut queue = SendQueue::start("Server", "items", 2, move |items| {
+            // A sender whose server is down, backing off for a long time
+            let mut abandoned = 0;
+            while let Some(_item) = items.recv() {
+                if items.is_closing() {
+                    abandoned = 1 + items.abandon();
+                    break;
+                }
+                items.sleep(Duration::from_secs(60));
+            }
+            let _ = report.send(abandoned);
+        });
+        queue.push(0);
+        thread::sleep(Duration::from_millis(50));
+        for item in 1..4 {
+            queue.push(item);
+        }
+        assert_eq!(queue.dropped(), 1);
+
+        let start = Instant::now();
+        queue.close();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // The two items queued behind the one backed off on are abandoned
+        assert_eq!(reported.recv().unwrap(), 2);
+    }
+}
@@ -1,29 +1,333 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
-use serialport::SerialPort;
+use serialport::{DataBits, Parity, SerialPort, StopBits};
 use std::cell::RefCell;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use super::error::ReceiverError;
-use super::types::SensorData;
+use super::raw_capture::RawCapture;
+use super::status::{eprint_status, Health};
+use super::types::{DataFormat, SensorData};
 
 // Buffer to hold incomplete lines between reads
 thread_local! {
     static LINE_BUFFER: RefCell<String> = RefCell::new(String::with_capacity(4096));
 }
 
+// Baud-rate sanity check applied to the first bytes read from the port
+thread_local! {
+    static BAUD_CHECK: RefCell<BaudRateCheck> = RefCell::new(BaudRateCheck::new());
+}
+
+/// Number of initial bytes inspected before deciding on a baud-rate mismatch
+const BAUD_CHECK_SAMPLE_BYTES: usize = 256;
+
+/// Fraction of suspicious bytes above which a baud-rate mismatch is reported
+const BAUD_CHECK_THRESHOLD: f64 = 0.25;
+
+/// Heuristic detector for a serial baud-rate mismatch
+///
+/// The firmware only ever sends ASCII hex digits, commas and line endings.
+/// When the receiver runs at the wrong baud rate the UART produces framing
+/// garbage instead, typically dominated by 0x00/0xFF and bytes with the high
+/// bit set. This checker inspects the first bytes received and reports once
+/// if the proportion of such bytes is implausibly high.
+#[derive(Debug, Default)]
+pub struct BaudRateCheck {
+    inspected: usize,
+    suspicious: usize,
+    finished: bool,
+}
+
+impl BaudRateCheck {
+    /// Creates a new checker that has not inspected any bytes yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds received bytes into the checker
+    ///
+    /// Returns a warning message the first time enough bytes have been seen
+    /// and they look like the output of a mismatched baud rate. Once a decision
+    /// has been made, further bytes are ignored.
+    pub fn observe(&mut self, bytes: &[u8]) -> Option<String> {
+        if self.finished {
+            return None;
+        }
+
+        let remaining = BAUD_CHECK_SAMPLE_BYTES - self.inspected;
+        let sample = &bytes[..bytes.len().min(remaining)];
+        self.inspected += sample.len();
+        self.suspicious += sample.iter().filter(|&&b| Self::is_suspicious(b)).count();
+
+        if self.inspected < BAUD_CHECK_SAMPLE_BYTES {
+            return None;
+        }
+
+        self.finished = true;
+        let ratio = self.suspicious as f64 / self.inspected as f64;
+        if ratio > BAUD_CHECK_THRESHOLD {
+            Some(format!(
+                "{:.0}% of the first {} bytes received are not valid text; \
+                 the baud rate probably does not match the device (check --baud-rate)",
+                ratio * 100.0,
+                self.inspected
+            ))
+        } else {
+            None
+        }
+    }
+
+    // Bytes that never appear in the firmware's ASCII output
+    fn is_suspicious(byte: u8) -> bool {
+        match byte {
+            b'\n' | b'\r' | b'\t' => false,
+            0x00 | 0xFF => true,
+            b if b >= 0x80 => true,
+            b => b.is_ascii_control(),
+        }
+    }
+}
+
+/// Number of initial records inspected before deciding on the byte order
+const BYTE_ORDER_SAMPLE_RECORDS: usize = 32;
+
+/// Fraction of implausible floats above which the byte order is questioned
+const BYTE_ORDER_IMPLAUSIBLE_THRESHOLD: f64 = 0.5;
+
+/// Heuristic detector and fixer for byte-swapped hex words
+///
+/// When the firmware emits its 32-bit words in the other byte order, the
+/// decoded floats turn into NaNs, huge magnitudes or denormal-sized values.
+/// This checker holds back the first records received; if most of their floats
+/// are implausible but become plausible with the bytes of every word swapped,
+/// it warns once and swaps all later records as well.
+#[derive(Debug, Default)]
+pub struct ByteOrderCheck {
+    window: Vec<SensorData>,
+    swap: Option<bool>,
+}
+
+impl ByteOrderCheck {
+    /// Creates a new checker that has not seen any records yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether records are being byte-swapped after detection
+    pub fn is_swapping(&self) -> bool {
+        self.swap == Some(true)
+    }
+
+    /// Feeds a parsed record into the checker
+    ///
+    /// Returns the records ready for output, in order: nothing while the sample
+    /// window is filling, the whole (possibly corrected) window once it is
+    /// full, and one record per call after that.
+    pub fn process(&mut self, data: SensorData) -> Vec<SensorData> {
+        match self.swap {
+            Some(true) => vec![swap_byte_order(&data)],
+            Some(false) => vec![data],
+            None => {
+                self.window.push(data);
+                if self.window.len() < BYTE_ORDER_SAMPLE_RECORDS {
+                    return Vec::new();
+                }
+                self.finish()
+            }
+        }
+    }
+
+    /// Decides on the records seen so far and releases any held back
+    pub fn finish(&mut self) -> Vec<SensorData> {
+        if self.swap.is_none() && !self.window.is_empty() {
+            self.swap = Some(self.decide());
+        }
+        let window = std::mem::take(&mut self.window);
+        if self.is_swapping() {
+            window.iter().map(swap_byte_order).collect()
+        } else {
+            window
+        }
+    }
+
+    fn decide(&self) -> bool {
+        let original = implausible_fraction(self.window.iter());
+        if original <= BYTE_ORDER_IMPLAUSIBLE_THRESHOLD {
+            return false;
+        }
+
+        let swapped_window: Vec<SensorData> = self.window.iter().map(swap_byte_order).collect();
+        let swapped = implausible_fraction(swapped_window.iter());
+        if swapped < original && swapped <= 1.0 - BYTE_ORDER_IMPLAUSIBLE_THRESHOLD {
+            let warning = format!(
+                "Warning: {:.0}% of the decoded floats are implausible but only {:.0}% \
+                 after swapping byte order; correcting the byte order of all records",
+                original * 100.0,
+                swapped * 100.0
+            );
+            eprint_status(Health::Degraded, &warning);
+            true
+        } else {
+            let warning = format!(
+                "Warning: {:.0}% of the decoded floats are implausible; \
+                 check the device output format",
+                original * 100.0
+            );
+            eprint_status(Health::Degraded, &warning);
+            false
+        }
+    }
+}
+
+/// Reverses the bytes of every 32-bit word of a record
+pub fn swap_byte_order(data: &SensorData) -> SensorData {
+    let swap = |v: f32| f32::from_bits(v.to_bits().swap_bytes());
+    SensorData {
+        timestamp: data.timestamp.swap_bytes(),
+        temp: swap(data.temp),
+        gx: swap(data.gx),
+        gy: swap(data.gy),
+        gz: swap(data.gz),
+        ax: swap(data.ax),
+        ay: swap(data.ay),
+        az: swap(data.az),
+        ..data.clone()
+    }
+}
+
+// Fraction of float fields outside what the IMU can plausibly report
+fn implausible_fraction<'a>(records: impl Iterator<Item = &'a SensorData>) -> f64 {
+    let (mut total, mut implausible) = (0, 0);
+    for data in records {
+        let fields = [
+            (data.temp, 150.0),
+            (data.gx, 5000.0),
+            (data.gy, 5000.0),
+            (data.gz, 5000.0),
+            (data.ax, 5000.0),
+            (data.ay, 5000.0),
+            (data.az, 5000.0),
+        ];
+        for (value, limit) in fields {
+            total += 1;
+            // Byte-swapped small values decode to denormal-sized numbers
+            let tiny = value != 0.0 && value.abs() < 1e-20;
+            if !value.is_finite() || value.abs() > limit || tiny {
+                implausible += 1;
+            }
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        implausible as f64 / total as f64
+    }
+}
+
+/// Reads the monotonic clock in nanoseconds since the receiver started
+///
+/// Unlike the wall clock this never jumps when the system time is adjusted,
+/// which makes it suitable for measuring intervals between records.
+pub fn monotonic_timestamp() -> i64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as i64
+}
+
+/// Character framing of a serial line: data bits, parity and stop bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialFraming {
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl Default for SerialFraming {
+    fn default() -> Self {
+        SerialFraming {
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        }
+    }
+}
+
+impl SerialFraming {
+    /// Parses a framing string such as `8N1`, `7E1` or `8O1`
+    ///
+    /// The string is the number of data bits (5-8), the parity (`N`one,
+    /// `E`ven or `O`dd) and the number of stop bits (1 or 2).
+    ///
+    /// # Returns
+    /// The framing, or a configuration error if the string is not valid
+    pub fn parse(config: &str) -> Result<Self> {
+        let invalid = || -> anyhow::Error {
+            ReceiverError::ConfigError(format!(
+                "Invalid serial config '{}': expected data bits (5-8), parity (N, E, O) \
+                 and stop bits (1, 2), e.g. 8N1",
+                config
+            ))
+            .into()
+        };
+
+        let chars: Vec<char> = config.trim().to_uppercase().chars().collect();
+        let [data_bits, parity, stop_bits] = chars[..] else {
+            return Err(invalid());
+        };
+        Ok(SerialFraming {
+            data_bits: match data_bits {
+                '5' => DataBits::Five,
+                '6' => DataBits::Six,
+                '7' => DataBits::Seven,
+                '8' => DataBits::Eight,
+                _ => return Err(invalid()),
+            },
+            parity: match parity {
+                'N' => Parity::None,
+                'E' => Parity::Even,
+                'O' => Parity::Odd,
+                _ => return Err(invalid()),
+            },
+            stop_bits: match stop_bits {
+                '1' => StopBits::One,
+                '2' => StopBits::Two,
+                _ => return Err(invalid()),
+            },
+        })
+    }
+}
+
 /// Opens a serial port with the specified settings
-pub fn open_serial_port(port: &str, baud_rate: u32) -> Result<Box<dyn SerialPort>> {
+pub fn open_serial_port(
+    port: &str,
+    baud_rate: u32,
+    framing: SerialFraming,
+) -> Result<Box<dyn SerialPort>> {
     serialport::new(port, baud_rate)
+        .data_bits(framing.data_bits)
+        .parity(framing.parity)
+        .stop_bits(framing.stop_bits)
         .timeout(Duration::from_millis(100)) // Increased timeout for high-speed data
         .open()
         .with_context(|| format!("Failed to open serial port {}", port))
 }
 
+/// Splits a text line into its comma-separated fields
+///
+/// A leading UTF-8 byte order mark, as left by editors at the start of a
+/// file, is dropped and every field is trimmed, so `" 41C80000 "` decodes
+/// like `"41C80000"`. The number of fields is not changed.
+pub fn split_fields(line: &str) -> Vec<&str> {
+    let line = line.trim_start().trim_start_matches('\u{FEFF}');
+    line.split(',').map(str::trim).collect()
+}
+
 /// Parse a line of hex data into a SensorData struct
 pub fn parse_sensor_data(line: &str) -> Result<SensorData> {
     // Example format: 00000123,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000
-    let parts: Vec<&str> = line.trim().split(',').collect();
+    let parts = split_fields(line);
 
     if parts.len() != 8 {
         return Err(ReceiverError::ParseError(format!(
@@ -89,31 +393,505 @@ pub fn parse_sensor_data(line: &str) -> Result<SensorData> {
         ay,
         az,
         system_timestamp: system_ts,
+        monotonic_timestamp: monotonic_timestamp(),
+        ..Default::default()
     })
 }
 
+/// Parse a line of hex data, keeping the record if individual float fields are bad
+///
+/// Behaves like [`parse_sensor_data`], except that a float field that is not
+/// valid hex is stored as NaN and flagged in `null_fields` instead of failing
+/// the whole line. The line still fails if the field count or the timestamp
+/// is wrong, or if every float field is bad.
+pub fn parse_sensor_data_lenient(line: &str) -> Result<SensorData> {
+    let parts = split_fields(line);
+
+    if parts.len() != 8 {
+        return Err(ReceiverError::ParseError(format!(
+            "Expected 8 parts, got {}: {}",
+            parts.len(),
+            line
+        ))
+        .into());
+    }
+
+    let timestamp = u32::from_str_radix(parts[0], 16).map_err(|e| {
+        ReceiverError::ParseError(format!("Invalid timestamp: {}, error: {}", parts[0], e))
+    })?;
+
+    let mut values = [f32::NAN; 7];
+    let mut null_fields = 0u8;
+    for (index, part) in parts[1..].iter().enumerate() {
+        match u32::from_str_radix(part, 16) {
+            Ok(bits) => values[index] = f32::from_bits(bits),
+            Err(_) => null_fields |= 1 << index,
+        }
+    }
+    if null_fields == 0x7F {
+        return Err(ReceiverError::ParseError(format!("No valid fields: {}", line)).into());
+    }
+
+    let [temp, gx, gy, gz, ax, ay, az] = values;
+    Ok(SensorData {
+        timestamp,
+        temp,
+        gx,
+        gy,
+        gz,
+        ax,
+        ay,
+        az,
+        system_timestamp: Utc::now().timestamp_millis(),
+        monotonic_timestamp: monotonic_timestamp(),
+        null_fields,
+        ..Default::default()
+    })
+}
+
+/// Parse a line of hex data without a timestamp field into a SensorData struct
+///
+/// Accepts the firmware variants that send only the IMU values: seven fields
+/// (temperature, gyro, accel) or six fields (gyro, accel), in which case the
+/// temperature is NaN. The timestamp is left at 0 for a
+/// [`TimestampSynthesizer`] to fill in.
+pub fn parse_untimed_sensor_data(line: &str) -> Result<SensorData> {
+    // Example format: 41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000
+    let parts = split_fields(line);
+
+    let names: &[&str] = match parts.len() {
+        7 => &["temperature", "gx", "gy", "gz", "ax", "ay", "az"],
+        6 => &["gx", "gy", "gz", "ax", "ay", "az"],
+        n => {
+            return Err(ReceiverError::ParseError(format!(
+                "Expected 6 or 7 parts, got {}: {}",
+                n, line
+            ))
+            .into())
+        }
+    };
+
+    let mut values = Vec::with_capacity(7);
+    if parts.len() == 6 {
+        values.push(f32::NAN);
+    }
+    for (part, name) in parts.iter().zip(names) {
+        let bits = u32::from_str_radix(part, 16).map_err(|e| {
+            ReceiverError::ParseError(format!("Invalid {}: {}, error: {}", name, part, e))
+        })?;
+        values.push(f32::from_bits(bits));
+    }
+
+    Ok(SensorData {
+        temp: values[0],
+        gx: values[1],
+        gy: values[2],
+        gz: values[3],
+        ax: values[4],
+        ay: values[5],
+        az: values[6],
+        system_timestamp: Utc::now().timestamp_millis(),
+        monotonic_timestamp: monotonic_timestamp(),
+        ..Default::default()
+    })
+}
+
+/// Splits a packed timestamp field into a 24-bit timestamp and a status byte
+///
+/// Some firmware sends the device status in the top byte of the first field
+/// and the counter in the low 24 bits. The status is moved to `status` and
+/// the counter is left in `timestamp`.
+pub fn unpack_timestamp_status(mut data: SensorData) -> SensorData {
+    data.status = Some((data.timestamp >> 24) as u8);
+    data.timestamp &= 0x00FF_FFFF;
+    data
+}
+
+/// Assigns sensor counter values to records from firmware that sends none
+///
+/// Records are numbered in arrival order, one count per record, so the
+/// counter rate equals the sample rate given with `--counter-hz`.
+#[derive(Debug, Default)]
+pub struct TimestampSynthesizer {
+    next: u32,
+}
+
+impl TimestampSynthesizer {
+    /// Creates a synthesizer starting at counter value 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stamps a record with the next counter value
+    pub fn assign(&mut self, mut data: SensorData) -> SensorData {
+        data.timestamp = self.next;
+        self.next = self.next.wrapping_add(1);
+        data
+    }
+}
+
+/// Discards serial lines until the firmware's boot banner has been received
+///
+/// Output printed while the device starts up is not sensor data; waiting for
+/// the banner keeps it from being reported as parse errors. The line holding
+/// the banner is discarded as well.
+#[derive(Debug)]
+pub struct BannerWait {
+    banner: String,
+    seen: bool,
+}
+
+impl BannerWait {
+    /// Creates a gate waiting for a line containing `banner`
+    pub fn new(banner: &str) -> Self {
+        BannerWait {
+            banner: banner.to_string(),
+            seen: false,
+        }
+    }
+
+    /// Returns whether the banner has been received
+    pub fn seen(&self) -> bool {
+        self.seen
+    }
+
+    /// Returns whether a line should be parsed
+    pub fn admit(&mut self, line: &str) -> bool {
+        if self.seen {
+            return true;
+        }
+        if line.contains(&self.banner) {
+            self.seen = true;
+            println!("Received banner '{}', starting capture", self.banner);
+        }
+        false
+    }
+}
+
+/// Bytes requested from the serial port per read unless configured otherwise
+///
+/// Larger buffers take more data per system call, which helps throughput at
+/// high baud rates, but records wait longer before they are handed on.
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
+
+/// Sync word opening every binary frame
+pub const BINARY_SYNC: [u8; 2] = [0xAA, 0x55];
+
+/// Length of a binary frame: sync word, u32 timestamp, seven f32 values and a CRC16
+pub const BINARY_FRAME_LEN: usize = 2 + 4 + 7 * 4 + 2;
+
+/// CRC-16/CCITT-FALSE checksum (polynomial 0x1021, initial value 0xFFFF)
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Encodes a record as a binary frame
+///
+/// All fields are little-endian; the CRC covers every byte before it,
+/// including the sync word.
+pub fn encode_binary_frame(data: &SensorData) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(BINARY_FRAME_LEN);
+    frame.extend_from_slice(&BINARY_SYNC);
+    frame.extend_from_slice(&data.timestamp.to_le_bytes());
+    for value in [
+        data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+    ] {
+        frame.extend_from_slice(&value.to_le_bytes());
+    }
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+/// Parses one binary frame into a SensorData struct
+///
+/// # Arguments
+/// * `frame` - Exactly [`BINARY_FRAME_LEN`] bytes starting with [`BINARY_SYNC`]
+///
+/// # Returns
+/// The record, or a parse error if the length, sync word or CRC is wrong
+pub fn parse_binary_sensor_data(frame: &[u8]) -> Result<SensorData> {
+    if frame.len() != BINARY_FRAME_LEN {
+        return Err(ReceiverError::ParseError(format!(
+            "Expected a {}-byte frame, got {} bytes",
+            BINARY_FRAME_LEN,
+            frame.len()
+        ))
+        .into());
+    }
+    if frame[..2] != BINARY_SYNC {
+        return Err(ReceiverError::ParseError(format!(
+            "Invalid sync word: {:02X} {:02X}",
+            frame[0], frame[1]
+        ))
+        .into());
+    }
+    let (body, trailer) = frame.split_at(BINARY_FRAME_LEN - 2);
+    let expected = u16::from_le_bytes([trailer[0], trailer[1]]);
+    let actual = crc16(body);
+    if actual != expected {
+        return Err(ReceiverError::ParseError(format!(
+            "CRC mismatch: frame says {:04X}, computed {:04X}",
+            expected, actual
+        ))
+        .into());
+    }
+
+    let word = |offset: usize| {
+        [
+            body[offset],
+            body[offset + 1],
+            body[offset + 2],
+            body[offset + 3],
+        ]
+    };
+    let value = |index: usize| f32::from_le_bytes(word(6 + index * 4));
+    Ok(SensorData {
+        timestamp: u32::from_le_bytes(word(2)),
+        temp: value(0),
+        gx: value(1),
+        gy: value(2),
+        gz: value(3),
+        ax: value(4),
+        ay: value(5),
+        az: value(6),
+        system_timestamp: Utc::now().timestamp_millis(),
+        monotonic_timestamp: monotonic_timestamp(),
+        ..Default::default()
+    })
+}
+
+/// Accumulates received bytes and cuts them into binary frames
+///
+/// Bytes arrive in arbitrary chunks; an incomplete frame is kept until the
+/// rest of it arrives. Bytes before a sync word, and frames failing the CRC
+/// check, are discarded and the search for the next sync word resumes one
+/// byte further on.
+///
+/// Records are stamped with the host time at which the chunk holding the
+/// frame's sync word arrived, not the time the frame was completed, so a
+/// frame split across reads is not delayed by the UART latency of its tail.
+#[derive(Debug, Default)]
+pub struct BinaryFrameBuffer {
+    pending: Vec<u8>,
+    // Offset into `pending` of each received chunk still buffered, with its
+    // receive time (system milliseconds, monotonic nanoseconds)
+    arrivals: VecDeque<(usize, i64, i64)>,
+    discarded_bytes: usize,
+    corrupt_frames: usize,
+}
+
+impl BinaryFrameBuffer {
+    /// Creates an empty frame buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of bytes dropped so far while searching for valid frames
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    /// Number of frames dropped so far for failing the CRC or length check
+    pub fn corrupt_frames(&self) -> usize {
+        self.corrupt_frames
+    }
+
+    /// Appends bytes received now and returns every record completed by them
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SensorData> {
+        self.push_at(bytes, Utc::now().timestamp_millis(), monotonic_timestamp())
+    }
+
+    /// Appends bytes received at the given host times and returns every
+    /// record completed by them
+    ///
+    /// # Arguments
+    /// * `bytes` - Received chunk
+    /// * `system_timestamp` - Host time the chunk arrived, in milliseconds
+    /// * `monotonic_timestamp` - Monotonic time the chunk arrived, in nanoseconds
+    pub fn push_at(
+        &mut self,
+        bytes: &[u8],
+        system_timestamp: i64,
+        monotonic_timestamp: i64,
+    ) -> Vec<SensorData> {
+        if !bytes.is_empty() {
+            self.arrivals
+                .push_back((self.pending.len(), system_timestamp, monotonic_timestamp));
+        }
+        self.pending.extend_from_slice(bytes);
+        let mut records = Vec::new();
+        let mut start = 0;
+
+        while self.pending.len() - start >= BINARY_FRAME_LEN {
+            let window = &self.pending[start..];
+            let Some(offset) = window.windows(2).position(|pair| pair == BINARY_SYNC) else {
+                // Keep a trailing first sync byte; it may start the next frame
+                let keep = usize::from(window.last() == Some(&BINARY_SYNC[0]));
+                self.discarded_bytes += window.len() - keep;
+                start = self.pending.len() - keep;
+                break;
+            };
+            if offset > 0 {
+                self.discarded_bytes += offset;
+                start += offset;
+                continue;
+            }
+
+            match parse_binary_sensor_data(&window[..BINARY_FRAME_LEN]) {
+                Ok(mut data) => {
+                    (data.system_timestamp, data.monotonic_timestamp) = self.arrival_at(start);
+                    records.push(data);
+                    start += BINARY_FRAME_LEN;
+                }
+                Err(e) => {
+                    let warning = format!("Warning: dropping corrupt frame: {}", e);
+                    eprint_status(Health::Degraded, &warning);
+                    self.corrupt_frames += 1;
+                    self.discarded_bytes += 1;
+                    start += 1;
+                }
+            }
+        }
+
+        self.consume(start);
+        records
+    }
+
+    // Receive time of the chunk holding the byte at `offset` in `pending`
+    fn arrival_at(&self, offset: usize) -> (i64, i64) {
+        self.arrivals
+            .iter()
+            .rev()
+            .find(|(chunk_offset, _, _)| *chunk_offset <= offset)
+            .map_or((0, 0), |&(_, system, monotonic)| (system, monotonic))
+    }
+
+    // Drops the first `count` pending bytes and the arrivals only they used
+    fn consume(&mut self, count: usize) {
+        self.pending.drain(..count);
+        while self.arrivals.len() > 1 && self.arrivals[1].0 <= count {
+            self.arrivals.pop_front();
+        }
+        if self.pending.is_empty() {
+            self.arrivals.clear();
+        }
+        for (offset, _, _) in &mut self.arrivals {
+            *offset = offset.saturating_sub(count);
+        }
+    }
+}
+
+/// Read all available binary frames from a serial port
+///
+/// Reads at most `buf.len()` bytes. Incomplete frames are kept in `frames`
+/// until the next call; every byte read is also appended to `capture`, if
+/// given.
+pub fn read_binary_sensor_data(
+    port: &mut Box<dyn SerialPort>,
+    frames: &mut BinaryFrameBuffer,
+    buf: &mut [u8],
+    capture: Option<&mut RawCapture>,
+) -> Result<Vec<SensorData>> {
+    let n = read_chunk(port, buf, capture)?;
+    Ok(frames.push(&buf[..n]))
+}
+
+/// Whether a serial read error means the port is gone rather than a passing glitch
+///
+/// Unplugging a USB adapter fails reads with a hang-up (`BrokenPipe`) or,
+/// depending on the driver, EIO, ENXIO or ENODEV; the handle stays dead until
+/// the port is reopened. Timeouts never reach this point (see `read_chunk`).
+pub fn is_fatal_port_error(error: &anyhow::Error) -> bool {
+    let Some(e) = error.downcast_ref::<std::io::Error>() else {
+        return false;
+    };
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::NotFound
+            | std::io::ErrorKind::NotConnected
+            | std::io::ErrorKind::UnexpectedEof
+    ) || cfg!(unix) && matches!(e.raw_os_error(), Some(5 | 6 | 19))
+}
+
+// Reads whatever the port has available, logging it to the capture if given
+//
+// A read timeout counts as zero bytes read.
+pub(crate) fn read_chunk(
+    port: &mut Box<dyn SerialPort>,
+    buf: &mut [u8],
+    capture: Option<&mut RawCapture>,
+) -> Result<usize> {
+    let n = match port.read(buf) {
+        Ok(n) => n,
+        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    if n > 0 {
+        if let Some(capture) = capture {
+            if let Err(e) = capture.write(&buf[..n]) {
+                let warning = format!("Warning: {:#}", e);
+                eprint_status(Health::Degraded, &warning);
+            }
+        }
+    }
+    Ok(n)
+}
+
 /// Read all available sensor data lines from a serial port
 ///
 /// This improved version uses a fixed buffer to read multiple bytes at once
 /// and maintains state between calls to handle incomplete lines.
 /// It processes all complete lines in the buffer at once to avoid data loss.
 pub fn read_serial_data(port: &mut Box<dyn SerialPort>) -> Result<Vec<String>> {
-    let mut buf = [0u8; 4096]; // Large buffer to read multiple lines at once
-    let mut complete_lines = Vec::new();
+    read_serial_data_with_capture(port, &mut [0u8; DEFAULT_READ_BUFFER_SIZE], None)
+}
 
+/// Read all available sensor data lines, logging the raw bytes to a capture
+///
+/// Behaves like [`read_serial_data`], reading at most `buf.len()` bytes;
+/// every byte read is also appended to `capture`, if given. A failing
+/// capture is reported but does not interrupt reading.
+pub fn read_serial_data_with_capture(
+    port: &mut Box<dyn SerialPort>,
+    buf: &mut [u8],
+    capture: Option<&mut RawCapture>,
+) -> Result<Vec<String>> {
     // Read available data into buffer
-    let n = match port.read(&mut buf) {
-        Ok(n) => n,
-        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return Ok(Vec::new()),
-        Err(e) => return Err(e.into()),
-    };
-
+    let n = read_chunk(port, buf, capture)?;
     if n == 0 {
         return Ok(Vec::new());
     }
+    Ok(split_lines(&buf[..n]))
+}
+
+// Appends received bytes to the line buffer and returns every completed line
+pub(crate) fn split_lines(bytes: &[u8]) -> Vec<String> {
+    let mut complete_lines = Vec::new();
+
+    // Warn early if the first bytes look like a baud-rate mismatch
+    BAUD_CHECK.with(|check| {
+        if let Some(warning) = check.borrow_mut().observe(bytes) {
+            let warning = format!("Warning: {}", warning);
+            eprint_status(Health::Degraded, &warning);
+        }
+    });
 
     // Convert received bytes to string
-    let data = String::from_utf8_lossy(&buf[..n]).to_string();
+    let data = String::from_utf8_lossy(bytes).to_string();
 
     // Process the data with our line buffer
     LINE_BUFFER.with(|buffer| {
@@ -142,22 +920,143 @@ pub fn read_serial_data(port: &mut Box<dyn SerialPort>) -> Result<Vec<String>> {
             *line_buffer = line_buffer[pos + 1..].to_string();
         }
 
-        Ok(complete_lines)
+        complete_lines
     })
 }
 
+/// Frames or lines that must agree before auto detection settles on a format
+const AUTO_DETECT_CONFIRMATIONS: usize = 2;
+
+/// Bytes kept while the format is undecided; older bytes are dropped
+const AUTO_DETECT_LIMIT: usize = 16 * 1024;
+
+/// Decides the encoding of a byte stream, if the bytes are conclusive
+///
+/// The stream is binary once [`AUTO_DETECT_CONFIRMATIONS`] consecutive frames
+/// pass their CRC check, and text once as many complete lines after the first
+/// line break hold only printable ASCII. Until then the result is `None`.
+pub fn detect_data_format(bytes: &[u8]) -> Option<DataFormat> {
+    let binary = (0..bytes.len().saturating_sub(1))
+        .filter(|&start| bytes[start..start + 2] == BINARY_SYNC)
+        .any(|start| {
+            (0..AUTO_DETECT_CONFIRMATIONS).all(|i| {
+                let offset = start + i * BINARY_FRAME_LEN;
+                bytes
+                    .get(offset..offset + BINARY_FRAME_LEN)
+                    .is_some_and(|frame| parse_binary_sensor_data(frame).is_ok())
+            })
+        });
+    if binary {
+        return Some(DataFormat::Binary);
+    }
+
+    // Skip the first, possibly partial, line
+    let first_break = bytes.iter().position(|&b| b == b'\n')?;
+    let lines = bytes[first_break + 1..]
+        .split_inclusive(|&b| b == b'\n')
+        .filter(|line| line.ends_with(b"\n"));
+    let mut text_lines = 0;
+    for line in lines {
+        let printable = line
+            .iter()
+            .all(|&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\r' | b'\n'));
+        if !printable {
+            return None;
+        }
+        if !line.trim_ascii().is_empty() {
+            text_lines += 1;
+        }
+    }
+    (text_lines >= AUTO_DETECT_CONFIRMATIONS).then_some(DataFormat::Text)
+}
+
+/// Output of [`read_auto_detect_data`], in the format detected so far
+#[derive(Debug)]
+pub enum AutoDetectRead {
+    /// Complete text lines, to be parsed like `--input-format text`
+    Lines(Vec<String>),
+    /// Records decoded from binary frames
+    Records(Vec<SensorData>),
+}
+
+/// Per-connection state of `--input-format auto`
+///
+/// Received bytes are held back until [`detect_data_format`] decides the
+/// format; the decision then sticks for the rest of the connection, so noise
+/// cannot switch formats, and the held bytes are decoded in that format.
+#[derive(Debug, Default)]
+pub struct AutoDetectState {
+    detected: Option<DataFormat>,
+    sniffed: Vec<u8>,
+    frames: BinaryFrameBuffer,
+}
+
+impl AutoDetectState {
+    /// Creates a state for a new connection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Format the stream was detected as, once decided
+    pub fn detected(&self) -> Option<DataFormat> {
+        self.detected
+    }
+
+    /// Number of binary frames dropped so far as corrupt
+    pub fn corrupt_frames(&self) -> usize {
+        self.frames.corrupt_frames()
+    }
+
+    /// Feeds received bytes, returning whatever they complete
+    pub fn push(&mut self, bytes: &[u8]) -> AutoDetectRead {
+        let bytes = match self.detected {
+            Some(_) => bytes.to_vec(),
+            None => {
+                self.sniffed.extend_from_slice(bytes);
+                self.detected = detect_data_format(&self.sniffed);
+                if self.detected.is_none() {
+                    let excess = self.sniffed.len().saturating_sub(AUTO_DETECT_LIMIT);
+                    self.sniffed.drain(..excess);
+                    return AutoDetectRead::Lines(Vec::new());
+                }
+                std::mem::take(&mut self.sniffed)
+            }
+        };
+        match self.detected {
+            Some(DataFormat::Binary) => AutoDetectRead::Records(self.frames.push(&bytes)),
+            _ => AutoDetectRead::Lines(split_lines(&bytes)),
+        }
+    }
+}
+
+/// Reads from the serial port, detecting whether it sends text or binary frames
+///
+/// Behaves like [`read_serial_data_with_capture`] or
+/// [`read_binary_sensor_data`] once `state` has settled on a format; nothing
+/// is returned before that.
+pub fn read_auto_detect_data(
+    port: &mut Box<dyn SerialPort>,
+    state: &mut AutoDetectState,
+    buf: &mut [u8],
+    capture: Option<&mut RawCapture>,
+) -> Result<AutoDetectRead> {
+    let n = read_chunk(port, buf, capture)?;
+    Ok(state.push(&buf[..n]))
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use std::io::{Cursor, Read};
 
-    // MockSerialPort to simulate serial port behavior in tests
-    struct MockSerialPort {
+    // MockSerialPort to simulate serial port behavior in tests, also used
+    // by the reader tests in async_worker
+    pub(crate) struct MockSerialPort {
         cursor: Cursor<Vec<u8>>,
     }
 
     impl MockSerialPort {
-        fn new(data: &[u8]) -> Self {
+        pub(crate) fn new(data: &[u8]) -> Self {
             Self {
                 cursor: Cursor::new(data.to_vec()),
             }
@@ -286,6 +1185,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unplugged_port_errors_are_fatal() {
+        use std::io::{Error, ErrorKind};
+
+        let fatal = |e: Error| is_fatal_port_error(&anyhow::Error::from(e));
+        assert!(fatal(Error::new(ErrorKind::BrokenPipe, "hang-up")));
+        assert!(fatal(Error::new(ErrorKind::NotFound, "no such device")));
+        assert!(!fatal(Error::new(ErrorKind::Interrupted, "interrupted")));
+        assert!(!fatal(Error::new(ErrorKind::InvalidData, "framing")));
+        assert!(!is_fatal_port_error(&anyhow::anyhow!("parse error")));
+        #[cfg(unix)]
+        assert!(fatal(Error::from_raw_os_error(5)));
+    }
+
     #[test]
     fn test_read_serial_data_multiple_lines() {
         // Initialize a mock serial port with multiple lines of data
@@ -388,6 +1301,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_buffer_size_does_not_change_lines() {
+        let data = "00000123,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n\
+                   00000124,41300000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n";
+
+        for size in [16, DEFAULT_READ_BUFFER_SIZE, 64 * 1024] {
+            LINE_BUFFER.with(|buffer| {
+                *buffer.borrow_mut() = String::new();
+            });
+            let mut port = Box::new(MockSerialPort::new(data.as_bytes())) as Box<dyn SerialPort>;
+            let mut buf = vec![0u8; size];
+            let mut lines = Vec::new();
+            // One read per buffer's worth of data, plus one that finds the port drained
+            for _ in 0..=data.len().div_ceil(size) {
+                lines.extend(read_serial_data_with_capture(&mut port, &mut buf, None).unwrap());
+            }
+            assert_eq!(lines.len(), 2, "buffer of {} bytes", size);
+            assert!(lines[1].starts_with("00000124,"));
+        }
+    }
+
     #[test]
     fn test_parse_sensor_data_valid() {
         let line = "00000123,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000";
@@ -426,6 +1360,281 @@ mod tests {
         assert!(result.is_err(), "Should fail with invalid hex");
     }
 
+    #[test]
+    fn test_serial_framing_strings() {
+        assert_eq!(
+            SerialFraming::parse("8N1").unwrap(),
+            SerialFraming::default()
+        );
+        assert_eq!(
+            SerialFraming::parse("7e1").unwrap(),
+            SerialFraming {
+                data_bits: DataBits::Seven,
+                parity: Parity::Even,
+                stop_bits: StopBits::One,
+            }
+        );
+        assert_eq!(SerialFraming::parse("8O1").unwrap().parity, Parity::Odd);
+
+        for config in ["9Z3", "8N", "8N12", ""] {
+            let err = SerialFraming::parse(config).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref(), Some(ReceiverError::ConfigError(_))),
+                "{}",
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn test_lenient_parse_nulls_bad_field() {
+        let line = "00000123,41C80000,3F800000,XYZ,40400000,3DCCCCCD,3E4CCCCD,3F800000";
+        assert!(parse_sensor_data(line).is_err());
+
+        let data = parse_sensor_data_lenient(line).unwrap();
+        assert_eq!(data.timestamp, 0x123);
+        assert!(data.gy.is_nan());
+        for (index, name) in SensorData::FLOAT_FIELDS.iter().enumerate() {
+            assert_eq!(data.is_null(index), *name == "gy", "{}", name);
+        }
+        assert_eq!((data.temp, data.gx, data.gz), (25.0, 1.0, 3.0));
+        assert_eq!(data.az, 1.0);
+
+        // The record still needs a timestamp and the full field count
+        assert!(parse_sensor_data_lenient(
+            "ZZZ,41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000"
+        )
+        .is_err());
+        assert!(parse_sensor_data_lenient("00000123,41C80000,3F800000").is_err());
+    }
+
+    #[test]
+    fn test_auto_detect_sticks_to_first_confirmed_format() {
+        let text = b"00000123,41C80000\n00000001,41C80000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n00000002,41C80000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\n";
+        assert_eq!(detect_data_format(&text[..40]), None);
+        assert_eq!(detect_data_format(text), Some(DataFormat::Text));
+
+        let mut binary = vec![0x13, 0x37];
+        for timestamp in 0..3 {
+            binary.extend(encode_binary_frame(&SensorData {
+                timestamp,
+                ..parse_binary_sensor_data(&hand_built_frame()).unwrap()
+            }));
+        }
+        assert_eq!(detect_data_format(&binary[..BINARY_FRAME_LEN + 2]), None);
+        assert_eq!(detect_data_format(&binary), Some(DataFormat::Binary));
+
+        // Held bytes are decoded once the format is known; later noise does not flip it
+        let mut state = AutoDetectState::new();
+        let AutoDetectRead::Lines(lines) = state.push(&binary[..BINARY_FRAME_LEN]) else {
+            panic!("undecided read returned records");
+        };
+        assert!(lines.is_empty());
+        let AutoDetectRead::Records(records) = state.push(&binary[BINARY_FRAME_LEN..]) else {
+            panic!("binary stream returned lines");
+        };
+        assert_eq!(state.detected(), Some(DataFormat::Binary));
+        assert_eq!(
+            records.iter().map(|d| d.timestamp).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(matches!(state.push(text), AutoDetectRead::Records(_)));
+        assert_eq!(state.detected(), Some(DataFormat::Binary));
+    }
+
+    #[test]
+    fn test_bom_and_padded_fields_parse() {
+        let expected = parse_sensor_data(
+            "00000123,41C80000,3F800000,40000000,40400000,BF800000,3F000000,3F800000",
+        )
+        .unwrap();
+
+        let bom = "\u{FEFF}00000123,41C80000,3F800000,40000000,40400000,BF800000,3F000000,3F800000";
+        let padded =
+            " 00000123 , 41C80000,3F800000 ,\t40000000,40400000,BF800000,3F000000,3F800000 \r";
+        for line in [bom, padded] {
+            let data = parse_sensor_data(line).unwrap();
+            assert_eq!(data.timestamp, expected.timestamp);
+            assert_eq!(
+                (data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az),
+                (
+                    expected.temp,
+                    expected.gx,
+                    expected.gy,
+                    expected.gz,
+                    expected.ax,
+                    expected.ay,
+                    expected.az
+                )
+            );
+            assert!(parse_sensor_data_lenient(line).is_ok());
+        }
+
+        let untimed = "\u{FEFF} 3F800000, 40000000 ,40400000,BF800000,3F000000,3F800000";
+        assert_eq!(parse_untimed_sensor_data(untimed).unwrap().gy, 2.0);
+
+        // The field count is still checked
+        assert!(parse_sensor_data("\u{FEFF}00000123, 41C80000, 3F800000").is_err());
+        assert!(parse_sensor_data(
+            "00000123, , 3F800000,40000000,40400000,BF800000,3F000000,3F800000"
+        )
+        .is_err());
+    }
+
+    // Frame laid out byte by byte rather than with encode_binary_frame
+    fn hand_built_frame() -> Vec<u8> {
+        let mut frame = vec![0xAA, 0x55];
+        frame.extend_from_slice(&[0x23, 0x01, 0x00, 0x00]); // timestamp 0x123
+        frame.extend_from_slice(&[0x00, 0x00, 0xC8, 0x41]); // temp 25.0
+        frame.extend_from_slice(&[0x00, 0x00, 0x80, 0x3F]); // gx 1.0
+        frame.extend_from_slice(&[0x00, 0x00, 0x00, 0x40]); // gy 2.0
+        frame.extend_from_slice(&[0x00, 0x00, 0x40, 0x40]); // gz 3.0
+        frame.extend_from_slice(&[0x00, 0x00, 0x80, 0xBF]); // ax -1.0
+        frame.extend_from_slice(&[0x00, 0x00, 0x00, 0x3F]); // ay 0.5
+        frame.extend_from_slice(&[0x00, 0x00, 0x80, 0x3F]); // az 1.0
+        let crc = crc16(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_parse_hand_built_binary_frame() {
+        // CRC-16/CCITT-FALSE check value
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+
+        let frame = hand_built_frame();
+        assert_eq!(frame.len(), BINARY_FRAME_LEN);
+        let data = parse_binary_sensor_data(&frame).unwrap();
+        assert_eq!(data.timestamp, 0x123);
+        assert_eq!(data.temp, 25.0);
+        assert_eq!((data.gx, data.gy, data.gz), (1.0, 2.0, 3.0));
+        assert_eq!((data.ax, data.ay, data.az), (-1.0, 0.5, 1.0));
+        assert_eq!(frame, encode_binary_frame(&data));
+    }
+
+    #[test]
+    fn test_binary_frame_with_bad_crc_rejected() {
+        let is_parse_error = |frame: &[u8]| {
+            let err = parse_binary_sensor_data(frame).unwrap_err();
+            matches!(err.downcast_ref(), Some(ReceiverError::ParseError(_)))
+        };
+
+        let mut corrupt_crc = hand_built_frame();
+        corrupt_crc[BINARY_FRAME_LEN - 1] ^= 0x01;
+        assert!(is_parse_error(&corrupt_crc));
+
+        let mut corrupt_payload = hand_built_frame();
+        corrupt_payload[10] ^= 0x80;
+        assert!(is_parse_error(&corrupt_payload));
+
+        let mut bad_sync = hand_built_frame();
+        bad_sync[1] = 0x56;
+        assert!(is_parse_error(&bad_sync));
+
+        assert!(is_parse_error(&hand_built_frame()[..BINARY_FRAME_LEN - 1]));
+    }
+
+    #[test]
+    fn test_binary_frame_stamped_at_frame_start() {
+        let frame = |timestamp| {
+            encode_binary_frame(&SensorData {
+                timestamp,
+                temp: 25.0,
+                az: 1.0,
+                ..Default::default()
+            })
+        };
+        let (first, second) = (frame(1), frame(2));
+        let mut frames = BinaryFrameBuffer::new();
+
+        // The first frame starts in one read and completes in the next, which
+        // also brings the start of the second frame
+        assert!(frames.push_at(&first[..10], 1_000, 10).is_empty());
+        let mut chunk = first[10..].to_vec();
+        chunk.extend_from_slice(&second[..20]);
+        let records = frames.push_at(&chunk, 1_050, 60);
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            (records[0].system_timestamp, records[0].monotonic_timestamp),
+            (1_000, 10)
+        );
+
+        let records = frames.push_at(&second[20..], 1_100, 110);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, 2);
+        assert_eq!(
+            (records[0].system_timestamp, records[0].monotonic_timestamp),
+            (1_050, 60)
+        );
+    }
+
+    #[test]
+    fn test_lines_before_banner_are_ignored() {
+        let mut gate = BannerWait::new("BOOT OK");
+        let lines = [
+            "\u{0}\u{ff}garbage",
+            "00000001,41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+            "Spresense BOOT OK v1.2",
+            "00000002,41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+            "00000003,41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+        ];
+
+        let timestamps: Vec<u32> = lines
+            .iter()
+            .filter(|line| gate.admit(line))
+            .map(|line| parse_sensor_data(line).unwrap().timestamp)
+            .collect();
+        assert!(gate.seen());
+        assert_eq!(timestamps, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_synthesized_timestamps_increment() {
+        let mut synthesizer = TimestampSynthesizer::new();
+
+        let lines = [
+            "41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+            "3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+            "41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000",
+        ];
+        let records: Vec<SensorData> = lines
+            .iter()
+            .map(|line| synthesizer.assign(parse_untimed_sensor_data(line).unwrap()))
+            .collect();
+
+        let timestamps: Vec<u32> = records.iter().map(|data| data.timestamp).collect();
+        assert_eq!(timestamps, vec![0, 1, 2]);
+
+        assert_eq!(records[0].temp, 25.0);
+        assert!(records[1].temp.is_nan());
+        for data in &records {
+            assert_eq!((data.gx, data.gy, data.gz), (1.0, 2.0, 3.0));
+            assert_eq!(data.az, 1.0);
+        }
+
+        // A line that still carries a timestamp has too many fields
+        assert!(parse_untimed_sensor_data(
+            "00000001,41C80000,3F800000,40000000,40400000,3DCCCCCD,3E4CCCCD,3F800000"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_unpack_timestamp_status() {
+        let data = parse_sensor_data(
+            "A5123456,41C80000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000",
+        )
+        .unwrap();
+        let data = unpack_timestamp_status(data);
+        assert_eq!(data.timestamp, 0x123456);
+        assert_eq!(data.status, Some(0xA5));
+        assert_eq!(data.temp, 25.0);
+
+        let data = parse_sensor_data("00FFFFFF,0,0,0,0,0,0,0").unwrap();
+        let data = unpack_timestamp_status(data);
+        assert_eq!((data.timestamp, data.status), (0xFFFFFF, Some(0)));
+    }
+
     #[test]
     fn test_bit_conversion() {
         // Test specific known bit patterns
@@ -462,4 +1671,93 @@ mod tests {
             "80000000 should be -0.0"
         );
     }
+
+    #[test]
+    fn test_baud_check_detects_noise() {
+        // Deterministic pseudo-random bytes standing in for UART framing garbage
+        let mut state: u32 = 0x1234_5678;
+        let noise: Vec<u8> = (0..BAUD_CHECK_SAMPLE_BYTES)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect();
+
+        let mut check = BaudRateCheck::new();
+        let (first, rest) = noise.split_at(100);
+        assert!(
+            check.observe(first).is_none(),
+            "Should wait for a full sample"
+        );
+        assert!(
+            check.observe(rest).is_some(),
+            "High-entropy noise should trigger the warning"
+        );
+
+        // The warning is only emitted once
+        assert!(check.observe(&noise).is_none());
+    }
+
+    #[test]
+    fn test_baud_check_accepts_ascii() {
+        let line = "00000123,41200000,3F800000,3F800000,3F800000,3F800000,3F800000,3F800000\r\n";
+        let data = line.repeat(4);
+
+        let mut check = BaudRateCheck::new();
+        assert!(
+            check.observe(data.as_bytes()).is_none(),
+            "Clean ASCII should not trigger the warning"
+        );
+    }
+
+    #[test]
+    fn test_byte_order_check_corrects_swapped_words() {
+        // Hex line as sent by a device emitting words in the other byte order
+        let swapped_line = |i: u32| {
+            let values = [25.5f32, 0.01 * i as f32, -0.2, 0.3, 0.05, -0.1, 9.8];
+            let mut words = vec![format!("{:08X}", (1000 + i).swap_bytes())];
+            words.extend(
+                values
+                    .iter()
+                    .map(|v| format!("{:08X}", v.to_bits().swap_bytes())),
+            );
+            words.join(",")
+        };
+
+        let mut check = ByteOrderCheck::new();
+        let mut output = Vec::new();
+        for i in 0..40 {
+            let parsed = parse_sensor_data(&swapped_line(i)).unwrap();
+            output.extend(check.process(parsed));
+        }
+        output.extend(check.finish());
+
+        assert!(check.is_swapping());
+        assert_eq!(output.len(), 40);
+        for (i, data) in output.iter().enumerate() {
+            assert_eq!(data.timestamp, 1000 + i as u32);
+            assert_eq!(data.temp, 25.5);
+            assert_eq!(data.gx, 0.01 * i as f32);
+            assert_eq!(data.az, 9.8);
+        }
+    }
+
+    #[test]
+    fn test_byte_order_check_keeps_plausible_data() {
+        let line = "00000123,41CC0000,3DCCCCCD,BE4CCCCD,3E99999A,3D4CCCCD,BDCCCCCD,411CCCCD";
+        let mut check = ByteOrderCheck::new();
+        let mut output = Vec::new();
+        for _ in 0..5 {
+            output.extend(check.process(parse_sensor_data(line).unwrap()));
+        }
+        // Fewer records than the sample window are released on finish
+        assert!(output.is_empty());
+        output.extend(check.finish());
+
+        assert!(!check.is_swapping());
+        assert_eq!(output.len(), 5);
+        assert_eq!(output[0].temp, 25.5);
+    }
 }
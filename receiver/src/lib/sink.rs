@@ -0,0 +1,503 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use super::compact::CompactBinaryWriter;
+use super::csv_writer::{CsvProfile, CsvWriter};
+use super::error::ReceiverError;
+use super::jsonl_writer::JsonlWriter;
+use super::parquet_writer::{ParquetWriter, WriterOptions};
+use super::status::{eprint_status, Health};
+use super::types::{CompressionType, FileNaming, SensorData};
+
+/// Output that the file writer worker hands records to
+///
+/// Implemented by every output format; the worker only relies on this
+/// surface, so new formats need no changes to its wiring.
+pub trait DataSink: Send {
+    /// Adds a record, writing buffered records once the sink's buffer is full
+    fn add_data(&mut self, data: SensorData) -> Result<()>;
+
+    /// Writes any buffered records
+    fn flush(&mut self) -> Result<()>;
+
+    /// Closes the current file and continues in a new one
+    ///
+    /// # Returns
+    /// The final paths of the files closed, after any renaming on close, and
+    /// of their companions such as stats sidecars; empty for sinks that do
+    /// not write files
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>>;
+
+    /// Flushes and closes the output
+    ///
+    /// # Returns
+    /// The final paths of the files closed, as for [`rotate_file`](Self::rotate_file)
+    fn close(self: Box<Self>) -> Result<Vec<String>>;
+
+    /// Stores a key-value pair in the current file's metadata, if supported
+    fn append_metadata(&mut self, _key: &str, _value: String) {}
+
+    /// Size of the current file so far in bytes, for size-based rotation
+    ///
+    /// Sinks that cannot tell return 0 and are never rotated by size.
+    fn bytes_written(&self) -> u64 {
+        0
+    }
+
+    /// Path of the file currently being written, if the sink writes files
+    fn output_path(&self) -> Option<&str> {
+        None
+    }
+
+    /// Capture session identifier written with the data, if any
+    fn run_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Settings available to sink constructors
+#[derive(Debug, Clone)]
+pub struct SinkConfig {
+    pub output_dir: String,
+    pub prefix: String,
+    pub compression: CompressionType,
+    pub buffer_size: usize,
+    pub options: WriterOptions,
+    /// Start a new file after this many records (formats that support it)
+    pub max_records_per_file: Option<usize>,
+    /// Sort each batch by timestamp before writing (formats that support it)
+    pub sort_on_flush: bool,
+    /// Write every flushed batch to a file of its own (formats that support it)
+    pub file_per_row_group: bool,
+    /// Cut row groups at exact multiples of this many records (formats that support it)
+    pub row_group_alignment: Option<usize>,
+    /// Time each file is named after (formats that support it)
+    pub file_naming: FileNaming,
+    /// Write a `.stats.json` summary next to each closed file (formats that support it)
+    pub stats_sidecar: bool,
+    pub csv_profile: CsvProfile,
+}
+
+/// Creates a sink from the shared settings
+pub type SinkConstructor = Box<dyn Fn(&SinkConfig) -> Result<Box<dyn DataSink>> + Send + Sync>;
+
+/// Output formats selectable by name (`--format`)
+pub struct SinkRegistry {
+    constructors: BTreeMap<String, SinkConstructor>,
+}
+
+impl SinkRegistry {
+    /// Creates a registry without any formats
+    pub fn new() -> Self {
+        SinkRegistry {
+            constructors: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a registry with the built-in `parquet`, `csv`, `compact` and `jsonl` formats
+    pub fn with_builtin_sinks() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "parquet",
+            Box::new(|config| {
+                let mut writer = ParquetWriter::new_with_options(
+                    &config.output_dir,
+                    &config.prefix,
+                    config.compression,
+                    config.buffer_size,
+                    config.options.clone(),
+                )?;
+                if let Some(max_records) = config.max_records_per_file {
+                    writer = writer.with_max_records_per_file(max_records);
+                }
+                if config.sort_on_flush {
+                    writer = writer.with_sort_on_flush();
+                }
+                if config.file_per_row_group {
+                    writer = writer.with_file_per_row_group();
+                }
+                if config.stats_sidecar {
+                    writer = writer.with_stats_sidecar();
+                }
+                if let Some(records) = config.row_group_alignment {
+                    writer = writer.with_row_group_alignment(records);
+                }
+                writer = writer.with_file_naming(config.file_naming);
+                Ok(Box::new(writer))
+            }),
+        );
+        registry.register(
+            "csv",
+            Box::new(|config| {
+                let writer =
+                    CsvWriter::new(&config.output_dir, &config.prefix, config.csv_profile)?;
+                Ok(Box::new(writer))
+            }),
+        );
+        registry.register(
+            "compact",
+            Box::new(|config| {
+                let writer = CompactBinaryWriter::new(&config.output_dir, &config.prefix)?;
+                Ok(Box::new(writer))
+            }),
+        );
+        registry.register(
+            "jsonl",
+            Box::new(|config| {
+                let writer = JsonlWriter::new(&config.output_dir, &config.prefix)?;
+                Ok(Box::new(writer))
+            }),
+        );
+        registry
+    }
+
+    /// Adds a format, replacing any format registered under the same name
+    pub fn register(&mut self, name: &str, constructor: SinkConstructor) {
+        self.constructors.insert(name.to_lowercase(), constructor);
+    }
+
+    /// Names of the registered formats, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    /// Constructs the sink registered under `name`
+    ///
+    /// # Returns
+    /// The sink, or a configuration error listing the available formats if
+    /// the name is unknown
+    pub fn create(&self, name: &str, config: &SinkConfig) -> Result<Box<dyn DataSink>> {
+        let Some(constructor) = self.constructors.get(&name.to_lowercase()) else {
+            return Err(ReceiverError::ConfigError(format!(
+                "Unknown output format '{}' (available: {})",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            ))
+            .into());
+        };
+        constructor(config)
+    }
+
+    /// Constructs the sinks for a comma-separated list of format names
+    ///
+    /// A single name gives that format's sink; several give a [`FanoutSink`]
+    /// writing every record to each of them, in the listed order.
+    pub fn create_all(
+        &self,
+        names: &str,
+        config: &SinkConfig,
+        policy: SinkFailurePolicy,
+    ) -> Result<Box<dyn DataSink>> {
+        let names = split_format_names(names);
+        if let [name] = names[..] {
+            return self.create(name, config);
+        }
+        if names.is_empty() {
+            return Err(ReceiverError::ConfigError("No output format given".to_string()).into());
+        }
+        let sinks = names
+            .iter()
+            .map(|name| Ok((name.to_lowercase(), self.create(name, config)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(FanoutSink::new(sinks, policy)))
+    }
+}
+
+/// Splits a comma-separated `--format` value into format names
+pub fn split_format_names(names: &str) -> Vec<&str> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// What a [`FanoutSink`] does when one of its sinks fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkFailurePolicy {
+    /// Pass the error on, stopping the writer like a single sink would
+    #[default]
+    Stop,
+    /// Report the error, close the failed sink and keep writing the others
+    Continue,
+}
+
+impl std::str::FromStr for SinkFailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stop" => Ok(SinkFailurePolicy::Stop),
+            "continue" => Ok(SinkFailurePolicy::Continue),
+            _ => Err(format!("Unknown sink failure policy: {}", s)),
+        }
+    }
+}
+
+/// Sink writing every record to several sinks, e.g. Parquet and CSV at once
+///
+/// Rotation, flushes and metadata go to all sinks. Size-based rotation and
+/// the run ID follow the first sink. Under [`SinkFailurePolicy::Continue`]
+/// a failing sink is dropped from the fan-out and the error is only passed
+/// on once no sink is left.
+pub struct FanoutSink {
+    // Format name and sink; None once the sink failed and was dropped
+    sinks: Vec<(String, Option<Box<dyn DataSink>>)>,
+    policy: SinkFailurePolicy,
+}
+
+impl FanoutSink {
+    /// Creates a fan-out over named sinks
+    pub fn new(sinks: Vec<(String, Box<dyn DataSink>)>, policy: SinkFailurePolicy) -> Self {
+        FanoutSink {
+            sinks: sinks
+                .into_iter()
+                .map(|(name, sink)| (name, Some(sink)))
+                .collect(),
+            policy,
+        }
+    }
+
+    /// Names of the sinks still being written
+    pub fn active(&self) -> Vec<&str> {
+        self.sinks
+            .iter()
+            .filter(|(_, sink)| sink.is_some())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    // Applies an operation to every active sink, handling failures per the policy
+    fn for_each(&mut self, mut op: impl FnMut(&mut dyn DataSink) -> Result<()>) -> Result<()> {
+        let mut last_error = None;
+        for (name, slot) in &mut self.sinks {
+            let Some(sink) = slot.as_mut() else {
+                continue;
+            };
+            let Err(e) = op(sink.as_mut()) else {
+                continue;
+            };
+            if self.policy == SinkFailurePolicy::Stop {
+                return Err(e);
+            }
+            let message = format!("Output format {} failed and was dropped: {:#}", name, e);
+            eprint_status(Health::Degraded, &message);
+            if let Some(sink) = slot.take() {
+                // Keep what the failed sink wrote so far, if it can still close
+                let _ = sink.close();
+            }
+            last_error = Some(e);
+        }
+        match last_error {
+            Some(e) if self.sinks.iter().all(|(_, sink)| sink.is_none()) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn first(&self) -> Option<&dyn DataSink> {
+        self.sinks.iter().find_map(|(_, sink)| sink.as_deref())
+    }
+}
+
+impl DataSink for FanoutSink {
+    fn add_data(&mut self, data: SensorData) -> Result<()> {
+        self.for_each(|sink| sink.add_data(data.clone()))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.for_each(|sink| sink.flush())
+    }
+
+    fn rotate_file(&mut self, output_dir: &str, prefix: &str) -> Result<Vec<String>> {
+        let mut closed = Vec::new();
+        self.for_each(|sink| {
+            closed.extend(sink.rotate_file(output_dir, prefix)?);
+            Ok(())
+        })?;
+        Ok(closed)
+    }
+
+    fn close(self: Box<Self>) -> Result<Vec<String>> {
+        let mut result = Ok(Vec::new());
+        for sink in self.sinks.into_iter().filter_map(|(_, sink)| sink) {
+            // Close every sink even if an earlier one failed
+            match (&mut result, sink.close()) {
+                (Ok(closed), Ok(paths)) => closed.extend(paths),
+                (Ok(_), Err(e)) => result = Err(e),
+                (Err(_), _) => {}
+            }
+        }
+        result
+    }
+
+    fn append_metadata(&mut self, key: &str, value: String) {
+        for sink in self.sinks.iter_mut().filter_map(|(_, sink)| sink.as_mut()) {
+            sink.append_metadata(key, value.clone());
+        }
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.first().map_or(0, |sink| sink.bytes_written())
+    }
+
+    fn output_path(&self) -> Option<&str> {
+        self.first().and_then(|sink| sink.output_path())
+    }
+
+    fn run_id(&self) -> Option<&str> {
+        self.first().and_then(|sink| sink.run_id())
+    }
+}
+
+impl Default for SinkRegistry {
+    fn default() -> Self {
+        Self::with_builtin_sinks()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    struct DummySink {
+        records: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl DataSink for DummySink {
+        fn add_data(&mut self, data: SensorData) -> Result<()> {
+            self.records.lock().unwrap().push(data.timestamp);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn rotate_file(&mut self, _output_dir: &str, _prefix: &str) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn close(self: Box<Self>) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_registered_sink_constructed_by_name() {
+        let temp_dir = tempdir().unwrap();
+        let config = SinkConfig {
+            output_dir: temp_dir.path().to_str().unwrap().to_string(),
+            prefix: "registry".to_string(),
+            compression: CompressionType::None,
+            buffer_size: 10,
+            options: WriterOptions::default(),
+            max_records_per_file: None,
+            sort_on_flush: false,
+            file_per_row_group: false,
+            row_group_alignment: None,
+            file_naming: FileNaming::Creation,
+            stats_sidecar: false,
+            csv_profile: CsvProfile::default(),
+        };
+
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let seen = records.clone();
+        let mut registry = SinkRegistry::with_builtin_sinks();
+        registry.register(
+            "dummy",
+            Box::new(move |_| {
+                Ok(Box::new(DummySink {
+                    records: seen.clone(),
+                }))
+            }),
+        );
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            vec!["compact", "csv", "dummy", "jsonl", "parquet"]
+        );
+
+        let mut sink = registry.create("Dummy", &config).unwrap();
+        for timestamp in 0..3 {
+            let data = SensorData {
+                timestamp,
+                temp: 25.0,
+                az: 1.0,
+                ..Default::default()
+            };
+            sink.add_data(data).unwrap();
+        }
+        sink.close().unwrap();
+        assert_eq!(*records.lock().unwrap(), vec![0, 1, 2]);
+
+        let err = registry.create("hdf5", &config).err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("available: compact, csv, dummy, jsonl, parquet"));
+    }
+
+    #[test]
+    fn test_two_formats_write_matching_records() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let temp_dir = tempdir().unwrap();
+        let config = SinkConfig {
+            output_dir: temp_dir.path().to_str().unwrap().to_string(),
+            prefix: "fanout".to_string(),
+            compression: CompressionType::None,
+            buffer_size: 4,
+            options: WriterOptions::default(),
+            max_records_per_file: None,
+            sort_on_flush: false,
+            file_per_row_group: false,
+            row_group_alignment: None,
+            file_naming: FileNaming::Creation,
+            stats_sidecar: false,
+            csv_profile: CsvProfile::default(),
+        };
+        let registry = SinkRegistry::with_builtin_sinks();
+        let mut sink = registry
+            .create_all("parquet, csv", &config, SinkFailurePolicy::Continue)
+            .unwrap();
+        for timestamp in 0..10 {
+            let data = SensorData {
+                timestamp,
+                temp: 25.0,
+                az: 1.0,
+                ..Default::default()
+            };
+            sink.add_data(data).unwrap();
+        }
+        assert!(sink.output_path().unwrap().ends_with(".parquet"));
+        sink.close().unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 2);
+        let with_extension = |extension: &str| {
+            files
+                .iter()
+                .find(|path| path.extension().is_some_and(|ext| ext == extension))
+                .unwrap()
+        };
+        let parquet_rows: usize = ParquetRecordBatchReaderBuilder::try_new(
+            File::open(with_extension("parquet")).unwrap(),
+        )
+        .unwrap()
+        .build()
+        .unwrap()
+        .map(|batch| batch.unwrap().num_rows())
+        .sum();
+        let csv = std::fs::read_to_string(with_extension("csv")).unwrap();
+        assert_eq!(parquet_rows, 10);
+        assert_eq!(csv.lines().count() - 1, parquet_rows);
+
+        assert!(registry
+            .create_all("parquet,hdf5", &config, SinkFailurePolicy::Stop)
+            .is_err());
+    }
+}
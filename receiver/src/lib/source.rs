@@ -0,0 +1,604 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use super::async_worker::SerialReaderWorker;
+use super::calibration::{Calibration, CalibrationEstimator};
+use super::decimate::Decimator;
+use super::replay::ReplayReader;
+use super::stats::InFlightGauge;
+use super::status::{eprint_status, print_status, Health};
+use super::types::SensorData;
+
+/// Producer of sensor records for the file writer thread
+///
+/// Implemented by the serial reader, the simulator and the replay reader, so
+/// `main` and the tests feed the writer through the same [`run`] path.
+pub trait DataSource: Send {
+    /// Hands records to the callback until `running` is cleared or the source ends
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()>;
+}
+
+impl<S: DataSource + ?Sized> DataSource for Box<S> {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        S::read_loop(*self, running, data_callback)
+    }
+}
+
+impl DataSource for SerialReaderWorker {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        self.read_serial_loop(running, data_callback)
+    }
+}
+
+/// Simulated device, generating records with the reader's settings
+pub struct SimulatedSource {
+    reader: SerialReaderWorker,
+}
+
+impl SimulatedSource {
+    /// Generates records in place of the reader's serial port
+    pub fn new(reader: SerialReaderWorker) -> Self {
+        SimulatedSource { reader }
+    }
+}
+
+impl DataSource for SimulatedSource {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        self.reader.simulate_data_loop(running, data_callback)
+    }
+}
+
+impl DataSource for ReplayReader {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        self.replay_loop(running, data_callback).map(|_| ())
+    }
+}
+
+/// Source passing on only the records a decimator keeps
+///
+/// Decimating on the reader thread thins the stream before it reaches the
+/// writer channel, so the writer only ever sees the reduced rate.
+pub struct DecimatedSource {
+    source: Box<dyn DataSource>,
+    decimator: Decimator,
+}
+
+impl DecimatedSource {
+    /// Decimates the records of another source
+    pub fn new(source: Box<dyn DataSource>, decimator: Decimator) -> Self {
+        DecimatedSource { source, decimator }
+    }
+}
+
+impl DataSource for DecimatedSource {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        let DecimatedSource {
+            source,
+            mut decimator,
+        } = *self;
+        source.read_loop(running, &mut |data| match decimator.push(data) {
+            Some(data) => data_callback(data),
+            None => Ok(()),
+        })
+    }
+}
+
+/// Source that starts with a calibration capture while the device is at rest
+///
+/// The records of the first `duration_ms` milliseconds of host time are
+/// not passed on but used to estimate the gyro and accel biases (see
+/// [`CalibrationEstimator`]). The estimate is written as a calibration file
+/// and applied to every later record, or, if configured to stop, the
+/// running flag is cleared so that the capture ends.
+pub struct CalibratingSource {
+    source: Box<dyn DataSource>,
+    duration_ms: i64,
+    output: PathBuf,
+    stop_after: bool,
+}
+
+impl CalibratingSource {
+    /// Calibrates from the first records of another source
+    ///
+    /// # Arguments
+    /// * `source` - Source of the records
+    /// * `duration_ms` - Length of the calibration capture in milliseconds
+    /// * `output` - Calibration file written once the capture is complete
+    pub fn new(source: Box<dyn DataSource>, duration_ms: i64, output: PathBuf) -> Self {
+        CalibratingSource {
+            source,
+            duration_ms,
+            output,
+            stop_after: false,
+        }
+    }
+
+    /// Ends the capture once the calibration file is written
+    pub fn with_stop_after(mut self) -> Self {
+        self.stop_after = true;
+        self
+    }
+}
+
+impl DataSource for CalibratingSource {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        let CalibratingSource {
+            source,
+            duration_ms,
+            output,
+            stop_after,
+        } = *self;
+        println!(
+            "Calibrating: keep the sensor still for {:.1} s",
+            duration_ms as f64 / 1e3
+        );
+        let mut estimator = CalibrationEstimator::new();
+        let mut start = None;
+        let mut calibration: Option<Calibration> = None;
+        // Set once the estimate is written when stopping after calibration
+        let mut done = false;
+        let stop = running.clone();
+        source.read_loop(running, &mut |mut data| {
+            if done {
+                // Records the source read before noticing the stop
+                return Ok(());
+            }
+            if let Some(calibration) = &calibration {
+                calibration.apply(&mut data);
+                return data_callback(data);
+            }
+            let start = *start.get_or_insert(data.system_timestamp);
+            if data.system_timestamp - start < duration_ms {
+                estimator.add(&data);
+                return Ok(());
+            }
+
+            let estimate = estimator.estimate().ok_or_else(|| {
+                anyhow::anyhow!("No usable records during the calibration capture")
+            })?;
+            estimate.write_file(&output)?;
+            let message = format!(
+                "Calibration from {} records written to {}: gyro bias {:?}, accel bias {:?}",
+                estimator.samples(),
+                output.display(),
+                estimate.gyro.bias,
+                estimate.accel.bias
+            );
+            print_status(Health::Healthy, &message);
+            if stop_after {
+                stop.store(false, Ordering::SeqCst);
+                done = true;
+                return Ok(());
+            }
+            calibration = Some(estimate);
+            estimate.apply(&mut data);
+            data_callback(data)
+        })
+    }
+}
+
+/// Runs a source, sending its records to the file writer thread
+///
+/// # Arguments
+/// * `source` - Serial reader, simulator, replay or any other source
+/// * `running` - Atomic flag indicating whether the process should continue running
+/// * `tx` - Sender half of the channel read by the file writer thread
+pub fn run(
+    source: impl DataSource,
+    running: Arc<AtomicBool>,
+    tx: Sender<SensorData>,
+) -> Result<()> {
+    Box::new(source).read_loop(running, &mut |data| {
+        tx.send(data)
+            .map_err(|e| anyhow::anyhow!("Channel send error: {}", e))
+    })
+}
+
+/// Records the writer channel holds unless configured otherwise
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 100_000;
+
+/// What a reader does when the writer's channel is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait until the writer makes room; no record is lost
+    #[default]
+    Block,
+    /// Discard the oldest queued record to make room and count it
+    Drop,
+}
+
+/// Sending half of the bounded channel to the file writer thread
+///
+/// A stalled writer (slow disk, long fsync) fills the channel instead of
+/// growing memory without limit; the policy decides whether the reader then
+/// waits or sheds records. With [`BackpressurePolicy::Drop`], records wait in
+/// a queue that a relay thread hands on to the writer, so the oldest queued
+/// record can be discarded to make room for the newest. Clones share the
+/// queue and the drop counter.
+#[derive(Debug)]
+pub struct BoundedSender {
+    route: Route,
+    dropped: Arc<AtomicUsize>,
+    gauge: Option<InFlightGauge>,
+}
+
+// Where a sender puts its records
+#[derive(Debug)]
+enum Route {
+    // Straight into the writer's channel, waiting for room
+    Channel(SyncSender<SensorData>),
+    // Into the queue emptied by the relay thread
+    Backlog(Arc<Backlog>),
+}
+
+// Records waiting for the relay thread, oldest first
+#[derive(Debug)]
+struct Backlog {
+    state: Mutex<BacklogState>,
+    changed: Condvar,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct BacklogState {
+    records: VecDeque<SensorData>,
+    // Senders still open; the relay stops once they are gone and it is empty
+    senders: usize,
+    writer_gone: bool,
+}
+
+impl BoundedSender {
+    /// Creates the channel to the writer and its sending half
+    ///
+    /// # Arguments
+    /// * `capacity` - Records queued before the policy applies
+    /// * `policy` - What to do with a record that finds the queue full
+    ///
+    /// # Returns
+    /// The sender and the receiving half for the writer
+    pub fn channel(capacity: usize, policy: BackpressurePolicy) -> (Self, Receiver<SensorData>) {
+        let backlog = match policy {
+            BackpressurePolicy::Block => {
+                let (tx, rx) = sync_channel(capacity);
+                return (Self::with_route(Route::Channel(tx)), rx);
+            }
+            BackpressurePolicy::Drop => Arc::new(Backlog {
+                state: Mutex::new(BacklogState {
+                    senders: 1,
+                    ..Default::default()
+                }),
+                changed: Condvar::new(),
+                capacity: capacity.max(1),
+            }),
+        };
+        // The relay holds the record being handed over, beyond the queue
+        let (tx, rx) = sync_channel(0);
+        let relayed = backlog.clone();
+        thread::spawn(move || relay(&relayed, tx));
+        (Self::with_route(Route::Backlog(backlog)), rx)
+    }
+
+    fn with_route(route: Route) -> Self {
+        BoundedSender {
+            route,
+            dropped: Arc::new(AtomicUsize::new(0)),
+            gauge: None,
+        }
+    }
+
+    /// Counts every queued record on a gauge the writer decrements
+    pub fn with_gauge(mut self, gauge: InFlightGauge) -> Self {
+        self.gauge = Some(gauge);
+        self
+    }
+
+    /// Records discarded so far because the channel was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::SeqCst)
+    }
+
+    /// Queues a record for the writer, applying the policy if the channel is full
+    ///
+    /// # Returns
+    /// An error only if the writer has gone away
+    pub fn send(&self, data: SensorData) -> Result<()> {
+        // Enter before queueing so the writer never leaves first
+        if let Some(gauge) = &self.gauge {
+            gauge.enter();
+        }
+        let queued = self.queue(data);
+        if queued.is_err() {
+            if let Some(gauge) = &self.gauge {
+                gauge.leave();
+            }
+        }
+        queued
+    }
+
+    // Sends per the route, discarding the oldest queued record if full
+    fn queue(&self, data: SensorData) -> Result<()> {
+        let disconnected = || anyhow::anyhow!("Channel send error: the writer has stopped");
+        let backlog = match &self.route {
+            Route::Channel(tx) => return tx.send(data).map_err(|_| disconnected()),
+            Route::Backlog(backlog) => backlog,
+        };
+        let mut state = backlog.state.lock().map_err(|_| disconnected())?;
+        if state.writer_gone {
+            return Err(disconnected());
+        }
+        if state.records.len() >= backlog.capacity {
+            state.records.pop_front();
+            // The writer never sees the discarded record
+            if let Some(gauge) = &self.gauge {
+                gauge.leave();
+            }
+            let dropped = self.dropped.fetch_add(1, Ordering::SeqCst) + 1;
+            // Report the first drop of a stall, then every 1000th
+            if dropped == 1 || dropped.is_multiple_of(1000) {
+                let warning = format!(
+                    "Writer channel full, dropped {} oldest records so far",
+                    dropped
+                );
+                eprint_status(Health::Failing, &warning);
+            }
+        }
+        state.records.push_back(data);
+        backlog.changed.notify_all();
+        Ok(())
+    }
+}
+
+impl Clone for BoundedSender {
+    fn clone(&self) -> Self {
+        let route = match &self.route {
+            Route::Channel(tx) => Route::Channel(tx.clone()),
+            Route::Backlog(backlog) => {
+                if let Ok(mut state) = backlog.state.lock() {
+                    state.senders += 1;
+                }
+                Route::Backlog(backlog.clone())
+            }
+        };
+        BoundedSender {
+            route,
+            dropped: self.dropped.clone(),
+            gauge: self.gauge.clone(),
+        }
+    }
+}
+
+impl Drop for BoundedSender {
+    fn drop(&mut self) {
+        if let Route::Backlog(backlog) = &self.route {
+            if let Ok(mut state) = backlog.state.lock() {
+                state.senders -= 1;
+            }
+            backlog.changed.notify_all();
+        }
+    }
+}
+
+// Hands queued records to the writer in order, until the senders are gone
+// and the queue is empty or the writer has stopped
+fn relay(backlog: &Backlog, tx: SyncSender<SensorData>) {
+    loop {
+        let Ok(mut state) = backlog.state.lock() else {
+            return;
+        };
+        let data = loop {
+            if let Some(data) = state.records.pop_front() {
+                break data;
+            }
+            if state.senders == 0 {
+                return;
+            }
+            state = match backlog.changed.wait(state) {
+                Ok(state) => state,
+                Err(_) => return,
+            };
+        };
+        drop(state);
+        // Lets anyone waiting for the queue to drain see the record leave it
+        backlog.changed.notify_all();
+        if tx.send(data).is_err() {
+            if let Ok(mut state) = backlog.state.lock() {
+                state.writer_gone = true;
+                state.records.clear();
+            }
+            return;
+        }
+    }
+}
+
+/// Runs a source, sending its records over a bounded channel to the writer
+///
+/// Like [`run`], with a full channel handled per the sender's policy. The
+/// number of dropped records, if any, is reported when the source ends.
+pub fn run_bounded(
+    source: impl DataSource,
+    running: Arc<AtomicBool>,
+    tx: BoundedSender,
+) -> Result<()> {
+    let result = Box::new(source).read_loop(running, &mut |data| tx.send(data));
+    if tx.dropped() > 0 {
+        let message = format!("Records dropped on a full writer channel: {}", tx.dropped());
+        eprint_status(Health::Failing, &message);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn record(timestamp: u32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_full_channel_per_policy() {
+        // Drop: the oldest queued records make room for new ones
+        let (sender, rx) = BoundedSender::channel(2, BackpressurePolicy::Drop);
+        sender.send(record(0)).unwrap();
+        // The relay takes the first record out of the queue and waits for the writer
+        let Route::Backlog(backlog) = &sender.route else {
+            unreachable!("the drop policy queues records in a backlog");
+        };
+        drop(
+            backlog
+                .changed
+                .wait_while(backlog.state.lock().unwrap(), |state| {
+                    !state.records.is_empty()
+                })
+                .unwrap(),
+        );
+        for timestamp in 1..5 {
+            sender.send(record(timestamp)).unwrap();
+        }
+        assert_eq!(sender.dropped(), 2);
+        drop(sender);
+        let received: Vec<u32> = rx.iter().map(|data| data.timestamp).collect();
+        assert_eq!(received, vec![0, 3, 4]);
+
+        // Block: the reader waits for room and nothing is lost
+        let (sender, rx) = BoundedSender::channel(2, BackpressurePolicy::Block);
+        let reader = thread::spawn(move || {
+            for timestamp in 0..5 {
+                sender.send(record(timestamp)).unwrap();
+            }
+            sender.dropped()
+        });
+        thread::sleep(Duration::from_millis(100));
+        assert!(
+            !reader.is_finished(),
+            "reader should block on a full channel"
+        );
+        let received: Vec<u32> = rx.iter().map(|data| data.timestamp).collect();
+        assert_eq!(received, vec![0, 1, 2, 3, 4]);
+        assert_eq!(reader.join().unwrap(), 0);
+    }
+
+    // Source handing out fixed records regardless of the running flag
+    struct ScriptedSource {
+        records: Vec<SensorData>,
+        // Called after each record with its index
+        after: Box<dyn FnMut(usize) + Send>,
+    }
+
+    impl DataSource for ScriptedSource {
+        fn read_loop(
+            mut self: Box<Self>,
+            _running: Arc<AtomicBool>,
+            data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+        ) -> Result<()> {
+            for (index, data) in std::mem::take(&mut self.records).into_iter().enumerate() {
+                data_callback(data)?;
+                (self.after)(index);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_calibration_stop_writes_estimate_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("calibration.txt");
+        let records = (0..10)
+            .map(|i| SensorData {
+                system_timestamp: i * 100,
+                ..record(i as u32)
+            })
+            .collect();
+        // The file is removed right after the first record past the window
+        let written = output.clone();
+        let after = Box::new(move |index| {
+            if index == 3 {
+                std::fs::remove_file(&written).unwrap();
+            }
+        });
+        let source = CalibratingSource::new(
+            Box::new(ScriptedSource { records, after }),
+            300,
+            output.clone(),
+        )
+        .with_stop_after();
+
+        let running = Arc::new(AtomicBool::new(true));
+        let mut forwarded = 0;
+        Box::new(source)
+            .read_loop(running.clone(), &mut |_| {
+                forwarded += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert!(!running.load(Ordering::SeqCst));
+        assert_eq!(forwarded, 0);
+        // Later records neither estimate again nor rewrite the file
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_decimated_source_averages_before_sending() {
+        use crate::DecimateMode;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.txt");
+        let mut file = std::fs::File::create(&path).unwrap();
+        for i in 0..100u32 {
+            // gx counts up by one per record
+            let gx = (i as f32).to_bits();
+            writeln!(file, "{:08X},41C80000,{:08X},0,0,0,0,3F800000", i, gx).unwrap();
+        }
+
+        let decimator = Decimator::new(10).with_mode(DecimateMode::Average);
+        let source = DecimatedSource::new(Box::new(ReplayReader::new(&path)), decimator);
+        let (tx, rx) = std::sync::mpsc::channel();
+        run(source, Arc::new(AtomicBool::new(true)), tx).unwrap();
+
+        let records: Vec<SensorData> = rx.try_iter().collect();
+        assert_eq!(records.len(), 10);
+        assert_eq!(records[3].timestamp, 30);
+        assert_eq!(records[3].gx, 34.5);
+    }
+}
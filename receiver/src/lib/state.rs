@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs::{rename, write};
+use std::path::Path;
+
+use super::error::ReceiverError;
+use super::run_id::generate_run_id;
+use super::serial::crc16;
+use super::status::{eprint_status, Health};
+
+/// First word of a state file
+pub const STATE_MAGIC: &str = "receiver-state";
+
+/// Layout version of the state file; files of other versions are ignored
+pub const STATE_VERSION: u32 = 1;
+
+/// Capture session carried across restarts of the receiver (`--state-file`)
+///
+/// The file starts with a header line `receiver-state <version> <crc>`,
+/// where the CRC16 (hex) covers the JSON body on the following line. A file
+/// that is corrupt or written by another version is ignored with a warning
+/// and a new session is started in its place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionState {
+    /// Run ID shared by every file of the session
+    pub run_id: String,
+    /// When the session first started (RFC 3339)
+    pub started_at: String,
+    /// Number of times the receiver resumed the session
+    pub restarts: u64,
+}
+
+impl SessionState {
+    /// Creates a state for a new session
+    pub fn new() -> Self {
+        SessionState {
+            run_id: generate_run_id(),
+            started_at: Utc::now().to_rfc3339(),
+            restarts: 0,
+        }
+    }
+
+    /// Serializes the state with its version header and checksum
+    pub fn encode(&self) -> String {
+        let body = serde_json::json!({
+            "run_id": self.run_id,
+            "started_at": self.started_at,
+            "restarts": self.restarts,
+        })
+        .to_string();
+        format!(
+            "{} {} {:04x}\n{}\n",
+            STATE_MAGIC,
+            STATE_VERSION,
+            crc16(body.as_bytes()),
+            body
+        )
+    }
+
+    /// Parses a serialized state
+    ///
+    /// # Returns
+    /// The state, or a parse error if the header, version, checksum or body
+    /// is not valid
+    pub fn decode(text: &str) -> Result<SessionState> {
+        let invalid = |message: String| -> anyhow::Error {
+            ReceiverError::ParseError(format!("Invalid state file: {}", message)).into()
+        };
+        let (header, body) = text
+            .split_once('\n')
+            .ok_or_else(|| invalid("missing header".to_string()))?;
+        let body = body.trim_end_matches('\n');
+
+        let fields: Vec<&str> = header.split(' ').collect();
+        let [magic, version, crc] = fields[..] else {
+            return Err(invalid(format!("malformed header '{}'", header)));
+        };
+        if magic != STATE_MAGIC {
+            return Err(invalid(format!("malformed header '{}'", header)));
+        }
+        if version != STATE_VERSION.to_string() {
+            return Err(invalid(format!(
+                "version {} is not supported (expected {})",
+                version, STATE_VERSION
+            )));
+        }
+        let expected = u16::from_str_radix(crc, 16)
+            .map_err(|_| invalid(format!("malformed checksum '{}'", crc)))?;
+        let actual = crc16(body.as_bytes());
+        if actual != expected {
+            return Err(invalid(format!(
+                "checksum mismatch: header says {:04x}, computed {:04x}",
+                expected, actual
+            )));
+        }
+
+        let value: serde_json::Value =
+            serde_json::from_str(body).map_err(|e| invalid(e.to_string()))?;
+        let text_field = |name: &str| {
+            value[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("missing field '{}'", name)))
+        };
+        Ok(SessionState {
+            run_id: text_field("run_id")?,
+            started_at: text_field("started_at")?,
+            restarts: value["restarts"]
+                .as_u64()
+                .ok_or_else(|| invalid("missing field 'restarts'".to_string()))?,
+        })
+    }
+
+    /// Writes the state, replacing the file in one step
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let temp = path.with_extension("tmp");
+        write(&temp, self.encode())
+            .with_context(|| format!("Failed to write state file {}", temp.display()))?;
+        rename(&temp, path)
+            .with_context(|| format!("Failed to replace state file {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Reads the state file, if there is a valid one
+    ///
+    /// A missing file is not reported; an unreadable, corrupt or
+    /// incompatible one is reported as a warning.
+    pub fn load(path: &Path) -> Option<SessionState> {
+        if !path.exists() {
+            return None;
+        }
+        let loaded = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read state file {}", path.display()))
+            .and_then(|text| Self::decode(&text));
+        match loaded {
+            Ok(state) => Some(state),
+            Err(e) => {
+                let warning = format!("Warning: {:#}; starting a new session", e);
+                eprint_status(Health::Degraded, &warning);
+                None
+            }
+        }
+    }
+
+    /// Resumes the session of a valid state file, or starts a new one
+    ///
+    /// The resulting state is written back to the file.
+    pub fn resume_or_start(path: &Path) -> Result<SessionState> {
+        let state = match Self::load(path) {
+            Some(mut state) => {
+                state.restarts += 1;
+                state
+            }
+            None => Self::new(),
+        };
+        state.save(path)?;
+        Ok(state)
+    }
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_corrupt_state_file_starts_new_session() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("receiver.state");
+
+        let first = SessionState::resume_or_start(&path).unwrap();
+        let resumed = SessionState::resume_or_start(&path).unwrap();
+        assert_eq!(resumed.run_id, first.run_id);
+        assert_eq!(resumed.restarts, 1);
+
+        // Flip a byte of the body so the checksum no longer matches
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 3;
+        bytes[last] ^= 0x01;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(SessionState::load(&path).is_none());
+
+        let fresh = SessionState::resume_or_start(&path).unwrap();
+        assert_ne!(fresh.run_id, first.run_id);
+        assert_eq!(fresh.restarts, 0);
+        assert_eq!(SessionState::load(&path), Some(fresh));
+
+        // Other versions and garbage are ignored the same way
+        let other_version = SessionState::new().encode().replacen(" 1 ", " 2 ", 1);
+        assert!(SessionState::decode(&other_version).is_err());
+        std::fs::write(&path, b"\x00\xffnot a state file").unwrap();
+        assert!(SessionState::load(&path).is_none());
+    }
+}
@@ -0,0 +1,673 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::types::SensorData;
+
+/// Streaming quantile estimator using the P² algorithm
+///
+/// Tracks a single quantile in constant memory by maintaining five markers
+/// whose heights are adjusted with piecewise-parabolic interpolation
+/// (Jain & Chlamtac, 1985). Accuracy is typically within a few percent of the
+/// exact quantile for smooth distributions.
+#[derive(Debug, Clone)]
+pub struct P2Quantile {
+    p: f64,
+    count: usize,
+    // Marker heights
+    q: [f64; 5],
+    // Actual marker positions (1-based)
+    n: [f64; 5],
+    // Desired marker positions
+    desired: [f64; 5],
+    // Desired position increments per observation
+    increment: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Creates an estimator for the quantile `p` (0 < p < 1)
+    pub fn new(p: f64) -> Self {
+        P2Quantile {
+            p,
+            count: 0,
+            q: [0.0; 5],
+            n: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increment: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    /// Adds an observation
+    pub fn observe(&mut self, x: f64) {
+        if self.count < 5 {
+            self.q[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.q.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Find the cell containing x, extending the extremes if needed
+        let k = if x < self.q[0] {
+            self.q[0] = x;
+            0
+        } else if x >= self.q[4] {
+            self.q[4] = x;
+            3
+        } else {
+            (1..5).find(|&i| x < self.q[i]).map_or(3, |i| i - 1)
+        };
+
+        for i in k + 1..5 {
+            self.n[i] += 1.0;
+        }
+        for i in 0..5 {
+            self.desired[i] += self.increment[i];
+        }
+
+        // Adjust the middle markers towards their desired positions
+        for i in 1..4 {
+            let d = self.desired[i] - self.n[i];
+            if (d >= 1.0 && self.n[i + 1] - self.n[i] > 1.0)
+                || (d <= -1.0 && self.n[i - 1] - self.n[i] < -1.0)
+            {
+                let d = d.signum();
+                let candidate = self.parabolic(i, d);
+                self.q[i] = if self.q[i - 1] < candidate && candidate < self.q[i + 1] {
+                    candidate
+                } else {
+                    self.linear(i, d)
+                };
+                self.n[i] += d;
+            }
+        }
+    }
+
+    /// Current estimate of the quantile, if any observations were made
+    pub fn value(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            1..=4 => {
+                let mut initial = self.q[..self.count].to_vec();
+                initial.sort_by(f64::total_cmp);
+                let rank = (self.p * (self.count - 1) as f64).round() as usize;
+                Some(initial[rank])
+            }
+            _ => Some(self.q[2]),
+        }
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (q, n) = (&self.q, &self.n);
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        self.q[i] + d * (self.q[j] - self.q[i]) / (self.n[j] - self.n[i])
+    }
+}
+
+/// Running mean and variance using Welford's algorithm
+#[derive(Debug, Clone, Default)]
+pub struct RunningStats {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningStats {
+    /// Adds an observation
+    pub fn observe(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of the observations (0 if empty)
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Sample variance of the observations (0 with fewer than two)
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+}
+
+/// End-of-capture summary of the accelerometer magnitude
+///
+/// Updated per record in constant memory and printed when the writer shuts down.
+#[derive(Debug, Clone)]
+pub struct CaptureSummary {
+    accel: RunningStats,
+    p50: P2Quantile,
+    p95: P2Quantile,
+    p99: P2Quantile,
+}
+
+impl CaptureSummary {
+    /// Creates an empty summary
+    pub fn new() -> Self {
+        CaptureSummary {
+            accel: RunningStats::default(),
+            p50: P2Quantile::new(0.50),
+            p95: P2Quantile::new(0.95),
+            p99: P2Quantile::new(0.99),
+        }
+    }
+
+    /// Adds a record to the summary
+    ///
+    /// Records with an accel field that failed to decode are skipped.
+    pub fn update(&mut self, data: &SensorData) {
+        if (4..7).any(|index| data.is_null(index)) {
+            return;
+        }
+        let magnitude = data.accel_magnitude() as f64;
+        self.accel.observe(magnitude);
+        self.p50.observe(magnitude);
+        self.p95.observe(magnitude);
+        self.p99.observe(magnitude);
+    }
+
+    /// Number of records summarized
+    pub fn count(&self) -> u64 {
+        self.accel.count()
+    }
+
+    /// Approximate (p50, p95, p99) of the accel magnitude
+    pub fn accel_quantiles(&self) -> Option<(f64, f64, f64)> {
+        Some((self.p50.value()?, self.p95.value()?, self.p99.value()?))
+    }
+
+    /// Formats the summary for the shutdown report
+    pub fn report(&self) -> String {
+        let Some((p50, p95, p99)) = self.accel_quantiles() else {
+            return "Capture summary: no records".to_string();
+        };
+        format!(
+            "Capture summary: {} records; accel magnitude mean {:.4}, std {:.4}, \
+             p50 {:.4}, p95 {:.4}, p99 {:.4}",
+            self.count(),
+            self.accel.mean(),
+            self.accel.variance().sqrt(),
+            p50,
+            p95,
+            p99
+        )
+    }
+}
+
+impl Default for CaptureSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Minimum, maximum and mean of one sensor channel
+#[derive(Debug, Clone, Default)]
+struct ChannelRange {
+    range: Option<(f64, f64)>,
+    stats: RunningStats,
+}
+
+impl ChannelRange {
+    fn observe(&mut self, x: f64) {
+        self.range = Some(self.range.map_or((x, x), |(lo, hi)| (lo.min(x), hi.max(x))));
+        self.stats.observe(x);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self.range {
+            Some((min, max)) => serde_json::json!({
+                "min": min,
+                "max": max,
+                "mean": self.stats.mean(),
+            }),
+            None => serde_json::Value::Null,
+        }
+    }
+}
+
+/// Per-channel summary of the records written to one file
+///
+/// Updated as records are written, so the summary of a file is available
+/// when it closes without reading it back. Values that failed to decode or
+/// are not finite are left out of their channel.
+#[derive(Debug, Clone, Default)]
+pub struct FileStats {
+    count: u64,
+    channels: [ChannelRange; 7],
+    // Earliest and latest host time (ms)
+    span_ms: Option<(i64, i64)>,
+    // First and last sensor counter, in write order
+    counters: Option<(u32, u32)>,
+}
+
+impl FileStats {
+    /// Creates an empty summary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record to the summary
+    pub fn update(&mut self, data: &SensorData) {
+        self.count += 1;
+        let values = [
+            data.temp, data.gx, data.gy, data.gz, data.ax, data.ay, data.az,
+        ];
+        for (index, (channel, value)) in self.channels.iter_mut().zip(values).enumerate() {
+            if !data.is_null(index) && value.is_finite() {
+                channel.observe(value as f64);
+            }
+        }
+        let ms = data.system_timestamp;
+        self.span_ms = Some(
+            self.span_ms
+                .map_or((ms, ms), |(lo, hi)| (lo.min(ms), hi.max(ms))),
+        );
+        let counter = data.timestamp;
+        self.counters = Some(
+            self.counters
+                .map_or((counter, counter), |(first, _)| (first, counter)),
+        );
+    }
+
+    /// Number of records summarized
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Formats the summary as the JSON body of a `.stats.json` sidecar
+    ///
+    /// # Arguments
+    /// * `file` - Name of the file summarized
+    /// * `run_id` - Run ID of the capture the file belongs to, if any
+    pub fn to_json(&self, file: &str, run_id: Option<&str>) -> serde_json::Value {
+        let channels: serde_json::Map<String, serde_json::Value> = SensorData::FLOAT_FIELDS
+            .iter()
+            .zip(&self.channels)
+            .map(|(name, channel)| (name.to_string(), channel.to_json()))
+            .collect();
+        serde_json::json!({
+            "file": file,
+            "run_id": run_id,
+            "count": self.count,
+            "start_ms": self.span_ms.map(|(start, _)| start),
+            "end_ms": self.span_ms.map(|(_, end)| end),
+            "duration_ms": self.span_ms.map(|(start, end)| end - start),
+            "first_timestamp": self.counters.map(|(first, _)| first),
+            "last_timestamp": self.counters.map(|(_, last)| last),
+            "channels": channels,
+        })
+    }
+}
+
+/// Count of records between the reader and the output sink, with its peak
+///
+/// The reader calls [`enter`](Self::enter) before queueing a record and the
+/// writer calls [`leave`](Self::leave) once it has processed the record, so
+/// the count covers the channel and the record in hand. Records the writer
+/// holds back afterwards (burst detection, rate-change confirmation) and
+/// those buffered by the sinks are not counted. Clones share the same
+/// counters; the count is read within the crate, for the periodic readout
+/// and by tests.
+#[derive(Debug, Clone, Default)]
+pub struct InFlightGauge {
+    current: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl InFlightGauge {
+    /// Creates a gauge with no records in flight
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a record entering the pipeline
+    pub fn enter(&self) {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+    }
+
+    /// Counts a record leaving the pipeline
+    pub fn leave(&self) {
+        // Saturate rather than wrap if a record leaves without having entered
+        let _ = self
+            .current
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
+    /// Records currently in flight
+    pub(crate) fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    /// Largest number of records in flight at any time
+    #[cfg(test)]
+    pub(crate) fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// Counts of what the serial reader received and decoded
+///
+/// Updated by the reader thread as it goes; clones share the same counters,
+/// so the totals can be read from another thread or after the reader ended.
+#[derive(Debug, Clone, Default)]
+pub struct ReadCounters {
+    bytes_read: Arc<AtomicU64>,
+    records: Arc<AtomicU64>,
+    parse_errors: Arc<AtomicU64>,
+}
+
+impl ReadCounters {
+    /// Creates counters starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts bytes read from the port
+    pub fn add_bytes(&self, count: usize) {
+        self.bytes_read.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Counts decoded records
+    pub fn add_records(&self, count: usize) {
+        self.records.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Counts lines or frames that could not be decoded
+    pub fn add_parse_errors(&self, count: usize) {
+        self.parse_errors.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes read from the port
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Records decoded successfully
+    pub fn records(&self) -> u64 {
+        self.records.load(Ordering::Relaxed)
+    }
+
+    /// Lines or frames that could not be decoded
+    pub fn parse_errors(&self) -> u64 {
+        self.parse_errors.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of received lines or frames that could not be decoded
+    pub fn loss_ratio(&self) -> f64 {
+        let errors = self.parse_errors() as f64;
+        let total = self.records() as f64 + errors;
+        if total == 0.0 {
+            0.0
+        } else {
+            errors / total
+        }
+    }
+
+    /// Formats the counters for the shutdown report
+    pub fn report(&self) -> String {
+        format!(
+            "Read summary: parsed {}, {} parse errors, {:.3}% loss, {} bytes read",
+            self.records(),
+            self.parse_errors(),
+            self.loss_ratio() * 100.0,
+            self.bytes_read()
+        )
+    }
+}
+
+// Buckets per window; bounds the smoothed rate's error to about 1/RATE_BUCKETS
+const RATE_BUCKETS: u32 = 50;
+
+/// Record rate over a rolling time window, alongside the cumulative average
+///
+/// Records are counted in buckets a fiftieth of the window wide, so memory
+/// stays constant at any rate. The windowed rate covers the buckets started
+/// within the last window; before a full window has passed it covers the time
+/// since the first record.
+#[derive(Debug, Clone)]
+pub struct RateWindow {
+    window: Duration,
+    bucket_width: Duration,
+    // Start time and record count of each bucket, oldest first
+    buckets: VecDeque<(Instant, u64)>,
+    first: Option<Instant>,
+    total: u64,
+}
+
+impl RateWindow {
+    /// Creates a counter averaging over the given window
+    pub fn new(window: Duration) -> Self {
+        RateWindow {
+            window,
+            bucket_width: window / RATE_BUCKETS,
+            buckets: VecDeque::new(),
+            first: None,
+            total: 0,
+        }
+    }
+
+    /// Length of the rolling window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Counts one record received at `now`
+    pub fn record(&mut self, now: Instant) {
+        self.first.get_or_insert(now);
+        self.total += 1;
+        match self.buckets.back_mut() {
+            Some((start, count)) if now.saturating_duration_since(*start) < self.bucket_width => {
+                *count += 1
+            }
+            _ => self.buckets.push_back((now, 1)),
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(start, _)| now.saturating_duration_since(*start) >= self.window)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Records counted since the first one
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Records per second over the last window
+    pub fn windowed_rate(&self, now: Instant) -> f64 {
+        let Some(first) = self.first else {
+            return 0.0;
+        };
+        let span = now.saturating_duration_since(first).min(self.window);
+        if span.is_zero() {
+            return 0.0;
+        }
+        let count: u64 = self
+            .buckets
+            .iter()
+            .filter(|(start, _)| now.saturating_duration_since(*start) < self.window)
+            .map(|(_, count)| count)
+            .sum();
+        count as f64 / span.as_secs_f64()
+    }
+
+    /// Records per second since the first record
+    pub fn cumulative_rate(&self, now: Instant) -> f64 {
+        let Some(first) = self.first else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(first);
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        self.total as f64 / elapsed.as_secs_f64()
+    }
+
+    /// One-line report of both rates
+    pub fn report(&self, now: Instant) -> String {
+        format!(
+            "Throughput: {:.1} records/s over the last {} s, {:.1} records/s overall",
+            self.windowed_rate(now),
+            self.window.as_secs_f64(),
+            self.cumulative_rate(now)
+        )
+    }
+}
+
+/// Largest time between consecutive batches arriving at the writer
+///
+/// A long gap means the writer thread was waiting for data: either the
+/// device paused or the host stalled before the records were queued. The
+/// largest gap is kept for the whole capture and, separately, for the
+/// current reporting period.
+#[derive(Debug, Clone, Default)]
+pub struct GapTracker {
+    last: Option<Instant>,
+    max_gap: Duration,
+    period_max_gap: Duration,
+}
+
+impl GapTracker {
+    /// Creates a tracker that has not seen any batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes a batch arriving at `now`
+    pub fn observe(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            let gap = now.saturating_duration_since(last);
+            self.max_gap = self.max_gap.max(gap);
+            self.period_max_gap = self.period_max_gap.max(gap);
+        }
+        self.last = Some(now);
+    }
+
+    /// Largest gap since the first batch
+    pub fn max_gap(&self) -> Duration {
+        self.max_gap
+    }
+
+    /// Largest gap since the previous call, starting a new period
+    pub fn take_period_max(&mut self) -> Duration {
+        std::mem::take(&mut self.period_max_gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic xorshift generator producing values in [0, 1)
+    fn uniform_samples(count: usize) -> Vec<f64> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 11) as f64 / (1u64 << 53) as f64
+            })
+            .collect()
+    }
+
+    fn exact_quantile(sorted: &[f64], p: f64) -> f64 {
+        sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+    }
+
+    #[test]
+    fn test_p2_matches_exact_quantiles() {
+        // Squaring skews the distribution so the quantiles are not evenly spaced
+        let samples: Vec<f64> = uniform_samples(20_000).iter().map(|x| x * x).collect();
+        let mut sorted = samples.clone();
+        sorted.sort_by(f64::total_cmp);
+
+        for p in [0.5, 0.95, 0.99] {
+            let mut estimator = P2Quantile::new(p);
+            for &x in &samples {
+                estimator.observe(x);
+            }
+            let estimate = estimator.value().unwrap();
+            let exact = exact_quantile(&sorted, p);
+            assert!(
+                (estimate - exact).abs() < 0.01,
+                "p{}: estimate {} vs exact {}",
+                p * 100.0,
+                estimate,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn test_p2_few_samples() {
+        let mut estimator = P2Quantile::new(0.5);
+        assert_eq!(estimator.value(), None);
+        for x in [3.0, 1.0, 2.0] {
+            estimator.observe(x);
+        }
+        assert_eq!(estimator.value(), Some(2.0));
+    }
+
+    #[test]
+    fn test_running_stats() {
+        let mut stats = RunningStats::default();
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.observe(x);
+        }
+        assert_eq!(stats.mean(), 5.0);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_windowed_rate_follows_recent_records() {
+        let start = Instant::now();
+        let mut rate = RateWindow::new(Duration::from_secs(5));
+        assert_eq!(rate.windowed_rate(start), 0.0);
+
+        // 100 records/s for 10 s, then 1000 records/s for 5 s
+        let mut times: Vec<Duration> = (0..1_000).map(|i| Duration::from_millis(i * 10)).collect();
+        times.extend((0..5_000).map(|i| Duration::from_secs(10) + Duration::from_millis(i)));
+        for offset in times {
+            rate.record(start + offset);
+        }
+
+        let now = start + Duration::from_secs(15);
+        let windowed = rate.windowed_rate(now);
+        assert!(
+            (windowed - 1_000.0).abs() < 25.0,
+            "windowed rate {}",
+            windowed
+        );
+        let cumulative = rate.cumulative_rate(now);
+        assert!(
+            (cumulative - 400.0).abs() < 1.0,
+            "cumulative rate {}",
+            cumulative
+        );
+        assert_eq!(rate.total(), 6_000);
+    }
+}
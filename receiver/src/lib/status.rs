@@ -0,0 +1,89 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by --no-color; overrides terminal detection
+static COLOR_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Condition reported by a status message, shown as its color on a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// Data is flowing normally (green)
+    Healthy,
+    /// Data is flowing with gaps or recoverable problems (yellow)
+    Degraded,
+    /// Data is being lost to errors (red)
+    Failing,
+}
+
+impl Health {
+    fn ansi_color(&self) -> &'static str {
+        match self {
+            Health::Healthy => "\x1b[32m",
+            Health::Degraded => "\x1b[33m",
+            Health::Failing => "\x1b[31m",
+        }
+    }
+}
+
+/// Turns off colored status output for the rest of the process
+pub fn disable_color() {
+    COLOR_DISABLED.store(true, Ordering::SeqCst);
+}
+
+// Colors are used on terminals unless disabled by --no-color or NO_COLOR
+fn color_enabled(terminal: bool, disabled: bool) -> bool {
+    terminal && !disabled && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn color_for(terminal: bool) -> bool {
+    color_enabled(terminal, COLOR_DISABLED.load(Ordering::SeqCst))
+}
+
+/// Formats a status message, wrapped in the health's color if `color` is set
+pub fn styled(health: Health, message: &str, color: bool) -> String {
+    if color {
+        format!("{}{}\x1b[0m", health.ansi_color(), message)
+    } else {
+        message.to_string()
+    }
+}
+
+/// Formats a status message for stdout, colored if stdout is a terminal
+pub fn status(health: Health, message: &str) -> String {
+    styled(health, message, color_for(std::io::stdout().is_terminal()))
+}
+
+/// Formats a status message for stderr, colored if stderr is a terminal
+pub fn status_err(health: Health, message: &str) -> String {
+    styled(health, message, color_for(std::io::stderr().is_terminal()))
+}
+
+/// Prints a status message to stdout, see [`status`]
+pub fn print_status(health: Health, message: &str) {
+    println!("{}", status(health, message));
+}
+
+/// Prints a status message to stderr, see [`status_err`]
+pub fn eprint_status(health: Health, message: &str) {
+    eprintln!("{}", status_err(health, message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_color_has_no_escape_sequences() {
+        for health in [Health::Healthy, Health::Degraded, Health::Failing] {
+            assert!(styled(health, "Wrote 100 records", true).contains('\x1b'));
+            assert_eq!(
+                styled(health, "Wrote 100 records", false),
+                "Wrote 100 records"
+            );
+        }
+
+        // --no-color wins over terminal detection
+        assert!(!color_enabled(true, true));
+        assert!(!color_enabled(false, false));
+    }
+}
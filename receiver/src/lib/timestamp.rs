@@ -0,0 +1,404 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::path::Path;
+
+use super::error::ReceiverError;
+use super::serial::monotonic_timestamp;
+use super::types::SensorData;
+
+/// How the host timestamps of records read from the serial port are derived
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// Host clock at the time the record was parsed
+    #[default]
+    Wallclock,
+    /// Spread evenly between the end of the previous read and the end of the
+    /// read that returned the record, see [`ReadInterpolator`]
+    Interpolated,
+    /// Sensor counter plus a fitted offset to the host clock, see [`DeviceClock`]
+    Device,
+}
+
+impl std::str::FromStr for TimestampMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wallclock" | "wall" => Ok(TimestampMode::Wallclock),
+            "interpolated" => Ok(TimestampMode::Interpolated),
+            "device" => Ok(TimestampMode::Device),
+            _ => Err(format!("Unknown timestamp mode: {}", s)),
+        }
+    }
+}
+
+/// Spreads the records of each read over the time since the previous read
+///
+/// A whole read is parsed at once, so its records get nearly identical host
+/// stamps although the sensor sampled them over the whole interval since the
+/// previous read. Here the `i`-th of `n` records gets the time `i / n` of the
+/// way from the end of the previous read to the end of its own, for both
+/// `system_timestamp` and `monotonic_timestamp`. Reads that return nothing
+/// still end an interval. The first read after a (re)connect keeps its parse
+/// time stamps.
+#[derive(Debug, Clone, Default)]
+pub struct ReadInterpolator {
+    // Host time (ms, ns) at which the previous read ended
+    boundary: Option<(i64, i64)>,
+}
+
+impl ReadInterpolator {
+    /// Creates an interpolator waiting for its first read
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the previous read, e.g. after the port was reopened
+    pub fn reset(&mut self) {
+        self.boundary = None;
+    }
+
+    /// Restamps the records of a read that ended just now
+    pub fn spread(&mut self, records: &mut [SensorData]) {
+        self.spread_at(
+            records,
+            Utc::now().timestamp_millis(),
+            monotonic_timestamp(),
+        );
+    }
+
+    /// Restamps the records of a read that ended at the given host time
+    ///
+    /// # Arguments
+    /// * `records` - All records of the read, in order
+    /// * `system_ms` - Wall clock at the end of the read in milliseconds
+    /// * `monotonic_ns` - Monotonic clock at the end of the read in nanoseconds
+    pub fn spread_at(&mut self, records: &mut [SensorData], system_ms: i64, monotonic_ns: i64) {
+        let Some((start_ms, start_ns)) = self.boundary.replace((system_ms, monotonic_ns)) else {
+            return;
+        };
+        let n = records.len() as i64;
+        for (i, data) in records.iter_mut().enumerate() {
+            let k = i as i64 + 1;
+            data.system_timestamp = start_ms + (system_ms - start_ms) * k / n;
+            data.monotonic_timestamp = start_ns + (monotonic_ns - start_ns) * k / n;
+        }
+    }
+}
+
+/// Derives host timestamps from the sensor counter
+///
+/// Each record's time is the counter's elapsed time since the first record
+/// plus an offset to the host clock. The offset is fitted as the smallest
+/// difference between a record's host stamp and its counter time seen so
+/// far: transfer and parsing only ever delay a record, so the earliest
+/// arrival is the closest to when it was sampled. Records therefore keep the
+/// exact spacing of the counter, apart from a step whenever a record arrives
+/// earlier than all before it. The elapsed time is taken from the extended
+/// counter (`extended_timestamp`, see [`super::TickExtender`]), so rollovers
+/// of a counter of any width are handled; a drift between the two clocks is
+/// not corrected.
+#[derive(Debug, Clone)]
+pub struct DeviceClock {
+    counter_hz: f64,
+    // Extended counter of the first record
+    first_counter: Option<i64>,
+    // Fitted offsets of the wall and monotonic clocks, in nanoseconds
+    system_offset_ns: i64,
+    monotonic_offset_ns: i64,
+}
+
+impl DeviceClock {
+    /// Creates a clock for a counter advancing at `counter_hz`
+    pub fn new(counter_hz: f64) -> Self {
+        DeviceClock {
+            counter_hz,
+            first_counter: None,
+            system_offset_ns: i64::MAX,
+            monotonic_offset_ns: i64::MAX,
+        }
+    }
+
+    /// Restamps a record from its extended counter value
+    ///
+    /// A record not yet stamped by a [`super::TickExtender`] is taken at its
+    /// raw counter value.
+    pub fn map(&mut self, mut data: SensorData) -> SensorData {
+        let counter = data.extended_timestamp.unwrap_or(data.timestamp as i64);
+        let first = *self.first_counter.get_or_insert(counter);
+        let elapsed_ns = ((counter - first) as f64 * 1e9 / self.counter_hz).round() as i64;
+
+        self.system_offset_ns = self
+            .system_offset_ns
+            .min(data.system_timestamp * 1_000_000 - elapsed_ns);
+        self.monotonic_offset_ns = self
+            .monotonic_offset_ns
+            .min(data.monotonic_timestamp - elapsed_ns);
+        data.system_timestamp = (self.system_offset_ns + elapsed_ns).div_euclid(1_000_000);
+        data.monotonic_timestamp = self.monotonic_offset_ns + elapsed_ns;
+        data
+    }
+}
+
+/// Mapping of the sensor counter onto an external reference timebase
+///
+/// Built from anchor pairs `(reference_time, sensor_counter)`, e.g. logged by
+/// a sync pulse shared with other sensors. A counter between two anchors maps
+/// linearly between their reference times; a counter outside the anchors is
+/// extrapolated from the nearest pair. Anchors must not span a counter
+/// rollover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceClock {
+    // (sensor_counter, reference_ns), strictly increasing in the counter
+    anchors: Vec<(u32, i64)>,
+}
+
+impl ReferenceClock {
+    /// Creates a mapping from `(reference_ns, sensor_counter)` pairs
+    ///
+    /// # Returns
+    /// The mapping, or a configuration error if fewer than two anchors are
+    /// given or two anchors share a counter value
+    pub fn from_anchors(anchors: &[(i64, u32)]) -> Result<ReferenceClock> {
+        let mut anchors: Vec<(u32, i64)> = anchors
+            .iter()
+            .map(|&(reference, counter)| (counter, reference))
+            .collect();
+        anchors.sort_unstable();
+        if anchors.len() < 2 {
+            return Err(ReceiverError::ConfigError(
+                "A reference clock needs at least two anchors".to_string(),
+            )
+            .into());
+        }
+        if let Some(pair) = anchors.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(ReceiverError::ConfigError(format!(
+                "Repeated sensor counter {} in reference clock anchors",
+                pair[0].0
+            ))
+            .into());
+        }
+        Ok(ReferenceClock { anchors })
+    }
+
+    /// Reads anchors from a sync file
+    ///
+    /// Each line holds `reference_time,sensor_counter`: the reference time in
+    /// nanoseconds and the decimal counter value sampled at that time. Blank
+    /// lines and lines starting with `#` are ignored.
+    pub fn load(path: &Path) -> Result<ReferenceClock> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read sync file {}", path.display()))?;
+        let mut anchors = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let anchor = line.split_once(',').and_then(|(reference, counter)| {
+                Some((reference.trim().parse().ok()?, counter.trim().parse().ok()?))
+            });
+            match anchor {
+                Some(anchor) => anchors.push(anchor),
+                None => {
+                    return Err(ReceiverError::ParseError(format!(
+                        "{}:{}: expected reference_time,sensor_counter: {}",
+                        path.display(),
+                        number + 1,
+                        line
+                    ))
+                    .into())
+                }
+            }
+        }
+        Self::from_anchors(&anchors)
+    }
+
+    /// Reference time in nanoseconds of a sensor counter value
+    pub fn map(&self, counter: u32) -> i64 {
+        // Segment containing the counter, or the nearest one outside the anchors
+        let index = self
+            .anchors
+            .partition_point(|&(anchor, _)| anchor <= counter)
+            .clamp(1, self.anchors.len() - 1);
+        let (c0, t0) = self.anchors[index - 1];
+        let (c1, t1) = self.anchors[index];
+        let fraction = (counter as f64 - c0 as f64) / (c1 as f64 - c0 as f64);
+        t0 + (fraction * (t1 - t0) as f64).round() as i64
+    }
+}
+
+/// Resamples records onto a uniform grid of the reference timebase
+///
+/// Every grid point `k * period_ns` between two consecutive records gets a
+/// record whose float fields are interpolated linearly between them; the
+/// counter, host times and status are taken from the nearer record. Grid
+/// points before the first record are not produced.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    clock: ReferenceClock,
+    period_ns: i64,
+    previous: Option<(i64, SensorData)>,
+}
+
+impl Resampler {
+    /// Creates a resampler with the given grid period in nanoseconds
+    pub fn new(clock: ReferenceClock, period_ns: i64) -> Self {
+        Resampler {
+            clock,
+            period_ns: period_ns.max(1),
+            previous: None,
+        }
+    }
+
+    /// Feeds the next record
+    ///
+    /// # Returns
+    /// The grid records between the previous record and this one, each with
+    /// its reference time in nanoseconds
+    pub fn push(&mut self, data: SensorData) -> Vec<(i64, SensorData)> {
+        let time = self.clock.map(data.timestamp);
+        let Some((previous_time, previous)) = self.previous.replace((time, data.clone())) else {
+            // A grid point exactly at the first record
+            return match time.rem_euclid(self.period_ns) {
+                0 => vec![(time, data)],
+                _ => Vec::new(),
+            };
+        };
+        if time <= previous_time {
+            return Vec::new();
+        }
+
+        let first = previous_time.div_euclid(self.period_ns) + 1;
+        let last = time.div_euclid(self.period_ns);
+        (first..=last)
+            .map(|k| {
+                let grid_time = k * self.period_ns;
+                let fraction =
+                    ((grid_time - previous_time) as f64 / (time - previous_time) as f64) as f32;
+                (grid_time, interpolate(&previous, &data, fraction))
+            })
+            .collect()
+    }
+}
+
+// Record a fraction of the way from `a` to `b`
+fn interpolate(a: &SensorData, b: &SensorData, fraction: f32) -> SensorData {
+    let lerp = |x: f32, y: f32| x + (y - x) * fraction;
+    let nearer = if fraction < 0.5 { a } else { b };
+    SensorData {
+        temp: lerp(a.temp, b.temp),
+        gx: lerp(a.gx, b.gx),
+        gy: lerp(a.gy, b.gy),
+        gz: lerp(a.gz, b.gz),
+        ax: lerp(a.ax, b.ax),
+        ay: lerp(a.ay, b.ay),
+        az: lerp(a.az, b.az),
+        null_fields: a.null_fields | b.null_fields,
+        ..nearer.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CounterWidth, TickExtender};
+
+    fn record(timestamp: u32, ax: f32) -> SensorData {
+        SensorData {
+            timestamp,
+            temp: 25.0,
+            ax,
+            az: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_counter_between_anchors_maps_linearly() {
+        let clock = ReferenceClock::from_anchors(&[(1_000_000_000, 1_000), (2_000_000_000, 3_000)])
+            .unwrap();
+        assert_eq!(clock.map(1_000), 1_000_000_000);
+        assert_eq!(clock.map(1_500), 1_250_000_000);
+        assert_eq!(clock.map(3_000), 2_000_000_000);
+        // Extrapolated from the only segment
+        assert_eq!(clock.map(500), 750_000_000);
+
+        assert!(ReferenceClock::from_anchors(&[(0, 1_000)]).is_err());
+        assert!(ReferenceClock::from_anchors(&[(0, 1_000), (5, 1_000)]).is_err());
+    }
+
+    #[test]
+    fn test_resampler_interpolates_onto_grid() {
+        // One counter per microsecond, 250 us grid
+        let clock = ReferenceClock::from_anchors(&[(0, 0), (1_000_000, 1_000)]).unwrap();
+        let mut resampler = Resampler::new(clock, 250_000);
+
+        assert!(resampler.push(record(100, 0.0)).is_empty());
+        let grid = resampler.push(record(600, 5.0));
+        let times: Vec<i64> = grid.iter().map(|(time, _)| *time).collect();
+        assert_eq!(times, vec![250_000, 500_000]);
+        assert!((grid[0].1.ax - 1.5).abs() < 1e-6);
+        assert!((grid[1].1.ax - 4.0).abs() < 1e-6);
+        assert_eq!(grid[0].1.timestamp, 100);
+        assert_eq!(grid[1].1.timestamp, 600);
+    }
+
+    #[test]
+    fn test_interpolated_records_are_evenly_spaced() {
+        let mut interpolator = ReadInterpolator::new();
+        let mut first = vec![record(0, 0.0); 3];
+        interpolator.spread_at(&mut first, 1_000, 5_000_000);
+        // The first read has no start and keeps its stamps
+        assert!(first.iter().all(|data| data.system_timestamp == 0));
+
+        // Ten records parsed at once, read 20 ms after the previous read
+        let mut records: Vec<SensorData> = (0..10).map(|i| record(i, 0.0)).collect();
+        interpolator.spread_at(&mut records, 1_020, 25_000_000);
+        let monotonic: Vec<i64> = records.iter().map(|d| d.monotonic_timestamp).collect();
+        assert_eq!(monotonic[0], 7_000_000);
+        assert_eq!(monotonic[9], 25_000_000);
+        assert!(monotonic
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] == 2_000_000));
+        let system: Vec<i64> = records.iter().map(|d| d.system_timestamp).collect();
+        assert_eq!(
+            system,
+            [1_002, 1_004, 1_006, 1_008, 1_010, 1_012, 1_014, 1_016, 1_018, 1_020]
+        );
+
+        // An empty read still ends an interval
+        interpolator.spread_at(&mut [], 1_100, 105_000_000);
+        let mut late = vec![record(10, 0.0)];
+        interpolator.spread_at(&mut late, 1_110, 115_000_000);
+        assert_eq!(late[0].monotonic_timestamp, 115_000_000);
+        assert_eq!(late[0].system_timestamp, 1_110);
+    }
+
+    #[test]
+    fn test_device_clock_keeps_counter_spacing() {
+        // 1 kHz 24-bit counter across a rollover, arriving with up to 7 ms jitter
+        let mut clock = DeviceClock::new(1_000.0);
+        let mut ticks = TickExtender::new().with_width(CounterWidth::Bits24);
+        let jitter = [5, 0, 7, 2, 6, 1, 3, 4];
+        let mapped: Vec<SensorData> = (0..8u32)
+            .map(|i| {
+                let mut data = record((0xFF_FFFC + i * 2) & 0xFF_FFFF, 0.0);
+                data.system_timestamp = 10_000 + i as i64 * 2 + jitter[i as usize];
+                data.monotonic_timestamp = (500 + i as i64 * 2 + jitter[i as usize]) * 1_000_000;
+                clock.map(ticks.stamp(data))
+            })
+            .collect();
+        // Once the earliest arrival (the second record) has been seen, the
+        // stamps follow the counter exactly
+        let system: Vec<i64> = mapped[1..].iter().map(|d| d.system_timestamp).collect();
+        assert_eq!(
+            system,
+            [10_002, 10_004, 10_006, 10_008, 10_010, 10_012, 10_014]
+        );
+        assert!(mapped[1..]
+            .windows(2)
+            .all(|pair| pair[1].monotonic_timestamp - pair[0].monotonic_timestamp == 2_000_000));
+        assert_eq!(mapped[1].monotonic_timestamp, 502_000_000);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// Named buffering strategy trading write latency against throughput
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TuningPreset {
+    /// Small batches written promptly, for live monitoring of the files
+    LowLatency,
+    /// Moderate batching suited to most captures
+    #[default]
+    Balanced,
+    /// Large batches and row groups for long, high-rate captures
+    HighThroughput,
+}
+
+impl std::str::FromStr for TuningPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low-latency" => Ok(TuningPreset::LowLatency),
+            "balanced" => Ok(TuningPreset::Balanced),
+            "high-throughput" => Ok(TuningPreset::HighThroughput),
+            _ => Err(format!("Unknown tuning preset: {}", s)),
+        }
+    }
+}
+
+/// Writer parameters derived from a tuning preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuningParams {
+    /// Records buffered before a batch is written
+    pub buffer_size: usize,
+    /// Longest time buffered records wait before being written
+    pub flush_interval: Duration,
+    /// Largest number of rows per Parquet row group
+    pub row_group_size: usize,
+    /// Records taken from the channel per wakeup of the writer thread
+    pub coalesce: usize,
+}
+
+impl TuningPreset {
+    /// Returns the parameter set of this preset
+    pub fn params(&self) -> TuningParams {
+        match self {
+            TuningPreset::LowLatency => TuningParams {
+                buffer_size: 10,
+                flush_interval: Duration::from_millis(100),
+                row_group_size: 10_000,
+                coalesce: 1,
+            },
+            TuningPreset::Balanced => TuningParams {
+                buffer_size: 100,
+                flush_interval: Duration::from_secs(1),
+                row_group_size: 100_000,
+                coalesce: 64,
+            },
+            TuningPreset::HighThroughput => TuningParams {
+                buffer_size: 5_000,
+                flush_interval: Duration::from_secs(10),
+                row_group_size: 1_000_000,
+                coalesce: 1_024,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_presets_yield_documented_parameters() {
+        let expected = [
+            ("low-latency", 10, 100, 10_000, 1),
+            ("balanced", 100, 1_000, 100_000, 64),
+            ("high-throughput", 5_000, 10_000, 1_000_000, 1_024),
+        ];
+        for (name, buffer_size, flush_ms, row_group_size, coalesce) in expected {
+            let params = TuningPreset::from_str(name).unwrap().params();
+            assert_eq!(
+                params,
+                TuningParams {
+                    buffer_size,
+                    flush_interval: Duration::from_millis(flush_ms),
+                    row_group_size,
+                    coalesce,
+                },
+                "preset {}",
+                name
+            );
+        }
+
+        assert!(TuningPreset::from_str("fastest").is_err());
+    }
+}
@@ -1,5 +1,12 @@
+use parquet::basic::{GzipLevel, ZstdLevel};
+
+use super::error::ReceiverError;
+
 /// Data structure representing a single sensor reading
-#[derive(Debug, Clone)]
+///
+/// The default is an all-zero record with no optional field set, so literals
+/// can name only the fields they care about and end in `..Default::default()`.
+#[derive(Debug, Clone, Default)]
 pub struct SensorData {
     /// Timestamp from the sensor (uint32 from Arduino)
     pub timestamp: u32,
@@ -19,28 +26,321 @@ pub struct SensorData {
     pub az: f32,
     /// System timestamp when the data was received (i64 representation of time)
     pub system_timestamp: i64,
+    /// Monotonic clock reading when the data was received (nanoseconds since receiver start)
+    pub monotonic_timestamp: i64,
+    /// Device status byte, for firmware that packs it into the timestamp field
+    pub status: Option<u8>,
+    /// Float fields that failed to decode, one bit per entry of
+    /// [`SensorData::FLOAT_FIELDS`]; their values are NaN and nullable
+    /// layouts store them as null
+    pub null_fields: u8,
+    /// Impact event the record belongs to, assigned by burst detection
+    pub burst_id: Option<u32>,
+    /// `timestamp` extended to 64 bits across counter rollovers, assigned
+    /// by the reader (see [`TickExtender`](super::monotonic::TickExtender))
+    pub extended_timestamp: Option<i64>,
+    /// Sensor counter and host clock disagreed beyond the tolerance of the
+    /// clock check (see [`ClockCheck`](super::clock_check::ClockCheck))
+    pub clock_skew: bool,
+}
+
+impl SensorData {
+    /// Float fields in line order, as indexed by `null_fields`
+    pub const FLOAT_FIELDS: [&'static str; 7] = ["temp", "gx", "gy", "gz", "ax", "ay", "az"];
+
+    /// Returns whether the float field at `index` of [`SensorData::FLOAT_FIELDS`] failed to decode
+    pub fn is_null(&self, index: usize) -> bool {
+        self.null_fields & (1 << index) != 0
+    }
+
+    /// Column names of [`to_csv_row`](Self::to_csv_row), in the order of the Parquet schema
+    pub const CSV_HEADER: &'static str = "timestamp,temp,gx,gy,gz,ax,ay,az,system_timestamp";
+
+    /// Formats the record as a CSV line (without line terminator)
+    ///
+    /// Floats are written as decimals in their shortest representation that
+    /// parses back to the same value, so [`from_csv_row`](Self::from_csv_row)
+    /// restores them exactly. Columns follow [`CSV_HEADER`](Self::CSV_HEADER).
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.timestamp,
+            self.temp,
+            self.gx,
+            self.gy,
+            self.gz,
+            self.ax,
+            self.ay,
+            self.az,
+            self.system_timestamp
+        )
+    }
+
+    /// Formats the record as a JSON object
+    ///
+    /// Holds the counter, the seven float fields and both host timestamps,
+    /// plus `status`, `burst_id` and `extended_timestamp` when set and
+    /// `clock_skew` when flagged. Fields that failed to decode are `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut object = serde_json::json!({
+            "timestamp": self.timestamp,
+            "system_timestamp": self.system_timestamp,
+            "monotonic_timestamp": self.monotonic_timestamp,
+        });
+        let values = [
+            self.temp, self.gx, self.gy, self.gz, self.ax, self.ay, self.az,
+        ];
+        for (index, (name, value)) in Self::FLOAT_FIELDS.iter().zip(values).enumerate() {
+            object[*name] = if self.is_null(index) || !value.is_finite() {
+                serde_json::Value::Null
+            } else {
+                serde_json::json!(value)
+            };
+        }
+        if let Some(status) = self.status {
+            object["status"] = status.into();
+        }
+        if let Some(burst_id) = self.burst_id {
+            object["burst_id"] = burst_id.into();
+        }
+        if let Some(extended) = self.extended_timestamp {
+            object["extended_timestamp"] = extended.into();
+        }
+        if self.clock_skew {
+            object["clock_skew"] = true.into();
+        }
+        object
+    }
+
+    /// Parses a line written by [`to_csv_row`](Self::to_csv_row)
+    ///
+    /// The monotonic timestamp, status and null flags are not part of the
+    /// row and are left at zero / `None`.
+    pub fn from_csv_row(line: &str) -> Result<SensorData, ReceiverError> {
+        let parts: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+        if parts.len() != 9 {
+            return Err(ReceiverError::ParseError(format!(
+                "Expected 9 CSV fields, got {}: {}",
+                parts.len(),
+                line
+            )));
+        }
+        let invalid = |name: &str, part: &str, e: &dyn std::fmt::Display| {
+            ReceiverError::ParseError(format!("Invalid {}: {}, error: {}", name, part, e))
+        };
+        let float = |index: usize| -> Result<f32, ReceiverError> {
+            let part = parts[index + 1];
+            part.parse()
+                .map_err(|e| invalid(Self::FLOAT_FIELDS[index], part, &e))
+        };
+
+        Ok(SensorData {
+            timestamp: parts[0]
+                .parse()
+                .map_err(|e| invalid("timestamp", parts[0], &e))?,
+            temp: float(0)?,
+            gx: float(1)?,
+            gy: float(2)?,
+            gz: float(3)?,
+            ax: float(4)?,
+            ay: float(5)?,
+            az: float(6)?,
+            system_timestamp: parts[8]
+                .parse()
+                .map_err(|e| invalid("system_timestamp", parts[8], &e))?,
+            ..Default::default()
+        })
+    }
+
+    /// Magnitude of the acceleration vector, sqrt(ax² + ay² + az²)
+    pub fn accel_magnitude(&self) -> f32 {
+        (self.ax * self.ax + self.ay * self.ay + self.az * self.az).sqrt()
+    }
+}
+
+/// Clock used for the primary `timestamp` column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrimaryTime {
+    /// Sensor counter (Int64)
+    #[default]
+    Counter,
+    /// Host wall clock (Timestamp in milliseconds, UTC)
+    Wall,
+    /// Host monotonic clock (Int64 nanoseconds since receiver start)
+    Monotonic,
+}
+
+impl std::str::FromStr for PrimaryTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "counter" => Ok(PrimaryTime::Counter),
+            "wall" => Ok(PrimaryTime::Wall),
+            "monotonic" => Ok(PrimaryTime::Monotonic),
+            _ => Err(format!("Unknown time source: {}", s)),
+        }
+    }
+}
+
+/// Encoding of the sensor data stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataFormat {
+    /// Newline-delimited lines of comma-separated hex fields
+    #[default]
+    Text,
+    /// Fixed-size binary frames with a sync word and CRC
+    Binary,
+    /// Either of the above, detected from the first bytes received
+    Auto,
+}
+
+impl std::str::FromStr for DataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(DataFormat::Text),
+            "binary" => Ok(DataFormat::Binary),
+            "auto" => Ok(DataFormat::Auto),
+            _ => Err(format!("Unknown data format: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataFormat::Text => "text",
+            DataFormat::Binary => "binary",
+            DataFormat::Auto => "auto",
+        })
+    }
+}
+
+/// Time an output file is named after
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileNaming {
+    /// Host time at which the file was created
+    #[default]
+    Creation,
+    /// Host time of the earliest record in the file, applied when it is closed
+    FirstRecord,
+}
+
+impl std::str::FromStr for FileNaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "creation" => Ok(FileNaming::Creation),
+            "first-record" => Ok(FileNaming::FirstRecord),
+            _ => Err(format!("Unknown file naming: {}", s)),
+        }
+    }
 }
 
 /// Compression algorithm options
+///
+/// Gzip and zstd carry a compression level, trading CPU time for file size;
+/// it is written after a colon (`gzip:9`, `zstd:19`) and defaults to the
+/// codec's own default when omitted.
+#[derive(Debug, Clone, Copy)]
 pub enum CompressionType {
     None,
     Snappy,
-    Gzip,
+    Gzip(GzipLevel),
     Lz4,
-    Zstd,
+    Zstd(ZstdLevel),
 }
 
 impl std::str::FromStr for CompressionType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "none" => Ok(CompressionType::None),
-            "snappy" => Ok(CompressionType::Snappy),
-            "gzip" => Ok(CompressionType::Gzip),
-            "lz4" => Ok(CompressionType::Lz4),
-            "zstd" => Ok(CompressionType::Zstd),
+        let lower = s.to_lowercase();
+        let (name, level) = match lower.split_once(':') {
+            Some((name, level)) => (name, Some(level.trim())),
+            None => (lower.as_str(), None),
+        };
+        match (name, level) {
+            ("none", None) => Ok(CompressionType::None),
+            ("snappy", None) => Ok(CompressionType::Snappy),
+            ("gzip", None) => Ok(CompressionType::Gzip(GzipLevel::default())),
+            ("gzip", Some(level)) => level
+                .parse()
+                .ok()
+                .and_then(|level| GzipLevel::try_new(level).ok())
+                .map(CompressionType::Gzip)
+                .ok_or_else(|| format!("Invalid gzip level '{}' (expected 0 to 10)", level)),
+            ("lz4", None) => Ok(CompressionType::Lz4),
+            ("zstd", None) => Ok(CompressionType::Zstd(ZstdLevel::default())),
+            ("zstd", Some(level)) => level
+                .parse()
+                .ok()
+                .and_then(|level| ZstdLevel::try_new(level).ok())
+                .map(CompressionType::Zstd)
+                .ok_or_else(|| format!("Invalid zstd level '{}' (expected 1 to 22)", level)),
+            ("none" | "snappy" | "lz4", Some(_)) => {
+                Err(format!("Compression '{}' takes no level", name))
+            }
             _ => Err(format!("Unknown compression type: {}", s)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_round_trip() {
+        let data = SensorData {
+            timestamp: 4_000_000_123,
+            temp: 25.1,
+            gx: -0.000_123_45,
+            gy: 1.0e-30,
+            gz: f32::MAX,
+            ax: -1.0,
+            ay: 0.1 + 0.2,
+            az: 9.806_65,
+            system_timestamp: 1_735_732_800_123,
+            monotonic_timestamp: 42,
+            status: Some(3),
+            ..Default::default()
+        };
+        let row = data.to_csv_row();
+        assert!(!row.contains("0x"));
+        assert_eq!(
+            row.split(',').count(),
+            SensorData::CSV_HEADER.split(',').count()
+        );
+
+        let parsed = SensorData::from_csv_row(&row).unwrap();
+        assert_eq!(parsed.timestamp, data.timestamp);
+        assert_eq!(parsed.system_timestamp, data.system_timestamp);
+        let floats =
+            |d: &SensorData| [d.temp, d.gx, d.gy, d.gz, d.ax, d.ay, d.az].map(f32::to_bits);
+        assert_eq!(floats(&parsed), floats(&data));
+
+        assert!(SensorData::from_csv_row("1,2,3").is_err());
+        assert!(SensorData::from_csv_row("1,x,0,0,0,0,0,0,5").is_err());
+    }
+
+    #[test]
+    fn test_compression_levels_checked() {
+        use std::str::FromStr;
+        assert!(matches!(
+            CompressionType::from_str("zstd:19"),
+            Ok(CompressionType::Zstd(level)) if level.compression_level() == 19
+        ));
+        assert!(matches!(
+            CompressionType::from_str("GZIP:9"),
+            Ok(CompressionType::Gzip(level)) if level.compression_level() == 9
+        ));
+        assert!(CompressionType::from_str("zstd:23").is_err());
+        assert!(CompressionType::from_str("gzip:11").is_err());
+        assert!(CompressionType::from_str("gzip:fast").is_err());
+        assert!(CompressionType::from_str("snappy:3").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// Liveness notifications emitted by the serial reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// Data has started streaming
+    Ready,
+    /// The reader is still receiving data
+    Watchdog,
+}
+
+/// Hook receiving liveness notifications
+pub type NotifyHook = Box<dyn FnMut(NotifyEvent) + Send>;
+
+/// Forwards the reader's liveness to a service manager
+///
+/// `Ready` is sent once with the first record; afterwards `Watchdog` pings are
+/// sent at most once per interval, and only while records keep arriving, so a
+/// stalled reader stops pinging and lets the supervisor restart it.
+pub struct Watchdog {
+    hook: NotifyHook,
+    interval: Option<Duration>,
+    ready_sent: bool,
+    last_ping: Option<Instant>,
+}
+
+impl Watchdog {
+    /// Creates a new watchdog
+    ///
+    /// # Arguments
+    /// * `interval` - Minimum time between pings (None = only send `Ready`)
+    /// * `hook` - Callback invoked for each notification
+    ///
+    /// # Returns
+    /// A new Watchdog instance
+    pub fn new(interval: Option<Duration>, hook: NotifyHook) -> Self {
+        Watchdog {
+            hook,
+            interval,
+            ready_sent: false,
+            last_ping: None,
+        }
+    }
+
+    /// Reports that a record was received
+    pub fn alive(&mut self) {
+        if !self.ready_sent {
+            self.ready_sent = true;
+            (self.hook)(NotifyEvent::Ready);
+        }
+
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let now = Instant::now();
+        if let Some(last) = self.last_ping {
+            if now.duration_since(last) < interval {
+                return;
+            }
+        }
+        self.last_ping = Some(now);
+        (self.hook)(NotifyEvent::Watchdog);
+    }
+}
+
+/// Creates a watchdog reporting to systemd via `sd_notify`
+///
+/// Pings are sent at half the `WatchdogSec` interval advertised by systemd.
+/// Without a watchdog configured only `READY=1` is sent.
+#[cfg(all(feature = "systemd", target_os = "linux"))]
+pub fn systemd_watchdog() -> Option<Watchdog> {
+    use sd_notify::NotifyState;
+
+    let mut usec = 0;
+    let interval =
+        sd_notify::watchdog_enabled(false, &mut usec).then(|| Duration::from_micros(usec / 2));
+
+    Some(Watchdog::new(
+        interval,
+        Box::new(|event| {
+            let state = match event {
+                NotifyEvent::Ready => NotifyState::Ready,
+                NotifyEvent::Watchdog => NotifyState::Watchdog,
+            };
+            if let Err(e) = sd_notify::notify(false, &[state]) {
+                eprintln!("Failed to notify systemd: {}", e);
+            }
+        }),
+    ))
+}
+
+/// systemd is only available on Linux; elsewhere this is a no-op
+#[cfg(all(feature = "systemd", not(target_os = "linux")))]
+pub fn systemd_watchdog() -> Option<Watchdog> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_ready_then_rate_limited_pings() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut watchdog = Watchdog::new(
+            Some(Duration::from_secs(3600)),
+            Box::new(move |event| sink.lock().unwrap().push(event)),
+        );
+
+        for _ in 0..10 {
+            watchdog.alive();
+        }
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![NotifyEvent::Ready, NotifyEvent::Watchdog]
+        );
+    }
+}
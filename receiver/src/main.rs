@@ -1,71 +1,1436 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
-use receiver::{CompressionType, FileWriterWorker, ParquetWriter, SerialReaderWorker};
+use receiver::{
+    args_with_config, convert, disable_color, generate_run_id, marker_loop, merge_loop,
+    resume_run_id, schema_json, split_format_names, validate_file, BackpressurePolicy,
+    BoundedSender, BurstDetector, CalibratingSource, Calibration, ChangeFilter, ClockCheck,
+    ClockSkewPolicy, CompressionType, CounterWidth, CsvProfile, CsvWriter, DataFormat, DataSink,
+    DataSource, DecimateMode, DecimatedSource, Decimator, FanoutSink, FileNaming, FileWriterWorker,
+    Filter, HostInfo, InFlightGauge, InfluxSink, InfluxTarget, LineLayout, LineStatusLog, Marker,
+    MarkerLog, MergedParquetWriter, MonotonicPolicy, MqttSink, MqttUrl, NonFiniteHandler,
+    OutputLock, ParquetWriter, PauseControl, PrimaryTime, RangeCheck, RangePolicy, RawCapture,
+    ReceiverError, ReferenceClock, ReplayClock, ReplayLoops, ReplayReader, ReplayWindow,
+    RotationHook, SensorData, SerialFraming, SerialReaderWorker, SessionState, SimulatedSource,
+    SinkConfig, SinkFailurePolicy, SinkRegistry, StreamMerger, TimestampMode, TuningPreset,
+    WriterOptions, DEFAULT_APPEND_MAX_AGE_MINUTES, DEFAULT_BURST_WINDOW, DEFAULT_CHANNEL_CAPACITY,
+    DEFAULT_INFLUX_QUEUE, DEFAULT_MAX_DROPPED_BATCHES, DEFAULT_MQTT_QUEUE,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "receiver")]
 #[command(about = "Receives sensor data over UART and stores it in Parquet format")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print status messages without colors even on a terminal
+    #[arg(long, global = true, env = "RECEIVER_NO_COLOR")]
+    no_color: bool,
+
+    /// TOML file supplying defaults for the other options, keyed by option
+    /// name (e.g. `baud_rate = 921600`); flags and environment variables win
+    #[arg(long, value_name = "PATH", env = "RECEIVER_CONFIG")]
+    config: Option<PathBuf>,
+
     /// Serial port to connect to (e.g. /dev/ttyUSB0, COM3)
-    #[arg(short, long)]
-    port: String,
+    #[arg(short, long, required_unless_present = "input", env = "RECEIVER_PORT")]
+    port: Option<String>,
+
+    /// Replay a captured file (Parquet or text capture) instead of reading a serial port
+    #[arg(long, conflicts_with_all = ["port", "simulation", "merge_port"], env = "RECEIVER_INPUT")]
+    input: Option<PathBuf>,
+
+    /// Log the raw serial bytes to this file (zstd-compressed if it ends in .zst)
+    #[arg(long, conflicts_with_all = ["input", "simulation"], env = "RECEIVER_RAW_CAPTURE")]
+    raw_capture: Option<PathBuf>,
+
+    /// Record the port's CTS/DSR/CD/RI states at open and every reconnect in the file metadata
+    #[arg(long, conflicts_with_all = ["input", "simulation"], env = "RECEIVER_LINE_STATUS_METADATA")]
+    line_status_metadata: bool,
+
+    /// Record each line typed on stdin as a timestamped marker in this JSONL file
+    #[arg(long, value_name = "PATH", env = "RECEIVER_MARKERS")]
+    markers: Option<PathBuf>,
+
+    /// Encoding of the serial stream or capture (text, binary, auto)
+    #[arg(long, default_value = "text", env = "RECEIVER_INPUT_FORMAT")]
+    input_format: String,
+
+    /// Only replay records at or after this time (see --replay-clock)
+    #[arg(long, requires = "input", env = "RECEIVER_REPLAY_START")]
+    replay_start: Option<i64>,
+
+    /// Only replay records at or before this time (see --replay-clock)
+    #[arg(long, requires = "input", env = "RECEIVER_REPLAY_END")]
+    replay_end: Option<i64>,
+
+    /// Clock of the replay window (timestamp, system_timestamp)
+    #[arg(long, default_value = "timestamp", env = "RECEIVER_REPLAY_CLOCK")]
+    replay_clock: String,
+
+    /// Run through the replayed file this many times, or `infinite`
+    #[arg(
+        long,
+        default_value = "1",
+        requires = "input",
+        env = "RECEIVER_REPLAY_LOOP"
+    )]
+    replay_loop: String,
+
+    /// Shift the timestamps of each replay loop so they keep increasing
+    #[arg(long, requires = "input", env = "RECEIVER_REPLAY_LOOP_OFFSET")]
+    replay_loop_offset: bool,
 
     /// Baud rate for serial connection
-    #[arg(short, long, default_value = "921600")]
-    baud_rate: u32,
+    // Signed so that a negative rate reaches the range check instead of
+    // being taken for a flag
+    #[arg(
+        short,
+        long,
+        default_value = "921600",
+        allow_negative_numbers = true,
+        env = "RECEIVER_BAUD_RATE"
+    )]
+    baud_rate: i64,
+
+    /// Serial framing as data bits, parity and stop bits (e.g. 8N1, 7E1, 8O1)
+    #[arg(long, default_value = "8N1", env = "RECEIVER_SERIAL_CONFIG")]
+    serial_config: String,
 
     /// Output directory for Parquet files
-    #[arg(short, long, default_value = "./logs")]
+    #[arg(short, long, default_value = "./logs", env = "RECEIVER_OUTPUT_DIR")]
     output_dir: String,
 
     /// File split interval in minutes (0 = no splitting)
-    #[arg(short, long, default_value = "0")]
+    #[arg(short, long, default_value = "0", env = "RECEIVER_SPLIT_MINUTES")]
     split_minutes: u32,
 
+    /// Align file splits to wall-clock multiples of --split-minutes (e.g. whole hours)
+    #[arg(long, env = "RECEIVER_ALIGN_ROTATION")]
+    align_rotation: bool,
+
     /// Output file name prefix
-    #[arg(short = 'f', long, default_value = "sensor_log")]
+    #[arg(
+        short = 'f',
+        long,
+        default_value = "sensor_log",
+        env = "RECEIVER_PREFIX"
+    )]
     prefix: String,
 
-    /// Compression algorithm (none, snappy, gzip, lz4, zstd)
-    #[arg(short, long, default_value = "snappy")]
+    /// Compression algorithm (none, snappy, gzip, lz4, zstd); gzip and zstd
+    /// take a level after a colon, e.g. gzip:9 or zstd:19
+    #[arg(short, long, default_value = "snappy", env = "RECEIVER_COMPRESSION")]
     compression: String,
 
+    /// Output file format (parquet, csv, compact, jsonl); a comma-separated
+    /// list such as "parquet,csv" writes every record in each format
+    #[arg(
+        long,
+        alias = "output-format",
+        default_value = "parquet",
+        env = "RECEIVER_FORMAT"
+    )]
+    format: String,
+
+    /// What to do when one of several --format outputs fails (stop, continue)
+    #[arg(long, default_value = "stop", env = "RECEIVER_SINK_FAILURE")]
+    sink_failure: String,
+
     /// Buffer size (how many records to accumulate before writing)
-    #[arg(short = 'u', long, default_value = "100")]
+    #[arg(short = 'u', long, default_value = "100", env = "RECEIVER_BUFFER_SIZE")]
     buffer_size: usize,
 
+    /// Buffering preset setting buffer size, flush interval, row-group size and
+    /// coalescing together (low-latency, balanced, high-throughput)
+    #[arg(long, conflicts_with = "buffer_size", env = "RECEIVER_TUNING")]
+    tuning: Option<String>,
+
+    /// Largest number of rows per Parquet row group; smaller groups let
+    /// readers skip more data by column statistics at a slight size cost
+    #[arg(long, value_name = "ROWS", value_parser = clap::value_parser!(u64).range(1..), env = "RECEIVER_ROW_GROUP_SIZE")]
+    row_group_size: Option<u64>,
+
     /// Enable simulation mode (generate test data instead of reading from serial port)
-    #[arg(short = 'm', long)]
+    #[arg(short = 'm', long, env = "RECEIVER_SIMULATION")]
     simulation: bool,
+
+    /// Start with writing paused until the resume trigger fires
+    #[arg(long, env = "RECEIVER_START_PAUSED")]
+    start_paused: bool,
+
+    /// File whose creation resumes writing (default: <OUTPUT_DIR>/RESUME)
+    #[arg(long, env = "RECEIVER_RESUME_FILE")]
+    resume_file: Option<PathBuf>,
+
+    /// Sensor counter rate in Hz; adds a nanosecond `timestamp_ns` column derived from it
+    #[arg(long, env = "RECEIVER_COUNTER_HZ")]
+    counter_hz: Option<f64>,
+
+    /// Sync file of `reference_time,sensor_counter` lines (reference time in ns);
+    /// adds a `reference_time` column mapping the counter onto that timebase
+    #[arg(long, value_name = "FILE", env = "RECEIVER_REFERENCE_SYNC")]
+    reference_sync: Option<PathBuf>,
+
+    /// Records queued between the reader and the writer before the reader has to wait
+    #[arg(long, value_name = "RECORDS", default_value_t = DEFAULT_CHANNEL_CAPACITY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), env = "RECEIVER_CHANNEL_CAPACITY")]
+    channel_capacity: usize,
+
+    /// Drop records instead of waiting when the writer falls behind and the channel is full
+    #[arg(long, env = "RECEIVER_DROP_ON_FULL")]
+    drop_on_full: bool,
+
+    /// Bytes requested from the serial port per read; larger reads mean fewer
+    /// system calls at high baud rates but more latency per record
+    #[arg(long, value_name = "BYTES", default_value_t = 4096, value_parser = clap::value_parser!(u64).range(16..), env = "RECEIVER_READ_BUFFER_SIZE")]
+    read_buffer_size: u64,
+
+    /// Longest wait in seconds between attempts to reopen a lost serial port
+    #[arg(long, value_name = "SECS", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..), env = "RECEIVER_RECONNECT_MAX_DELAY")]
+    reconnect_max_delay: u64,
+
+    /// Sleep this many milliseconds after a serial read that produced no
+    /// records, lowering CPU use at low sample rates
+    #[arg(long, value_name = "MS", env = "RECEIVER_IDLE_SLEEP_MS")]
+    idle_sleep_ms: Option<u64>,
+
+    /// Parse text lines on this many threads; line splitting stays on the
+    /// reader thread and records keep their arrival order
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=64), env = "RECEIVER_PARSE_THREADS")]
+    parse_threads: u64,
+
+    /// Ignore serial input until a line containing this text (e.g. "BOOT OK") arrives
+    #[arg(long, conflicts_with_all = ["input", "simulation"], env = "RECEIVER_WAIT_FOR_BANNER")]
+    wait_for_banner: Option<String>,
+
+    /// Read lines without a timestamp field and number records in arrival order
+    #[arg(long, requires = "counter_hz", env = "RECEIVER_SYNTHESIZE_TIMESTAMP")]
+    synthesize_timestamp: bool,
+
+    /// How `system_timestamp` and `monotonic_timestamp` are derived: parse time
+    /// (wallclock), spread over each read (interpolated), or from the sensor
+    /// counter (device, requires --counter-hz)
+    #[arg(long, default_value = "wallclock", env = "RECEIVER_TIMESTAMP_MODE")]
+    timestamp_mode: String,
+
+    /// Field order and encoding of serial lines; `a:b` marks two channels packed
+    /// as signed 16-bit integers into one field (e.g. "timestamp,temp,gx,gy,gz,ax:ay,az");
+    /// "no-temp" reads 7-field lines without temperature
+    #[arg(long, conflicts_with = "synthesize_timestamp", env = "RECEIVER_LAYOUT")]
+    layout: Option<String>,
+
+    /// Value of one count of a paired 16-bit field (e.g. 0.000061 for ±2 g)
+    #[arg(
+        long,
+        default_value = "1.0",
+        requires = "layout",
+        env = "RECEIVER_PAIRED_SCALE"
+    )]
+    paired_scale: f32,
+
+    /// Keep records with undecodable float fields, storing those fields as null
+    #[arg(long, conflicts_with_all = ["layout", "synthesize_timestamp"], env = "RECEIVER_LENIENT_NULLS")]
+    lenient_nulls: bool,
+
+    /// Split the first field into a 24-bit timestamp and an 8-bit status byte,
+    /// stored in a `status` column
+    #[arg(
+        long,
+        conflicts_with = "synthesize_timestamp",
+        env = "RECEIVER_PACKED_TIMESTAMP"
+    )]
+    packed_timestamp: bool,
+
+    /// Start a new file when a sustained change of the sample rate is detected
+    #[arg(long, env = "RECEIVER_ROTATE_ON_RATE_CHANGE")]
+    rotate_on_rate_change: bool,
+
+    /// Prepend this to every column name (e.g. "imu_" gives imu_ax, imu_temp)
+    #[arg(long, env = "RECEIVER_COLUMN_PREFIX")]
+    column_prefix: Option<String>,
+
+    /// Store gyro and accel as nested {x, y, z} struct columns
+    #[arg(long, env = "RECEIVER_NESTED_SCHEMA")]
+    nested_schema: bool,
+
+    /// Add an `accel_mag` column with sqrt(ax² + ay² + az²)
+    #[arg(long, env = "RECEIVER_WITH_ACCEL_MAG")]
+    with_accel_mag: bool,
+
+    /// Device calibration file applied to gyro and accel before writing
+    #[arg(long, env = "RECEIVER_CALIBRATION")]
+    calibration: Option<PathBuf>,
+
+    /// Start with a calibration capture of this many seconds at rest,
+    /// estimating gyro and accel biases that apply to the rest of the run
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "calibration",
+        env = "RECEIVER_CALIBRATE_SECS"
+    )]
+    calibrate_secs: Option<u64>,
+
+    /// Calibration file written by --calibrate-secs [default: <output_dir>/calibration.txt]
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "calibrate_secs",
+        env = "RECEIVER_CALIBRATE_OUT"
+    )]
+    calibrate_out: Option<PathBuf>,
+
+    /// Exit once the --calibrate-secs capture is written instead of logging
+    #[arg(long, requires = "calibrate_secs", env = "RECEIVER_CALIBRATE_EXIT")]
+    calibrate_exit: bool,
+
+    /// Clock stored in the primary `timestamp` column (counter, wall, monotonic)
+    #[arg(long, default_value = "counter", env = "RECEIVER_PRIMARY_TIME")]
+    primary_time: String,
+
+    /// Also write a CSV copy using this formatting profile (standard, legacy)
+    #[arg(long, env = "RECEIVER_CSV_PROFILE")]
+    csv_profile: Option<String>,
+
+    /// Handling of NaN and infinite float values (keep, drop, zero, hold, null),
+    /// optionally per field, e.g. "zero,temp=null"
+    #[arg(long, value_name = "POLICY", env = "RECEIVER_NONFINITE_POLICY")]
+    nonfinite_policy: Option<String>,
+
+    /// Only write records matching this expression (e.g. "az > 2.0 || gx < -1.0")
+    #[arg(long, env = "RECEIVER_FILTER")]
+    filter: Option<String>,
+
+    /// Only write a record when a gyro or accel axis differs by more than
+    /// EPSILON from the last record written (the first is always written)
+    #[arg(
+        long,
+        value_name = "EPSILON",
+        value_parser = parse_epsilon,
+        allow_negative_numbers = true,
+        env = "RECEIVER_ON_CHANGE"
+    )]
+    on_change: Option<f32>,
+
+    /// Reduce the rate to one record in N (trigger events are always written)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), env = "RECEIVER_DECIMATE")]
+    decimate: Option<u64>,
+
+    /// How --decimate reduces each window of N records (every, average)
+    #[arg(
+        long,
+        default_value = "every",
+        requires = "decimate",
+        env = "RECEIVER_DECIMATE_MODE"
+    )]
+    decimate_mode: String,
+
+    /// Records matching this expression are trigger events, kept by --decimate
+    #[arg(
+        long,
+        value_name = "EXPR",
+        requires = "decimate",
+        env = "RECEIVER_TRIGGER"
+    )]
+    trigger: Option<String>,
+
+    /// Delete each closed file none of whose records match this expression
+    /// (e.g. "az > 1.5 || az < 0.5"), keeping only files with motion
+    #[arg(long, value_name = "EXPR", env = "RECEIVER_MOTION_FILTER")]
+    motion_filter: Option<String>,
+
+    /// Shell command run for each closed and kept file, with its path as $1
+    #[arg(long, value_name = "CMD", env = "RECEIVER_POST_ROTATE")]
+    post_rotate: Option<String>,
+
+    /// Also copy each closed and kept file into this directory
+    #[arg(long, value_name = "PATH", env = "RECEIVER_MIRROR_DIR")]
+    mirror_dir: Option<PathBuf>,
+
+    /// Tag records around acceleration spikes above this magnitude (in g)
+    /// with a burst ID, written as a nullable `burst_id` column
+    #[arg(long, value_name = "G", env = "RECEIVER_BURST_THRESHOLD")]
+    burst_threshold: Option<f32>,
+
+    /// Add an `extended_timestamp` column: the sensor counter extended to
+    /// 64 bits, counting up across u32 rollovers
+    #[arg(long, env = "RECEIVER_EXTENDED_TIMESTAMP")]
+    extended_timestamp: bool,
+
+    /// Records tagged before the first and after the last spike of a burst
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_BURST_WINDOW, requires = "burst_threshold", env = "RECEIVER_BURST_WINDOW")]
+    burst_window: usize,
+
+    /// Second device's serial port; merges both streams by timestamp into one
+    /// file, without the per-record processing, rotation and extra outputs
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "csv_profile", "tuning", "calibration",
+            "calibrate_secs", "filter", "on_change", "decimate", "trigger",
+            "nonfinite_policy", "validate_range", "require_monotonic", "max_clock_skew",
+            "burst_threshold", "state_file", "append", "no_host_metadata",
+            "split_minutes", "align_rotation", "records_per_file", "max_file_size",
+            "rotate_on_rate_change", "file_per_rowgroup", "row_group_align", "name_by",
+            "stats_sidecar", "motion_filter", "post_rotate", "mirror_dir", "preview_png",
+            "mqtt_url", "influx_url", "raw_capture", "markers",
+        ],
+        env = "RECEIVER_MERGE_PORT"
+    )]
+    merge_port: Option<String>,
+
+    /// Start a new file after exactly this many records
+    #[arg(long, env = "RECEIVER_RECORDS_PER_FILE")]
+    records_per_file: Option<usize>,
+
+    /// Time files are named after: when they are created, or the earliest
+    /// record they hold, applied on close (creation, first-record)
+    #[arg(long, default_value = "creation", env = "RECEIVER_NAME_BY")]
+    name_by: String,
+
+    /// Write every flushed batch to its own file, holding a single row group
+    #[arg(
+        long,
+        conflicts_with = "records_per_file",
+        env = "RECEIVER_FILE_PER_ROWGROUP"
+    )]
+    file_per_rowgroup: bool,
+
+    /// Cut row groups at exact multiples of this many records (e.g. 4096 for
+    /// mmap-based readers); a file's last row group holds the remainder
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = ["records_per_file", "file_per_rowgroup"],
+        env = "RECEIVER_ROW_GROUP_ALIGN"
+    )]
+    row_group_align: Option<u64>,
+
+    /// Write a <name>.stats.json summary (count, time span, per-channel
+    /// min/max/mean) next to every closed Parquet file
+    #[arg(long, env = "RECEIVER_STATS_SIDECAR")]
+    stats_sidecar: bool,
+
+    /// Start a new file once the current one reaches this many bytes (0 = no limit)
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value = "0",
+        env = "RECEIVER_MAX_FILE_SIZE"
+    )]
+    max_file_size: u64,
+
+    /// Stop the capture instead of rotating into a new file when the output
+    /// directory's disk has fewer than this many bytes free
+    #[arg(long, value_name = "BYTES", env = "RECEIVER_MIN_FREE_BYTES")]
+    min_free_bytes: Option<u64>,
+
+    /// Do not store the host name and user name in the files' metadata
+    #[arg(long, env = "RECEIVER_NO_HOST_METADATA")]
+    no_host_metadata: bool,
+
+    /// Keep the run ID across restarts in this file; a corrupt or incompatible
+    /// file is ignored and a new session started
+    #[arg(long, value_name = "PATH", env = "RECEIVER_STATE_FILE")]
+    state_file: Option<PathBuf>,
+
+    /// Continue the session of the newest `<prefix>_*.parquet` in the output
+    /// directory: reuse its run ID, add a `session_id` column and keep
+    /// `<prefix>.latest` naming the file being written (Parquet only)
+    #[arg(long, conflicts_with = "state_file", env = "RECEIVER_APPEND")]
+    append: bool,
+
+    /// With --append, only continue a session whose newest file was written
+    /// within this many minutes; otherwise start a new one
+    #[arg(long, value_name = "MINUTES", default_value_t = DEFAULT_APPEND_MAX_AGE_MINUTES, requires = "append", env = "RECEIVER_APPEND_MAX_AGE")]
+    append_max_age: u64,
+
+    /// Record batches that may fail to be built (and are dropped) before the writer stops
+    #[arg(long, default_value_t = DEFAULT_MAX_DROPPED_BATCHES, env = "RECEIVER_MAX_DROPPED_BATCHES")]
+    max_dropped_batches: usize,
+
+    /// Print the record rate averaged over the last N seconds and since the
+    /// start in the stats readout, at least every N seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), env = "RECEIVER_RATE_WINDOW")]
+    rate_window: Option<u64>,
+
+    /// Print the largest gap between batches received by the writer in the
+    /// stats readout, at least every N seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), env = "RECEIVER_GAP_REPORT")]
+    gap_report: Option<u64>,
+
+    /// Print the record rate, channel backlog and current output file every N
+    /// seconds (0 = off)
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 0,
+        env = "RECEIVER_STATS_INTERVAL"
+    )]
+    stats_interval: u64,
+
+    /// Plot the accel magnitude of the capture to this PNG file at shutdown
+    #[arg(long, value_name = "PATH", env = "RECEIVER_PREVIEW_PNG")]
+    preview_png: Option<PathBuf>,
+
+    /// Require strictly increasing timestamps; on a backwards step either abort
+    /// (default) or count the violation (abort, count)
+    #[arg(long, num_args = 0..=1, default_missing_value = "abort", env = "RECEIVER_REQUIRE_MONOTONIC")]
+    require_monotonic: Option<String>,
+
+    /// Largest accepted difference in ms between the sensor counter, mapped to
+    /// wall time with --counter-hz, and the host clock
+    #[arg(
+        long,
+        value_name = "MS",
+        requires = "counter_hz",
+        env = "RECEIVER_MAX_CLOCK_SKEW"
+    )]
+    max_clock_skew: Option<i64>,
+
+    /// Check decoded values against the IMU ranges, given as MAX_G,MAX_DPS
+    /// (e.g. 16,4000); NaN and infinite values always fail
+    #[arg(long, value_name = "MAX_G,MAX_DPS", env = "RECEIVER_VALIDATE_RANGE")]
+    validate_range: Option<String>,
+
+    /// What to do with records failing --validate-range (tag, reject)
+    #[arg(
+        long,
+        default_value = "tag",
+        requires = "validate_range",
+        env = "RECEIVER_RANGE_POLICY"
+    )]
+    range_policy: String,
+
+    /// What to do with records beyond --max-clock-skew (flag, drop)
+    #[arg(
+        long,
+        default_value = "flag",
+        requires = "max_clock_skew",
+        env = "RECEIVER_CLOCK_SKEW_POLICY"
+    )]
+    clock_skew_policy: String,
+
+    /// Sort each buffered batch by timestamp before writing (costs a sort per flush)
+    #[arg(long, env = "RECEIVER_SORT_ON_FLUSH")]
+    sort_on_flush: bool,
+
+    /// Serve records as an Arrow Flight stream on this address instead of writing files
+    #[cfg(feature = "flight")]
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "merge_port", "format", "csv_profile", "append", "records_per_file",
+            "file_per_rowgroup", "row_group_align", "name_by", "stats_sidecar", "post_rotate",
+            "mirror_dir",
+        ],
+        env = "RECEIVER_FLIGHT"
+    )]
+    flight: Option<std::net::SocketAddr>,
+
+    /// Also publish every record as JSON to this MQTT broker (mqtt://host[:port])
+    #[arg(long, value_name = "URL", env = "RECEIVER_MQTT_URL")]
+    mqtt_url: Option<String>,
+
+    /// MQTT topic records are published to
+    #[arg(long, default_value = "receiver/imu", env = "RECEIVER_MQTT_TOPIC")]
+    mqtt_topic: String,
+
+    /// MQTT quality of service level (0, 1, 2)
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=2),
+        env = "RECEIVER_MQTT_QOS"
+    )]
+    mqtt_qos: u8,
+
+    /// Also write records to this InfluxDB server (http://host[:port])
+    #[arg(
+        long,
+        value_name = "URL",
+        requires = "influx_bucket",
+        env = "RECEIVER_INFLUX_URL"
+    )]
+    influx_url: Option<String>,
+
+    /// InfluxDB bucket records are written to
+    #[arg(long, env = "RECEIVER_INFLUX_BUCKET")]
+    influx_bucket: Option<String>,
+
+    /// InfluxDB organization owning the bucket
+    #[arg(long, env = "RECEIVER_INFLUX_ORG")]
+    influx_org: Option<String>,
+
+    /// InfluxDB API token
+    #[arg(long, env = "RECEIVER_INFLUX_TOKEN", hide_env_values = true)]
+    influx_token: Option<String>,
+
+    /// InfluxDB measurement name of the records
+    #[arg(long, default_value = "imu", env = "RECEIVER_INFLUX_MEASUREMENT")]
+    influx_measurement: String,
+
+    /// Largest host-time difference in milliseconds between merged records
+    #[arg(long, default_value = "10", env = "RECEIVER_MERGE_TOLERANCE_MS")]
+    merge_tolerance_ms: i64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that a Parquet file matches the schema written by this logger
+    Validate {
+        /// Parquet file to check
+        file: PathBuf,
+
+        #[command(flatten)]
+        layout: LayoutArgs,
+    },
+
+    /// Print the Arrow schema written by this logger and exit
+    Schema {
+        /// Output format (`json`: one object per field in the Arrow JSON
+        /// integration format, with name, nullability, type and children)
+        #[arg(long, default_value = "json", value_parser = ["json"])]
+        format: String,
+
+        #[command(flatten)]
+        layout: LayoutArgs,
+    },
+
+    /// Join captured files of one session into a single file, in time order
+    Convert {
+        /// Files to read; `*` and `?` match within the file name
+        /// (e.g. "logs/session_*.parquet")
+        #[arg(long)]
+        from: String,
+
+        /// File to write; its extension (parquet, csv, jsonl, imuc) picks the format
+        #[arg(long)]
+        to: PathBuf,
+
+        /// Compression of Parquet output (none, snappy, gzip, lz4, zstd)
+        #[arg(long, default_value = "snappy")]
+        compression: String,
+
+        /// Replace the file given with --to if it exists
+        #[arg(long)]
+        overwrite: bool,
+    },
+}
+
+/// Options of the capture that decide which columns a file has
+#[derive(Args, Debug)]
+struct LayoutArgs {
+    /// Nested gyro/accel struct layout, as written with --nested-schema
+    #[arg(long)]
+    nested_schema: bool,
+
+    /// The `timestamp_ns` column written with --counter-hz
+    #[arg(long)]
+    timestamp_ns: bool,
+
+    /// The layout written with this --primary-time
+    #[arg(long, default_value = "counter")]
+    primary_time: String,
+
+    /// The `accel_mag` column written with --with-accel-mag
+    #[arg(long)]
+    with_accel_mag: bool,
+
+    /// The `status` column written with --packed-timestamp
+    #[arg(long)]
+    packed_timestamp: bool,
+
+    /// Nullable sensor columns, as written with --lenient-nulls
+    #[arg(long)]
+    lenient_nulls: bool,
+
+    /// The `reference_time` column written with --reference-sync
+    #[arg(long)]
+    reference_time: bool,
+
+    /// The `valid` column written with --validate-range and --range-policy tag
+    #[arg(long)]
+    valid_column: bool,
+
+    /// The `burst_id` column written with --burst-threshold
+    #[arg(long)]
+    burst_id: bool,
+
+    /// The `clock_skew` column written with --max-clock-skew and --clock-skew-policy flag
+    #[arg(long)]
+    clock_skew: bool,
+
+    /// The `extended_timestamp` column written with --extended-timestamp
+    #[arg(long)]
+    extended_timestamp: bool,
+
+    /// The `session_id` column written with --append
+    #[arg(long)]
+    session_id: bool,
+
+    /// Column names prefixed as written with --column-prefix
+    #[arg(long)]
+    column_prefix: Option<String>,
+}
+
+impl LayoutArgs {
+    /// Writer options producing the described layout
+    ///
+    /// Only the presence of the optional columns matters for the schema, so
+    /// rates, clocks and limits are filled in with placeholders.
+    fn writer_options(&self) -> Result<WriterOptions> {
+        let primary_time = PrimaryTime::from_str(&self.primary_time)
+            .map_err(|e| anyhow::anyhow!("Invalid primary time source: {}", e))?;
+        let reference_clock = match self.reference_time {
+            true => Some(ReferenceClock::from_anchors(&[(0, 0), (1, 1)])?),
+            false => None,
+        };
+        Ok(WriterOptions {
+            counter_hz: self.timestamp_ns.then_some(1.0),
+            nested_schema: self.nested_schema,
+            primary_time,
+            accel_mag: self.with_accel_mag,
+            status_column: self.packed_timestamp,
+            column_prefix: self.column_prefix.clone(),
+            nullable_fields: self.lenient_nulls,
+            reference_clock,
+            range_check: self
+                .valid_column
+                .then(|| RangeCheck::new(f32::MAX, f32::MAX, RangePolicy::Tag)),
+            burst_column: self.burst_id,
+            clock_skew_column: self.clock_skew,
+            extended_timestamp: self.extended_timestamp,
+            session_column: self.session_id,
+            ..Default::default()
+        })
+    }
+}
+
+/// Parses a change threshold, which must be a number of at least zero
+fn parse_epsilon(value: &str) -> Result<f32, String> {
+    let epsilon: f32 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(epsilon >= 0.0 && epsilon.is_finite()) {
+        return Err(format!("{} is not a finite number of at least 0", value));
+    }
+    Ok(epsilon)
+}
+
+/// Rejects an option that only the Parquet output supports, given other formats
+fn require_parquet(formats: &str, option: &str) -> Result<()> {
+    if split_format_names(formats)
+        .iter()
+        .any(|name| !name.eq_ignore_ascii_case("parquet"))
+    {
+        return Err(ReceiverError::ConfigError(format!(
+            "{} only supports --format parquet, not {}",
+            option, formats
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Validates a Parquet file's schema, failing if it drifted
+fn validate(file: &Path, options: &WriterOptions) -> Result<()> {
+    let drift = validate_file(file, options)?;
+    if drift.is_empty() {
+        println!("{}: schema OK", file.display());
+        return Ok(());
+    }
+
+    for problem in &drift {
+        println!("{}: {}", file.display(), problem);
+    }
+    anyhow::bail!(
+        "{} does not match the expected schema ({} problem(s))",
+        file.display(),
+        drift.len()
+    )
+}
+
+/// Slowest and fastest baud rates accepted
+const BAUD_RATE_RANGE: std::ops::RangeInclusive<i64> = 300..=12_000_000;
+
+/// Standard baud rates, offered as suggestions for a rejected one
+const COMMON_BAUD_RATES: [u32; 12] = [
+    9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600, 1_000_000, 2_000_000, 3_000_000,
+    4_000_000,
+];
+
+/// Checks that a baud rate is one a serial port can plausibly run at
+///
+/// Rates outside [`BAUD_RATE_RANGE`] are rejected with the nearest common
+/// rates as suggestions, instead of surfacing as an OS error when the port
+/// is opened. Nonstandard rates within the range are accepted, since some
+/// adapters support arbitrary divisors.
+fn check_baud_rate(baud_rate: i64) -> Result<u32, ReceiverError> {
+    if BAUD_RATE_RANGE.contains(&baud_rate) {
+        return Ok(baud_rate as u32);
+    }
+    let mut nearest = COMMON_BAUD_RATES;
+    nearest.sort_by_key(|&rate| (rate as i64).abs_diff(baud_rate));
+    Err(ReceiverError::ConfigError(format!(
+        "Baud rate {} is out of range ({} to {}); did you mean {} or {}? The firmware sends at 921600 by default",
+        baud_rate,
+        BAUD_RATE_RANGE.start(),
+        BAUD_RATE_RANGE.end(),
+        nearest[0],
+        nearest[1]
+    )))
+}
+
+/// Checks that the output path is a directory or can be created as one
+///
+/// Catches paths that point at (or through) an existing file before any
+/// writer tries to create files there.
+fn check_output_dir(output_dir: &Path) -> Result<(), ReceiverError> {
+    if let Some(file) = output_dir.ancestors().find(|p| p.exists() && !p.is_dir()) {
+        return Err(ReceiverError::ConfigError(if file == output_dir {
+            format!(
+                "Output path {} exists but is not a directory",
+                output_dir.display()
+            )
+        } else {
+            format!(
+                "Output path {} cannot be a directory because {} is a file",
+                output_dir.display(),
+                file.display()
+            )
+        }));
+    }
+    Ok(())
+}
+
+/// Creates a writer streaming batches to Arrow Flight clients, if --flight was given
+#[cfg(feature = "flight")]
+fn flight_writer(
+    cli: &Cli,
+    buffer_size: usize,
+    options: &WriterOptions,
+) -> Result<Option<ParquetWriter>> {
+    let Some(addr) = cli.flight else {
+        return Ok(None);
+    };
+    let schema = Arc::new(ParquetWriter::build_schema(options));
+    let publisher = receiver::flight::start_flight_server(addr, schema)?;
+    Ok(Some(ParquetWriter::for_stream(
+        buffer_size,
+        options.clone(),
+        publisher.into_sink(),
+    )?))
+}
+
+#[cfg(not(feature = "flight"))]
+fn flight_writer(
+    _cli: &Cli,
+    _buffer_size: usize,
+    _options: &WriterOptions,
+) -> Result<Option<ParquetWriter>> {
+    Ok(None)
+}
+
+/// Wraps a source to start with a calibration capture, if --calibrate-secs was given
+fn calibrating(source: Box<dyn DataSource>, cli: &Cli) -> Box<dyn DataSource> {
+    let Some(secs) = cli.calibrate_secs else {
+        return source;
+    };
+    let output = cli
+        .calibrate_out
+        .clone()
+        .unwrap_or_else(|| Path::new(&cli.output_dir).join("calibration.txt"));
+    let mut source = CalibratingSource::new(source, secs as i64 * 1000, output);
+    if cli.calibrate_exit {
+        source = source.with_stop_after();
+    }
+    Box::new(source)
+}
+
+/// Wraps a source to decimate its records on the reader thread, if configured
+fn decimated(source: Box<dyn DataSource>, decimator: Option<Decimator>) -> Box<dyn DataSource> {
+    match decimator {
+        Some(decimator) => Box::new(DecimatedSource::new(source, decimator)),
+        None => source,
+    }
+}
+
+/// Returns the serial reader, or a simulation of the device in its place
+fn serial_source(serial_reader: SerialReaderWorker, simulation: bool) -> Box<dyn DataSource> {
+    if simulation {
+        Box::new(SimulatedSource::new(serial_reader))
+    } else {
+        Box::new(serial_reader)
+    }
+}
+
+/// Creates the bounded channel from a reader thread to the writer
+fn writer_channel(cli: &Cli) -> (BoundedSender, mpsc::Receiver<SensorData>) {
+    let policy = if cli.drop_on_full {
+        BackpressurePolicy::Drop
+    } else {
+        BackpressurePolicy::Block
+    };
+    BoundedSender::channel(cli.channel_capacity, policy)
+}
+
+/// Starts a reader thread feeding records into a channel
+fn spawn_reader(
+    source: impl DataSource + 'static,
+    running: Arc<AtomicBool>,
+    tx: BoundedSender,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        if let Err(e) = receiver::run_bounded(source, running, tx) {
+            eprintln!("Error in reader thread: {}", e);
+        }
+    })
+}
+
+/// Reads two devices and writes their streams merged by timestamp into one file
+fn run_merged(
+    cli: &Cli,
+    port: String,
+    baud_rate: u32,
+    merge_port: &str,
+    compression: CompressionType,
+    buffer_size: usize,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    let writer = MergedParquetWriter::new(
+        &cli.output_dir,
+        &cli.prefix,
+        compression,
+        buffer_size,
+        cli.row_group_size.map(|rows| rows as usize),
+    )?;
+    let merger = StreamMerger::new(cli.merge_tolerance_ms);
+
+    let framing = SerialFraming::parse(&cli.serial_config)?;
+    let reader = |port: String| {
+        let reader = SerialReaderWorker::new(port, baud_rate)
+            .with_framing(framing)
+            .with_read_buffer_size(cli.read_buffer_size as usize)
+            .with_parse_threads(cli.parse_threads as usize)
+            .with_reconnect_max_delay(Duration::from_secs(cli.reconnect_max_delay));
+        let reader = match cli.idle_sleep_ms {
+            Some(ms) => reader.with_idle_sleep(Duration::from_millis(ms)),
+            None => reader,
+        };
+        match &cli.wait_for_banner {
+            Some(banner) => reader.with_banner_wait(banner),
+            None => reader,
+        }
+    };
+
+    let (tx_a, rx_a) = writer_channel(cli);
+    let (tx_b, rx_b) = writer_channel(cli);
+    let reader_a = spawn_reader(
+        serial_source(reader(port), cli.simulation),
+        running.clone(),
+        tx_a,
+    );
+    let reader_b = spawn_reader(
+        serial_source(reader(merge_port.to_string()), cli.simulation),
+        running.clone(),
+        tx_b,
+    );
+
+    let merge_handle = thread::spawn(move || {
+        if let Err(e) = merge_loop(rx_a, rx_b, merger, writer) {
+            eprintln!("Error in stream merger thread: {}", e);
+        }
+    });
+
+    reader_a.join().expect("Serial reader thread panicked");
+    reader_b.join().expect("Serial reader thread panicked");
+    merge_handle.join().expect("Stream merger thread panicked");
+
+    println!("Receiver shutdown complete");
+
+    Ok(())
 }
 
 fn run() -> Result<()> {
-    let cli = Cli::parse();
+    let args = args_with_config(&Cli::command(), std::env::args_os().collect())?;
+    let cli = Cli::parse_from(args);
+    if cli.no_color {
+        disable_color();
+    }
+
+    match &cli.command {
+        Some(Command::Validate { file, layout }) => {
+            return validate(file, &layout.writer_options()?);
+        }
+        Some(Command::Schema { layout, .. }) => {
+            let schema = ParquetWriter::build_schema(&layout.writer_options()?);
+            println!("{:#}", schema_json(&schema));
+            return Ok(());
+        }
+        Some(Command::Convert {
+            from,
+            to,
+            compression,
+            overwrite,
+        }) => {
+            let compression =
+                CompressionType::from_str(compression).map_err(ReceiverError::ConfigError)?;
+            convert(from, to, compression, *overwrite)?;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let port = match (&cli.port, &cli.input) {
+        (Some(port), _) => port.clone(),
+        (None, Some(input)) => format!("(replay of {})", input.display()),
+        (None, None) => anyhow::bail!("A serial port is required (--port)"),
+    };
+    let baud_rate = check_baud_rate(cli.baud_rate)?;
+
+    let replay_window = ReplayWindow {
+        clock: ReplayClock::from_str(&cli.replay_clock)
+            .map_err(|e| anyhow::anyhow!("Invalid replay clock: {}", e))?,
+        start: cli.replay_start,
+        end: cli.replay_end,
+    };
+    let replay_loops =
+        ReplayLoops::from_str(&cli.replay_loop).map_err(ReceiverError::ConfigError)?;
+    let mqtt_url = cli
+        .mqtt_url
+        .as_deref()
+        .map(MqttUrl::from_str)
+        .transpose()
+        .map_err(ReceiverError::ConfigError)?;
+    let influx_target = match (&cli.influx_url, &cli.influx_bucket) {
+        (Some(url), Some(bucket)) => Some(
+            InfluxTarget::new(url, bucket)?
+                .with_org(cli.influx_org.clone())
+                .with_token(cli.influx_token.clone()),
+        ),
+        _ => None,
+    };
 
     // Parse compression type
     let compression = CompressionType::from_str(&cli.compression)
-        .map_err(|e| anyhow::anyhow!("Invalid compression algorithm: {}", e))?;
+        .map_err(|e| ReceiverError::ConfigError(format!("Invalid compression algorithm: {}", e)))?;
+
+    let primary_time = PrimaryTime::from_str(&cli.primary_time)
+        .map_err(|e| anyhow::anyhow!("Invalid primary time source: {}", e))?;
+    // The status byte leaves the low 24 bits of a packed field to the counter
+    let counter_width = match cli.packed_timestamp {
+        true => CounterWidth::Bits24,
+        false => CounterWidth::Bits32,
+    };
+    let file_naming = FileNaming::from_str(&cli.name_by)
+        .map_err(|e| anyhow::anyhow!("Invalid file naming: {}", e))?;
+    let data_format = DataFormat::from_str(&cli.input_format)
+        .map_err(|e| anyhow::anyhow!("Invalid input format: {}", e))?;
+    let sink_failure = SinkFailurePolicy::from_str(&cli.sink_failure)
+        .map_err(|e| anyhow::anyhow!("Invalid sink failure policy: {}", e))?;
+    let range_policy = RangePolicy::from_str(&cli.range_policy)
+        .map_err(|e| anyhow::anyhow!("Invalid range policy: {}", e))?;
+    let range_check = cli
+        .validate_range
+        .as_deref()
+        .map(|spec| RangeCheck::parse(spec, range_policy))
+        .transpose()?;
+    let framing = SerialFraming::parse(&cli.serial_config)?;
+
+    if let Some(hz) = cli.counter_hz {
+        if !(hz.is_finite() && hz > 0.0) {
+            anyhow::bail!("Invalid counter rate: {} (must be a positive number)", hz);
+        }
+    }
+
+    let reference_clock = cli
+        .reference_sync
+        .as_deref()
+        .map(ReferenceClock::load)
+        .transpose()?;
+
+    let csv_profile = cli
+        .csv_profile
+        .as_deref()
+        .map(CsvProfile::from_str)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid CSV profile: {}", e))?;
+
+    let filter = cli.filter.as_deref().map(Filter::parse).transpose()?;
+    let trigger = cli.trigger.as_deref().map(Filter::parse).transpose()?;
+    let motion_filter = cli
+        .motion_filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()?;
+    let decimate_mode =
+        DecimateMode::from_str(&cli.decimate_mode).map_err(ReceiverError::ConfigError)?;
+    if decimate_mode == DecimateMode::Average && trigger.is_some() {
+        return Err(ReceiverError::ConfigError(
+            "--trigger cannot be combined with --decimate-mode average".to_string(),
+        )
+        .into());
+    }
+    let nonfinite = cli
+        .nonfinite_policy
+        .as_deref()
+        .map(NonFiniteHandler::parse)
+        .transpose()
+        .map_err(ReceiverError::ConfigError)?;
+    let timestamp_mode =
+        TimestampMode::from_str(&cli.timestamp_mode).map_err(ReceiverError::ConfigError)?;
+    if timestamp_mode == TimestampMode::Device && cli.counter_hz.is_none() {
+        return Err(ReceiverError::ConfigError(
+            "--timestamp-mode device requires --counter-hz".to_string(),
+        )
+        .into());
+    }
+    if timestamp_mode == TimestampMode::Interpolated && cli.parse_threads > 1 {
+        // The pool hands out a read's records only once parsed, often after
+        // later reads have ended, so they could not be spread over their own read
+        return Err(ReceiverError::ConfigError(
+            "--timestamp-mode interpolated cannot be combined with --parse-threads above 1"
+                .to_string(),
+        )
+        .into());
+    }
+    let decimator = cli
+        .decimate
+        .map(|factor| Decimator::new(factor as usize).with_mode(decimate_mode));
+    // Decimate on the reader thread, unless every record has to be checked
+    // against the filter or trigger first
+    let (reader_decimator, writer_decimator) =
+        if decimate_mode == DecimateMode::Every && (filter.is_some() || trigger.is_some()) {
+            (None, decimator)
+        } else {
+            (decimator, None)
+        };
+
+    let monotonic_policy = cli
+        .require_monotonic
+        .as_deref()
+        .map(MonotonicPolicy::from_str)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid monotonic policy: {}", e))?;
+
+    let clock_skew_policy = ClockSkewPolicy::from_str(&cli.clock_skew_policy)
+        .map_err(|e| anyhow::anyhow!("Invalid clock skew policy: {}", e))?;
+
+    let layout = cli
+        .layout
+        .as_deref()
+        .map(|spec| LineLayout::parse(spec, cli.paired_scale))
+        .transpose()?;
+
+    // Derive the buffering parameters before constructing the workers
+    let tuning = cli
+        .tuning
+        .as_deref()
+        .map(TuningPreset::from_str)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid tuning preset: {}", e))?
+        .map(|preset| preset.params());
+    let buffer_size = tuning.map_or(cli.buffer_size, |params| params.buffer_size);
+    if let (Some(rows), Some(records)) = (cli.row_group_size, cli.row_group_align) {
+        // Aligned groups would otherwise be cut again at the size limit
+        if rows < records || rows % records != 0 {
+            return Err(ReceiverError::ConfigError(format!(
+                "--row-group-size {} must be a multiple of --row-group-align {}",
+                rows, records
+            ))
+            .into());
+        }
+    }
+
+    if cli.append {
+        // Only Parquet files carry the run ID and session column to continue
+        require_parquet(&cli.format, "--append")?;
+    }
+    if range_check.is_some() && range_policy == RangePolicy::Tag {
+        // The other formats have no `valid` column to tag records in
+        require_parquet(&cli.format, "--range-policy tag")?;
+    }
+    let compact = split_format_names(&cli.format)
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("compact"));
+    for (given, option) in [
+        (cli.burst_threshold.is_some(), "--burst-threshold"),
+        (cli.extended_timestamp, "--extended-timestamp"),
+    ] {
+        // Compact records have no field to store the column in
+        if given && compact {
+            return Err(ReceiverError::ConfigError(format!(
+                "{} cannot be used with --format compact",
+                option
+            ))
+            .into());
+        }
+    }
+
+    // Load the device calibration, if any
+    let calibration = cli
+        .calibration
+        .as_deref()
+        .map(Calibration::from_file)
+        .transpose()?;
 
     // Create output directory if it doesn't exist
+    check_output_dir(Path::new(&cli.output_dir))?;
     std::fs::create_dir_all(&cli.output_dir)
         .with_context(|| format!("Failed to create output directory: {}", cli.output_dir))?;
+    // Held until the end of the run; released when dropped
+    let _lock = OutputLock::acquire(Path::new(&cli.output_dir), &cli.prefix)?;
+
+    // Identifies every file and report of this capture session
+    let session = cli
+        .state_file
+        .as_deref()
+        .map(SessionState::resume_or_start)
+        .transpose()?;
+    // Parquet files cannot be reopened, so an appended session continues in a
+    // new file carrying the run ID of the last one
+    let appended = match cli.append && session.is_none() {
+        true => resume_run_id(
+            Path::new(&cli.output_dir),
+            &cli.prefix,
+            std::time::Duration::from_secs(cli.append_max_age * 60),
+        )?,
+        false => None,
+    };
+    let run_id = match (&session, &appended) {
+        (Some(session), _) => session.run_id.clone(),
+        (None, Some((run_id, _))) => run_id.clone(),
+        (None, None) => generate_run_id(),
+    };
 
     println!("Starting receiver with the following configuration:");
-    println!("  Port: {}", cli.port);
-    println!("  Baud rate: {}", cli.baud_rate);
+    println!("  Run ID: {}", run_id);
+    if let (Some(path), Some(session)) = (&cli.state_file, &session) {
+        println!(
+            "  State file: {} (session started {}, {} restarts)",
+            path.display(),
+            session.started_at,
+            session.restarts
+        );
+    }
+    if cli.append {
+        match &appended {
+            Some((_, path)) => println!("  Append: continuing the session of {}", path.display()),
+            None => println!(
+                "  Append: no file from the last {} minutes, starting a new session",
+                cli.append_max_age
+            ),
+        }
+    }
+    let host = (!cli.no_host_metadata).then(HostInfo::gather);
+    if let Some(host) = &host {
+        println!("  Host: {}@{}", host.username, host.hostname);
+    }
+    if let Some(path) = &cli.config {
+        println!("  Config file: {}", path.display());
+    }
+    println!("  Port: {}", port);
+    println!("  Baud rate: {}", baud_rate);
+    println!("  Read buffer size: {} bytes", cli.read_buffer_size);
+    println!(
+        "  Channel capacity: {} records ({} when full)",
+        cli.channel_capacity,
+        if cli.drop_on_full { "drop" } else { "block" }
+    );
+    println!("  Reconnect max delay: {} s", cli.reconnect_max_delay);
+    if let Some(ms) = cli.idle_sleep_ms {
+        println!("  Idle sleep: {} ms", ms);
+    }
+    if cli.parse_threads > 1 {
+        println!("  Parse threads: {}", cli.parse_threads);
+    }
+    println!("  Serial config: {}", cli.serial_config);
     println!("  Output directory: {}", cli.output_dir);
     println!("  Split interval: {} minutes", cli.split_minutes);
+    if cli.align_rotation {
+        println!("  Aligned rotation: on");
+    }
+    if cli.max_file_size > 0 {
+        println!("  Max file size: {} bytes", cli.max_file_size);
+    }
     println!("  File prefix: {}", cli.prefix);
+    println!("  Name files by: {}", cli.name_by);
+    println!("  Input format: {}", cli.input_format);
+    if cli.input.is_some() && replay_loops != ReplayLoops::default() {
+        println!(
+            "  Replay loops: {} (offset: {})",
+            cli.replay_loop, cli.replay_loop_offset
+        );
+    }
+    println!("  Format: {}", cli.format);
+    if split_format_names(&cli.format).len() > 1 {
+        println!("  On output failure: {}", cli.sink_failure);
+    }
+    if let Some(url) = &cli.mqtt_url {
+        println!(
+            "  MQTT: {} (topic {}, QoS {})",
+            url, cli.mqtt_topic, cli.mqtt_qos
+        );
+    }
+    if let (Some(url), Some(bucket)) = (&cli.influx_url, &cli.influx_bucket) {
+        println!(
+            "  InfluxDB: {} (bucket {}, measurement {})",
+            url, bucket, cli.influx_measurement
+        );
+    }
     println!("  Compression: {}", cli.compression);
-    println!("  Buffer size: {}", cli.buffer_size);
+    println!("  Buffer size: {}", buffer_size);
+    if let (Some(preset), Some(params)) = (&cli.tuning, tuning) {
+        println!(
+            "  Tuning: {} (flush every {:?}, {} rows per row group, {} records per wakeup)",
+            preset, params.flush_interval, params.row_group_size, params.coalesce
+        );
+    }
+    if let Some(rows) = cli.row_group_size {
+        println!("  Row group size: {} rows", rows);
+    }
     println!("  Simulation mode: {}", cli.simulation);
+    println!("  Start paused: {}", cli.start_paused);
+    println!("  Rotate on rate change: {}", cli.rotate_on_rate_change);
+    println!("  Primary time: {}", cli.primary_time);
+    if let Some(path) = &cli.reference_sync {
+        println!("  Reference sync file: {}", path.display());
+    }
+    if let Some(banner) = &cli.wait_for_banner {
+        println!("  Wait for banner: {}", banner);
+    }
+    if cli.synthesize_timestamp {
+        println!("  Synthesized timestamps: on");
+    }
+    println!("  Timestamp mode: {}", cli.timestamp_mode);
+    if cli.packed_timestamp {
+        println!("  Packed timestamp/status: on");
+    }
+    if cli.lenient_nulls {
+        println!("  Lenient nulls: on");
+    }
+    if let Some(layout) = &cli.layout {
+        println!(
+            "  Line layout: {} (paired scale {})",
+            layout, cli.paired_scale
+        );
+    }
+    if let Some(path) = &cli.markers {
+        println!("  Markers: {}", path.display());
+    }
+    if let Some(path) = &cli.raw_capture {
+        println!("  Raw capture: {}", path.display());
+    }
+    if cli.line_status_metadata {
+        println!("  Line status metadata: on");
+    }
+    if let Some(secs) = cli.calibrate_secs {
+        println!(
+            "  Calibration capture: {} s{}",
+            secs,
+            if cli.calibrate_exit {
+                ", then exit"
+            } else {
+                ""
+            }
+        );
+    }
+    if let Some(path) = &cli.calibration {
+        println!("  Calibration: {}", path.display());
+    }
+    if let Some(profile) = &cli.csv_profile {
+        println!("  CSV profile: {}", profile);
+    }
+    if let Some(policy) = &cli.nonfinite_policy {
+        println!("  Non-finite policy: {}", policy);
+    }
+    if let Some(filter) = &cli.filter {
+        println!("  Filter: {}", filter);
+    }
+    if let Some(epsilon) = cli.on_change {
+        println!("  On change: {}", epsilon);
+    }
+    if let Some(factor) = cli.decimate {
+        println!("  Decimate: 1 in {} ({})", factor, cli.decimate_mode);
+    }
+    if let Some(trigger) = &cli.trigger {
+        println!("  Trigger: {}", trigger);
+    }
+    if let Some(motion) = &cli.motion_filter {
+        println!("  Motion filter: {}", motion);
+    }
+    if let Some(command) = &cli.post_rotate {
+        println!("  Post-rotate command: {}", command);
+    }
+    if let Some(dir) = &cli.mirror_dir {
+        println!("  Mirror directory: {}", dir.display());
+    }
+    if cli.extended_timestamp {
+        println!("  Extended timestamp column: on");
+    }
+    if let Some(threshold) = cli.burst_threshold {
+        println!(
+            "  Burst detection: above {} g, {} records around",
+            threshold, cli.burst_window
+        );
+    }
+    if let Some(max_records) = cli.records_per_file {
+        println!("  Records per file: {}", max_records);
+    }
+    if cli.stats_sidecar {
+        println!("  Stats sidecar: on");
+    }
+    if cli.file_per_rowgroup {
+        println!("  File per row group: on");
+    }
+    if let Some(records) = cli.row_group_align {
+        println!("  Row group alignment: {} records", records);
+    }
+    if cli.sort_on_flush {
+        println!("  Sort on flush: on");
+    }
+    if let Some(prefix) = &cli.column_prefix {
+        println!("  Column prefix: {}", prefix);
+    }
+    if let Some(policy) = &cli.require_monotonic {
+        println!("  Require monotonic timestamps: {}", policy);
+    }
+    if let Some(spec) = &cli.validate_range {
+        println!("  Validate range: {} ({})", spec, cli.range_policy);
+    }
+    if let Some(ms) = cli.max_clock_skew {
+        println!("  Max clock skew: {} ms ({})", ms, cli.clock_skew_policy);
+    }
+    if let Some(secs) = cli.rate_window {
+        println!("  Rate window: {} s", secs);
+    }
+    if let Some(secs) = cli.gap_report {
+        println!("  Gap report: every {} s", secs);
+    }
+    if cli.stats_interval > 0 {
+        println!("  Stats interval: {} s", cli.stats_interval);
+    }
+    if let Some(path) = &cli.preview_png {
+        println!("  Preview PNG: {}", path.display());
+    }
+    if let Some(bytes) = cli.min_free_bytes {
+        println!("  Minimum free space: {} bytes", bytes);
+    }
+    if let Some(merge_port) = &cli.merge_port {
+        println!("  Merge port: {}", merge_port);
+        println!("  Merge tolerance: {} ms", cli.merge_tolerance_ms);
+    }
 
     // Set up ctrl-c handler
     let running = Arc::new(AtomicBool::new(true));
@@ -77,22 +1442,286 @@ fn run() -> Result<()> {
     })
     .with_context(|| "Error setting Ctrl-C handler")?;
 
+    if let Some(merge_port) = &cli.merge_port {
+        return run_merged(
+            &cli,
+            port,
+            baud_rate,
+            merge_port,
+            compression,
+            buffer_size,
+            running,
+        );
+    }
+
     // Create a channel for communication between threads
-    let (tx, rx) = mpsc::channel();
+    let (mut tx, rx) = writer_channel(&cli);
+
+    // An explicit row-group size wins over the preset's, which is rounded up
+    // to whole aligned groups
+    let max_row_group_size = match (cli.row_group_size, tuning) {
+        (Some(rows), _) => Some(rows as usize),
+        (None, Some(params)) => Some(match cli.row_group_align {
+            Some(records) => params.row_group_size.div_ceil(records as usize) * records as usize,
+            None => params.row_group_size,
+        }),
+        (None, None) => None,
+    };
 
     // Create parquet writer
-    let writer = ParquetWriter::new(&cli.output_dir, &cli.prefix, compression, cli.buffer_size)?;
+    let options = WriterOptions {
+        counter_hz: cli.counter_hz,
+        nested_schema: cli.nested_schema,
+        primary_time,
+        accel_mag: cli.with_accel_mag,
+        run_id: Some(run_id.clone()),
+        host,
+        status_column: cli.packed_timestamp,
+        max_row_group_size,
+        column_prefix: cli.column_prefix.clone(),
+        // A layout without temperature, and non-finite values turned into
+        // nulls, are stored as null
+        nullable_fields: cli.lenient_nulls
+            || layout.as_ref().is_some_and(|l| !l.has_temp())
+            || nonfinite.as_ref().is_some_and(|h| h.needs_nullable()),
+        reference_clock,
+        range_check,
+        burst_column: cli.burst_threshold.is_some(),
+        clock_skew_column: cli.max_clock_skew.is_some()
+            && clock_skew_policy == ClockSkewPolicy::Flag,
+        extended_timestamp: cli.extended_timestamp,
+        counter_width,
+        session_column: cli.append,
+    };
+    let writer: Box<dyn DataSink> = match flight_writer(&cli, buffer_size, &options)? {
+        Some(mut writer) => {
+            if cli.sort_on_flush {
+                writer = writer.with_sort_on_flush();
+            }
+            Box::new(writer)
+        }
+        None => {
+            let config = SinkConfig {
+                output_dir: cli.output_dir.clone(),
+                prefix: cli.prefix.clone(),
+                compression,
+                buffer_size,
+                options,
+                max_records_per_file: cli.records_per_file,
+                sort_on_flush: cli.sort_on_flush,
+                file_per_row_group: cli.file_per_rowgroup,
+                row_group_alignment: cli.row_group_align.map(|records| records as usize),
+                file_naming,
+                stats_sidecar: cli.stats_sidecar,
+                csv_profile: csv_profile.unwrap_or_default(),
+            };
+            SinkRegistry::with_builtin_sinks().create_all(&cli.format, &config, sink_failure)?
+        }
+    };
+    let mut outputs: Vec<(String, Box<dyn DataSink>)> = vec![("files".to_string(), writer)];
+    if let Some(url) = mqtt_url {
+        let client_id = format!("receiver-{}", run_id);
+        let mqtt = MqttSink::start(
+            url,
+            &cli.mqtt_topic,
+            cli.mqtt_qos,
+            &client_id,
+            DEFAULT_MQTT_QUEUE,
+        )?;
+        outputs.push(("mqtt".to_string(), Box::new(mqtt)));
+    }
+    if let Some(target) = influx_target {
+        let influx = InfluxSink::start(
+            target,
+            &cli.influx_measurement,
+            buffer_size,
+            DEFAULT_INFLUX_QUEUE,
+        )?;
+        outputs.push(("influx".to_string(), Box::new(influx)));
+    }
+    // A problem with a live feed must never stop the file output
+    let writer: Box<dyn DataSink> = match outputs.len() {
+        1 => outputs.remove(0).1,
+        _ => Box::new(FanoutSink::new(outputs, SinkFailurePolicy::Continue)),
+    };
+
+    // Set up the pause control and its resume trigger
+    let resume_file = cli
+        .resume_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(&cli.output_dir).join("RESUME"));
+    let pause = PauseControl::new(cli.start_paused, Some(resume_file.clone()));
+    if cli.start_paused {
+        println!(
+            "Writing paused; create {} to start recording",
+            resume_file.display()
+        );
+    }
 
     // Create file writer worker
-    let file_writer = FileWriterWorker::new(
+    let mut file_writer = FileWriterWorker::new(
         writer,
         cli.split_minutes,
         cli.output_dir.clone(),
         cli.prefix.clone(),
-    );
+    )
+    .with_pause_control(pause)
+    .with_counter_width(counter_width)
+    .with_max_file_bytes(cli.max_file_size)
+    .with_max_dropped_batches(cli.max_dropped_batches);
+    if cli.align_rotation {
+        file_writer = file_writer.with_aligned_rotation();
+    }
+    if cli.rotate_on_rate_change {
+        file_writer = file_writer.with_rate_change_rotation(cli.counter_hz);
+    }
+    if let Some(calibration) = calibration {
+        file_writer = file_writer.with_calibration(calibration);
+    }
+    if let Some(handler) = nonfinite {
+        file_writer = file_writer.with_nonfinite_handler(handler);
+    }
+    if let Some(filter) = filter {
+        file_writer = file_writer.with_filter(filter);
+    }
+    if let Some(epsilon) = cli.on_change {
+        file_writer = file_writer.with_change_filter(ChangeFilter::new(epsilon));
+    }
+    if let Some(mut decimator) = writer_decimator {
+        if let Some(trigger) = trigger {
+            decimator = decimator.with_trigger(trigger);
+        }
+        file_writer = file_writer.with_decimator(decimator);
+    }
+    if motion_filter.is_some() || cli.post_rotate.is_some() || cli.mirror_dir.is_some() {
+        let mut hook = RotationHook::new();
+        if let Some(motion) = motion_filter {
+            hook = hook.with_motion_filter(motion);
+        }
+        if let Some(command) = cli.post_rotate.clone() {
+            hook = hook.with_command(command);
+        }
+        if let Some(dir) = cli.mirror_dir.clone() {
+            hook = hook.with_mirror_dir(dir);
+        }
+        file_writer = file_writer.with_rotation_hook(hook);
+    }
+    if cli.append {
+        file_writer = file_writer.with_latest_pointer();
+    }
+    if let Some(threshold) = cli.burst_threshold {
+        file_writer =
+            file_writer.with_burst_detector(BurstDetector::new(threshold, cli.burst_window));
+    }
+    if let Some(policy) = monotonic_policy {
+        file_writer = file_writer.with_monotonic_check(policy);
+    }
+    if let (Some(ms), Some(hz)) = (cli.max_clock_skew, cli.counter_hz) {
+        file_writer = file_writer.with_clock_check(ClockCheck::new(hz, ms, clock_skew_policy));
+    }
+    if let Some(bytes) = cli.min_free_bytes {
+        file_writer = file_writer.with_min_free_bytes(bytes);
+    }
+    if let Some(secs) = cli.rate_window {
+        file_writer = file_writer.with_rate_window(Duration::from_secs(secs));
+    }
+    if let Some(secs) = cli.gap_report {
+        file_writer = file_writer.with_gap_report(Duration::from_secs(secs));
+    }
+    if cli.stats_interval > 0 {
+        // The gauge tracks the channel backlog for the readout
+        let gauge = InFlightGauge::new();
+        tx = tx.with_gauge(gauge.clone());
+        file_writer = file_writer
+            .with_in_flight_gauge(gauge)
+            .with_stats_interval(Duration::from_secs(cli.stats_interval));
+    }
+    if let Some(path) = cli.preview_png.clone() {
+        file_writer = file_writer.with_preview_png(path);
+    }
+    if let Some(params) = tuning {
+        file_writer = file_writer
+            .with_flush_interval(params.flush_interval)
+            .with_coalescing(params.coalesce);
+    }
+    // A CSV primary output already uses the profile; no separate copy is needed
+    let writes_csv = split_format_names(&cli.format)
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case("csv"));
+    if let Some(profile) = csv_profile.filter(|_| !writes_csv) {
+        let csv = CsvWriter::new(&cli.output_dir, &cli.prefix, profile)?;
+        file_writer = file_writer.with_csv_writer(csv);
+    }
 
     // Create serial reader worker
-    let serial_reader = SerialReaderWorker::new(cli.port.clone(), cli.baud_rate);
+    let mut serial_reader = SerialReaderWorker::new(port, baud_rate)
+        .with_framing(framing)
+        .with_data_format(data_format)
+        .with_read_buffer_size(cli.read_buffer_size as usize)
+        .with_parse_threads(cli.parse_threads as usize)
+        .with_reconnect_max_delay(Duration::from_secs(cli.reconnect_max_delay));
+    if let Some(ms) = cli.idle_sleep_ms {
+        serial_reader = serial_reader.with_idle_sleep(Duration::from_millis(ms));
+    }
+    if let Some(check) = range_check {
+        serial_reader = serial_reader.with_range_check(check);
+    }
+    if let Some(banner) = &cli.wait_for_banner {
+        serial_reader = serial_reader.with_banner_wait(banner);
+    }
+    if cli.synthesize_timestamp {
+        serial_reader = serial_reader.with_synthesized_timestamps();
+    }
+    match (timestamp_mode, cli.counter_hz) {
+        (TimestampMode::Interpolated, _) => {
+            serial_reader = serial_reader.with_interpolated_timestamps();
+        }
+        (TimestampMode::Device, Some(hz)) => {
+            serial_reader = serial_reader.with_device_timestamps(hz);
+        }
+        _ => {}
+    }
+    if cli.packed_timestamp {
+        serial_reader = serial_reader.with_packed_timestamp();
+    }
+    if cli.lenient_nulls {
+        serial_reader = serial_reader.with_lenient_nulls();
+    }
+    if let Some(layout) = layout {
+        serial_reader = serial_reader.with_layout(layout);
+    }
+    if let Some(path) = &cli.raw_capture {
+        serial_reader = serial_reader.with_raw_capture(RawCapture::create(path)?);
+    }
+    if cli.line_status_metadata {
+        let log = LineStatusLog::new();
+        serial_reader = serial_reader.with_line_status_log(log.clone());
+        file_writer = file_writer.with_line_status_log(log);
+    }
+
+    // Report liveness to systemd when running as a service
+    #[cfg(feature = "systemd")]
+    if let Some(watchdog) = receiver::watchdog::systemd_watchdog() {
+        serial_reader = serial_reader.with_watchdog(watchdog);
+    }
+
+    // Not joined: the thread stays blocked reading stdin until input ends
+    if let Some(path) = &cli.markers {
+        let log = MarkerLog::create(path)?;
+        let running_markers = running.clone();
+        thread::spawn(move || {
+            let stdin = std::io::stdin().lock();
+            let mut confirm = |marker: &Marker| {
+                println!("Marker at {}: {}", marker.system_timestamp, marker.label);
+            };
+            if let Err(e) = marker_loop(stdin, log, running_markers, &mut confirm) {
+                eprintln!("Error in marker thread: {}", e);
+            }
+        });
+        println!("Type a label and press Enter to record a marker");
+    }
+
+    let detected_format = serial_reader.detected_format();
 
     // Start file writer thread
     let running_writer = running.clone();
@@ -102,32 +1731,42 @@ fn run() -> Result<()> {
         }
     });
 
-    // Start serial reader thread
-    let running_reader = running.clone();
-    let reader_handle = thread::spawn(move || {
-        let result = if cli.simulation {
-            // Run in simulation mode
-            serial_reader.simulate_data_loop(running_reader, move |data| {
-                tx.send(data)
-                    .map_err(|e| anyhow::anyhow!("Channel send error: {}", e))
-            })
-        } else {
-            // Run with real serial port
-            serial_reader.read_serial_loop(running_reader, move |data| {
-                tx.send(data)
-                    .map_err(|e| anyhow::anyhow!("Channel send error: {}", e))
-            })
-        };
-
-        if let Err(e) = result {
-            eprintln!("Error in serial reader thread: {}", e);
+    // Start serial reader thread, or replay a captured file
+    let reader_handle = match cli.input.clone() {
+        Some(input) => {
+            let replay = ReplayReader::new(input)
+                .with_window(replay_window)
+                .with_data_format(data_format)
+                .with_loops(replay_loops, cli.replay_loop_offset)
+                .with_counter_width(counter_width);
+            spawn_reader(
+                decimated(calibrating(Box::new(replay), &cli), reader_decimator),
+                running.clone(),
+                tx,
+            )
         }
-    });
+        None => spawn_reader(
+            decimated(
+                calibrating(serial_source(serial_reader, cli.simulation), &cli),
+                reader_decimator,
+            ),
+            running.clone(),
+            tx,
+        ),
+    };
 
-    // Wait for threads to complete
+    // Shutdown handshake: the reader stops on the cleared running flag, sends
+    // its last records and drops its sender; only then does the writer, which
+    // keeps receiving until that disconnect, close the files
     reader_handle.join().expect("Serial reader thread panicked");
     writer_handle.join().expect("File writer thread panicked");
 
+    if data_format == DataFormat::Auto && cli.input.is_none() {
+        match detected_format.get() {
+            Some(format) => println!("Input format: {} (detected)", format),
+            None => println!("Input format: not detected before shutdown"),
+        }
+    }
     println!("Receiver shutdown complete");
 
     Ok(())
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
@@ -8,7 +8,10 @@ use tempfile::tempdir;
 
 // Import crate from the lib
 extern crate receiver;
-use receiver::{CompressionType, FileWriterWorker, ParquetWriter, SensorData, SerialReaderWorker};
+use receiver::{
+    CompressionType, DataSource, FileWriterWorker, ParquetWriter, ReplayReader, SensorData,
+    SerialReaderWorker, SimulatedSource,
+};
 
 #[test]
 fn test_end_to_end_async_processing() -> Result<()> {
@@ -34,7 +37,7 @@ fn test_end_to_end_async_processing() -> Result<()> {
 
     // Create file writer worker
     let file_writer = FileWriterWorker::new(
-        writer,
+        Box::new(writer),
         0, // No file splitting
         dir_path.clone(),
         "async_test".to_string(),
@@ -112,7 +115,7 @@ fn test_file_rotation() -> Result<()> {
 
     // Create file writer worker with very short rotation time for testing
     let file_writer = FileWriterWorker::new(
-        writer,
+        Box::new(writer),
         0, // We'll trigger rotation manually
         dir_path.clone(),
         "rotation_test".to_string(),
@@ -137,6 +140,8 @@ fn test_file_rotation() -> Result<()> {
             ay: 1.1 * i as f32,
             az: 1.2 * i as f32,
             system_timestamp: chrono::Utc::now().timestamp_millis(),
+            monotonic_timestamp: receiver::monotonic_timestamp(),
+            ..Default::default()
         };
         tx.send(data)?;
     }
@@ -167,3 +172,227 @@ fn test_file_rotation() -> Result<()> {
 
     Ok(())
 }
+
+// Source yielding a fixed sequence of counter values, then ending
+struct FixedSource {
+    timestamps: Vec<u32>,
+}
+
+impl DataSource for FixedSource {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        for timestamp in self.timestamps {
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+            data_callback(SensorData {
+                timestamp,
+                temp: 25.0,
+                az: 1.0,
+                system_timestamp: chrono::Utc::now().timestamp_millis(),
+                monotonic_timestamp: receiver::monotonic_timestamp(),
+                ..Default::default()
+            })?;
+        }
+        Ok(())
+    }
+}
+
+// Source streaming records until stopped, then releasing a held-back final
+// batch the way the serial reader flushes its parse pool and byte-order check
+struct FlushingSource {
+    sent: Arc<AtomicUsize>,
+}
+
+impl DataSource for FlushingSource {
+    fn read_loop(
+        self: Box<Self>,
+        running: Arc<AtomicBool>,
+        data_callback: &mut dyn FnMut(SensorData) -> Result<()>,
+    ) -> Result<()> {
+        let record = |timestamp| SensorData {
+            timestamp,
+            temp: 25.0,
+            az: 1.0,
+            system_timestamp: chrono::Utc::now().timestamp_millis(),
+            monotonic_timestamp: receiver::monotonic_timestamp(),
+            ..Default::default()
+        };
+        let mut timestamp = 0;
+        while running.load(Ordering::SeqCst) {
+            data_callback(record(timestamp))?;
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            timestamp += 1;
+            thread::sleep(Duration::from_micros(200));
+        }
+        // Finishing up takes a while after the stop signal
+        thread::sleep(Duration::from_millis(20));
+        for _ in 0..7 {
+            data_callback(record(timestamp))?;
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            timestamp += 1;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_repeated_short_captures_keep_final_batch() -> Result<()> {
+    for capture in 0..20 {
+        let temp_dir = tempdir()?;
+        let dir_path = temp_dir.path().to_str().unwrap().to_string();
+        let (tx, rx) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let writer = ParquetWriter::new(&dir_path, "short", CompressionType::None, 16)?;
+        let file_writer =
+            FileWriterWorker::new(Box::new(writer), 0, dir_path.clone(), "short".to_string());
+        let running_writer = running.clone();
+        let writer_handle =
+            thread::spawn(move || file_writer.process_data_loop(rx, running_writer));
+
+        let sent = Arc::new(AtomicUsize::new(0));
+        let source = FlushingSource { sent: sent.clone() };
+        let running_reader = running.clone();
+        let reader_handle = thread::spawn(move || receiver::run(source, running_reader, tx));
+
+        // Stop at varying points, including right away
+        thread::sleep(Duration::from_millis(capture % 5 * 3));
+        running.store(false, Ordering::SeqCst);
+        reader_handle
+            .join()
+            .expect("Serial reader thread panicked")?;
+        writer_handle.join().expect("File writer thread panicked")?;
+
+        let mut written = 0;
+        for entry in std::fs::read_dir(&dir_path)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "parquet") {
+                ReplayReader::new(path).replay_loop(Arc::new(AtomicBool::new(true)), |_| {
+                    written += 1;
+                    Ok(())
+                })?;
+            }
+        }
+        assert_eq!(written, sent.load(Ordering::SeqCst), "capture {}", capture);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_source_feeds_writer() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir_path = temp_dir.path().to_str().unwrap().to_string();
+    let (tx, rx) = mpsc::channel();
+    let running = Arc::new(AtomicBool::new(true));
+
+    let writer = ParquetWriter::new(&dir_path, "source_test", CompressionType::Snappy, 4)?;
+    let file_writer = FileWriterWorker::new(
+        Box::new(writer),
+        0,
+        dir_path.clone(),
+        "source_test".to_string(),
+    );
+    let running_writer = running.clone();
+    let writer_handle = thread::spawn(move || file_writer.process_data_loop(rx, running_writer));
+
+    // The writer stops once the source ends and the channel disconnects
+    let timestamps = vec![3, 5, 8, 13, 21, 34, 55];
+    let source = FixedSource {
+        timestamps: timestamps.clone(),
+    };
+    receiver::run(source, running, tx)?;
+    writer_handle.join().expect("File writer thread panicked")?;
+
+    let path = std::fs::read_dir(&dir_path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "parquet"))
+        .expect("No Parquet file was created");
+    let mut written = Vec::new();
+    ReplayReader::new(path).replay_loop(Arc::new(AtomicBool::new(true)), |data| {
+        written.push(data.timestamp);
+        Ok(())
+    })?;
+    assert_eq!(written, timestamps);
+
+    Ok(())
+}
+
+#[test]
+fn test_simulated_run_writes_preview_png() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir_path = temp_dir.path().to_str().unwrap().to_string();
+    let preview_path = temp_dir.path().join("preview.png");
+    let (tx, rx) = mpsc::channel();
+    let running = Arc::new(AtomicBool::new(true));
+
+    let writer = ParquetWriter::new(&dir_path, "preview_test", CompressionType::Snappy, 10)?;
+    let file_writer = FileWriterWorker::new(
+        Box::new(writer),
+        0,
+        dir_path.clone(),
+        "preview_test".to_string(),
+    )
+    .with_preview_png(preview_path.clone());
+    let running_writer = running.clone();
+    let writer_handle = thread::spawn(move || file_writer.process_data_loop(rx, running_writer));
+
+    let source = SimulatedSource::new(SerialReaderWorker::new("test_port".to_string(), 115200));
+    let running_reader = running.clone();
+    let reader_handle = thread::spawn(move || receiver::run(source, running_reader, tx));
+    thread::sleep(Duration::from_millis(300));
+    running.store(false, Ordering::SeqCst);
+    reader_handle
+        .join()
+        .expect("Serial reader thread panicked")?;
+    writer_handle.join().expect("File writer thread panicked")?;
+
+    // PNG signature followed by an IHDR chunk with the image size
+    let png = std::fs::read(&preview_path)?;
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    let width = u32::from_be_bytes(png[16..20].try_into()?);
+    let height = u32::from_be_bytes(png[20..24].try_into()?);
+    assert_eq!((width, height), receiver::DEFAULT_PREVIEW_SIZE);
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+    Ok(())
+}
+
+#[test]
+fn test_preview_failure_does_not_fail_capture() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir_path = temp_dir.path().to_str().unwrap().to_string();
+    // The preview's directory does not exist, so it cannot be written
+    let preview_path = temp_dir.path().join("missing").join("preview.png");
+    let (tx, rx) = mpsc::channel();
+
+    let writer = ParquetWriter::new(&dir_path, "preview_test", CompressionType::Snappy, 10)?;
+    let file_writer = FileWriterWorker::new(
+        Box::new(writer),
+        0,
+        dir_path.clone(),
+        "preview_test".to_string(),
+    )
+    .with_preview_png(preview_path.clone());
+    tx.send(receiver::parse_sensor_data("00000123,0,0,0,0,0,0,0")?)?;
+    drop(tx);
+    file_writer.process_data_loop(rx, Arc::new(AtomicBool::new(true)))?;
+
+    assert!(!preview_path.exists());
+    let parquet_files = std::fs::read_dir(&dir_path)?
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .is_ok_and(|e| e.path().extension().and_then(|ext| ext.to_str()) == Some("parquet"))
+        })
+        .count();
+    assert_eq!(parquet_files, 1);
+
+    Ok(())
+}
@@ -65,3 +65,347 @@ fn test_cli_output_dir_creation() {
     // Success - we don't actually need to run the command
     // The real integration test is in async_tests.rs
 }
+
+#[test]
+fn test_cli_output_dir_is_file() {
+    let temp_dir = tempdir().unwrap();
+    let file_path = temp_dir.path().join("not_a_dir");
+    std::fs::write(&file_path, b"").unwrap();
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args(["-p", "dummy_port", "-m", "-o"]).arg(&file_path);
+    cmd.assert().failure().stderr(
+        predicate::str::contains("Invalid configuration: Output path")
+            .and(predicate::str::contains("exists but is not a directory")),
+    );
+}
+
+fn write_valid_file(dir: &std::path::Path) -> std::path::PathBuf {
+    let mut writer = receiver::ParquetWriter::new(
+        dir.to_str().unwrap(),
+        "validate",
+        receiver::CompressionType::Snappy,
+        10,
+    )
+    .unwrap();
+    writer
+        .add_data(receiver::SensorData {
+            timestamp: 1,
+            temp: 25.0,
+            az: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+    writer.close().unwrap();
+
+    std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+}
+
+#[test]
+fn test_cli_validate_valid_file() {
+    let temp_dir = tempdir().unwrap();
+    let path = write_valid_file(temp_dir.path());
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.arg("validate").arg(&path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("schema OK"));
+}
+
+#[test]
+fn test_cli_validate_mismatched_file() {
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("mismatch.parquet");
+
+    let schema = Arc::new(Schema::new(vec![Field::new(
+        "timestamp",
+        DataType::Int32,
+        false,
+    )]));
+    let batch =
+        RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(vec![1]))]).unwrap();
+    let file = std::fs::File::create(&path).unwrap();
+    let mut writer = ArrowWriter::try_new(file, schema, None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.arg("validate").arg(&path);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("missing column 'temp'"))
+        .stderr(predicate::str::contains(
+            "does not match the expected schema",
+        ));
+}
+
+#[test]
+fn test_cli_env_var_used_unless_flag_given() {
+    let temp_dir = tempdir().unwrap();
+    let env_file = temp_dir.path().join("env_output");
+    let flag_file = temp_dir.path().join("flag_output");
+    std::fs::write(&env_file, b"").unwrap();
+    std::fs::write(&flag_file, b"").unwrap();
+
+    // Both paths are files, so the error names whichever output directory won
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env("RECEIVER_PORT", "dummy_port")
+        .env("RECEIVER_SIMULATION", "true")
+        .env("RECEIVER_OUTPUT_DIR", &env_file);
+    cmd.assert().failure().stderr(
+        predicate::str::contains(env_file.to_string_lossy())
+            .and(predicate::str::contains("exists but is not a directory")),
+    );
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env("RECEIVER_PORT", "dummy_port")
+        .env("RECEIVER_SIMULATION", "true")
+        .env("RECEIVER_OUTPUT_DIR", &env_file)
+        .arg("-o")
+        .arg(&flag_file);
+    cmd.assert().failure().stderr(
+        predicate::str::contains(flag_file.to_string_lossy())
+            .and(predicate::str::contains(env_file.to_string_lossy()).not()),
+    );
+}
+
+#[test]
+fn test_cli_schema_json_lists_fields() {
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args([
+        "schema",
+        "--format",
+        "json",
+        "--with-accel-mag",
+        "--valid-column",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let schema: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let names: Vec<&str> = schema["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "timestamp",
+            "temp",
+            "gx",
+            "gy",
+            "gz",
+            "ax",
+            "ay",
+            "az",
+            "accel_mag",
+            "system_timestamp",
+            "valid"
+        ]
+    );
+    assert_eq!(schema["fields"][1]["type"]["precision"], "SINGLE");
+    assert_eq!(schema["fields"][1]["nullable"], false);
+}
+
+#[test]
+fn test_cli_config_file_precedence() {
+    let temp_dir = tempdir().unwrap();
+    let config_file = temp_dir.path().join("config_output");
+    let env_file = temp_dir.path().join("env_output");
+    let flag_file = temp_dir.path().join("flag_output");
+    for file in [&config_file, &env_file, &flag_file] {
+        std::fs::write(file, b"").unwrap();
+    }
+    let config = temp_dir.path().join("receiver.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "port = \"dummy_port\"\nsimulation = true\noutput_dir = {:?}\n",
+            config_file.to_string_lossy()
+        ),
+    )
+    .unwrap();
+
+    // Every output directory is a file, so the error names whichever one won
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env_remove("RECEIVER_OUTPUT_DIR")
+        .arg("--config")
+        .arg(&config);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(config_file.to_string_lossy()));
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env("RECEIVER_OUTPUT_DIR", &env_file)
+        .arg("--config")
+        .arg(&config);
+    cmd.assert().failure().stderr(
+        predicate::str::contains(env_file.to_string_lossy())
+            .and(predicate::str::contains(config_file.to_string_lossy()).not()),
+    );
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env("RECEIVER_OUTPUT_DIR", &env_file)
+        .arg("--config")
+        .arg(&config)
+        .arg("-o")
+        .arg(&flag_file);
+    cmd.assert().failure().stderr(
+        predicate::str::contains(flag_file.to_string_lossy())
+            .and(predicate::str::contains(env_file.to_string_lossy()).not())
+            .and(predicate::str::contains(config_file.to_string_lossy()).not()),
+    );
+}
+
+#[test]
+fn test_cli_channel_capacity_rejects_zero() {
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args(["-p", "dummy_port", "-m", "--channel-capacity", "0"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--channel-capacity"));
+}
+
+#[test]
+fn test_cli_flag_beats_conflicting_config_keys() {
+    let temp_dir = tempdir().unwrap();
+    let output_file = temp_dir.path().join("output");
+    std::fs::write(&output_file, b"").unwrap();
+    let config = temp_dir.path().join("receiver.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "port = \"dummy_port\"\nsimulation = true\nbuffer_size = 50\noutput_dir = {:?}\n",
+            output_file.to_string_lossy()
+        ),
+    )
+    .unwrap();
+
+    // --tuning conflicts with buffer_size; the flag wins and the run gets as
+    // far as the output directory
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env_remove("RECEIVER_OUTPUT_DIR")
+        .env_remove("RECEIVER_BUFFER_SIZE")
+        .arg("--config")
+        .arg(&config)
+        .args(["--tuning", "balanced"]);
+    cmd.assert().failure().stderr(
+        predicate::str::contains(output_file.to_string_lossy())
+            .and(predicate::str::contains("cannot be used with").not()),
+    );
+
+    // --input conflicts with port and simulation
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.env_remove("RECEIVER_OUTPUT_DIR")
+        .arg("--config")
+        .arg(&config)
+        .arg("--input")
+        .arg(temp_dir.path().join("missing.parquet"));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with").not());
+}
+
+#[test]
+fn test_cli_merge_port_rejects_unsupported_options() {
+    for extra in [
+        &["--format", "csv"][..],
+        &["--append"],
+        &["--split-minutes", "5"],
+    ] {
+        let mut cmd = Command::cargo_bin("receiver").unwrap();
+        cmd.args(["-p", "port_a", "--merge-port", "port_b", "-m"])
+            .args(extra);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn test_cli_baud_rate_out_of_range() {
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args(["-p", "dummy_port", "-m", "--baud-rate", "0"]);
+    cmd.assert().failure().stderr(
+        predicate::str::contains("Invalid configuration: Baud rate 0 is out of range")
+            .and(predicate::str::contains("did you mean 9600 or 19200?")),
+    );
+
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args(["-p", "dummy_port", "-m", "--baud-rate", "-115200"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Baud rate -115200 is out of range",
+    ));
+}
+
+#[test]
+fn test_cli_on_change_rejects_negative_and_nan() {
+    for epsilon in ["-0.5", "NaN"] {
+        let mut cmd = Command::cargo_bin("receiver").unwrap();
+        cmd.args(["-p", "dummy_port", "-m", "--on-change", epsilon]);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "{} is not a finite number of at least 0",
+                epsilon
+            )));
+    }
+}
+
+#[test]
+fn test_cli_options_rejected_with_unsupported_formats() {
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args([
+        "-p",
+        "dummy_port",
+        "-m",
+        "--append",
+        "--format",
+        "parquet,csv",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--append only supports --format parquet, not parquet,csv",
+    ));
+
+    // Records tagged as out of range need the `valid` column
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args([
+        "-p",
+        "dummy_port",
+        "-m",
+        "--validate-range",
+        "16,2000",
+        "--format",
+        "jsonl",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--range-policy tag only supports --format parquet, not jsonl",
+    ));
+
+    // Compact records have no burst or extended counter field
+    let mut cmd = Command::cargo_bin("receiver").unwrap();
+    cmd.args([
+        "-p",
+        "dummy_port",
+        "-m",
+        "--extended-timestamp",
+        "--format",
+        "parquet,compact",
+    ]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--extended-timestamp cannot be used with --format compact",
+    ));
+}